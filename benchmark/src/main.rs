@@ -0,0 +1,171 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
+
+use clap::Parser;
+use vaporetto::metrics::{mean_std, Evaluator};
+use vaporetto::{CharacterBoundary, Sentence};
+
+/// A tokenizer command.
+#[derive(Clone, Debug)]
+struct Tool {
+    name: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl FromStr for Tool {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, command) = s
+            .split_once('=')
+            .ok_or_else(|| format!("the tool must be in the form of NAME=COMMAND: {s}"))?;
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words
+            .next()
+            .ok_or_else(|| format!("the command is empty: {s}"))?;
+        Ok(Self {
+            name: name.to_string(),
+            program,
+            args: words.collect(),
+        })
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "A program to compare the speed and the agreement of tokenizers on the same corpus."
+)]
+struct Args {
+    /// A raw text corpus with one sentence per line.
+    #[arg(long)]
+    corpus: PathBuf,
+
+    /// A tokenizer in the form of `NAME=COMMAND`. The command reads the corpus from stdin and
+    /// writes space-separated tokens of each line to stdout, e.g.,
+    /// `vaporetto=target/release/predict --model model.zst --output-format plain` or
+    /// `mecab=mecab -Owakati`. Arguments are split by whitespace without quoting. The first tool
+    /// is the reference of the agreement. This option can be repeated.
+    #[arg(long, required = true)]
+    tool: Vec<Tool>,
+
+    /// The number of measured runs of each tool.
+    #[arg(long, default_value = "10")]
+    runs: usize,
+
+    /// The number of unmeasured runs of each tool before measurement, e.g., for warming up the
+    /// file cache.
+    #[arg(long, default_value = "1")]
+    warmup: usize,
+}
+
+/// Runs the tool with the corpus as stdin and returns the elapsed time and the output.
+fn run(tool: &Tool, corpus: &[u8]) -> Result<(f64, String), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let mut child = Command::new(&tool.program)
+        .args(&tool.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {e}", tool.name))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let output = thread::scope(|scope| {
+        // Writes stdin in another thread so that the pipes do not block each other.
+        let writer = scope.spawn(move || stdin.write_all(corpus));
+        let mut output = vec![];
+        let result = stdout.read_to_end(&mut output);
+        writer.join().unwrap().and(result).map(|_| output)
+    })?;
+    let status = child.wait()?;
+    let elapsed = start.elapsed().as_secs_f64();
+    if !status.success() {
+        return Err(format!("{} exited with {status}", tool.name).into());
+    }
+    Ok((elapsed, String::from_utf8(output)?))
+}
+
+/// Converts a line of space-separated tokens into a sentence.
+///
+/// Unlike [`Sentence::from_tokenized()`], slashes and backslashes are not interpreted.
+fn parse_tokens(line: &str) -> Option<Sentence<'static, 'static>> {
+    let mut text = String::new();
+    let mut boundaries = vec![];
+    for token in line.split_whitespace() {
+        if !text.is_empty() {
+            boundaries.push(CharacterBoundary::WordBoundary);
+        }
+        let n_chars = token.chars().count();
+        boundaries.resize(
+            boundaries.len() + n_chars - 1,
+            CharacterBoundary::NotWordBoundary,
+        );
+        text.push_str(token);
+    }
+    let mut s = Sentence::from_raw(text).ok()?;
+    s.boundaries_mut().copy_from_slice(&boundaries);
+    Some(s)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.runs == 0 {
+        return Err("--runs must be at least 1".into());
+    }
+
+    let corpus = fs::read(&args.corpus)?;
+
+    let mut outputs = vec![];
+    println!("# tool\tmean [sec]\tstd [sec]");
+    for tool in &args.tool {
+        for i in 0..args.warmup {
+            eprintln!("{}: warming up {}/{}", tool.name, i + 1, args.warmup);
+            run(tool, &corpus)?;
+        }
+        let mut times = vec![];
+        let mut output = String::new();
+        for i in 0..args.runs {
+            eprintln!("{}: run {}/{}", tool.name, i + 1, args.runs);
+            let (elapsed, out) = run(tool, &corpus)?;
+            times.push(elapsed);
+            output = out;
+        }
+        let (mean, std) = mean_std(&times);
+        println!("{}\t{mean}\t{std}", tool.name);
+        outputs.push(output);
+    }
+
+    println!();
+    println!("# tool\tboundary F1\tword F1\tskipped lines");
+    let ref_sents: Vec<_> = outputs[0].lines().map(parse_tokens).collect();
+    for (tool, output) in args.tool.iter().zip(&outputs).skip(1) {
+        let mut evaluator = Evaluator::new();
+        let mut n_skipped = 0;
+        let mut sys_lines = output.lines();
+        for ref_s in &ref_sents {
+            let sys_s = sys_lines.next().and_then(parse_tokens);
+            // Lines are skipped if either tool fails to output them or changes their text, e.g.,
+            // by normalization.
+            match (ref_s, sys_s) {
+                (Some(ref_s), Some(sys_s)) if ref_s.as_raw_text() == sys_s.as_raw_text() => {
+                    evaluator.add(ref_s, &sys_s)?;
+                }
+                _ => n_skipped += 1,
+            }
+        }
+        let counts = evaluator.total();
+        println!(
+            "{}\t{}\t{}\t{n_skipped}",
+            tool.name,
+            counts.boundary_f1(),
+            counts.word_f1(false),
+        );
+    }
+
+    Ok(())
+}
@@ -1,4 +1,3 @@
-use std::convert::TryFrom;
 use std::fs;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -23,21 +22,42 @@ struct Args {
     /// The number of workers for zstd (0 means multithreaded will be disabled)
     #[arg(long, default_value = "0")]
     zstd_workers: u32,
+
+    /// Drop global tag models, which cannot be converted. Tags of unknown words are not
+    /// predicted by the converted model.
+    #[arg(long)]
+    drop_global_tag_models: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     eprintln!("Loading model file...");
-    let mut f = BufReader::new(fs::File::open(args.model_in).unwrap());
-    let model = KyteaModel::read(&mut f)?;
+    let mut model = KyteaModel::read(BufReader::new(fs::File::open(args.model_in)?))?;
+    if model.has_global_tag_models() {
+        if !args.drop_global_tag_models {
+            return Err(
+                "the model contains global tag models, which cannot be converted. \
+                 Specify --drop-global-tag-models to drop them."
+                    .into(),
+            );
+        }
+        eprintln!("Dropping global tag models...");
+        model.remove_global_tag_models();
+    }
+    let model = Model::try_from(model)?;
 
     eprintln!("Saving model file...");
-    let model = Model::try_from(model)?;
-    let mut f = zstd::Encoder::new(fs::File::create(args.model_out)?, 19)?;
-    f.multithread(args.zstd_workers)?;
-    model.write(&mut f)?;
-    f.finish()?;
+    vaporetto::write_atomically(
+        args.model_out,
+        |file| -> Result<(), Box<dyn std::error::Error>> {
+            let mut f = zstd::Encoder::new(file, 19)?;
+            f.multithread(args.zstd_workers)?;
+            model.write(&mut f)?;
+            f.finish()?;
+            Ok(())
+        },
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(about = "A program to sample and split corpora read from stdin.")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Splits the corpus into training, development, and test sets.
+    ///
+    /// The sets are written to `<PREFIX>.train`, `<PREFIX>.dev`, and `<PREFIX>.test`.
+    Split(SplitArgs),
+
+    /// Randomly samples the given number of lines and prints them to stdout.
+    Sample(SampleArgs),
+}
+
+#[derive(Args, Debug)]
+struct CommonArgs {
+    /// The seed of the random number generator.
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Removes duplicated lines before sampling. The first occurrence is kept.
+    #[arg(long)]
+    dedup: bool,
+
+    /// Samples lines from each group of similar lengths in proportion to its size, so that the
+    /// distribution of lengths is preserved. Lines are grouped by the binary logarithm of the
+    /// number of characters, ignoring spaces.
+    #[arg(long)]
+    stratify_by_length: bool,
+}
+
+#[derive(Args, Debug)]
+struct SplitArgs {
+    /// The prefix of output files.
+    #[arg(long)]
+    output_prefix: PathBuf,
+
+    /// The ratio of the training set.
+    #[arg(long, default_value = "0.9")]
+    train: f64,
+
+    /// The ratio of the development set.
+    #[arg(long, default_value = "0.05")]
+    dev: f64,
+
+    /// The ratio of the test set.
+    #[arg(long, default_value = "0.05")]
+    test: f64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Args, Debug)]
+struct SampleArgs {
+    /// The number of lines to sample. If the corpus is smaller, all lines are printed.
+    #[arg(long)]
+    size: usize,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// SplitMix64 pseudo random number generator.
+///
+/// The same seed always produces the same split on every platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+fn read_lines(dedup: bool) -> io::Result<Vec<String>> {
+    let mut lines = vec![];
+    let mut seen = HashSet::new();
+    let mut n_dups = 0;
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if dedup && !seen.insert(line.clone()) {
+            n_dups += 1;
+            continue;
+        }
+        lines.push(line);
+    }
+    if dedup {
+        eprintln!("Removed {n_dups} duplicated lines");
+    }
+    Ok(lines)
+}
+
+/// Returns shuffled groups of line indices. Without stratification, all lines are in one group.
+fn shuffled_groups(lines: &[String], common: &CommonArgs) -> Vec<Vec<usize>> {
+    let mut groups = BTreeMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        let key = if common.stratify_by_length {
+            let len = line.chars().filter(|c| !c.is_whitespace()).count();
+            len.checked_ilog2().unwrap_or(0)
+        } else {
+            0
+        };
+        groups.entry(key).or_insert_with(Vec::new).push(i);
+    }
+    let mut rng = SplitMix64(common.seed);
+    let mut groups: Vec<_> = groups.into_values().collect();
+    for group in &mut groups {
+        rng.shuffle(group);
+    }
+    groups
+}
+
+/// Divides `n` into parts proportional to `ratios` so that the sum is exactly `n`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn allocate(n: usize, ratios: &[f64]) -> Vec<usize> {
+    let total: f64 = ratios.iter().sum();
+    let mut sizes = vec![];
+    let mut cum_ratio = 0.0;
+    let mut cum_size = 0;
+    for &ratio in ratios {
+        cum_ratio += ratio;
+        let end = ((n as f64) * cum_ratio / total).round() as usize;
+        let end = end.min(n);
+        sizes.push(end - cum_size);
+        cum_size = end;
+    }
+    sizes
+}
+
+fn write_lines(path: PathBuf, lines: &[String], indices: &[usize]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for &i in indices {
+        writeln!(out, "{}", lines[i])?;
+    }
+    out.flush()
+}
+
+fn split(args: SplitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let ratios = [args.train, args.dev, args.test];
+    if ratios.iter().any(|&r| !r.is_finite() || r < 0.0) || ratios.iter().sum::<f64>() <= 0.0 {
+        return Err("ratios must be non-negative and their sum must be positive".into());
+    }
+    let lines = read_lines(args.common.dedup)?;
+    let mut sets = [vec![], vec![], vec![]];
+    for group in shuffled_groups(&lines, &args.common) {
+        let mut rest = &group[..];
+        for (set, size) in sets.iter_mut().zip(allocate(group.len(), &ratios)) {
+            let (head, tail) = rest.split_at(size);
+            set.extend_from_slice(head);
+            rest = tail;
+        }
+    }
+    for (set, ext) in sets.iter_mut().zip(["train", "dev", "test"]) {
+        // Keeps the original order within each set.
+        set.sort_unstable();
+        let mut path = args.output_prefix.clone().into_os_string();
+        path.push(".");
+        path.push(ext);
+        write_lines(path.into(), &lines, set)?;
+        eprintln!("{ext}: {} lines", set.len());
+    }
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn sample(args: SampleArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let lines = read_lines(args.common.dedup)?;
+    let size = args.size.min(lines.len());
+    let groups = shuffled_groups(&lines, &args.common);
+    let group_sizes: Vec<f64> = groups.iter().map(|g| g.len() as f64).collect();
+    let mut indices = vec![];
+    for (group, n) in groups.iter().zip(allocate(size, &group_sizes)) {
+        indices.extend_from_slice(&group[..n.min(group.len())]);
+    }
+    indices.sort_unstable();
+    let mut out = BufWriter::new(io::stdout().lock());
+    for i in indices {
+        writeln!(out, "{}", lines[i])?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    match Cli::parse().command {
+        Command::Split(args) => split(args),
+        Command::Sample(args) => sample(args),
+    }
+}
@@ -0,0 +1,426 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+use vaporetto::{Model, Predictor, Sentence};
+use vaporetto_rules::{
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
+    StringFilter,
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "A program to tokenize text and count token frequencies.")]
+struct Args {
+    /// The model file to use when analyzing text
+    #[arg(long, required_unless_present = "tokenized")]
+    model: Option<PathBuf>,
+
+    /// Reads tokenized text (tokens separated by spaces) instead of tokenizing raw text.
+    #[arg(long)]
+    tokenized: bool,
+
+    /// Counts tokens together with their tags.
+    #[arg(long)]
+    with_tags: bool,
+
+    /// Lowercases tokens before counting.
+    #[arg(long)]
+    lowercase: bool,
+
+    /// Counts normalized (fullwidth) forms of surfaces instead of the original ones. Tags are not
+    /// normalized.
+    #[arg(long)]
+    normalize_width: bool,
+
+    /// Do not normalize input strings before prediction.
+    #[arg(long)]
+    no_norm: bool,
+
+    /// Counts token n-grams of lengths from 1 to the given value. N-grams do not cross lines.
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u8).range(1..=3))]
+    order: u8,
+
+    /// Omits tokens that appear less than the given number of times.
+    #[arg(long, default_value = "1")]
+    min_count: usize,
+
+    /// The maximum number of entries kept in memory. When the number of distinct n-grams exceeds
+    /// this value, counts are written to temporary files and merged at the end.
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_entries: Option<usize>,
+}
+
+/// Builds a key of the given token in the tokenized text format.
+fn token_key(surface: &str, tags: &[Option<std::borrow::Cow<str>>], args: &Args) -> String {
+    let mut key = if args.lowercase {
+        surface.to_lowercase()
+    } else {
+        surface.to_string()
+    };
+    if args.with_tags {
+        for tag in &tags[..tags.iter().rposition(|x| x.is_some()).map_or(0, |x| x + 1)] {
+            key.push('/');
+            if let Some(tag) = tag {
+                key.push_str(tag);
+            }
+        }
+    }
+    key
+}
+
+/// The maximum number of run files merged at once.
+const MERGE_FAN_IN: usize = 16;
+
+/// Order of entries in run files and the output.
+#[derive(Clone, Copy)]
+enum Order {
+    /// Ascending order of keys.
+    Key,
+    /// Descending order of counts, and then ascending order of keys.
+    Count,
+}
+
+impl Order {
+    fn sort(self, entries: &mut [(String, usize)]) {
+        match self {
+            Self::Key => entries.sort_unstable(),
+            Self::Count => entries.sort_unstable_by(|(key1, count1), (key2, count2)| {
+                count2.cmp(count1).then_with(|| key1.cmp(key2))
+            }),
+        }
+    }
+}
+
+/// Creates a directory only accessible by the current user under the temporary directory.
+fn create_private_dir() -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    for i in 0..100 {
+        let path = std::env::temp_dir().join(format!(
+            "vaporetto-count-{}-{nanos}-{i}",
+            std::process::id(),
+        ));
+        // Creating a directory fails if the path already exists, even as a symbolic link.
+        match builder.create(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "failed to create a temporary directory",
+    ))
+}
+
+/// Temporary run files in a private directory, which is removed when dropped even if counting
+/// fails.
+#[derive(Default)]
+struct RunFiles {
+    dir: Option<PathBuf>,
+    n_created: usize,
+}
+
+impl RunFiles {
+    /// Creates a new run file.
+    fn create(&mut self) -> io::Result<(PathBuf, BufWriter<File>)> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => self.dir.insert(create_private_dir()?),
+        };
+        let path = dir.join(format!("{}.tmp", self.n_created));
+        self.n_created += 1;
+        let f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok((path, BufWriter::new(f)))
+    }
+
+    /// Writes the given entries into a new run file in the given order and appends its path to
+    /// `paths`.
+    fn write(
+        &mut self,
+        entries: &mut Vec<(String, usize)>,
+        order: Order,
+        paths: &mut Vec<PathBuf>,
+    ) -> io::Result<()> {
+        order.sort(entries);
+        let (path, mut f) = self.create()?;
+        for (key, count) in entries.drain(..) {
+            writeln!(f, "{count}\t{key}")?;
+        }
+        f.flush()?;
+        paths.push(path);
+        Ok(())
+    }
+
+    /// Merges runs in batches until the number of runs is at most `MERGE_FAN_IN` to bound the
+    /// number of open files.
+    fn reduce(&mut self, paths: &mut Vec<PathBuf>, order: Order) -> io::Result<()> {
+        while paths.len() > MERGE_FAN_IN {
+            let (path, mut f) = self.create()?;
+            merge_runs(&paths[..MERGE_FAN_IN], order, |key, count| {
+                writeln!(f, "{count}\t{key}")
+            })?;
+            f.flush()?;
+            for path in paths.drain(..MERGE_FAN_IN) {
+                fs::remove_file(path)?;
+            }
+            paths.push(path);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RunFiles {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Merges runs sorted in the given order and passes the entries to `emit` in the same order.
+///
+/// Counts of the same key are summed if the runs are sorted by keys.
+fn merge_runs<F>(paths: &[PathBuf], order: Order, mut emit: F) -> io::Result<()>
+where
+    F: FnMut(String, usize) -> io::Result<()>,
+{
+    let mut readers = vec![];
+    for path in paths {
+        readers.push(BufReader::new(File::open(path)?).lines());
+    }
+    let read_entry = |lines: &mut io::Lines<BufReader<File>>| -> io::Result<_> {
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(None);
+        };
+        let (count, key) = line
+            .split_once('\t')
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "broken run file"))?;
+        let count: usize = count
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some((key.to_string(), count)))
+    };
+    // Entries are popped in the ascending order of (descending count, key) for Order::Count, and
+    // of keys for Order::Key.
+    let heap_entry = |key: String, i: usize, count: usize| {
+        let primary = match order {
+            Order::Key => 0,
+            Order::Count => count,
+        };
+        Reverse((Reverse(primary), key, i, count))
+    };
+    let mut heap = BinaryHeap::new();
+    for (i, lines) in readers.iter_mut().enumerate() {
+        if let Some((key, count)) = read_entry(lines)? {
+            heap.push(heap_entry(key, i, count));
+        }
+    }
+    let mut current: Option<(String, usize)> = None;
+    while let Some(Reverse((_, key, i, count))) = heap.pop() {
+        match current.as_mut() {
+            Some((cur_key, cur_count)) if *cur_key == key => *cur_count += count,
+            _ => {
+                if let Some((cur_key, cur_count)) = current.replace((key, count)) {
+                    emit(cur_key, cur_count)?;
+                }
+            }
+        }
+        if let Some((key, count)) = read_entry(&mut readers[i])? {
+            heap.push(heap_entry(key, i, count));
+        }
+    }
+    if let Some((cur_key, cur_count)) = current {
+        emit(cur_key, cur_count)?;
+    }
+    Ok(())
+}
+
+/// Frequency counter that spills sorted runs to temporary files when it grows too large.
+struct Counter {
+    counts: HashMap<String, usize>,
+    max_entries: Option<usize>,
+    runs: RunFiles,
+    key_runs: Vec<PathBuf>,
+}
+
+impl Counter {
+    fn new(max_entries: Option<usize>) -> Self {
+        Self {
+            counts: HashMap::new(),
+            max_entries,
+            runs: RunFiles::default(),
+            key_runs: vec![],
+        }
+    }
+
+    fn add(&mut self, key: String) -> io::Result<()> {
+        *self.counts.entry(key).or_default() += 1;
+        if self
+            .max_entries
+            .is_some_and(|max_entries| self.counts.len() > max_entries)
+        {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the counts in memory into a temporary file sorted by keys.
+    fn spill(&mut self) -> io::Result<()> {
+        let mut counts: Vec<_> = self.counts.drain().collect();
+        self.runs.write(&mut counts, Order::Key, &mut self.key_runs)
+    }
+
+    /// Writes the counts in the descending order of counts.
+    ///
+    /// If the counts have been spilled, runs sorted by keys are merged to sum up the counts, and
+    /// the merged counts are sorted again by counts in runs of at most `max_entries` entries.
+    fn write(mut self, min_count: usize, mut out: impl Write) -> io::Result<()> {
+        let mut counts: Vec<_> = if self.key_runs.is_empty() {
+            self.counts
+                .drain()
+                .filter(|&(_, count)| count >= min_count)
+                .collect()
+        } else {
+            self.spill()?;
+            self.runs.reduce(&mut self.key_runs, Order::Key)?;
+            let max_entries = self.max_entries.unwrap_or(usize::MAX);
+            let runs = &mut self.runs;
+            let mut count_runs = vec![];
+            let mut counts = vec![];
+            merge_runs(&self.key_runs, Order::Key, |key, count| {
+                if count >= min_count {
+                    counts.push((key, count));
+                    if counts.len() >= max_entries {
+                        runs.write(&mut counts, Order::Count, &mut count_runs)?;
+                    }
+                }
+                Ok(())
+            })?;
+            if !count_runs.is_empty() {
+                if !counts.is_empty() {
+                    runs.write(&mut counts, Order::Count, &mut count_runs)?;
+                }
+                runs.reduce(&mut count_runs, Order::Count)?;
+                return merge_runs(&count_runs, Order::Count, |key, count| {
+                    writeln!(out, "{count}\t{key}")
+                });
+            }
+            counts
+        };
+        Order::Count.sort(&mut counts);
+        for (key, count) in counts {
+            writeln!(out, "{count}\t{key}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds n-grams of the given keys of tokens.
+fn count_ngrams(counter: &mut Counter, keys: &[String], order: usize) -> io::Result<()> {
+    for n in 1..=order {
+        for ngram in keys.windows(n) {
+            counter.add(ngram.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if args.no_norm && args.normalize_width {
+        return Err("--normalize-width cannot be used with --no-norm".into());
+    }
+
+    let pre_filter = KyteaFullwidthFilter;
+    let order = usize::from(args.order);
+    let mut counter = Counter::new(args.max_entries);
+    let mut keys = vec![];
+
+    if args.tokenized {
+        eprintln!("Start counting");
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let s = Sentence::from_tokenized(&line)?;
+            keys.clear();
+            for token in s.iter_tokens() {
+                // Only surfaces are normalized, and tags are kept as is.
+                keys.push(if args.normalize_width {
+                    token_key(&pre_filter.filter(token.surface()), token.tags(), &args)
+                } else {
+                    token_key(token.surface(), token.tags(), &args)
+                });
+            }
+            count_ngrams(&mut counter, &keys, order)?;
+        }
+    } else {
+        eprintln!("Loading model file...");
+        let mut f = zstd::Decoder::new(File::open(args.model.as_ref().unwrap())?)?;
+        let model = Model::read(&mut f)?;
+        let normalization = if args.no_norm {
+            NO_NORMALIZATION
+        } else {
+            KyteaFullwidthFilter::NAME
+        };
+        if let Some(warning) = model.normalization_warning(normalization) {
+            eprintln!("Warning: {warning}");
+        }
+        let predictor = Predictor::new(model, args.with_tags)?;
+
+        eprintln!("Start counting");
+        let mut s = Sentence::default();
+        let mut s_orig = Sentence::default();
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            let line_preproc = if args.no_norm {
+                line.clone()
+            } else {
+                pre_filter.filter(&line)
+            };
+            if s.update_raw(line_preproc).is_err() {
+                continue;
+            }
+            predictor.predict(&mut s);
+            if args.with_tags {
+                s.fill_tags();
+            }
+            let s_count = if args.no_norm || args.normalize_width {
+                &s
+            } else {
+                s_orig.update_raw(line)?;
+                s_orig.reset_tags(s.n_tags());
+                s_orig.boundaries_mut().copy_from_slice(s.boundaries());
+                s_orig.tags_mut().clone_from_slice(s.tags());
+                &s_orig
+            };
+            keys.clear();
+            keys.extend(
+                s_count
+                    .iter_tokens()
+                    .map(|token| token_key(token.surface(), token.tags(), &args)),
+            );
+            count_ngrams(&mut counter, &keys, order)?;
+        }
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    counter.write(args.min_count, &mut out)?;
+    out.flush()?;
+
+    Ok(())
+}
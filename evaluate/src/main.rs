@@ -4,10 +4,11 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use clap::Parser;
-use vaporetto::{CharacterBoundary, CharacterType, Model, Predictor, Sentence};
+use vaporetto::metrics::{Counts, Evaluator};
+use vaporetto::{CharacterType, Model, Predictor, Sentence};
 use vaporetto_rules::{
     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
-    string_filters::KyteaFullwidthFilter,
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
     SentenceFilter, StringFilter,
 };
 
@@ -64,6 +65,19 @@ struct Args {
     /// word: evaluates each word using Nagata's method.
     #[arg(long, default_value = "char")]
     metric: EvaluationMetric,
+
+    /// The number of bootstrap resamples of sentences for estimating confidence intervals.
+    /// Intervals are not printed if 0 is given.
+    #[arg(long, default_value = "0")]
+    bootstrap: usize,
+
+    /// The confidence level of the intervals.
+    #[arg(long, default_value = "0.95")]
+    confidence: f64,
+
+    /// The seed of bootstrap resampling.
+    #[arg(long, default_value = "0")]
+    seed: u64,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -83,113 +97,77 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("Loading model file...");
     let mut f = zstd::Decoder::new(File::open(args.model)?)?;
     let model = Model::read(&mut f)?;
+    let normalization = if args.no_norm {
+        NO_NORMALIZATION
+    } else {
+        KyteaFullwidthFilter::NAME
+    };
+    if let Some(warning) = model.normalization_warning(normalization) {
+        eprintln!("Warning: {warning}");
+    }
     let predictor = Predictor::new(model, args.predict_tags)?;
 
     eprintln!("Start tokenization");
 
-    let mut results = vec![];
+    let mut evaluator = Evaluator::new();
     let lines = io::stdin().lock().lines();
     for line in lines {
         let line = line?;
         if line.is_empty() {
             continue;
         }
-        let mut s = Sentence::from_tokenized(&line)?;
-        let ref_boundaries = s.boundaries().to_vec();
-        let mut ref_tags = vec![];
-        for i in 0..=ref_boundaries.len() {
-            ref_tags.push(s.tags()[i * s.n_tags()..(i + 1) * s.n_tags()].to_vec());
-        }
-        if !args.no_norm {
-            let new_line = fullwidth_filter.filter(s.as_raw_text());
-            s = Sentence::from_raw(new_line)?
+        let gold = Sentence::from_tokenized(&line)?;
+        let mut s = if args.no_norm {
+            Sentence::from_raw(gold.as_raw_text())?
+        } else {
+            Sentence::from_raw(fullwidth_filter.filter(gold.as_raw_text()))?
         };
         predictor.predict(&mut s);
         post_filters.iter().for_each(|filter| filter.filter(&mut s));
         if args.predict_tags {
             s.fill_tags();
         }
-        let sys_boundaries = s.boundaries().to_vec();
-        let mut sys_tags = vec![];
-        for i in 0..=sys_boundaries.len() {
-            sys_tags.push(s.tags()[i * s.n_tags()..(i + 1) * s.n_tags()].to_vec());
-        }
-        results.push((ref_boundaries, ref_tags, sys_boundaries, sys_tags));
+        evaluator.add(&gold, &s)?;
     }
 
+    let print_metric = |name: &str, metric: &dyn Fn(&Counts) -> f64| {
+        let value = metric(&evaluator.total());
+        if args.bootstrap == 0 {
+            println!("{name}: {value}");
+        } else {
+            let (lower, upper) =
+                evaluator.bootstrap(metric, args.bootstrap, args.confidence, args.seed);
+            println!(
+                "{name}: {value} ({}% CI: {lower} - {upper})",
+                args.confidence * 100.
+            );
+        }
+    };
     match args.metric {
         EvaluationMetric::Char => {
-            let mut n_tp = 0;
-            let mut n_tn = 0;
-            let mut n_fp = 0;
-            let mut n_fn = 0;
-            for (rs_b, _, hs_b, _) in results {
-                for (r, h) in rs_b.into_iter().zip(hs_b) {
-                    if r == h {
-                        if h == CharacterBoundary::WordBoundary {
-                            n_tp += 1;
-                        } else {
-                            n_tn += 1;
-                        }
-                    } else if h == CharacterBoundary::WordBoundary {
-                        n_fp += 1;
-                    } else {
-                        n_fn += 1;
-                    }
-                }
-            }
-            let precision = f64::from(n_tp) / f64::from(n_tp + n_fp);
-            let recall = f64::from(n_tp) / f64::from(n_tp + n_fn);
-            let f1 = 2. * precision * recall / (precision + recall);
-            println!("Precision: {precision}");
-            println!("Recall: {recall}");
-            println!("F1: {f1}");
-            println!("TP: {n_tp}, TN: {n_tn}, FP: {n_fp}, FN: {n_fn}");
+            print_metric("Precision", &Counts::boundary_precision);
+            print_metric("Recall", &Counts::boundary_recall);
+            print_metric("F1", &Counts::boundary_f1);
+            let total = evaluator.total();
+            println!(
+                "TP: {}, TN: {}, FP: {}, FN: {}",
+                total.boundary_tp, total.boundary_tn, total.boundary_fp, total.boundary_fn,
+            );
         }
         EvaluationMetric::Word => {
             // Reference:
             // Masaaki Nagata. 1994. A stochastic Japanese morphological analyzer using a forward-DP
             // backward-A* n-best search algorithm. In COLING 1994 Volume 1: The 15th International
             // Conference on Computational Linguistics.
-            let mut n_sys = 0;
-            let mut n_ref = 0;
-            let mut n_cor = 0;
-            for (refs_b, refs_t, syss_b, syss_t) in results {
-                let mut matched = true;
-                for (((r_b, r_t), s_b), s_t) in refs_b.iter().zip(&refs_t).zip(&syss_b).zip(&syss_t)
-                {
-                    if r_b == s_b {
-                        if *s_b == CharacterBoundary::WordBoundary {
-                            if matched && r_t == s_t {
-                                n_cor += 1;
-                            }
-                            matched = true;
-                            n_ref += 1;
-                            n_sys += 1;
-                        }
-                    } else {
-                        if *s_b == CharacterBoundary::WordBoundary {
-                            n_sys += 1;
-                        } else {
-                            n_ref += 1;
-                        }
-                        matched = false;
-                    }
-                }
-                if matched && refs_t.last().unwrap() == syss_t.last().unwrap() {
-                    n_cor += 1;
-                }
-                n_sys += 1;
-                n_ref += 1;
-            }
-            let precision = f64::from(n_cor) / f64::from(n_sys);
-            let recall = f64::from(n_cor) / f64::from(n_ref);
-            let f1 = 2. * precision * recall / (precision + recall);
-            println!("Precision: {precision}");
-            println!("Recall: {recall}");
-            println!("F1: {f1}");
+            let with_tags = args.predict_tags;
+            print_metric("Precision", &|c| c.word_precision(with_tags));
+            print_metric("Recall", &|c| c.word_recall(with_tags));
+            print_metric("F1", &|c| c.word_f1(with_tags));
         }
     }
+    if args.predict_tags {
+        print_metric("Tag accuracy", &Counts::tag_accuracy);
+    }
 
     Ok(())
 }
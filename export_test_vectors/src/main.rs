@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde::Serialize;
+use vaporetto::{Model, Predictor, Sentence};
+use vaporetto_rules::{
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
+    StringFilter,
+};
+
+/// Version of the test vector format. This is incremented when fields are changed.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "A program to export inputs and expected outputs of a model as test vectors for bindings."
+)]
+struct Args {
+    /// The model file to use when analyzing text
+    #[arg(long)]
+    model: PathBuf,
+
+    /// Predicts POS tags.
+    #[arg(long)]
+    predict_tags: bool,
+
+    /// Do not normalize input strings before prediction.
+    #[arg(long)]
+    no_norm: bool,
+}
+
+/// First record of the file describing the settings used to generate the vectors.
+#[derive(Serialize)]
+struct Header<'a> {
+    format_version: u32,
+    normalization: &'a str,
+    predict_tags: bool,
+}
+
+/// Expected token. Positions are in characters of the normalized text.
+#[derive(Serialize)]
+struct TokenVector<'a> {
+    surface: &'a str,
+    start: usize,
+    end: usize,
+    tags: Vec<Option<&'a str>>,
+}
+
+/// Input and expected outputs for one line.
+#[derive(Serialize)]
+struct TestVector<'a> {
+    input: &'a str,
+    normalized: &'a str,
+    tokens: Vec<TokenVector<'a>>,
+    boundary_scores: &'a [i32],
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    eprintln!("Loading model file...");
+    let mut f = zstd::Decoder::new(File::open(args.model)?)?;
+    let model = Model::read(&mut f)?;
+    let normalization = if args.no_norm {
+        NO_NORMALIZATION
+    } else {
+        KyteaFullwidthFilter::NAME
+    };
+    if model.assert_normalization(normalization).is_err() {
+        eprintln!(
+            "Warning: the model is trained with `{}` normalization, but `{normalization}` is applied",
+            model.normalization().unwrap_or_default(),
+        );
+    }
+    let predictor = Predictor::new(model, args.predict_tags)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let header = Header {
+        format_version: FORMAT_VERSION,
+        normalization,
+        predict_tags: args.predict_tags,
+    };
+    serde_json::to_writer(&mut out, &header)?;
+    out.write_all(b"\n")?;
+
+    eprintln!("Start exporting");
+    let mut s = Sentence::default();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        // Empty lines cannot be predicted.
+        if line.is_empty() {
+            continue;
+        }
+        let normalized = if args.no_norm {
+            line.clone()
+        } else {
+            KyteaFullwidthFilter.filter(&line)
+        };
+        s.update_raw(normalized)?;
+        predictor.predict(&mut s);
+        if args.predict_tags {
+            s.fill_tags();
+        }
+        let tokens = s
+            .iter_tokens()
+            .map(|token| TokenVector {
+                surface: token.surface(),
+                start: token.start(),
+                end: token.end(),
+                tags: token.tags().iter().map(|tag| tag.as_deref()).collect(),
+            })
+            .collect();
+        let vector = TestVector {
+            input: &line,
+            normalized: s.as_raw_text(),
+            tokens,
+            boundary_scores: s.boundary_scores(),
+        };
+        serde_json::to_writer(&mut out, &vector)?;
+        out.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
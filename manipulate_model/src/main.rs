@@ -1,9 +1,13 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use clap::Parser;
 use serde::{Deserialize, Serialize};
-use vaporetto::{Model, WordWeightRecord};
+use vaporetto::{DiskDictionary, Model, WordWeightRecord};
+use vaporetto_rules::pipeline::parse_filter_name;
 
 #[derive(Parser, Debug)]
 #[command(about = "A program to manipulate tarined models.")]
@@ -20,10 +24,85 @@ struct Args {
     #[arg(long)]
     dump_dict: Option<PathBuf>,
 
+    /// Output character n-grams and their weights in TSV format. Each line contains an n-gram, the
+    /// range of boundaries that the weights are added to relative to the n-gram (-1 is the
+    /// boundary just before the n-gram), and space-separated weights.
+    #[arg(long)]
+    dump_char_ngrams: Option<PathBuf>,
+
+    /// Output character type n-grams and their weights in the same format as
+    /// --dump-char-ngrams.
+    #[arg(long)]
+    dump_type_ngrams: Option<PathBuf>,
+
+    /// Output the character type definition stored in the model in the format of the
+    /// --char-types option of the train command.
+    #[arg(long)]
+    dump_char_types: Option<PathBuf>,
+
     /// Replace a dictionary if the argument is specified.
     #[arg(long)]
     replace_dict: Option<PathBuf>,
 
+    /// Format of the dictionary files of --dump-dict and --replace-dict.
+    #[arg(long, default_value = "csv", value_parser = clap::builder::PossibleValuesParser::new(["csv", "tsv"]))]
+    dict_format: String,
+
+    /// Apply edits written in the given file to the dictionary. Each line of the file is one of
+    /// the following tab-separated operations: `add WORD WEIGHTS [COMMENT]`, `remove WORD`,
+    /// `reweight WORD WEIGHTS`, and `comment WORD COMMENT`. WEIGHTS is either space-separated
+    /// weights of all boundaries of the word or `LEFT,INSIDE,RIGHT` like --mecab-weights.
+    /// Empty lines and lines starting with `#` are ignored. This option can be repeated.
+    #[arg(long)]
+    edit_dict: Vec<PathBuf>,
+
+    /// Add words of MeCab dictionary CSV files, such as IPAdic, UniDic, and user dictionaries,
+    /// to the dictionary. Files must be encoded in UTF-8. Words already in the dictionary are
+    /// kept.
+    #[arg(long)]
+    merge_mecab_dict: Vec<PathBuf>,
+
+    /// Weights of words added by --merge-mecab-dict in the form of `LEFT,INSIDE,RIGHT`. LEFT and
+    /// RIGHT are added to the boundaries at both ends of the word, and INSIDE is added to the
+    /// boundaries inside the word.
+    #[arg(long, default_value = "3000,-5000,3000")]
+    mecab_weights: String,
+
+    /// Weights of words of the given part-of-speech (the fifth column) in the form of
+    /// `POS=LEFT,INSIDE,RIGHT`, overriding --mecab-weights. This option can be repeated.
+    #[arg(long)]
+    mecab_pos_weights: Vec<String>,
+
+    /// Move the dictionary of the model to a disk dictionary file, which is loaded by the
+    /// --disk-dict option of the predict command. Use this for dictionaries too large to be
+    /// loaded into memory.
+    #[arg(long)]
+    move_dict_to_disk: Option<PathBuf>,
+
+    /// The number of words in each block of the disk dictionary. Only the first word of each
+    /// block is loaded into memory.
+    #[arg(long, default_value = "256")]
+    disk_dict_block_len: usize,
+
+    /// Remove n-grams and dictionary words whose absolute weights are all less than this value.
+    #[arg(long)]
+    prune: Option<i32>,
+
+    /// Write the model in the format of the given older version for old runtimes.
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(["0.5", "0.6"]))]
+    to_version: Option<String>,
+
+    /// Remove tag models from the model.
+    #[arg(long)]
+    strip_tag_models: bool,
+
+    /// Record comma-separated names of filters recommended for the model, which the predict
+    /// command applies automatically (e.g., `kytea-fullwidth,wsconst:D`). Available names are
+    /// kytea-fullwidth, nfc, nfd, nfkc, nfkd, wsconst:{D,R,H,T,K,O,G}, concat-patterns,
+    /// concat-numbers, and split-linebreaks. An empty value removes the recorded filters.
+    #[arg(long)]
+    set_pipeline: Option<String>,
+
     /// The number of workers for zstd (0 means multithreaded will be disabled)
     #[arg(long, default_value = "0")]
     zstd_workers: u32,
@@ -36,6 +115,175 @@ struct WordWeightRecordFlatten {
     comment: String,
 }
 
+/// Weights added by a dictionary word.
+#[derive(Clone, Copy)]
+struct DictWeight {
+    left: i32,
+    inside: i32,
+    right: i32,
+}
+
+impl FromStr for DictWeight {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut it = s.split(',');
+        if let (Some(left), Some(inside), Some(right), None) =
+            (it.next(), it.next(), it.next(), it.next())
+        {
+            Ok(Self {
+                left: left.trim().parse()?,
+                inside: inside.trim().parse()?,
+                right: right.trim().parse()?,
+            })
+        } else {
+            Err(format!("invalid weights: {s}").into())
+        }
+    }
+}
+
+impl DictWeight {
+    /// Returns weights of all boundaries of a word of the given length.
+    fn to_weights(self, n_chars: usize) -> Vec<i32> {
+        let mut weights = vec![self.inside; n_chars + 1];
+        *weights.first_mut().unwrap() = self.left;
+        *weights.last_mut().unwrap() = self.right;
+        weights
+    }
+}
+
+/// Parses weights of a word, which are either space-separated weights of all boundaries or
+/// `LEFT,INSIDE,RIGHT`.
+fn parse_word_weights(word: &str, s: &str) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    if s.contains(',') {
+        Ok(s.parse::<DictWeight>()?.to_weights(word.chars().count()))
+    } else {
+        Ok(s.split_whitespace()
+            .map(|w| w.parse())
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+/// Counts of applied dictionary edits.
+#[derive(Default)]
+struct EditCounts {
+    added: usize,
+    removed: usize,
+    reweighted: usize,
+    commented: usize,
+}
+
+/// Applies edits written in the file to the dictionary.
+fn apply_dict_edits(
+    dict: Vec<WordWeightRecord>,
+    path: &Path,
+    counts: &mut EditCounts,
+) -> Result<Vec<WordWeightRecord>, Box<dyn std::error::Error>> {
+    let mut index: HashMap<_, _> = dict
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (record.get_word().to_string(), i))
+        .collect();
+    let mut slots: Vec<_> = dict.into_iter().map(Some).collect();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |msg: &str| format!("{}:{}: {msg}", path.display(), i + 1);
+        let cols: Vec<_> = line.split('\t').collect();
+        let (op, word) = match cols[..] {
+            [op, word, ..] => (op, word),
+            _ => return Err(error("too few columns").into()),
+        };
+        let slot = index.get(word).copied();
+        match (op, &cols[2..], slot) {
+            ("add", [weights] | [weights, _], None) => {
+                let weights =
+                    parse_word_weights(word, weights).map_err(|e| error(&e.to_string()))?;
+                let comment = cols.get(3).unwrap_or(&"").to_string();
+                let record = WordWeightRecord::new(word.to_string(), weights, comment)
+                    .map_err(|e| error(&e.to_string()))?;
+                index.insert(word.to_string(), slots.len());
+                slots.push(Some(record));
+                counts.added += 1;
+            }
+            ("add", _, Some(_)) => {
+                return Err(error(&format!(
+                    "{word} is already in the dictionary, use reweight"
+                ))
+                .into());
+            }
+            ("remove", [], Some(slot)) => {
+                slots[slot] = None;
+                index.remove(word);
+                counts.removed += 1;
+            }
+            ("reweight", [weights], Some(slot)) => {
+                let weights =
+                    parse_word_weights(word, weights).map_err(|e| error(&e.to_string()))?;
+                let comment = slots[slot].as_ref().unwrap().get_comment().to_string();
+                slots[slot] = Some(
+                    WordWeightRecord::new(word.to_string(), weights, comment)
+                        .map_err(|e| error(&e.to_string()))?,
+                );
+                counts.reweighted += 1;
+            }
+            ("comment", [comment], Some(slot)) => {
+                let record = slots[slot].as_ref().unwrap();
+                let weights = record.get_weights().to_vec();
+                slots[slot] = Some(WordWeightRecord::new(
+                    word.to_string(),
+                    weights,
+                    comment.to_string(),
+                )?);
+                counts.commented += 1;
+            }
+            ("remove" | "reweight" | "comment", _, None) => {
+                return Err(error(&format!("{word} is not in the dictionary")).into());
+            }
+            ("add" | "remove" | "reweight" | "comment", _, _) => {
+                return Err(error("invalid number of columns").into());
+            }
+            _ => return Err(error(&format!("unknown operation: {op}")).into()),
+        }
+    }
+    Ok(slots.into_iter().flatten().collect())
+}
+
+/// Reads words of a MeCab dictionary CSV file.
+///
+/// Each row starts with the surface, the left and right context IDs, the cost, and features
+/// beginning with the part-of-speech. The features are stored as comments.
+fn read_mecab_dict(
+    path: &Path,
+    default_weight: DictWeight,
+    pos_weights: &HashMap<String, DictWeight>,
+) -> Result<Vec<WordWeightRecord>, Box<dyn std::error::Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut dict = vec![];
+    for result in rdr.records() {
+        let record = result?;
+        let Some(word) = record.get(0).filter(|w| !w.is_empty()) else {
+            continue;
+        };
+        let features: Vec<_> = record.iter().skip(4).collect();
+        let weight = features
+            .first()
+            .and_then(|pos| pos_weights.get(*pos))
+            .copied()
+            .unwrap_or(default_weight);
+        dict.push(WordWeightRecord::new(
+            word.to_string(),
+            weight.to_weights(word.chars().count()),
+            features.join(","),
+        )?);
+    }
+    Ok(dict)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
@@ -43,10 +291,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut f = zstd::Decoder::new(fs::File::open(args.model_in)?)?;
     let mut model = Model::read(&mut f)?;
 
+    let dict_delimiter = if args.dict_format == "tsv" {
+        b'\t'
+    } else {
+        b','
+    };
+
     if let Some(path) = args.dump_dict {
         eprintln!("Saving dictionary file...");
         let file = fs::File::create(path)?;
-        let mut wtr = csv::Writer::from_writer(file);
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(dict_delimiter)
+            .from_writer(file);
         for data in model.dictionary() {
             let str_weights: Vec<_> = data.get_weights().iter().map(|w| w.to_string()).collect();
             wtr.serialize(WordWeightRecordFlatten {
@@ -57,10 +313,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(path) = args.dump_char_ngrams {
+        eprintln!("Saving character n-grams...");
+        let mut wtr = BufWriter::new(fs::File::create(path)?);
+        model.dump_char_ngrams(&mut wtr)?;
+        wtr.flush()?;
+    }
+
+    if let Some(path) = args.dump_type_ngrams {
+        eprintln!("Saving character type n-grams...");
+        let mut wtr = BufWriter::new(fs::File::create(path)?);
+        model.dump_type_ngrams(&mut wtr)?;
+        wtr.flush()?;
+    }
+
+    if let Some(path) = args.dump_char_types {
+        eprintln!("Saving character type definition...");
+        fs::write(path, model.char_type_ranges().to_string())?;
+    }
+
     if let Some(path) = args.replace_dict {
         eprintln!("Loading dictionary file...");
         let file = fs::File::open(path)?;
-        let mut rdr = csv::Reader::from_reader(file);
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(dict_delimiter)
+            .from_reader(file);
         let mut dict = vec![];
         for result in rdr.deserialize() {
             let record: WordWeightRecordFlatten = result?;
@@ -73,12 +350,102 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         model.replace_dictionary(dict);
     }
 
+    if !args.merge_mecab_dict.is_empty() {
+        let default_weight = args.mecab_weights.parse()?;
+        let mut pos_weights = HashMap::new();
+        for s in &args.mecab_pos_weights {
+            let (pos, weight) = s
+                .split_once('=')
+                .ok_or_else(|| format!("invalid part-of-speech weights: {s}"))?;
+            pos_weights.insert(pos.to_string(), weight.parse()?);
+        }
+        for path in &args.merge_mecab_dict {
+            eprintln!("Loading MeCab dictionary file {}...", path.display());
+            let dict = read_mecab_dict(path, default_weight, &pos_weights)?;
+            let n_words = dict.len();
+            let n_added = model.merge_dict(dict);
+            eprintln!("Added {n_added} of {n_words} words");
+        }
+    }
+
+    if !args.edit_dict.is_empty() {
+        let mut dict = model.dictionary().to_vec();
+        let mut counts = EditCounts::default();
+        for path in &args.edit_dict {
+            eprintln!("Applying dictionary edits {}...", path.display());
+            dict = apply_dict_edits(dict, path, &mut counts)?;
+        }
+        model.replace_dictionary(dict);
+        eprintln!(
+            "Added {} words, removed {} words, reweighted {} words, and commented {} words",
+            counts.added, counts.removed, counts.reweighted, counts.commented,
+        );
+    }
+
+    if let Some(threshold) = args.prune {
+        eprintln!("Pruning model...");
+        let report = model.prune(threshold);
+        eprintln!(
+            "Removed {} character n-grams, {} type n-grams, and {} dictionary words",
+            report.removed_char_ngrams, report.removed_type_ngrams, report.removed_dict_words,
+        );
+        eprintln!(
+            "Expected perturbation per removed entry: {:.2}",
+            report.expected_perturbation(),
+        );
+    }
+
+    if let Some(path) = args.move_dict_to_disk {
+        eprintln!("Saving disk dictionary file...");
+        let dict = model.dictionary().to_vec();
+        let n_words = dict.len();
+        vaporetto::write_atomically(path, |f| {
+            DiskDictionary::write(dict, args.disk_dict_block_len, f)
+        })?;
+        model.replace_dictionary(vec![]);
+        eprintln!("Moved {n_words} words");
+    }
+
+    if args.strip_tag_models {
+        let n = model.remove_tag_models();
+        eprintln!("Removed {n} tag models");
+    }
+
+    if let Some(pipeline) = &args.set_pipeline {
+        let names: Vec<String> = pipeline
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(|name| name.trim().to_string())
+            .collect();
+        for name in &names {
+            parse_filter_name(name).map_err(|e| e.to_string())?;
+        }
+        model.set_pipeline(names);
+    }
+
     if let Some(path) = args.model_out {
         eprintln!("Saving model file...");
-        let mut f = zstd::Encoder::new(fs::File::create(path)?, 19)?;
-        f.multithread(args.zstd_workers)?;
-        model.write(&mut f)?;
-        f.finish()?;
+        if args.to_version.as_deref() == Some("0.5")
+            && (!model.char_type_ranges().is_empty()
+                || model.calibration().is_some()
+                || !model.tag_transitions().is_empty()
+                || !model.pipeline().is_empty())
+        {
+            eprintln!(
+                "Warning: user-defined character types, calibration, tag transitions, and the pipeline are dropped in the 0.5 format"
+            );
+        }
+        vaporetto::write_atomically(path, |file| -> Result<(), Box<dyn std::error::Error>> {
+            let mut f = zstd::Encoder::new(file, 19)?;
+            f.multithread(args.zstd_workers)?;
+            if let Some(version) = args.to_version.as_ref() {
+                model.write_downgraded(&mut f, version)?;
+            } else {
+                model.write(&mut f)?;
+            }
+            f.finish()?;
+            Ok(())
+        })?;
     }
 
     Ok(())
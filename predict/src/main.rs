@@ -1,17 +1,32 @@
 use std::fs::File;
 use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Instant;
 
 use clap::Parser;
-use vaporetto::{CharacterType, Model, Predictor, Sentence};
+use regex::Regex;
+use vaporetto::{
+    Attribution, CharacterBoundary, CharacterType, DiskDictionary, FeatureKind, Model, OffsetMap,
+    Predictor, Sentence, StageTimings,
+};
 use vaporetto_rules::{
-    sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
-    string_filters::KyteaFullwidthFilter,
+    pipeline::{parse_filter_name, PipelineFilter},
+    sentence_filters::{
+        ConcatGraphemeClustersFilter, KyteaWsConstFilter, NumberConcatenator, PatternConcatenator,
+        RegexJoinFilter, RegexSplitFilter, UncertainBoundariesFilter,
+    },
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
     SentenceFilter, StringFilter,
 };
 
+mod writers;
+
+use writers::{
+    writer_by_name, KwicWriter, NullWriter, OutputWriter, TagFilterWriter, WRITER_NAMES,
+};
+
 #[derive(Clone, Debug)]
 enum WsConst {
     GraphemeCluster,
@@ -45,12 +60,45 @@ struct Args {
     #[arg(long)]
     predict_tags: bool,
 
+    /// Removes tag model entries inconsistent with the boundary model before prediction.
+    #[arg(long)]
+    prune_tag_models: bool,
+
     /// Do not segment some character types: {D, R, H, T, K, O, G}.
     /// D: Digit, R: Roman, H: Hiragana, T: Katakana, K: Kanji, O: Other, G: Grapheme cluster.
     #[arg(long)]
     wsconst: Vec<WsConst>,
 
-    /// Prints boundary scores.
+    /// Applies --wsconst only to boundaries whose absolute scores are smaller than the given
+    /// value, i.e., boundaries the model is uncertain about.
+    #[arg(long)]
+    wsconst_max_score: Option<i32>,
+
+    /// The decision threshold of boundary scores. Boundaries with scores greater than this value
+    /// are word boundaries. A larger value produces longer tokens.
+    #[arg(long, default_value = "0", allow_hyphen_values = true)]
+    threshold: i32,
+
+    /// Outputs URLs, email addresses, hashtags, and mentions as single tokens.
+    #[arg(long)]
+    concat_patterns: bool,
+
+    /// Outputs numbers (e.g., `1,234.5`), numbers followed by counters (e.g., `1234円`), and
+    /// dates (e.g., `2024年1月15日`) as single tokens.
+    #[arg(long)]
+    concat_numbers: bool,
+
+    /// Outputs each match of the given regular expression as a single token, e.g., for URLs and
+    /// email addresses. This option can be repeated.
+    #[arg(long)]
+    join_regex: Vec<Regex>,
+
+    /// Places word boundaries at both ends of each match of the given regular expression.
+    /// This option can be repeated.
+    #[arg(long)]
+    split_regex: Vec<Regex>,
+
+    /// Prints boundary scores. In the jsonl format, they are stored in each record.
     #[arg(long)]
     scores: bool,
 
@@ -58,9 +106,266 @@ struct Args {
     #[arg(long)]
     tag_scores: bool,
 
+    /// Prints boundary scores and the features contributing to them, i.e., character n-grams
+    /// (char), character type n-grams (type), and dictionary words (dict), with their weights.
+    #[arg(long, conflicts_with_all = ["kwic", "profile"])]
+    explain: bool,
+
+    /// Uses the last given number of characters of the previous line as context for predicting
+    /// the boundaries at the beginning of each line. The context is not printed. An empty line
+    /// resets the context.
+    #[arg(long, conflicts_with_all = ["protect_spans", "explain", "profile"])]
+    context_chars: Option<usize>,
+
+    /// Samples each boundary with the probability given by the sigmoid of its score divided by
+    /// the given temperature instead of thresholding the scores. This produces diverse
+    /// segmentations for data augmentation such as subword regularization. Larger values (e.g.,
+    /// 10000) produce more diverse segmentations.
+    #[arg(long, conflicts_with_all = ["explain", "context_chars", "protect_spans", "profile"])]
+    sample_temperature: Option<f64>,
+
+    /// The seed of the random number generator used by --sample-temperature. The same seed
+    /// always produces the same segmentations.
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
     /// Do not normalize input strings before prediction.
     #[arg(long)]
     no_norm: bool,
+
+    /// Do not apply the filters recommended by the model. By default, string filters recorded
+    /// in the model replace the default normalization unless --no-norm is given, and sentence
+    /// filters are applied before those given by --wsconst.
+    #[arg(long)]
+    ignore_model_pipeline: bool,
+
+    /// The maximum number of dictionary words applied at each position.
+    /// Words with smaller weights are ignored when the limit is exceeded.
+    #[arg(long)]
+    max_dict_matches: Option<usize>,
+
+    /// Disk dictionary files created by the --move-dict-to-disk option of manipulate_model.
+    /// Words are looked up in the files during prediction instead of being loaded into memory.
+    #[arg(long)]
+    disk_dict: Vec<PathBuf>,
+
+    /// Outputs runs of characters unknown to the model as single tokens tagged `UNSEG`
+    /// if the runs are longer than or equal to the given number of characters.
+    #[arg(long)]
+    keep_unknown_spans: Option<usize>,
+
+    /// Outputs spans enclosed in `⟦` and `⟧` and XML-like tags (e.g., `<br/>` and `</a>`) as is
+    /// as single tokens without tags. The text between the spans is predicted separately, so the
+    /// spans do not affect the surrounding boundaries.
+    #[arg(long, conflicts_with_all = ["scores", "tag_scores", "explain", "profile"])]
+    protect_spans: bool,
+
+    /// The number of leading tab-separated columns (e.g., IDs) that are printed as is.
+    /// Only the remaining part of each line is tokenized.
+    #[arg(long, default_value = "0")]
+    passthrough_columns: usize,
+
+    /// Outputs only tokens having a tag that matches one of the given values. A value ending
+    /// with `*` matches tags starting with the preceding string (e.g., `名詞*`).
+    /// The number of extracted tokens is printed at the end. Requires --predict-tags.
+    #[arg(long)]
+    tag_filter: Vec<String>,
+
+    /// Outputs keyword-in-context lines of tokens whose surface or tag equals one of the given
+    /// values instead of the tokenized text. Each line consists of the left context, the keyword,
+    /// and the right context separated by tabs.
+    #[arg(long, conflicts_with_all = ["tag_filter", "scores", "tag_scores"])]
+    kwic: Vec<String>,
+
+    /// The number of tokens printed on each side of a keyword in --kwic mode.
+    #[arg(long, default_value = "5")]
+    kwic_window: usize,
+
+    /// The output format: {tokenized, kytea, tsv, json, mecab, plain, jsonl}.
+    #[arg(
+        long,
+        default_value = "tokenized",
+        value_parser = clap::builder::PossibleValuesParser::new(WRITER_NAMES),
+        conflicts_with_all = ["tag_filter", "kwic"],
+    )]
+    output_format: String,
+
+    /// Prints the elapsed time of each stage (filters, scorers, and tag prediction) at the end.
+    #[arg(long)]
+    profile: bool,
+
+    /// Tokenizes the input without printing any results, and reports the throughput (characters
+    /// and sentences per second) and the peak resident set size at the end.
+    #[arg(
+        long,
+        conflicts_with_all = ["output_format", "tag_filter", "kwic", "scores", "tag_scores", "explain"],
+    )]
+    benchmark: bool,
+}
+
+/// Returns the peak resident set size of this process in KiB if available.
+fn peak_rss_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Splits a line into the passthrough columns (including the last tab) and the text.
+fn split_columns(
+    mut line: String,
+    n_columns: usize,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    if n_columns == 0 {
+        return Ok((String::new(), line));
+    }
+    let mut pos = 0;
+    for _ in 0..n_columns {
+        let Some(i) = line[pos..].find('\t') else {
+            return Err(
+                format!("the line does not have {n_columns} passthrough columns: {line}").into(),
+            );
+        };
+        pos += i + 1;
+    }
+    let text = line.split_off(pos);
+    Ok((line, text))
+}
+
+/// Returns byte ranges of spans protected by --protect-spans.
+fn find_protected_spans(line: &str) -> Vec<Range<usize>> {
+    let mut spans = vec![];
+    let mut pos = 0;
+    while let Some(i) = line[pos..].find(['⟦', '<']) {
+        let start = pos + i;
+        let rest = &line[start..];
+        let end = if rest.starts_with('⟦') {
+            rest.find('⟧').map(|j| start + j + '⟧'.len_utf8())
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c)) {
+            rest.find('>').map(|j| start + j + 1)
+        } else {
+            None
+        };
+        // Unclosed brackets are treated as normal characters.
+        pos = end.unwrap_or_else(|| start + rest.chars().next().unwrap().len_utf8());
+        if let Some(end) = end {
+            spans.push(start..end);
+        }
+    }
+    spans
+}
+
+/// Applies string filters in order and returns the mapping of character positions from the result
+/// to `text`.
+fn apply_string_filters_with_offsets(
+    filters: &[Box<dyn StringFilter<String>>],
+    text: &str,
+) -> (String, OffsetMap) {
+    let init = (text.to_string(), OffsetMap::identity(text.chars().count()));
+    filters.iter().fold(init, |(text, offsets), filter| {
+        let (text, next) = filter.filter_with_offsets(text);
+        (text, offsets.compose(&next))
+    })
+}
+
+/// Applies filters to a sentence holding `text`, the text before normalization, so that patterns
+/// are matched against the input as is. If the normalization moves characters, i.e., `aligned` is
+/// `false`, the filters are applied to `s` instead.
+fn filter_original_text(
+    filters: &[Box<dyn SentenceFilter>],
+    text: &str,
+    aligned: bool,
+    s: &mut Sentence,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if filters.is_empty() {
+        return Ok(());
+    }
+    if !aligned {
+        filters.iter().for_each(|filter| filter.filter(s));
+        return Ok(());
+    }
+    let mut s_orig = Sentence::from_raw(text)?;
+    s_orig.boundaries_mut().copy_from_slice(s.boundaries());
+    filters.iter().for_each(|filter| filter.filter(&mut s_orig));
+    s.boundaries_mut().copy_from_slice(s_orig.boundaries());
+    Ok(())
+}
+
+/// Predicts the text segments between protected spans one by one and stores the result in
+/// `s_orig`. Returns `false` if the line is empty.
+#[allow(clippy::too_many_arguments)]
+fn predict_protected<'b>(
+    predictor: &'b Predictor,
+    pre_filters: &[Box<dyn StringFilter<String>>],
+    post_filters: &[Box<dyn SentenceFilter>],
+    original_filters: &[Box<dyn SentenceFilter>],
+    predict_tags: bool,
+    line: String,
+    s: &mut Sentence<'_, 'b>,
+    s_orig: &mut Sentence<'_, 'b>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Text segments and protected spans in character positions.
+    let mut segments = vec![];
+    let mut protected = vec![];
+    let mut pos = 0;
+    let mut n_chars = 0;
+    for span in find_protected_spans(&line)
+        .into_iter()
+        .chain(std::iter::once(line.len()..line.len()))
+    {
+        let text = &line[pos..span.start];
+        if !text.is_empty() {
+            segments.push((n_chars, text.to_string()));
+            n_chars += text.chars().count();
+        }
+        let span_len = line[span.clone()].chars().count();
+        if span_len != 0 {
+            protected.push(n_chars..n_chars + span_len);
+            n_chars += span_len;
+        }
+        pos = span.end;
+    }
+    if s_orig.update_raw(line).is_err() {
+        return Ok(false);
+    }
+    s_orig
+        .boundaries_mut()
+        .fill(CharacterBoundary::WordBoundary);
+    for range in protected {
+        s_orig.boundaries_mut()[range.start..range.end - 1]
+            .fill(CharacterBoundary::NotWordBoundary);
+    }
+    for (offset, text) in segments {
+        let (text_preproc, offsets) = apply_string_filters_with_offsets(pre_filters, &text);
+        s.update_raw(text_preproc)?;
+        predictor.predict(s);
+        post_filters.iter().for_each(|filter| filter.filter(s));
+        filter_original_text(original_filters, &text, offsets.is_identity(), s)?;
+        if predict_tags {
+            s.fill_tags();
+        }
+        // Segments must keep their lengths to be placed in the line.
+        let projected;
+        let s = if offsets.is_identity() {
+            &*s
+        } else {
+            projected = s.project_boundaries(text.as_str(), &offsets)?;
+            &projected
+        };
+        let len = s.boundaries().len() + 1;
+        let n_tags = s.n_tags();
+        if s_orig.n_tags() != n_tags {
+            s_orig.reset_tags(n_tags);
+        }
+        s_orig.boundaries_mut()[offset..offset + len - 1].copy_from_slice(s.boundaries());
+        s_orig.tags_mut()[offset * n_tags..(offset + len) * n_tags].clone_from_slice(s.tags());
+    }
+    Ok(true)
 }
 
 fn print_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::error::Error>> {
@@ -74,6 +379,28 @@ fn print_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
+fn print_attributions(
+    s: &Sentence,
+    attributions: &[Vec<Attribution>],
+    mut out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let chars: Vec<_> = s.as_raw_text().chars().collect();
+    for (i, (score, features)) in s.boundary_scores().iter().zip(attributions).enumerate() {
+        writeln!(out, "{i}:{}{} {score}", chars[i], chars[i + 1])?;
+        for a in features {
+            let kind = match a.kind {
+                FeatureKind::CharNgram => "char",
+                FeatureKind::TypeNgram => "type",
+                FeatureKind::Dictionary => "dict",
+            };
+            let surface: String = chars[a.range.clone()].iter().collect();
+            writeln!(out, "\t{kind}\t{surface}\t{}", a.weight)?;
+        }
+    }
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
 fn print_tag_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::error::Error>> {
     for token in s.iter_tokens() {
         out.write_all(token.surface().as_bytes())?;
@@ -95,94 +422,261 @@ fn print_tag_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let pre_filter = KyteaFullwidthFilter;
+    if !args.tag_filter.is_empty() && !args.predict_tags {
+        return Err("--tag-filter requires --predict-tags".into());
+    }
+    let mut writer: Box<dyn OutputWriter> = if args.benchmark {
+        Box::new(NullWriter)
+    } else if !args.kwic.is_empty() {
+        Box::new(KwicWriter::new(args.kwic.clone(), args.kwic_window))
+    } else if !args.tag_filter.is_empty() {
+        Box::new(TagFilterWriter::new(args.tag_filter.clone()))
+    } else {
+        writer_by_name(&args.output_format).unwrap()
+    };
+
     let mut post_filters: Vec<Box<dyn SentenceFilter>> = vec![];
     for wsconst in &args.wsconst {
-        match wsconst {
-            WsConst::GraphemeCluster => post_filters.push(Box::new(ConcatGraphemeClustersFilter)),
-            WsConst::CharType(char_type) => {
-                post_filters.push(Box::new(KyteaWsConstFilter::new(*char_type)))
-            }
+        let filter: Box<dyn SentenceFilter> = match wsconst {
+            WsConst::GraphemeCluster => Box::new(ConcatGraphemeClustersFilter),
+            WsConst::CharType(char_type) => Box::new(KyteaWsConstFilter::new(*char_type)),
+        };
+        if let Some(threshold) = args.wsconst_max_score {
+            post_filters.push(Box::new(UncertainBoundariesFilter::new(filter, threshold)));
+        } else {
+            post_filters.push(filter);
         }
     }
+    // Filters matching patterns against the text before normalization.
+    let mut original_filters: Vec<Box<dyn SentenceFilter>> = vec![];
+    if args.concat_patterns {
+        original_filters.push(Box::new(PatternConcatenator::new()));
+    }
+    if args.concat_numbers {
+        original_filters.push(Box::new(NumberConcatenator::new()));
+    }
+    for pattern in &args.split_regex {
+        original_filters.push(Box::new(RegexSplitFilter::new(pattern.clone())));
+    }
+    for pattern in &args.join_regex {
+        original_filters.push(Box::new(RegexJoinFilter::new(pattern.clone())));
+    }
 
     eprintln!("Loading model file...");
     let mut f = zstd::Decoder::new(File::open(args.model)?)?;
-    let model = Model::read(&mut f)?;
-    let mut predictor = Predictor::new(model, args.predict_tags)?;
+    let mut model = Model::read(&mut f)?;
+    let mut model_string_filters: Vec<Box<dyn StringFilter<String>>> = vec![];
+    if !args.ignore_model_pipeline {
+        let mut model_post_filters = vec![];
+        for name in model.pipeline() {
+            match parse_filter_name(name).map_err(|e| e.to_string())? {
+                PipelineFilter::String(filter) => model_string_filters.push(filter),
+                PipelineFilter::Sentence(filter) => model_post_filters.push(filter),
+            }
+        }
+        post_filters.splice(0..0, model_post_filters);
+    }
+    let normalization = if args.no_norm {
+        NO_NORMALIZATION
+    } else {
+        KyteaFullwidthFilter::NAME
+    };
+    // The normalization is not checked if the model specifies its own string filters.
+    let check_normalization = args.no_norm || model_string_filters.is_empty();
+    let pre_filters: Vec<Box<dyn StringFilter<String>>> = if args.no_norm {
+        vec![]
+    } else if !model_string_filters.is_empty() {
+        model_string_filters
+    } else {
+        vec![Box::new(KyteaFullwidthFilter)]
+    };
+    if check_normalization {
+        if let Some(warning) = model.normalization_warning(normalization) {
+            eprintln!("Warning: {warning}");
+        }
+    }
+    if let Some(max_matches) = args.max_dict_matches {
+        let n_capped = model.limit_dictionary_matches(max_matches)?;
+        if n_capped != 0 {
+            eprintln!("Dictionary matches of {n_capped} words are capped to {max_matches}");
+        }
+    }
+    if args.prune_tag_models {
+        let report = model.prune_tag_models();
+        eprintln!(
+            "Pruned tag models: {} invalid biases, {} invalid n-gram weights, {} dangling character n-grams",
+            report.invalid_biases, report.invalid_weights, report.dangling_char_ngrams,
+        );
+    }
+    let mut predictor = if args.explain {
+        Predictor::with_attribution(model, args.predict_tags)?
+    } else {
+        Predictor::new_parallel(model, args.predict_tags, false)?
+    };
+    let report = predictor.tag_model_report();
+    if !report.is_valid() {
+        eprintln!(
+            "Warning: inconsistent tag models: {} invalid biases, {} invalid n-gram weights, {} dangling character n-grams",
+            report.invalid_biases, report.invalid_weights, report.dangling_char_ngrams,
+        );
+    }
     if args.tag_scores {
         predictor.store_tag_scores(true);
     }
+    predictor.keep_unknown_spans(args.keep_unknown_spans);
+    predictor.set_threshold(args.threshold);
+    for path in &args.disk_dict {
+        predictor.add_disk_dictionary(DiskDictionary::open(path)?);
+    }
 
     let is_tty = atty::is(atty::Stream::Stdout);
 
     eprintln!("Start tokenization");
     let mut out = BufWriter::new(io::stdout().lock());
-    let mut buf = String::new();
     let mut s = Sentence::default();
+    let mut s_orig = Sentence::default();
 
+    let mut timings = StageTimings::default();
+    let mut attributions = vec![];
+    let mut context = String::new();
+    let mut n_sentences = 0;
+    let mut n_chars = 0;
     let start = Instant::now();
-    if args.no_norm {
-        let lines = io::stdin().lock().lines();
-        for line in lines {
-            let line = line?;
-            if s.update_raw(line).is_ok() {
-                predictor.predict(&mut s);
-                post_filters.iter().for_each(|filter| filter.filter(&mut s));
-                if args.predict_tags {
-                    s.fill_tags();
-                }
-                s.write_tokenized_text(&mut buf);
-                out.write_all(buf.as_bytes())?;
-                if args.scores {
-                    print_scores(&s, &mut out)?;
-                }
-            }
-            out.write_all(b"\n")?;
-            if args.tag_scores {
-                print_tag_scores(&s, &mut out)?;
+    let lines = io::stdin().lock().lines();
+    for (line_id, line) in (0..).zip(lines) {
+        let (columns, line) = split_columns(line?, args.passthrough_columns)?;
+        n_sentences += 1;
+        n_chars += line.chars().count();
+        if args.protect_spans {
+            if predict_protected(
+                &predictor,
+                &pre_filters,
+                &post_filters,
+                &original_filters,
+                args.predict_tags,
+                line,
+                &mut s,
+                &mut s_orig,
+            )? {
+                writer.write_sentence(&columns, &s_orig, &mut out)?;
+            } else {
+                writer.write_empty(&columns, &mut out)?;
             }
             if is_tty {
                 out.flush()?;
             }
+            continue;
         }
-    } else {
-        let mut s_orig = Sentence::default();
-        let lines = io::stdin().lock().lines();
-        for line in lines {
-            let line = line?;
-            let line_preproc = pre_filter.filter(&line);
-            if s.update_raw(line_preproc).is_ok() {
-                predictor.predict(&mut s);
+        let (line_preproc, offsets) = if pre_filters.is_empty() {
+            (line.clone(), None)
+        } else {
+            let (line_preproc, offsets) =
+                timings.measure_filters(|| apply_string_filters_with_offsets(&pre_filters, &line));
+            (line_preproc, Some(offsets))
+        };
+        let aligned = offsets.iter().all(OffsetMap::is_identity);
+        if s.update_raw(line_preproc).is_ok() {
+            if args.profile {
+                predictor.predict_timed(&mut s, &mut timings);
+                timings.measure_filters(|| {
+                    post_filters.iter().for_each(|filter| filter.filter(&mut s));
+                    filter_original_text(&original_filters, &line, aligned, &mut s)
+                })?;
+                if args.predict_tags {
+                    s.fill_tags_timed(&mut timings);
+                }
+            } else {
+                if args.explain {
+                    attributions = predictor.predict_with_attribution(&mut s)?;
+                } else if let Some(n_chars) = args.context_chars {
+                    predictor.predict_with_context(&mut s, &context);
+                    let len = s.boundaries().len() + 1;
+                    context = s
+                        .as_raw_text()
+                        .chars()
+                        .skip(len.saturating_sub(n_chars))
+                        .collect();
+                } else if let Some(temperature) = args.sample_temperature {
+                    predictor.predict_sampled(&mut s, temperature, args.seed.wrapping_add(line_id));
+                } else {
+                    predictor.predict(&mut s);
+                }
                 post_filters.iter().for_each(|filter| filter.filter(&mut s));
+                filter_original_text(&original_filters, &line, aligned, &mut s)?;
                 if args.predict_tags {
                     s.fill_tags();
                 }
+            }
+            let scores_written = args.scores && writer.set_boundary_scores(s.boundary_scores());
+            if let Some(offsets) = offsets.as_ref().filter(|_| !aligned) {
+                // The result is projected onto the input if the normalization moves characters.
+                let s_proj = s.project_boundaries(line.as_str(), offsets)?;
+                writer.write_sentence(&columns, &s_proj, &mut out)?;
+            } else if pre_filters.is_empty() {
+                writer.write_sentence(&columns, &s, &mut out)?;
+            } else {
                 s_orig.update_raw(line)?;
                 s_orig.reset_tags(s.n_tags());
                 s_orig.boundaries_mut().copy_from_slice(s.boundaries());
                 s_orig.tags_mut().clone_from_slice(s.tags());
-                s_orig.write_tokenized_text(&mut buf);
-                out.write_all(buf.as_bytes())?;
-                out.write_all(b"\n")?;
-                if args.scores {
-                    print_scores(&s, &mut out)?;
-                }
-            } else {
-                out.write_all(b"\n")?;
+                writer.write_sentence(&columns, &s_orig, &mut out)?;
             }
-            if args.tag_scores {
-                print_tag_scores(&s, &mut out)?;
+            if args.scores && !scores_written {
+                print_scores(&s, &mut out)?;
             }
-            if is_tty {
-                out.flush()?;
+            if args.explain {
+                print_attributions(&s, &attributions, &mut out)?;
             }
+        } else {
+            writer.write_empty(&columns, &mut out)?;
+            context.clear();
+        }
+        if args.tag_scores {
+            print_tag_scores(&s, &mut out)?;
+        }
+        if is_tty {
+            out.flush()?;
         }
     }
+    out.flush()?;
+    writer.finish();
+    // Words of a disk dictionary are ignored in sentences where reading it failed.
+    if let Some(e) = predictor
+        .disk_dictionaries()
+        .iter()
+        .find_map(DiskDictionary::take_error)
+    {
+        return Err(format!("failed to read a disk dictionary: {e}").into());
+    }
 
     let duration = start.elapsed();
 
     eprintln!("Elapsed: {} [sec]", duration.as_secs_f64());
+    if args.profile {
+        for (stage, elapsed) in [
+            ("filters", timings.filters),
+            ("char scorer", timings.char_scorer),
+            ("type scorer", timings.type_scorer),
+            ("dict scorer", timings.dict_scorer),
+            ("tag fill", timings.tag_fill),
+        ] {
+            eprintln!("  {stage}: {} [sec]", elapsed.as_secs_f64());
+        }
+    }
+    if args.benchmark {
+        #[allow(clippy::cast_precision_loss)]
+        let per_sec = |n: usize| n as f64 / duration.as_secs_f64();
+        eprintln!(
+            "Sentences: {n_sentences} ({:.1} sentences/sec)",
+            per_sec(n_sentences)
+        );
+        eprintln!("Characters: {n_chars} ({:.1} chars/sec)", per_sec(n_chars));
+        if let Some(rss) = peak_rss_kib() {
+            eprintln!("Peak RSS: {rss} [KiB]");
+        } else {
+            eprintln!("Peak RSS: unavailable");
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,502 @@
+//! Output formats of the predict command.
+//!
+//! To add a new format, implement [`OutputWriter`] and register it in [`writer_by_name()`].
+
+use std::io::{self, Write};
+
+use vaporetto::{Sentence, Token};
+
+/// Writer of prediction results.
+pub trait OutputWriter {
+    /// Writes a predicted sentence.
+    ///
+    /// `columns` are the passthrough columns of the line including the last tab, and `s` holds
+    /// the original (not normalized) text.
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()>;
+
+    /// Writes an output for a line that does not contain any text.
+    fn write_empty(&mut self, columns: &str, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        out.write_all(b"\n")
+    }
+
+    /// Passes the boundary scores of the sentence written next and returns whether this writer
+    /// prints them. If false is returned, the scores are printed separately.
+    fn set_boundary_scores(&mut self, _scores: &[i32]) -> bool {
+        false
+    }
+
+    /// Called after all lines are written.
+    fn finish(&mut self) {}
+}
+
+/// Names of the available output formats.
+pub const WRITER_NAMES: &[&str] = &[
+    "tokenized",
+    "kytea",
+    "tsv",
+    "json",
+    "mecab",
+    "plain",
+    "jsonl",
+];
+
+/// Returns the writer of the given format name.
+pub fn writer_by_name(name: &str) -> Option<Box<dyn OutputWriter>> {
+    Some(match name {
+        "tokenized" => Box::new(TokenizedWriter::default()),
+        "kytea" => Box::new(KyteaWriter),
+        "tsv" => Box::new(TsvWriter),
+        "json" => Box::new(JsonWriter),
+        "mecab" => Box::new(MecabWriter),
+        "plain" => Box::new(PlainWriter),
+        "jsonl" => Box::new(JsonlWriter::default()),
+        _ => return None,
+    })
+}
+
+/// Writes a string escaping spaces, backslashes, and slashes.
+fn write_escaped(out: &mut dyn Write, s: &str) -> io::Result<()> {
+    let mut start = 0;
+    for (i, b) in s.bytes().enumerate() {
+        if matches!(b, b' ' | b'\\' | b'/') {
+            out.write_all(&s.as_bytes()[start..i])?;
+            out.write_all(b"\\")?;
+            start = i;
+        }
+    }
+    out.write_all(&s.as_bytes()[start..])
+}
+
+/// Writes a token in the tokenized text format.
+///
+/// If `n_tags` is given, exactly `n_tags` tags are written and missing tags are filled with
+/// `UNK`. Otherwise, tags are written up to the last existing one.
+fn write_token(out: &mut dyn Write, token: &Token, n_tags: Option<usize>) -> io::Result<()> {
+    write_escaped(out, token.surface())?;
+    let tags = token.tags();
+    if let Some(n_tags) = n_tags {
+        for i in 0..n_tags {
+            out.write_all(b"/")?;
+            match tags.get(i) {
+                Some(Some(tag)) => write_escaped(out, tag)?,
+                _ => out.write_all(b"UNK")?,
+            }
+        }
+    } else {
+        for tag in &tags[..tags.iter().rposition(|x| x.is_some()).map_or(0, |x| x + 1)] {
+            out.write_all(b"/")?;
+            if let Some(tag) = tag {
+                write_escaped(out, tag)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Discards all outputs. This is used by --benchmark.
+pub struct NullWriter;
+
+impl OutputWriter for NullWriter {
+    fn write_sentence(
+        &mut self,
+        _columns: &str,
+        _s: &Sentence,
+        _out: &mut dyn Write,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_empty(&mut self, _columns: &str, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Space-separated tokens followed by slash-separated tags. This is the default format.
+#[derive(Default)]
+pub struct TokenizedWriter {
+    buf: String,
+}
+
+impl OutputWriter for TokenizedWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        s.write_tokenized_text(&mut self.buf);
+        out.write_all(self.buf.as_bytes())?;
+        out.write_all(b"\n")
+    }
+}
+
+/// KyTea's full annotation format, where every token has all tags and unknown tags are `UNK`.
+pub struct KyteaWriter;
+
+impl OutputWriter for KyteaWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        for (i, token) in s.iter_tokens().enumerate() {
+            if i != 0 {
+                out.write_all(b" ")?;
+            }
+            write_token(out, &token, Some(s.n_tags()))?;
+        }
+        out.write_all(b"\n")
+    }
+}
+
+/// One token per line with tab-separated tags. Each sentence is followed by an empty line.
+pub struct TsvWriter;
+
+impl OutputWriter for TsvWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for token in s.iter_tokens() {
+            out.write_all(columns.as_bytes())?;
+            out.write_all(token.surface().as_bytes())?;
+            for tag in token.tags() {
+                out.write_all(b"\t")?;
+                if let Some(tag) = tag {
+                    out.write_all(tag.as_bytes())?;
+                }
+            }
+            out.write_all(b"\n")?;
+        }
+        out.write_all(b"\n")
+    }
+
+    fn write_empty(&mut self, _columns: &str, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(b"\n")
+    }
+}
+
+/// MeCab's default format, where each token is written as `surface\tfeature,...` and each
+/// sentence is terminated by `EOS`. Missing tags are written as `*`.
+pub struct MecabWriter;
+
+impl OutputWriter for MecabWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for token in s.iter_tokens() {
+            out.write_all(columns.as_bytes())?;
+            out.write_all(token.surface().as_bytes())?;
+            out.write_all(b"\t")?;
+            let tags = token.tags();
+            if tags.is_empty() {
+                out.write_all(b"*")?;
+            }
+            for (i, tag) in tags.iter().enumerate() {
+                if i != 0 {
+                    out.write_all(b",")?;
+                }
+                out.write_all(tag.as_deref().unwrap_or("*").as_bytes())?;
+            }
+            out.write_all(b"\n")?;
+        }
+        self.write_empty(columns, out)
+    }
+
+    fn write_empty(&mut self, columns: &str, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        out.write_all(b"EOS\n")
+    }
+}
+
+/// Space-separated surfaces without tags or escapes, e.g., for feeding into other tools.
+pub struct PlainWriter;
+
+impl OutputWriter for PlainWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        write_surfaces(out, &s.iter_tokens().collect::<Vec<_>>())?;
+        out.write_all(b"\n")
+    }
+}
+
+/// Writes a JSON string literal.
+pub fn write_json_str(out: &mut dyn Write, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if u32::from(c) < 0x20 => write!(out, "\\u{:04x}", u32::from(c))?,
+            c => write!(out, "{c}")?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+/// One JSON array of tokens per line. Each token is an object with `surface` and `tags`.
+pub struct JsonWriter;
+
+impl OutputWriter for JsonWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        out.write_all(b"[")?;
+        for (i, token) in s.iter_tokens().enumerate() {
+            if i != 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"{\"surface\":")?;
+            write_json_str(out, token.surface())?;
+            out.write_all(b",\"tags\":[")?;
+            for (j, tag) in token.tags().iter().enumerate() {
+                if j != 0 {
+                    out.write_all(b",")?;
+                }
+                if let Some(tag) = tag {
+                    write_json_str(out, tag)?;
+                } else {
+                    out.write_all(b"null")?;
+                }
+            }
+            out.write_all(b"]}")?;
+        }
+        out.write_all(b"]\n")
+    }
+
+    fn write_empty(&mut self, columns: &str, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        out.write_all(b"[]\n")
+    }
+}
+
+/// One JSON object per line containing the text, tokens with byte spans and tags, and, if
+/// `--scores` is given, boundary scores. Passthrough columns are stored in `columns`.
+#[derive(Default)]
+pub struct JsonlWriter {
+    scores: Option<Vec<i32>>,
+}
+
+impl JsonlWriter {
+    fn write_record(
+        &mut self,
+        columns: &str,
+        s: Option<&Sentence>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(b"{")?;
+        if !columns.is_empty() {
+            out.write_all(b"\"columns\":[")?;
+            let columns = columns.strip_suffix('\t').unwrap_or(columns);
+            for (i, column) in columns.split('\t').enumerate() {
+                if i != 0 {
+                    out.write_all(b",")?;
+                }
+                write_json_str(out, column)?;
+            }
+            out.write_all(b"],")?;
+        }
+        out.write_all(b"\"text\":")?;
+        write_json_str(out, s.map_or("", |s| s.as_raw_text()))?;
+        out.write_all(b",\"tokens\":[")?;
+        for (i, token) in s.iter().flat_map(|s| s.iter_tokens()).enumerate() {
+            if i != 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"{\"surface\":")?;
+            write_json_str(out, token.surface())?;
+            write!(
+                out,
+                ",\"start\":{},\"end\":{},\"tags\":[",
+                token.byte_start(),
+                token.byte_end(),
+            )?;
+            for (j, tag) in token.tags().iter().enumerate() {
+                if j != 0 {
+                    out.write_all(b",")?;
+                }
+                if let Some(tag) = tag {
+                    write_json_str(out, tag)?;
+                } else {
+                    out.write_all(b"null")?;
+                }
+            }
+            out.write_all(b"]}")?;
+        }
+        out.write_all(b"]")?;
+        if let Some(scores) = self.scores.take() {
+            out.write_all(b",\"boundary_scores\":[")?;
+            for (i, score) in scores.iter().enumerate() {
+                if i != 0 {
+                    out.write_all(b",")?;
+                }
+                write!(out, "{score}")?;
+            }
+            out.write_all(b"]")?;
+        }
+        out.write_all(b"}\n")
+    }
+}
+
+impl OutputWriter for JsonlWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.write_record(columns, Some(s), out)
+    }
+
+    fn write_empty(&mut self, columns: &str, out: &mut dyn Write) -> io::Result<()> {
+        self.write_record(columns, None, out)
+    }
+
+    fn set_boundary_scores(&mut self, scores: &[i32]) -> bool {
+        self.scores = Some(scores.to_vec());
+        true
+    }
+}
+
+/// Extracts tokens with specified tags and counts them.
+pub struct TagFilterWriter {
+    patterns: Vec<String>,
+    counts: Vec<usize>,
+    n_tokens: usize,
+}
+
+impl TagFilterWriter {
+    pub fn new(patterns: Vec<String>) -> Self {
+        let counts = vec![0; patterns.len()];
+        Self {
+            patterns,
+            counts,
+            n_tokens: 0,
+        }
+    }
+
+    fn is_match(pattern: &str, tag: &str) -> bool {
+        pattern
+            .strip_suffix('*')
+            .map_or(pattern == tag, |prefix| tag.starts_with(prefix))
+    }
+}
+
+impl OutputWriter for TagFilterWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(columns.as_bytes())?;
+        let mut is_first = true;
+        for token in s.iter_tokens() {
+            self.n_tokens += 1;
+            let Some(i) = self.patterns.iter().position(|pattern| {
+                token
+                    .tags()
+                    .iter()
+                    .flatten()
+                    .any(|tag| Self::is_match(pattern, tag))
+            }) else {
+                continue;
+            };
+            self.counts[i] += 1;
+            if !is_first {
+                out.write_all(b" ")?;
+            }
+            is_first = false;
+            write_token(out, &token, None)?;
+        }
+        out.write_all(b"\n")
+    }
+
+    fn finish(&mut self) {
+        eprintln!(
+            "Extracted {} tokens out of {} tokens",
+            self.counts.iter().sum::<usize>(),
+            self.n_tokens,
+        );
+        for (pattern, count) in self.patterns.iter().zip(&self.counts) {
+            eprintln!("{pattern}\t{count}");
+        }
+    }
+}
+
+/// Keyword-in-context lines of tokens whose surface or tag equals one of the queries.
+pub struct KwicWriter {
+    queries: Vec<String>,
+    window: usize,
+}
+
+impl KwicWriter {
+    pub const fn new(queries: Vec<String>, window: usize) -> Self {
+        Self { queries, window }
+    }
+}
+
+fn write_surfaces(out: &mut dyn Write, tokens: &[Token]) -> io::Result<()> {
+    for (i, token) in tokens.iter().enumerate() {
+        if i != 0 {
+            out.write_all(b" ")?;
+        }
+        out.write_all(token.surface().as_bytes())?;
+    }
+    Ok(())
+}
+
+impl OutputWriter for KwicWriter {
+    fn write_sentence(
+        &mut self,
+        columns: &str,
+        s: &Sentence,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let tokens: Vec<_> = s.iter_tokens().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            let is_match = self.queries.iter().any(|query| {
+                token.surface() == query || token.tags().iter().flatten().any(|tag| tag == query)
+            });
+            if !is_match {
+                continue;
+            }
+            out.write_all(columns.as_bytes())?;
+            write_surfaces(out, &tokens[i.saturating_sub(self.window)..i])?;
+            write!(out, "\t{}\t", token.surface())?;
+            write_surfaces(out, &tokens[i + 1..tokens.len().min(i + 1 + self.window)])?;
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn write_empty(&mut self, _columns: &str, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
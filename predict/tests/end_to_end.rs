@@ -0,0 +1,202 @@
+//! End-to-end tests training a tiny model, running the `predict` command, and comparing its
+//! outputs with those of `vaporetto_rules`.
+//!
+//! These tests exercise the seams between crates, i.e., the model file format shared by the
+//! library and the command and the order of filters recorded in the model.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use vaporetto::{Model, Predictor, Sentence, SolverType, Trainer};
+use vaporetto_rules::{
+    pipeline::PipelineBuilder,
+    sentence_filters::NumberConcatenator,
+    string_filters::{KyteaFullwidthFilter, NfkcFilter},
+    SentenceFilter, StringFilter,
+};
+
+const CORPUS: &[&str] = &[
+    "まぁ 社長 は 火星 猫 だ",
+    "まぁ 良い だろう",
+    "社長 は 猫 だ",
+    "火星 に 行く",
+    "猫 は 火星 に いる",
+    "3 月 に 1 , 000 円 を 払う",
+    "12 匹 の 猫 だ",
+    "社長 が 2 匹 の 猫 に 会う",
+    "パン を 食べる",
+    "火星 の パン は 良い",
+];
+
+const INPUTS: &[&str] = &[
+    "社長は火星猫だ",
+    "ﾊﾟﾝを3月に食べる",
+    "猫は１，０００円のパンだ",
+    "火星に12匹の猫がいる",
+];
+
+/// Trains a tiny model with the corpus normalized by `normalize`.
+fn train_model<F>(normalize: F, pipeline: &[&str]) -> Model
+where
+    F: StringFilter<&'static str>,
+{
+    let sentences: Vec<_> = CORPUS
+        .iter()
+        .map(|line| {
+            let normalized = normalize.filter(line);
+            Sentence::from_tokenized(&normalized).unwrap()
+        })
+        .collect();
+    let mut trainer = Trainer::new(3, 3, 3, 3, vec![], 4, &[]).unwrap();
+    for s in &sentences {
+        trainer.add_example(s);
+    }
+    let mut model = trainer
+        .train(0.01, 1.0, SolverType::L1RegularizedL2LossSVC)
+        .unwrap();
+    model.set_pipeline(pipeline.iter().map(|name| name.to_string()).collect());
+    model
+}
+
+/// Writes the model compressed by zstd into a temporary file unique to the test.
+fn write_model(model: &Model, name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "vaporetto-end-to-end-{}-{name}.model.zst",
+        std::process::id(),
+    ));
+    let mut wtr = zstd::Encoder::new(File::create(&path).unwrap(), 19).unwrap();
+    model.write(&mut wtr).unwrap();
+    wtr.finish().unwrap();
+    path
+}
+
+/// Runs the `predict` command with the given arguments and returns the output lines.
+fn run_predict(model_path: &PathBuf, args: &[&str], inputs: &[&str]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_predict"))
+        .arg("--model")
+        .arg(model_path)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for line in inputs {
+        writeln!(stdin, "{line}").unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn tokenized<'a>(s: &Sentence<'a, '_>) -> String {
+    let mut buf = String::new();
+    s.write_tokenized_text(&mut buf);
+    buf
+}
+
+#[test]
+fn test_model_file_roundtrip() {
+    let model = train_model(KyteaFullwidthFilter, &[]);
+    let path = write_model(&model, "roundtrip");
+    let model_read = Model::read(zstd::Decoder::new(File::open(&path).unwrap()).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let predictor = Predictor::new(model, false).unwrap();
+    let predictor_read = Predictor::new(model_read, false).unwrap();
+    for line in INPUTS {
+        let normalized = KyteaFullwidthFilter.filter(line);
+        let mut s = Sentence::from_raw(normalized.as_str()).unwrap();
+        let mut s_read = Sentence::from_raw(normalized.as_str()).unwrap();
+        predictor.predict(&mut s);
+        predictor_read.predict(&mut s_read);
+        assert_eq!(s.boundaries(), s_read.boundaries());
+        assert_eq!(s.boundary_scores(), s_read.boundary_scores());
+    }
+}
+
+#[test]
+fn test_cli_matches_default_normalization() {
+    let model = train_model(KyteaFullwidthFilter, &[]);
+    let path = write_model(&model, "default");
+    let outputs = run_predict(&path, &[], INPUTS);
+    std::fs::remove_file(&path).unwrap();
+
+    let predictor = Predictor::new(model, false).unwrap();
+    assert_eq!(INPUTS.len(), outputs.len());
+    for (line, output) in INPUTS.iter().zip(outputs) {
+        let mut s = Sentence::from_raw(KyteaFullwidthFilter.filter(line)).unwrap();
+        predictor.predict(&mut s);
+        let mut s_orig = Sentence::from_raw(*line).unwrap();
+        s_orig.boundaries_mut().copy_from_slice(s.boundaries());
+        assert_eq!(tokenized(&s_orig), output);
+    }
+}
+
+#[test]
+fn test_cli_matches_model_pipeline() {
+    let pipeline = ["nfkc", "wsconst:D"];
+    let model = train_model(NfkcFilter, &pipeline);
+    let path = write_model(&model, "pipeline");
+    let outputs = run_predict(&path, &[], INPUTS);
+    let outputs_numbers = run_predict(&path, &["--concat-numbers"], INPUTS);
+    std::fs::remove_file(&path).unwrap();
+
+    let tokenizer = PipelineBuilder::new(Predictor::new(model, false).unwrap())
+        .spec(pipeline)
+        .unwrap()
+        .build();
+    assert_eq!(INPUTS.len(), outputs.len());
+    for (line, output) in INPUTS.iter().zip(&outputs) {
+        let s = tokenizer.tokenize_original(line).unwrap();
+        assert_eq!(&tokenized(&s), output);
+    }
+
+    // --concat-numbers matches the input before normalization after the model pipeline.
+    let number_concatenator = NumberConcatenator::new();
+    for (output, output_numbers) in outputs.iter().zip(outputs_numbers) {
+        let mut s = Sentence::from_tokenized(output).unwrap();
+        number_concatenator.filter(&mut s);
+        assert_eq!(tokenized(&s), output_numbers);
+    }
+}
+
+#[test]
+fn test_cli_benchmark() {
+    let model = train_model(KyteaFullwidthFilter, &[]);
+    let path = write_model(&model, "benchmark");
+    let output = Command::new(env!("CARGO_BIN_EXE_predict"))
+        .arg("--model")
+        .arg(&path)
+        .arg("--benchmark")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            let mut stdin = child.stdin.take().unwrap();
+            for line in INPUTS {
+                writeln!(stdin, "{line}")?;
+            }
+            drop(stdin);
+            child.wait_with_output()
+        })
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let n_chars: usize = INPUTS.iter().map(|line| line.chars().count()).sum();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(&format!("Sentences: {} (", INPUTS.len())));
+    assert!(stderr.contains(&format!("Characters: {n_chars} (")));
+    assert!(stderr.contains("Peak RSS: "));
+}
@@ -1,11 +1,20 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::fs::File;
-use std::io::{prelude::*, stderr, BufReader};
+use std::io::{prelude::*, stderr, BufReader, BufWriter};
 use std::path::PathBuf;
+use std::thread;
 
 use clap::{ArgGroup, Parser};
-use vaporetto::{Sentence, SolverType, Trainer};
-use vaporetto_rules::{string_filters::KyteaFullwidthFilter, StringFilter};
+use vaporetto::metrics::{mean_std, Evaluator};
+use vaporetto::{
+    CharTypeRanges, CharacterBoundary, Model, PlattScaling, Predictor, Sentence, SolverType,
+    Trainer, TypeScheme,
+};
+use vaporetto_rules::{
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
+    StringFilter,
+};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -25,9 +34,21 @@ struct Args {
     #[arg(long)]
     dict: Vec<PathBuf>,
 
-    /// The file to write the trained model to
+    /// Each line of --tok and --part corpora starts with a document ID followed by a tab.
     #[arg(long)]
-    model: PathBuf,
+    doc_ids: bool,
+
+    /// Adds words of --tok corpora to the dictionary, and excludes words seen only in the
+    /// document of each sentence from its dictionary features (jackknifing), so that the model
+    /// does not rely on the dictionary too much. Words of --dict are always used.
+    /// Requires --doc-ids.
+    #[arg(long, requires = "doc_ids", conflicts_with = "cross_validation")]
+    leave_one_doc_out: bool,
+
+    /// The file to write the trained model to. In --cross-validation mode, the model is trained
+    /// on the full data after cross-validation only if this option is given.
+    #[arg(long, required_unless_present = "cross_validation")]
+    model: Option<PathBuf>,
 
     /// The character window to use for word segmentation
     #[arg(long, default_value = "3")]
@@ -66,20 +87,446 @@ struct Args {
     #[arg(long)]
     no_norm: bool,
 
+    /// A file defining custom character types.
+    /// Each line contains a type ID (1-255) and a range of code points in hexadecimal,
+    /// e.g., "7 2500 257F". IDs 1-6 are built-in types (Digit, Roman, Hiragana, Katakana, Kanji,
+    /// and Other), which can also be specified by D, R, H, T, K, and O. The end of the range can
+    /// be omitted for a single character, and texts following `#` are ignored. The definition is
+    /// stored in the model.
+    #[arg(long)]
+    char_types: Option<PathBuf>,
+
+    /// Classify characters by Unicode scripts, general categories, or both instead of the
+    /// built-in character types, e.g., for mixed-script technical text. Characters not in the
+    /// built-in table keep the built-in types. The definition is stored in the model.
+    #[arg(
+        long,
+        conflicts_with = "char_types",
+        value_parser = clap::builder::PossibleValuesParser::new(["script", "category", "script-category"]),
+    )]
+    char_type_scheme: Option<String>,
+
+    /// A tokenized corpus used for calibrating boundary scores into probabilities. It should
+    /// not overlap with the training data.
+    #[arg(long)]
+    calibration_corpus: Vec<PathBuf>,
+
     /// The number of workers for zstd (0 means multithreaded will be disabled)
     #[arg(long, default_value = "0")]
     zstd_workers: u32,
+
+    /// Runs k-fold cross-validation with the given number of folds and reports the mean and
+    /// standard deviation of boundary and word F1 scores. The i-th sentence of --tok corpora is
+    /// held out in the (i mod k)-th fold, and --part corpora are always used for training.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(2..))]
+    cross_validation: Option<u16>,
+
+    /// Comma-separated candidates of --charw searched by cross-validation, e.g., `2,3,4`.
+    /// The best combination of all --sweep-* candidates in terms of the mean word F1 score is
+    /// used for training the model. Requires --cross-validation.
+    #[arg(long, value_delimiter = ',', requires = "cross_validation")]
+    sweep_charw: Vec<u8>,
+
+    /// Comma-separated candidates of --charn searched by cross-validation.
+    #[arg(long, value_delimiter = ',', requires = "cross_validation")]
+    sweep_charn: Vec<u8>,
+
+    /// Comma-separated candidates of --typew searched by cross-validation.
+    #[arg(long, value_delimiter = ',', requires = "cross_validation")]
+    sweep_typew: Vec<u8>,
+
+    /// Comma-separated candidates of --typen searched by cross-validation.
+    #[arg(long, value_delimiter = ',', requires = "cross_validation")]
+    sweep_typen: Vec<u8>,
+
+    /// Comma-separated candidates of --cost searched by cross-validation.
+    #[arg(long, value_delimiter = ',', requires = "cross_validation")]
+    sweep_cost: Vec<f64>,
+
+    /// Searches only the given number of combinations randomly sampled from the candidates
+    /// instead of all of them.
+    #[arg(long, requires = "cross_validation")]
+    sweep_random: Option<usize>,
+
+    /// The seed of --sweep-random.
+    #[arg(long, default_value = "0")]
+    sweep_seed: u64,
+
+    /// Writes a TSV report of all searched combinations.
+    #[arg(long, requires = "cross_validation")]
+    sweep_report: Option<PathBuf>,
+}
+
+/// Splits a line into the document ID and the rest if `doc_ids` is true.
+fn split_doc_id(line: &str, doc_ids: bool) -> Result<(Option<&str>, &str), String> {
+    if !doc_ids {
+        return Ok((None, line));
+    }
+    line.split_once('\t')
+        .map(|(doc_id, line)| (Some(doc_id), line))
+        .ok_or_else(|| format!("the line does not have a document ID: {line}"))
+}
+
+/// Fits the calibration of boundary scores on tokenized corpora.
+fn fit_calibration(
+    model: &Model,
+    paths: &[PathBuf],
+    no_norm: bool,
+) -> Result<PlattScaling, Box<dyn std::error::Error>> {
+    let fullwidth_filter = KyteaFullwidthFilter;
+    let (model, _) = Model::read_slice(&model.to_vec()?)?;
+    let predictor = Predictor::new(model, false)?;
+    let mut scores = vec![];
+    let mut labels = vec![];
+    for path in paths {
+        eprintln!("Loading {path:?} ...");
+        let f = BufReader::new(File::open(path)?);
+        for line in f.lines() {
+            let s = Sentence::from_tokenized(&line?)?;
+            let text = if no_norm {
+                s.as_raw_text().to_string()
+            } else {
+                fullwidth_filter.filter(s.as_raw_text())
+            };
+            let mut s_pred = Sentence::from_raw(text)?;
+            predictor.predict(&mut s_pred);
+            scores.extend_from_slice(s_pred.boundary_scores());
+            labels.extend(
+                s.boundaries()
+                    .iter()
+                    .map(|&b| b == CharacterBoundary::WordBoundary),
+            );
+        }
+    }
+    Ok(PlattScaling::fit(&scores, &labels)?)
+}
+
+/// Hyperparameters searched by the `--sweep-*` options.
+#[derive(Clone, Copy, Debug)]
+struct Hyperparams {
+    charw: u8,
+    charn: u8,
+    typew: u8,
+    typen: u8,
+    cost: f64,
+}
+
+impl fmt::Display for Hyperparams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "--charw {} --charn {} --typew {} --typen {} --cost {}",
+            self.charw, self.charn, self.typew, self.typen, self.cost,
+        )
+    }
+}
+
+impl Hyperparams {
+    /// Returns all combinations of the candidates. Empty candidates are replaced with the values
+    /// given by the non-sweep options.
+    fn grid(args: &Args) -> Vec<Self> {
+        let or_default = |v: &[u8], default| {
+            if v.is_empty() {
+                vec![default]
+            } else {
+                v.to_vec()
+            }
+        };
+        let costs = if args.sweep_cost.is_empty() {
+            vec![args.cost]
+        } else {
+            args.sweep_cost.clone()
+        };
+        let mut grid = vec![];
+        for &charw in &or_default(&args.sweep_charw, args.charw) {
+            for &charn in &or_default(&args.sweep_charn, args.charn) {
+                for &typew in &or_default(&args.sweep_typew, args.typew) {
+                    for &typen in &or_default(&args.sweep_typen, args.typen) {
+                        for &cost in &costs {
+                            grid.push(Self {
+                                charw,
+                                charn,
+                                typew,
+                                typen,
+                                cost,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+}
+
+/// SplitMix64 pseudo random number generator.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+}
+
+/// Extracts features of the sentences and trains a model.
+///
+/// Dictionary words for which `is_excluded` returns `true` with the index of a sentence are not
+/// used as features of the sentence. If `verbose` is false, the progress is not printed.
+#[allow(clippy::too_many_arguments)]
+fn train_model<'a, I>(
+    args: &Args,
+    params: Hyperparams,
+    sents: I,
+    dictionary: &[String],
+    tag_dictionary: &'a [Sentence<'a, 'a>],
+    char_type_ranges: &CharTypeRanges,
+    is_excluded: &(dyn Fn(usize, &str) -> bool + Sync),
+    verbose: bool,
+) -> Result<Model, Box<dyn std::error::Error + Send + Sync>>
+where
+    I: Iterator<Item = &'a Sentence<'a, 'a>>,
+{
+    if verbose {
+        eprintln!("Extracting into features...");
+    }
+    let mut trainer = Trainer::new(
+        params.charw,
+        params.charn,
+        params.typew,
+        params.typen,
+        dictionary.to_vec(),
+        args.dictn,
+        tag_dictionary,
+    )?;
+    for (i, s) in sents.enumerate() {
+        if verbose && i % 10000 == 0 {
+            eprint!("# of features: {}\r", trainer.n_features(),);
+            stderr().flush()?;
+        }
+        trainer.add_example_excluding_words(s, |word| is_excluded(i, word));
+    }
+    if verbose {
+        eprintln!("# of features: {}", trainer.n_features(),);
+        eprintln!("Start training...");
+    }
+    let mut model = trainer.train(args.eps, params.cost, args.solver)?;
+    model.set_normalization(Some(
+        if args.no_norm {
+            NO_NORMALIZATION
+        } else {
+            KyteaFullwidthFilter::NAME
+        }
+        .to_string(),
+    ));
+    model.set_char_type_ranges(char_type_ranges.clone());
+    if verbose {
+        eprintln!("Finish training.");
+    }
+    Ok(model)
+}
+
+/// Means and standard deviations of F1 scores of cross-validation.
+#[derive(Clone, Copy, Debug)]
+struct CvResult {
+    boundary_f1: (f64, f64),
+    word_f1: (f64, f64),
+}
+
+/// Runs k-fold cross-validation and prints F1 scores of each fold. Folds are trained in
+/// parallel.
+#[allow(clippy::too_many_arguments)]
+fn cross_validate<'a>(
+    args: &Args,
+    params: Hyperparams,
+    tok_sents: &'a [Sentence<'a, 'a>],
+    part_sents: &'a [Sentence<'a, 'a>],
+    dictionary: &[String],
+    tag_dictionary: &'a [Sentence<'a, 'a>],
+    char_type_ranges: &CharTypeRanges,
+    n_folds: usize,
+) -> Result<CvResult, Box<dyn std::error::Error>> {
+    if tok_sents.len() < n_folds {
+        return Err(format!(
+            "--cross-validation requires at least {n_folds} sentences in --tok corpora"
+        )
+        .into());
+    }
+    eprintln!("Cross-validating with {params}");
+    let results = thread::scope(|scope| {
+        // Spawns all threads before joining them.
+        #[allow(clippy::needless_collect)]
+        let handles: Vec<_> = (0..n_folds)
+            .map(|fold| {
+                scope.spawn(
+                    move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                        let train_sents = tok_sents
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| i % n_folds != fold)
+                            .map(|(_, s)| s)
+                            .chain(part_sents);
+                        let model = train_model(
+                            args,
+                            params,
+                            train_sents,
+                            dictionary,
+                            tag_dictionary,
+                            char_type_ranges,
+                            &|_, _| false,
+                            false,
+                        )?;
+                        let predictor = Predictor::new(model, false)?;
+                        let mut evaluator = Evaluator::new();
+                        for gold in tok_sents.iter().skip(fold).step_by(n_folds) {
+                            let mut s = Sentence::from_raw(gold.as_raw_text())?;
+                            predictor.predict(&mut s);
+                            evaluator.add(gold, &s)?;
+                        }
+                        Ok(evaluator.total())
+                    },
+                )
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .map_err(|e| e as Box<dyn std::error::Error>)?;
+
+    let mut boundary_f1s = vec![];
+    let mut word_f1s = vec![];
+    for (fold, counts) in results.iter().enumerate() {
+        println!(
+            "Fold {}: boundary F1 = {}, word F1 = {}",
+            fold + 1,
+            counts.boundary_f1(),
+            counts.word_f1(false),
+        );
+        boundary_f1s.push(counts.boundary_f1());
+        word_f1s.push(counts.word_f1(false));
+    }
+    let result = CvResult {
+        boundary_f1: mean_std(&boundary_f1s),
+        word_f1: mean_std(&word_f1s),
+    };
+    println!(
+        "Boundary F1: mean = {}, std = {}",
+        result.boundary_f1.0, result.boundary_f1.1,
+    );
+    println!(
+        "Word F1: mean = {}, std = {}",
+        result.word_f1.0, result.word_f1.1,
+    );
+    Ok(result)
+}
+
+/// Cross-validates hyperparameters of the grid and returns the best one in terms of the mean
+/// word F1 score.
+#[allow(clippy::too_many_arguments)]
+fn sweep<'a>(
+    args: &Args,
+    tok_sents: &'a [Sentence<'a, 'a>],
+    part_sents: &'a [Sentence<'a, 'a>],
+    dictionary: &[String],
+    tag_dictionary: &'a [Sentence<'a, 'a>],
+    char_type_ranges: &CharTypeRanges,
+    n_folds: usize,
+) -> Result<Hyperparams, Box<dyn std::error::Error>> {
+    let mut grid = Hyperparams::grid(args);
+    if let Some(n) = args.sweep_random {
+        // Partial Fisher-Yates shuffle
+        let mut rng = SplitMix64(args.sweep_seed);
+        let n = n.min(grid.len());
+        for i in 0..n {
+            let j = i + rng.below(grid.len() - i);
+            grid.swap(i, j);
+        }
+        grid.truncate(n);
+    }
+    let mut report = vec![];
+    for (i, &params) in grid.iter().enumerate() {
+        println!("[{}/{}] {params}", i + 1, grid.len());
+        let result = cross_validate(
+            args,
+            params,
+            tok_sents,
+            part_sents,
+            dictionary,
+            tag_dictionary,
+            char_type_ranges,
+            n_folds,
+        )?;
+        report.push((params, result));
+    }
+    // The first one is chosen if there are ties.
+    let (best, best_result) = *report
+        .iter()
+        .reduce(|a, b| if b.1.word_f1.0 > a.1.word_f1.0 { b } else { a })
+        .ok_or("no hyperparameters to sweep")?;
+    println!(
+        "Best: {best} (word F1: mean = {}, std = {})",
+        best_result.word_f1.0, best_result.word_f1.1,
+    );
+    if let Some(path) = args.sweep_report.as_ref() {
+        let mut f = BufWriter::new(File::create(path)?);
+        writeln!(
+            f,
+            "charw\tcharn\ttypew\ttypen\tcost\tboundary_f1_mean\tboundary_f1_std\tword_f1_mean\tword_f1_std"
+        )?;
+        for (p, r) in &report {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                p.charw,
+                p.charn,
+                p.typew,
+                p.typen,
+                p.cost,
+                r.boundary_f1.0,
+                r.boundary_f1.1,
+                r.word_f1.0,
+                r.word_f1.1,
+            )?;
+        }
+        f.flush()?;
+    }
+    Ok(best)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     let fullwidth_filter = KyteaFullwidthFilter;
+    let char_type_ranges = if let Some(path) = args.char_types.as_ref() {
+        std::fs::read_to_string(path)?.parse()?
+    } else if let Some(scheme) = args.char_type_scheme.as_deref() {
+        CharTypeRanges::from_scheme(match scheme {
+            "script" => TypeScheme::Script,
+            "category" => TypeScheme::Category,
+            _ => TypeScheme::ScriptCategory,
+        })
+    } else {
+        CharTypeRanges::new()
+    };
 
     eprintln!("Loading dataset...");
     let mut train_sents = vec![];
+    // Document index of each sentence if --doc-ids is specified.
+    let mut sent_docs = vec![];
+    let mut doc_indices = HashMap::new();
 
-    for path in args.tok {
+    for path in &args.tok {
         eprintln!("Loading {path:?} ...");
         let f = File::open(path)?;
         let f = BufReader::new(f);
@@ -88,8 +535,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprint!("# of sentences: {i}\r");
                 stderr().flush()?;
             }
-            let s = Sentence::from_tokenized(&line?)?;
-            let s = if args.no_norm {
+            let line = line?;
+            let (doc_id, line) = split_doc_id(&line, args.doc_ids)?;
+            if let Some(doc_id) = doc_id {
+                let n_docs = doc_indices.len();
+                sent_docs.push(*doc_indices.entry(doc_id.to_string()).or_insert(n_docs));
+            }
+            let s = Sentence::from_tokenized(line)?;
+            let mut s = if args.no_norm {
                 s
             } else {
                 let new_line = fullwidth_filter.filter(s.as_raw_text());
@@ -99,11 +552,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 new_s.tags_mut().clone_from_slice(s.tags());
                 new_s
             };
+            s.apply_char_type_ranges(&char_type_ranges);
             train_sents.push(s);
         }
         eprintln!("# of sentences: {}", train_sents.len());
     }
-    for path in args.part {
+    let n_tok_sents = train_sents.len();
+    for path in &args.part {
         eprintln!("Loading {path:?} ...");
         let f = File::open(path)?;
         let f = BufReader::new(f);
@@ -112,8 +567,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprint!("# of sentences: {i}\r");
                 stderr().flush()?;
             }
-            let s = Sentence::from_partial_annotation(&line?)?;
-            let s = if args.no_norm {
+            let line = line?;
+            let (doc_id, line) = split_doc_id(&line, args.doc_ids)?;
+            if let Some(doc_id) = doc_id {
+                let n_docs = doc_indices.len();
+                sent_docs.push(*doc_indices.entry(doc_id.to_string()).or_insert(n_docs));
+            }
+            let s = Sentence::from_partial_annotation(line)?;
+            let mut s = if args.no_norm {
                 s
             } else {
                 let new_line = fullwidth_filter.filter(s.as_raw_text());
@@ -123,6 +584,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 new_s.tags_mut().clone_from_slice(s.tags());
                 new_s
             };
+            s.apply_char_type_ranges(&char_type_ranges);
             train_sents.push(s);
         }
         eprintln!("# of sentences: {}", train_sents.len());
@@ -130,7 +592,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut tag_dictionary = vec![];
     let mut dictionary = BTreeSet::new();
-    for path in args.dict {
+    for path in &args.dict {
         eprintln!("Loading {path:?} ...");
         let f = File::open(path)?;
         let f = BufReader::new(f);
@@ -140,7 +602,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 stderr().flush()?;
             }
             let s = Sentence::from_tokenized(&line?)?;
-            let s = if args.no_norm {
+            let mut s = if args.no_norm {
                 s
             } else {
                 let new_line = fullwidth_filter.filter(s.as_raw_text());
@@ -150,6 +612,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 new_s.tags_mut().clone_from_slice(s.tags());
                 new_s
             };
+            s.apply_char_type_ranges(&char_type_ranges);
             for token in s.iter_tokens() {
                 dictionary.insert(token.surface().to_string());
             }
@@ -157,35 +620,111 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         eprintln!("# of words: {}", dictionary.len());
     }
-    let dictionary = dictionary.into_iter().collect();
 
-    eprintln!("Extracting into features...");
-    let mut trainer = Trainer::new(
-        args.charw,
-        args.charn,
-        args.typew,
-        args.typen,
-        dictionary,
-        args.dictn,
-        &tag_dictionary,
-    )?;
-    for (i, s) in train_sents.iter().enumerate() {
-        if i % 10000 == 0 {
-            eprint!("# of features: {}\r", trainer.n_features(),);
-            stderr().flush()?;
+    // Words of the corpora seen only in a single document, mapped to the document.
+    let mut only_in_doc = HashMap::new();
+    if args.leave_one_doc_out {
+        let mut word_docs = HashMap::new();
+        for (s, &doc) in train_sents[..n_tok_sents].iter().zip(&sent_docs) {
+            for token in s.iter_tokens() {
+                word_docs
+                    .entry(token.surface().to_string())
+                    .and_modify(|d| {
+                        if *d != Some(doc) {
+                            *d = None;
+                        }
+                    })
+                    .or_insert(Some(doc));
+            }
+        }
+        for (word, doc) in word_docs {
+            if dictionary.insert(word.clone()) {
+                if let Some(doc) = doc {
+                    only_in_doc.insert(word, doc);
+                }
+            }
+        }
+        eprintln!(
+            "# of words including the corpora: {}, seen only in a single document: {}",
+            dictionary.len(),
+            only_in_doc.len(),
+        );
+    }
+    let dictionary: Vec<String> = dictionary.into_iter().collect();
+
+    let mut params = Hyperparams {
+        charw: args.charw,
+        charn: args.charn,
+        typew: args.typew,
+        typen: args.typen,
+        cost: args.cost,
+    };
+    if let Some(n_folds) = args.cross_validation {
+        let (tok_sents, part_sents) = train_sents.split_at(n_tok_sents);
+        if Hyperparams::grid(&args).len() > 1 || args.sweep_random.is_some() {
+            params = sweep(
+                &args,
+                tok_sents,
+                part_sents,
+                &dictionary,
+                &tag_dictionary,
+                &char_type_ranges,
+                usize::from(n_folds),
+            )?;
+        } else {
+            cross_validate(
+                &args,
+                params,
+                tok_sents,
+                part_sents,
+                &dictionary,
+                &tag_dictionary,
+                &char_type_ranges,
+                usize::from(n_folds),
+            )?;
         }
-        trainer.add_example(s);
     }
-    eprintln!("# of features: {}", trainer.n_features(),);
+    let Some(model_path) = args.model.as_ref() else {
+        return Ok(());
+    };
+
+    let mut model = train_model(
+        &args,
+        params,
+        train_sents.iter(),
+        &dictionary,
+        &tag_dictionary,
+        &char_type_ranges,
+        &|i, word| {
+            only_in_doc
+                .get(word)
+                .is_some_and(|&doc| sent_docs.get(i) == Some(&doc))
+        },
+        true,
+    )
+    .map_err(|e| e as Box<dyn std::error::Error>)?;
 
-    eprintln!("Start training...");
-    let model = trainer.train(args.eps, args.cost, args.solver)?;
-    eprintln!("Finish training.");
+    if !args.calibration_corpus.is_empty() {
+        eprintln!("Fitting calibration...");
+        let calibration = fit_calibration(&model, &args.calibration_corpus, args.no_norm)?;
+        eprintln!(
+            "Calibration parameters: a={}, b={}",
+            calibration.a(),
+            calibration.b(),
+        );
+        model.set_calibration(Some(calibration));
+    }
 
-    let mut f = zstd::Encoder::new(File::create(args.model)?, 19)?;
-    f.multithread(args.zstd_workers)?;
-    model.write(&mut f)?;
-    f.finish()?;
+    vaporetto::write_atomically(
+        model_path,
+        |file| -> Result<(), Box<dyn std::error::Error>> {
+            let mut f = zstd::Encoder::new(file, 19)?;
+            f.multithread(args.zstd_workers)?;
+            model.write(&mut f)?;
+            f.finish()?;
+            Ok(())
+        },
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,61 @@
+//! IME-like demonstration of incremental tokenization.
+//!
+//! Each line read from stdin is typed one character at a time. After every keystroke, the
+//! composed text is re-tokenized with the same [`Sentence`] buffer, and the segmentation with
+//! tags and the tag candidates of the token under the cursor are printed.
+//!
+//! ```sh
+//! echo まぁ良いだろう | cargo run --example ime -- resources/model.bin
+//! ```
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+use vaporetto::{Model, Predictor, Sentence};
+
+/// Number of tag candidates printed for each tag level.
+const N_CANDIDATES: usize = 3;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let model_path = env::args()
+        .nth(1)
+        .ok_or("usage: ime <MODEL>, where MODEL is an uncompressed Vaporetto model")?;
+    let model = Model::read(File::open(model_path)?)?;
+    let mut predictor = Predictor::new(model, true)?;
+    predictor.store_tag_scores(true);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut s = Sentence::default();
+    let mut composition = String::new();
+    let mut buf = String::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        composition.clear();
+        for c in line.chars() {
+            composition.push(c);
+            s.update_raw(composition.clone())?;
+            predictor.predict(&mut s);
+            s.fill_tags();
+            s.write_tokenized_text(&mut buf);
+            writeln!(out, "{composition}\t{buf}")?;
+
+            // The last token is still being typed, so its tags are shown as candidates.
+            if let Some(token) = s.iter_tokens().last() {
+                for (level, cands) in token.tag_candidates().iter_mut().enumerate() {
+                    cands.sort_unstable_by_key(|&(_, score)| -i64::from(score));
+                    let cands: Vec<_> = cands
+                        .iter()
+                        .take(N_CANDIDATES)
+                        .map(|(tag, score)| format!("{tag}({score})"))
+                        .collect();
+                    writeln!(out, "\t[{}] {level}: {}", token.surface(), cands.join(" "))?;
+                }
+            }
+        }
+        // Enter commits the composition.
+        writeln!(out)?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,284 @@
+//! Selection of annotation candidates for active learning.
+//!
+//! The predictor is least confident about boundaries whose scores are close to 0, so annotating
+//! the text around them is expected to improve the model the most. [`select_sentences()`] and
+//! [`select_spans()`] predict unlabeled texts and return the sentences or spans containing the
+//! boundaries with the smallest absolute scores, i.e., margins.
+
+use core::ops::Range;
+
+use alloc::collections::BinaryHeap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::predictor::Predictor;
+use crate::sentence::Sentence;
+
+/// A sentence selected as an annotation candidate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SentenceCandidate {
+    /// Index of the text in the input.
+    pub index: usize,
+
+    /// The smallest margin of the boundaries in the sentence.
+    pub margin: u32,
+
+    /// Position of the boundary having the smallest margin, which is between the `boundary`-th
+    /// and the `(boundary + 1)`-th characters.
+    pub boundary: usize,
+}
+
+/// A span selected as an annotation candidate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanCandidate {
+    /// Index of the text in the input.
+    pub index: usize,
+
+    /// Byte range of the span in the text.
+    pub range: Range<usize>,
+
+    /// The smallest margin of the boundaries in the span.
+    pub margin: u32,
+}
+
+/// Keeps the `n` smallest items.
+struct SmallestN<T> {
+    heap: BinaryHeap<T>,
+    n: usize,
+}
+
+impl<T: Ord> SmallestN<T> {
+    fn new(n: usize) -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            n,
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        if self.heap.len() < self.n {
+            self.heap.push(item);
+        } else if self.heap.peek().is_some_and(|max| item < *max) {
+            self.heap.pop();
+            self.heap.push(item);
+        }
+    }
+
+    fn into_sorted_vec(self) -> Vec<T> {
+        self.heap.into_sorted_vec()
+    }
+}
+
+/// Predicts each text and calls `f` with the index, the sentence, and the margins.
+fn predict_margins<I, S, F>(predictor: &Predictor, texts: I, mut f: F)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+    F: FnMut(usize, &Sentence, Vec<u32>),
+{
+    let mut sentence = Sentence::default();
+    for (i, text) in texts.into_iter().enumerate() {
+        // Empty texts have no boundaries.
+        if sentence.update_raw(text.as_ref().to_string()).is_err() {
+            continue;
+        }
+        predictor.predict(&mut sentence);
+        let margins = sentence
+            .boundary_scores()
+            .iter()
+            .map(|s| s.unsigned_abs())
+            .collect();
+        f(i, &sentence, margins);
+    }
+}
+
+/// Returns up to `n` sentences with the smallest margins in ascending order of the margins.
+///
+/// Texts are predicted one by one, so the input can be a large stream. Texts of a single
+/// character are never selected because they have no boundaries.
+///
+#[cfg_attr(
+    feature = "std",
+    doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{active, Model, Predictor};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::new(model, false)?;
+
+let texts = [\"まぁ社長は火星猫だ\", \"火星猫の生態\", \"猫\"];
+let candidates = active::select_sentences(&predictor, &texts, 2);
+assert_eq!(2, candidates.len());
+assert!(candidates[0].margin <= candidates[1].margin);
+# Ok(())
+# }
+```
+"
+)]
+pub fn select_sentences<I, S>(predictor: &Predictor, texts: I, n: usize) -> Vec<SentenceCandidate>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut selected = SmallestN::new(n);
+    predict_margins(predictor, texts, |index, _, margins| {
+        if let Some((boundary, &margin)) = margins
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &margin)| margin)
+        {
+            selected.push((margin, index, boundary));
+        }
+    });
+    selected
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(margin, index, boundary)| SentenceCandidate {
+            index,
+            margin,
+            boundary,
+        })
+        .collect()
+}
+
+/// Returns up to `n` spans around the boundaries with the smallest margins in ascending order
+/// of the margins.
+///
+/// Each span contains `context` characters on each side of the selected boundary, clipped to
+/// the text. Overlapping spans in the same text are merged into one, so fewer than `n` spans
+/// may be returned.
+///
+#[cfg_attr(
+    feature = "std",
+    doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{active, Model, Predictor};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::new(model, false)?;
+
+let texts = [\"まぁ社長は火星猫だ\", \"火星猫の生態\"];
+for candidate in active::select_spans(&predictor, &texts, 3, 2) {
+    println!(\"{}\", &texts[candidate.index][candidate.range]);
+}
+# Ok(())
+# }
+```
+"
+)]
+pub fn select_spans<I, S>(
+    predictor: &Predictor,
+    texts: I,
+    n: usize,
+    context: usize,
+) -> Vec<SpanCandidate>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut selected = SmallestN::new(n);
+    predict_margins(predictor, texts, |index, sentence, margins| {
+        let text = sentence.as_raw_text();
+        let mut char_to_str_pos: Vec<_> = text.char_indices().map(|(i, _)| i).collect();
+        char_to_str_pos.push(text.len());
+        for (boundary, margin) in margins.into_iter().enumerate() {
+            let start = (boundary + 1).saturating_sub(context);
+            let end = (boundary + 1 + context).min(char_to_str_pos.len() - 1);
+            selected.push((margin, index, char_to_str_pos[start], char_to_str_pos[end]));
+        }
+    });
+
+    // Merges overlapping spans.
+    let mut spans = selected.into_sorted_vec();
+    spans.sort_unstable_by_key(|&(_, index, start, _)| (index, start));
+    let mut merged: Vec<SpanCandidate> = vec![];
+    for (margin, index, start, end) in spans {
+        if let Some(last) = merged.last_mut() {
+            if last.index == index && start < last.range.end {
+                last.range.end = last.range.end.max(end);
+                last.margin = last.margin.min(margin);
+                continue;
+            }
+        }
+        merged.push(SpanCandidate {
+            index,
+            range: start..end,
+            margin,
+        });
+    }
+    merged.sort_by_key(|c| (c.margin, c.index, c.range.start));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::model::Model;
+
+    fn predictor() -> Predictor {
+        let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin")).unwrap();
+        Predictor::new(model, false).unwrap()
+    }
+
+    #[test]
+    fn test_select_sentences() {
+        let predictor = predictor();
+        let texts = [
+            "まぁ社長は火星猫だ",
+            "",
+            "猫",
+            "火星猫の生態",
+            "まぁ良いだろう",
+        ];
+        let candidates = select_sentences(&predictor, texts, 2);
+        assert_eq!(2, candidates.len());
+        assert!(candidates[0].margin <= candidates[1].margin);
+
+        // The margin is the smallest absolute score of the sentence.
+        let all = select_sentences(&predictor, texts, 10);
+        assert_eq!(3, all.len());
+        for c in &all {
+            let mut s = Sentence::from_raw(texts[c.index]).unwrap();
+            predictor.predict(&mut s);
+            let min = s.boundary_scores().iter().map(|x| x.unsigned_abs()).min();
+            assert_eq!(Some(c.margin), min);
+            assert_eq!(c.margin, s.boundary_scores()[c.boundary].unsigned_abs());
+        }
+        assert_eq!(all[..2], candidates[..]);
+
+        assert!(select_sentences(&predictor, texts, 0).is_empty());
+    }
+
+    #[test]
+    fn test_select_spans() {
+        let predictor = predictor();
+        let texts = ["まぁ社長は火星猫だ", "火星猫の生態"];
+
+        // All boundaries are selected, so spans cover each text.
+        let candidates = select_spans(&predictor, texts, 100, 1);
+        assert_eq!(2, candidates.len());
+        for c in &candidates {
+            assert_eq!(0..texts[c.index].len(), c.range);
+        }
+
+        let candidates = select_spans(&predictor, texts, 1, 1);
+        assert_eq!(1, candidates.len());
+        assert_eq!(
+            2,
+            texts[candidates[0].index][candidates[0].range.clone()]
+                .chars()
+                .count()
+        );
+    }
+}
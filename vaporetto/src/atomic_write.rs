@@ -0,0 +1,105 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the path of a temporary file placed in the same directory as `path`, so that the file
+/// can be renamed without crossing file systems.
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".tmp{}", std::process::id()));
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Writes a file atomically.
+///
+/// `f` writes data into a temporary file in the same directory as `path`. After `f` succeeds,
+/// the temporary file is flushed to the disk and renamed to `path`. Therefore, `path` holds
+/// either the previous content or the complete new content even if the process is interrupted.
+/// If `f` fails, the temporary file is removed and `path` is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// let path = std::env::temp_dir().join("vaporetto-write-atomically-example.txt");
+/// vaporetto::write_atomically(&path, |f| f.write_all(b"hello"))?;
+/// assert_eq!("hello", std::fs::read_to_string(&path)?);
+/// # std::fs::remove_file(&path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error when `f` fails or the file cannot be written or renamed.
+pub fn write_atomically<P, F, E>(path: P, f: F) -> Result<(), E>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> Result<(), E>,
+    E: From<io::Error>,
+{
+    let path = path.as_ref();
+    let temp_path = temp_path(path)?;
+    // The file is closed before renaming.
+    let result = {
+        let mut file = File::create(&temp_path)?;
+        f(&mut file).and_then(|()| Ok(file.sync_all()?))
+    };
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    // Persists the rename. Directories cannot be opened as files on some platforms, so errors
+    // are ignored.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = std::env::temp_dir().join(format!("vaporetto-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.bin");
+
+        write_atomically(&path, |f| f.write_all(b"first")).unwrap();
+        assert_eq!("first", fs::read_to_string(&path).unwrap());
+
+        let result: io::Result<()> = write_atomically(&path, |f| {
+            f.write_all(b"trunc")?;
+            Err(io::Error::other("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!("first", fs::read_to_string(&path).unwrap());
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+        write_atomically(&path, |f| f.write_all(b"second")).unwrap();
+        assert_eq!("second", fs::read_to_string(&path).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use core::ops::Range;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::dict_model::WordWeightRecord;
+use crate::model::Model;
+use crate::sentence::Sentence;
+use crate::utils::HeapBytes;
+
+/// Kind of a feature contributing to a boundary score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeatureKind {
+    /// Character n-gram.
+    CharNgram,
+
+    /// Character type n-gram.
+    TypeNgram,
+
+    /// Word of the dictionary of the model or the user dictionary.
+    Dictionary,
+}
+
+/// A feature contributing to a boundary score, returned by
+/// [`Predictor::predict_with_attribution()`](crate::Predictor::predict_with_attribution).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attribution {
+    /// Kind of the feature.
+    pub kind: FeatureKind,
+
+    /// Character range of the n-gram or the word in the sentence.
+    pub range: Range<usize>,
+
+    /// Weight added to the boundary.
+    pub weight: i32,
+}
+
+/// N-grams and words of a model kept for attribution.
+pub struct AttributionModel {
+    char_ngrams: HashMap<String, Vec<i32>>,
+    type_ngrams: HashMap<Vec<u8>, Vec<i32>>,
+    dict: HashMap<String, Vec<i32>>,
+    char_window_size: usize,
+    type_window_size: usize,
+    char_ngram_max_len: usize,
+    type_ngram_max_len: usize,
+    dict_word_max_len: usize,
+}
+
+impl HeapBytes for AttributionModel {
+    fn heap_bytes(&self) -> usize {
+        self.char_ngrams.heap_bytes() + self.type_ngrams.heap_bytes() + self.dict.heap_bytes()
+    }
+}
+
+impl AttributionModel {
+    pub fn new(model: &Model) -> Self {
+        let data = &model.0;
+        let char_ngram_max_len = data
+            .char_ngram_model
+            .0
+            .iter()
+            .map(|d| d.ngram.chars().count())
+            .max()
+            .unwrap_or(0);
+        let type_ngram_max_len = data
+            .type_ngram_model
+            .0
+            .iter()
+            .map(|d| d.ngram.len())
+            .max()
+            .unwrap_or(0);
+        let dict_word_max_len = data
+            .dict_model
+            .0
+            .iter()
+            .map(|d| d.word.chars().count())
+            .max()
+            .unwrap_or(0);
+        Self {
+            char_ngrams: data
+                .char_ngram_model
+                .0
+                .iter()
+                .map(|d| (d.ngram.clone(), d.weights.clone()))
+                .collect(),
+            type_ngrams: data
+                .type_ngram_model
+                .0
+                .iter()
+                .map(|d| (d.ngram.clone(), d.weights.clone()))
+                .collect(),
+            dict: data
+                .dict_model
+                .0
+                .iter()
+                .map(|d| (d.word.clone(), d.weights.clone()))
+                .collect(),
+            char_window_size: usize::from(data.char_window_size),
+            type_window_size: usize::from(data.type_window_size),
+            char_ngram_max_len,
+            type_ngram_max_len,
+            dict_word_max_len,
+        }
+    }
+
+    /// Returns features contributing to each boundary of the sentence.
+    ///
+    /// Character types of the sentence must be updated in advance.
+    pub fn attribute(
+        &self,
+        sentence: &Sentence,
+        user_dict: &[WordWeightRecord],
+    ) -> Vec<Vec<Attribution>> {
+        let n_boundaries = sentence.boundaries().len();
+        let mut result = vec![vec![]; n_boundaries];
+        // The `pos`-th weight of a feature ending at `end` is added to the boundary
+        // `end + pos - offset - 1`.
+        let mut add = |kind, range: Range<usize>, weights: &[i32], offset: usize| {
+            for (pos, &weight) in weights.iter().enumerate() {
+                if weight == 0 {
+                    continue;
+                }
+                if let Some(i) = (range.end + pos).checked_sub(offset + 1) {
+                    if let Some(attributions) = result.get_mut(i) {
+                        attributions.push(Attribution {
+                            kind,
+                            range: range.clone(),
+                            weight,
+                        });
+                    }
+                }
+            }
+        };
+
+        let len = sentence.len();
+        for start in 0..len {
+            for end in start + 1..=len.min(start + self.char_ngram_max_len) {
+                if let Some(weights) = self.char_ngrams.get(sentence.text_substring(start, end)) {
+                    add(
+                        FeatureKind::CharNgram,
+                        start..end,
+                        weights,
+                        self.char_window_size,
+                    );
+                }
+            }
+            for end in start + 1..=len.min(start + self.type_ngram_max_len) {
+                if let Some(weights) = self.type_ngrams.get(&sentence.char_types()[start..end]) {
+                    add(
+                        FeatureKind::TypeNgram,
+                        start..end,
+                        weights,
+                        self.type_window_size,
+                    );
+                }
+            }
+            for end in start + 1..=len.min(start + self.dict_word_max_len) {
+                if let Some(weights) = self.dict.get(sentence.text_substring(start, end)) {
+                    add(FeatureKind::Dictionary, start..end, weights, end - start);
+                }
+            }
+        }
+        for record in user_dict {
+            let n = record.word.chars().count();
+            for start in 0..(len + 1).saturating_sub(n) {
+                if sentence.text_substring(start, start + n) == record.word {
+                    add(
+                        FeatureKind::Dictionary,
+                        start..start + n,
+                        &record.weights,
+                        n,
+                    );
+                }
+            }
+        }
+
+        for attributions in &mut result {
+            attributions.sort_by_key(|a| core::cmp::Reverse(a.weight.unsigned_abs()));
+        }
+        result
+    }
+}
@@ -0,0 +1,202 @@
+use bincode::{Decode, Encode};
+
+#[cfg(feature = "std")]
+use crate::errors::{Result, VaporettoError};
+
+/// Sigmoid calibration of boundary scores proposed by Platt (1999).
+///
+/// The probability of a word boundary with score `s` is `1 / (1 + exp(a * s + b))`.
+#[derive(Clone, Copy, Debug, PartialEq, Decode, Encode)]
+pub struct PlattScaling {
+    a: f64,
+    b: f64,
+}
+
+impl PlattScaling {
+    /// Creates a new calibration with the given parameters.
+    pub const fn new(a: f64, b: f64) -> Self {
+        Self { a, b }
+    }
+
+    /// Returns the parameter `a`.
+    pub const fn a(&self) -> f64 {
+        self.a
+    }
+
+    /// Returns the parameter `b`.
+    pub const fn b(&self) -> f64 {
+        self.b
+    }
+
+    /// Returns the probability that the boundary with the given score is a word boundary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::PlattScaling;
+    ///
+    /// let calib = PlattScaling::new(-0.001, 0.0);
+    /// assert_eq!(0.5, calib.probability(0));
+    /// assert!(calib.probability(1000) > 0.7);
+    /// assert!(calib.probability(-1000) < 0.3);
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn probability(&self, score: i32) -> f64 {
+        let f_apb = self.a.mul_add(f64::from(score), self.b);
+        // Avoids overflow of exp().
+        if f_apb >= 0.0 {
+            let e = (-f_apb).exp();
+            e / (1.0 + e)
+        } else {
+            1.0 / (1.0 + f_apb.exp())
+        }
+    }
+
+    /// Fits the parameters to the given scores and labels using Newton's method with backtracking
+    /// line search (Lin et al., 2007).
+    ///
+    /// # Arguments
+    ///
+    /// * `scores` - Boundary scores predicted by the model. The data should not be the one used
+    ///   for training the model because the model overfits the training data.
+    /// * `labels` - `true` if the corresponding boundary is a word boundary.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the lengths of the arguments are different or the
+    /// data is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::PlattScaling;
+    ///
+    /// let scores = [-3000, -2000, -500, 300, 1000, 2500, -100, 200];
+    /// let labels = [false, false, false, true, true, true, true, false];
+    /// let calib = PlattScaling::fit(&scores, &labels).unwrap();
+    ///
+    /// assert!(calib.a() < 0.0);
+    /// assert!(calib.probability(2500) > calib.probability(-2000));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[allow(
+        clippy::imprecise_flops,
+        clippy::suboptimal_flops,
+        clippy::suspicious_operation_groupings,
+        clippy::while_float
+    )]
+    pub fn fit(scores: &[i32], labels: &[bool]) -> Result<Self> {
+        if scores.len() != labels.len() {
+            return Err(VaporettoError::invalid_argument(
+                "labels",
+                "does not match the length of the `scores`",
+            ));
+        }
+        if scores.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "scores",
+                "must not be empty",
+            ));
+        }
+
+        const MAX_ITER: usize = 100;
+        const MIN_STEP: f64 = 1e-10;
+        const SIGMA: f64 = 1e-12;
+        const EPS: f64 = 1e-5;
+
+        let n_pos = labels.iter().filter(|&&l| l).count() as f64;
+        let n_neg = labels.len() as f64 - n_pos;
+        let hi_target = (n_pos + 1.0) / (n_pos + 2.0);
+        let lo_target = 1.0 / (n_neg + 2.0);
+        let data: Vec<(f64, f64)> = scores
+            .iter()
+            .zip(labels)
+            .map(|(&s, &l)| (f64::from(s), if l { hi_target } else { lo_target }))
+            .collect();
+
+        let objective = |a: f64, b: f64| {
+            data.iter()
+                .map(|&(s, t)| {
+                    let f_apb = s * a + b;
+                    if f_apb >= 0.0 {
+                        t * f_apb + (1.0 + (-f_apb).exp()).ln()
+                    } else {
+                        (t - 1.0) * f_apb + (1.0 + f_apb.exp()).ln()
+                    }
+                })
+                .sum::<f64>()
+        };
+
+        let mut a = 0.0;
+        let mut b = ((n_neg + 1.0) / (n_pos + 1.0)).ln();
+        let mut fval = objective(a, b);
+        for _ in 0..MAX_ITER {
+            // Gradient and Hessian (plus a small value to ensure positive definiteness).
+            let (mut h11, mut h22, mut h21, mut g1, mut g2) = (SIGMA, SIGMA, 0.0, 0.0, 0.0);
+            for &(s, t) in &data {
+                let f_apb = s * a + b;
+                let (p, q) = if f_apb >= 0.0 {
+                    let e = (-f_apb).exp();
+                    (e / (1.0 + e), 1.0 / (1.0 + e))
+                } else {
+                    let e = f_apb.exp();
+                    (1.0 / (1.0 + e), e / (1.0 + e))
+                };
+                let d2 = p * q;
+                h11 += s * s * d2;
+                h22 += d2;
+                h21 += s * d2;
+                let d1 = t - p;
+                g1 += s * d1;
+                g2 += d1;
+            }
+            if g1.abs() < EPS && g2.abs() < EPS {
+                break;
+            }
+            let det = h11 * h22 - h21 * h21;
+            let da = -(h22 * g1 - h21 * g2) / det;
+            let db = -(-h21 * g1 + h11 * g2) / det;
+            let gd = g1 * da + g2 * db;
+            let mut step = 1.0;
+            while step >= MIN_STEP {
+                let new_a = a + step * da;
+                let new_b = b + step * db;
+                let new_f = objective(new_a, new_b);
+                if new_f < fval + 0.0001 * step * gd {
+                    a = new_a;
+                    b = new_b;
+                    fval = new_f;
+                    break;
+                }
+                step /= 2.0;
+            }
+            if step < MIN_STEP {
+                break;
+            }
+        }
+        Ok(Self { a, b })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_separable() {
+        let scores: Vec<i32> = (-100..100).map(|i| i * 10).collect();
+        let labels: Vec<bool> = scores.iter().map(|&s| s > 0).collect();
+        let calib = PlattScaling::fit(&scores, &labels).unwrap();
+        assert!(calib.probability(1000) > 0.9);
+        assert!(calib.probability(-1000) < 0.1);
+        assert!((calib.probability(0) - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_fit_invalid() {
+        assert!(PlattScaling::fit(&[1, 2], &[true]).is_err());
+        assert!(PlattScaling::fit(&[], &[]).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Classification of characters by Unicode scripts and general categories.
+
+use alloc::vec::Vec;
+
+/// Unicode script of a character.
+///
+/// Only scripts that frequently appear in Japanese texts are distinguished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Script {
+    /// Characters shared by multiple scripts, e.g., digits, punctuation, and symbols.
+    Common = 0,
+
+    /// Combining marks that inherit the script of the preceding character.
+    Inherited = 1,
+
+    /// Latin script.
+    Latin = 2,
+
+    /// Greek script.
+    Greek = 3,
+
+    /// Cyrillic script.
+    Cyrillic = 4,
+
+    /// Japanese Hiragana.
+    Hiragana = 5,
+
+    /// Japanese Katakana.
+    Katakana = 6,
+
+    /// Han script (Kanji).
+    Han = 7,
+
+    /// Korean Hangul.
+    Hangul = 8,
+}
+
+/// Major class of the Unicode general category of a character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CharCategory {
+    /// Letter (L).
+    Letter = 0,
+
+    /// Mark (M).
+    Mark = 1,
+
+    /// Number (N).
+    Number = 2,
+
+    /// Punctuation (P).
+    Punctuation = 3,
+
+    /// Symbol (S).
+    Symbol = 4,
+
+    /// Separator (Z).
+    Separator = 5,
+
+    /// Other (C), e.g., control and format characters.
+    Other = 6,
+}
+
+/// A pair of a script and a general category.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScriptCategory {
+    /// Script of the character.
+    pub script: Script,
+
+    /// General category of the character.
+    pub category: CharCategory,
+}
+
+impl ScriptCategory {
+    /// Gets the script and the general category of a given character.
+    ///
+    /// The classification is coarse: ASCII, Latin-1, CJK symbols and punctuation, kana, and
+    /// halfwidth and fullwidth forms are classified character by character, and other
+    /// characters are classified by their blocks.
+    ///
+    /// # Returns
+    ///
+    /// The script and the category, or `None` if the character is not in the built-in table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{CharCategory, Script, ScriptCategory};
+    ///
+    /// let t = ScriptCategory::get_type('％').unwrap();
+    /// assert_eq!(Script::Common, t.script);
+    /// assert_eq!(CharCategory::Punctuation, t.category);
+    /// ```
+    pub fn get_type(c: char) -> Option<Self> {
+        let c = u32::from(c);
+        let idx = TABLE.partition_point(|&(start, _, _, _)| start <= c);
+        let &(_, end, script, category) = TABLE.get(idx.checked_sub(1)?)?;
+        (c <= end).then_some(Self { script, category })
+    }
+}
+
+/// Scheme to assign character type IDs by scripts and general categories.
+///
+/// IDs are assigned from 7 so that they do not conflict with [`CharacterType`](crate::CharacterType).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TypeScheme {
+    /// Characters are classified by scripts.
+    Script,
+
+    /// Characters are classified by general categories.
+    Category,
+
+    /// Characters are classified by pairs of scripts and general categories.
+    ScriptCategory,
+}
+
+impl TypeScheme {
+    /// Returns the type ID assigned to the given script and category.
+    pub const fn type_id(self, t: ScriptCategory) -> u8 {
+        let (script, category) = (t.script as u8, t.category as u8);
+        7 + match self {
+            Self::Script => script,
+            Self::Category => category,
+            Self::ScriptCategory => script * 7 + category,
+        }
+    }
+
+    /// Returns sorted non-overlapping ranges of code points and their type IDs, where adjacent
+    /// ranges of the same ID are merged.
+    pub(crate) fn ranges(self) -> impl Iterator<Item = (u32, u32, u8)> {
+        let mut ranges: Vec<(u32, u32, u8)> = vec![];
+        for &(start, end, script, category) in TABLE {
+            let type_id = self.type_id(ScriptCategory { script, category });
+            match ranges.last_mut() {
+                Some(last) if last.1 + 1 == start && last.2 == type_id => last.1 = end,
+                _ => ranges.push((start, end, type_id)),
+            }
+        }
+        ranges.into_iter()
+    }
+}
+
+/// Sorted non-overlapping ranges of code points (inclusive) and their classes.
+#[rustfmt::skip]
+const TABLE: &[(u32, u32, Script, CharCategory)] = &[
+    (0x0000, 0x001F, Script::Common, CharCategory::Other),
+    (0x0020, 0x0020, Script::Common, CharCategory::Separator),
+    (0x0021, 0x0023, Script::Common, CharCategory::Punctuation),
+    (0x0024, 0x0024, Script::Common, CharCategory::Symbol),
+    (0x0025, 0x002A, Script::Common, CharCategory::Punctuation),
+    (0x002B, 0x002B, Script::Common, CharCategory::Symbol),
+    (0x002C, 0x002F, Script::Common, CharCategory::Punctuation),
+    (0x0030, 0x0039, Script::Common, CharCategory::Number),
+    (0x003A, 0x003B, Script::Common, CharCategory::Punctuation),
+    (0x003C, 0x003E, Script::Common, CharCategory::Symbol),
+    (0x003F, 0x0040, Script::Common, CharCategory::Punctuation),
+    (0x0041, 0x005A, Script::Latin, CharCategory::Letter),
+    (0x005B, 0x005D, Script::Common, CharCategory::Punctuation),
+    (0x005E, 0x005E, Script::Common, CharCategory::Symbol),
+    (0x005F, 0x005F, Script::Common, CharCategory::Punctuation),
+    (0x0060, 0x0060, Script::Common, CharCategory::Symbol),
+    (0x0061, 0x007A, Script::Latin, CharCategory::Letter),
+    (0x007B, 0x007B, Script::Common, CharCategory::Punctuation),
+    (0x007C, 0x007C, Script::Common, CharCategory::Symbol),
+    (0x007D, 0x007D, Script::Common, CharCategory::Punctuation),
+    (0x007E, 0x007E, Script::Common, CharCategory::Symbol),
+    (0x007F, 0x009F, Script::Common, CharCategory::Other),
+    (0x00A0, 0x00A0, Script::Common, CharCategory::Separator),
+    (0x00A1, 0x00A1, Script::Common, CharCategory::Punctuation),
+    (0x00A2, 0x00A6, Script::Common, CharCategory::Symbol),
+    (0x00A7, 0x00A7, Script::Common, CharCategory::Punctuation),
+    (0x00A8, 0x00A9, Script::Common, CharCategory::Symbol),
+    (0x00AA, 0x00AA, Script::Latin, CharCategory::Letter),
+    (0x00AB, 0x00AB, Script::Common, CharCategory::Punctuation),
+    (0x00AC, 0x00AC, Script::Common, CharCategory::Symbol),
+    (0x00AD, 0x00AD, Script::Common, CharCategory::Other),
+    (0x00AE, 0x00B1, Script::Common, CharCategory::Symbol),
+    (0x00B2, 0x00B3, Script::Common, CharCategory::Number),
+    (0x00B4, 0x00B4, Script::Common, CharCategory::Symbol),
+    (0x00B5, 0x00B5, Script::Common, CharCategory::Letter),
+    (0x00B6, 0x00B7, Script::Common, CharCategory::Punctuation),
+    (0x00B8, 0x00B8, Script::Common, CharCategory::Symbol),
+    (0x00B9, 0x00B9, Script::Common, CharCategory::Number),
+    (0x00BA, 0x00BA, Script::Latin, CharCategory::Letter),
+    (0x00BB, 0x00BB, Script::Common, CharCategory::Punctuation),
+    (0x00BC, 0x00BE, Script::Common, CharCategory::Number),
+    (0x00BF, 0x00BF, Script::Common, CharCategory::Punctuation),
+    (0x00C0, 0x00D6, Script::Latin, CharCategory::Letter),
+    (0x00D7, 0x00D7, Script::Common, CharCategory::Symbol),
+    (0x00D8, 0x00F6, Script::Latin, CharCategory::Letter),
+    (0x00F7, 0x00F7, Script::Common, CharCategory::Symbol),
+    (0x00F8, 0x02AF, Script::Latin, CharCategory::Letter),
+    (0x02B0, 0x02FF, Script::Common, CharCategory::Letter),
+    (0x0300, 0x036F, Script::Inherited, CharCategory::Mark),
+    (0x0370, 0x03FF, Script::Greek, CharCategory::Letter),
+    (0x0400, 0x0482, Script::Cyrillic, CharCategory::Letter),
+    (0x0483, 0x0489, Script::Cyrillic, CharCategory::Mark),
+    (0x048A, 0x052F, Script::Cyrillic, CharCategory::Letter),
+    (0x1100, 0x11FF, Script::Hangul, CharCategory::Letter),
+    (0x1AB0, 0x1AFF, Script::Inherited, CharCategory::Mark),
+    (0x1DC0, 0x1DFF, Script::Inherited, CharCategory::Mark),
+    (0x1E00, 0x1EFF, Script::Latin, CharCategory::Letter),
+    (0x1F00, 0x1FFF, Script::Greek, CharCategory::Letter),
+    (0x2000, 0x200A, Script::Common, CharCategory::Separator),
+    (0x200B, 0x200F, Script::Common, CharCategory::Other),
+    (0x2010, 0x2027, Script::Common, CharCategory::Punctuation),
+    (0x2028, 0x2029, Script::Common, CharCategory::Separator),
+    (0x202A, 0x202E, Script::Common, CharCategory::Other),
+    (0x202F, 0x202F, Script::Common, CharCategory::Separator),
+    (0x2030, 0x205E, Script::Common, CharCategory::Punctuation),
+    (0x205F, 0x205F, Script::Common, CharCategory::Separator),
+    (0x2060, 0x206F, Script::Common, CharCategory::Other),
+    (0x2070, 0x209F, Script::Common, CharCategory::Number),
+    (0x20A0, 0x20CF, Script::Common, CharCategory::Symbol),
+    (0x20D0, 0x20FF, Script::Inherited, CharCategory::Mark),
+    (0x2100, 0x214F, Script::Common, CharCategory::Symbol),
+    (0x2150, 0x218F, Script::Common, CharCategory::Number),
+    (0x2190, 0x23FF, Script::Common, CharCategory::Symbol),
+    (0x2460, 0x24FF, Script::Common, CharCategory::Number),
+    (0x2500, 0x2BFF, Script::Common, CharCategory::Symbol),
+    (0x2E80, 0x2FDF, Script::Han, CharCategory::Symbol),
+    (0x3000, 0x3000, Script::Common, CharCategory::Separator),
+    (0x3001, 0x3003, Script::Common, CharCategory::Punctuation),
+    (0x3004, 0x3004, Script::Common, CharCategory::Symbol),
+    (0x3005, 0x3005, Script::Han, CharCategory::Letter),
+    (0x3006, 0x3006, Script::Common, CharCategory::Letter),
+    (0x3007, 0x3007, Script::Han, CharCategory::Number),
+    (0x3008, 0x3011, Script::Common, CharCategory::Punctuation),
+    (0x3012, 0x3013, Script::Common, CharCategory::Symbol),
+    (0x3014, 0x301F, Script::Common, CharCategory::Punctuation),
+    (0x3020, 0x3020, Script::Common, CharCategory::Symbol),
+    (0x3021, 0x3029, Script::Han, CharCategory::Number),
+    (0x302A, 0x302F, Script::Inherited, CharCategory::Mark),
+    (0x3030, 0x3030, Script::Common, CharCategory::Punctuation),
+    (0x3031, 0x3035, Script::Common, CharCategory::Letter),
+    (0x3036, 0x3037, Script::Common, CharCategory::Symbol),
+    (0x3038, 0x303A, Script::Han, CharCategory::Number),
+    (0x303B, 0x303B, Script::Han, CharCategory::Letter),
+    (0x303C, 0x303C, Script::Common, CharCategory::Letter),
+    (0x303D, 0x303D, Script::Common, CharCategory::Punctuation),
+    (0x303E, 0x303F, Script::Common, CharCategory::Symbol),
+    (0x3041, 0x3096, Script::Hiragana, CharCategory::Letter),
+    (0x3099, 0x309A, Script::Inherited, CharCategory::Mark),
+    (0x309B, 0x309C, Script::Common, CharCategory::Symbol),
+    (0x309D, 0x309F, Script::Hiragana, CharCategory::Letter),
+    (0x30A0, 0x30A0, Script::Common, CharCategory::Punctuation),
+    (0x30A1, 0x30FA, Script::Katakana, CharCategory::Letter),
+    (0x30FB, 0x30FB, Script::Common, CharCategory::Punctuation),
+    (0x30FC, 0x30FC, Script::Common, CharCategory::Letter),
+    (0x30FD, 0x30FF, Script::Katakana, CharCategory::Letter),
+    (0x3131, 0x318E, Script::Hangul, CharCategory::Letter),
+    (0x31F0, 0x31FF, Script::Katakana, CharCategory::Letter),
+    (0x3200, 0x33FF, Script::Common, CharCategory::Symbol),
+    (0x3400, 0x4DBF, Script::Han, CharCategory::Letter),
+    (0x4E00, 0x9FFF, Script::Han, CharCategory::Letter),
+    (0xAC00, 0xD7A3, Script::Hangul, CharCategory::Letter),
+    (0xF900, 0xFAFF, Script::Han, CharCategory::Letter),
+    (0xFE00, 0xFE0F, Script::Inherited, CharCategory::Mark),
+    (0xFE30, 0xFE4F, Script::Common, CharCategory::Punctuation),
+    (0xFF01, 0xFF03, Script::Common, CharCategory::Punctuation),
+    (0xFF04, 0xFF04, Script::Common, CharCategory::Symbol),
+    (0xFF05, 0xFF0A, Script::Common, CharCategory::Punctuation),
+    (0xFF0B, 0xFF0B, Script::Common, CharCategory::Symbol),
+    (0xFF0C, 0xFF0F, Script::Common, CharCategory::Punctuation),
+    (0xFF10, 0xFF19, Script::Common, CharCategory::Number),
+    (0xFF1A, 0xFF1B, Script::Common, CharCategory::Punctuation),
+    (0xFF1C, 0xFF1E, Script::Common, CharCategory::Symbol),
+    (0xFF1F, 0xFF20, Script::Common, CharCategory::Punctuation),
+    (0xFF21, 0xFF3A, Script::Latin, CharCategory::Letter),
+    (0xFF3B, 0xFF3D, Script::Common, CharCategory::Punctuation),
+    (0xFF3E, 0xFF3E, Script::Common, CharCategory::Symbol),
+    (0xFF3F, 0xFF3F, Script::Common, CharCategory::Punctuation),
+    (0xFF40, 0xFF40, Script::Common, CharCategory::Symbol),
+    (0xFF41, 0xFF5A, Script::Latin, CharCategory::Letter),
+    (0xFF5B, 0xFF5B, Script::Common, CharCategory::Punctuation),
+    (0xFF5C, 0xFF5C, Script::Common, CharCategory::Symbol),
+    (0xFF5D, 0xFF5D, Script::Common, CharCategory::Punctuation),
+    (0xFF5E, 0xFF5E, Script::Common, CharCategory::Symbol),
+    (0xFF5F, 0xFF65, Script::Common, CharCategory::Punctuation),
+    (0xFF66, 0xFF6F, Script::Katakana, CharCategory::Letter),
+    (0xFF70, 0xFF70, Script::Common, CharCategory::Letter),
+    (0xFF71, 0xFF9D, Script::Katakana, CharCategory::Letter),
+    (0xFF9E, 0xFF9F, Script::Common, CharCategory::Letter),
+    (0xFFA0, 0xFFDC, Script::Hangul, CharCategory::Letter),
+    (0xFFE0, 0xFFEE, Script::Common, CharCategory::Symbol),
+    (0x1F000, 0x1FAFF, Script::Common, CharCategory::Symbol),
+    (0x20000, 0x2FA1F, Script::Han, CharCategory::Letter),
+    (0x30000, 0x3134F, Script::Han, CharCategory::Letter),
+    (0xE0100, 0xE01EF, Script::Inherited, CharCategory::Mark),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_sorted() {
+        for w in TABLE.windows(2) {
+            assert!(w[0].0 <= w[0].1);
+            assert!(w[0].1 < w[1].0);
+        }
+    }
+
+    #[test]
+    fn test_get_type() {
+        let get = |c| {
+            let t = ScriptCategory::get_type(c).unwrap();
+            (t.script, t.category)
+        };
+        assert_eq!((Script::Latin, CharCategory::Letter), get('a'));
+        assert_eq!((Script::Latin, CharCategory::Letter), get('Ｚ'));
+        assert_eq!((Script::Common, CharCategory::Number), get('７'));
+        assert_eq!((Script::Common, CharCategory::Symbol), get('+'));
+        assert_eq!((Script::Common, CharCategory::Punctuation), get('_'));
+        assert_eq!((Script::Common, CharCategory::Separator), get('\u{3000}'));
+        assert_eq!((Script::Hiragana, CharCategory::Letter), get('あ'));
+        assert_eq!((Script::Katakana, CharCategory::Letter), get('ｱ'));
+        assert_eq!((Script::Common, CharCategory::Letter), get('ー'));
+        assert_eq!((Script::Han, CharCategory::Letter), get('々'));
+        assert_eq!((Script::Han, CharCategory::Letter), get('猫'));
+        assert_eq!((Script::Greek, CharCategory::Letter), get('α'));
+        assert_eq!((Script::Inherited, CharCategory::Mark), get('\u{3099}'));
+        assert_eq!(None, ScriptCategory::get_type('\u{0900}'));
+    }
+
+    #[test]
+    fn test_type_id() {
+        let t = ScriptCategory::get_type('A').unwrap();
+        assert_eq!(9, TypeScheme::Script.type_id(t));
+        assert_eq!(7, TypeScheme::Category.type_id(t));
+        assert_eq!(21, TypeScheme::ScriptCategory.type_id(t));
+        let max = ScriptCategory {
+            script: Script::Hangul,
+            category: CharCategory::Other,
+        };
+        assert_eq!(69, TypeScheme::ScriptCategory.type_id(max));
+    }
+
+    #[test]
+    fn test_ranges_merged() {
+        let ranges: Vec<_> = TypeScheme::Category.ranges().collect();
+        assert!(ranges.len() < TABLE.len());
+        assert!(ranges.contains(&(0x3041, 0x3096, 7)));
+    }
+}
@@ -1,4 +1,5 @@
 mod boundary_scorer;
+mod boundary_scorer_lite;
 
 #[cfg(feature = "tag-prediction")]
 mod boundary_tag_scorer;
@@ -12,15 +13,19 @@ use alloc::vec::Vec;
 
 use bincode::{BorrowDecode, Encode};
 
-use crate::dict_model::DictModel;
-use crate::errors::Result;
+use crate::dict_model::{DictModel, MAX_WORD_LEN};
+use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::NgramModel;
+use crate::predictor::PositionalWeight;
 use crate::sentence::Sentence;
+use crate::utils::ScorerHeapBytes;
 
 #[cfg(feature = "tag-prediction")]
 use crate::ngram_model::TagNgramModel;
 
-use boundary_scorer::CharScorerBoundary;
+pub use boundary_scorer::CharScorerBoundary;
+
+use boundary_scorer_lite::CharScorerBoundaryLite;
 
 #[cfg(feature = "tag-prediction")]
 use boundary_tag_scorer::CharScorerBoundaryTag;
@@ -78,6 +83,40 @@ where
     }
 }
 
+// If the total number of character n-grams and dictionary words is less than or equal to this
+// value, a hash map is used instead of the automaton because the construction of the automaton
+// dominates the runtime for such tiny models.
+const LITE_MAX_NGRAMS: usize = 2048;
+
+// The hash map is looked up once for each length at each position, so it is also limited to
+// models whose n-grams and words are shorter than or equal to this value.
+const LITE_MAX_LEN: usize = 8;
+
+/// Merges weights of character n-grams and dictionary words so that the weight of each n-gram
+/// includes the weights of its suffixes.
+fn merge_weights(
+    ngram_model: NgramModel<String>,
+    dict_model: DictModel,
+    window_size: u8,
+) -> Result<Vec<(String, PositionalWeight<Vec<i32>>)>> {
+    let mut merger = CharWeightMerger::default();
+    for d in ngram_model.0 {
+        let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
+        merger.add(d.ngram, weight);
+    }
+    for d in dict_model.0 {
+        let word_len = d.word.chars().count();
+        let word_len = i16::try_from(word_len).map_err(|_| {
+            VaporettoError::invalid_model(format!(
+                "words must be shorter than or equal to {MAX_WORD_LEN} characters"
+            ))
+        })?;
+        let weight = PositionalWeight::new(-word_len, d.weights);
+        merger.add(d.word, weight);
+    }
+    Ok(merger.merge())
+}
+
 /// WARNING: Decoding is inherently unsafe. Do not publish this struct outside this
 /// crate.
 #[derive(BorrowDecode, Encode)]
@@ -86,6 +125,8 @@ pub enum CharScorer {
 
     #[cfg(feature = "tag-prediction")]
     BoundaryTag(CharScorerBoundaryTag),
+
+    BoundaryLite(CharScorerBoundaryLite),
 }
 
 impl CharScorer {
@@ -100,39 +141,71 @@ impl CharScorer {
         }
 
         #[cfg(feature = "tag-prediction")]
-        if tag_ngram_model.is_empty() {
-            Ok(Some(Self::Boundary(CharScorerBoundary::new(
+        if !tag_ngram_model.is_empty() {
+            return Ok(Some(Self::BoundaryTag(CharScorerBoundaryTag::new(
+                ngram_model,
+                dict_model,
+                window_size,
+                tag_ngram_model,
+            )?)));
+        }
+
+        if ngram_model.0.len() + dict_model.0.len() <= LITE_MAX_NGRAMS
+            && ngram_model
+                .0
+                .iter()
+                .map(|d| &d.ngram)
+                .chain(dict_model.0.iter().map(|d| &d.word))
+                .all(|ngram| ngram.chars().count() <= LITE_MAX_LEN)
+        {
+            Ok(Some(Self::BoundaryLite(CharScorerBoundaryLite::new(
                 ngram_model,
                 dict_model,
                 window_size,
             )?)))
         } else {
-            Ok(Some(Self::BoundaryTag(CharScorerBoundaryTag::new(
+            Ok(Some(Self::Boundary(CharScorerBoundary::new(
                 ngram_model,
                 dict_model,
                 window_size,
-                tag_ngram_model,
             )?)))
         }
+    }
+
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        match self {
+            Self::Boundary(scorer) => scorer.heap_bytes(),
+            Self::BoundaryLite(scorer) => scorer.heap_bytes(),
 
-        #[cfg(not(feature = "tag-prediction"))]
-        Ok(Some(Self::Boundary(CharScorerBoundary::new(
-            ngram_model,
-            dict_model,
-            window_size,
-        )?)))
+            #[cfg(feature = "tag-prediction")]
+            Self::BoundaryTag(scorer) => scorer.heap_bytes(),
+        }
     }
 
     #[inline]
     pub fn add_scores(&self, sentence: &mut Sentence) {
         match self {
             Self::Boundary(scorer) => scorer.add_scores(sentence),
+            Self::BoundaryLite(scorer) => scorer.add_scores(sentence),
 
             #[cfg(feature = "tag-prediction")]
             Self::BoundaryTag(scorer) => scorer.add_scores(sentence),
         }
     }
 
+    /// Sets `covered[i]` to `true` if the `i`-th character is a part of any n-gram or word
+    /// in the model.
+    #[inline]
+    pub fn mark_covered_chars(&self, sentence: &Sentence, covered: &mut [bool]) {
+        match self {
+            Self::Boundary(scorer) => scorer.mark_covered_chars(sentence, covered),
+            Self::BoundaryLite(scorer) => scorer.mark_covered_chars(sentence, covered),
+
+            #[cfg(feature = "tag-prediction")]
+            Self::BoundaryTag(scorer) => scorer.mark_covered_chars(sentence, covered),
+        }
+    }
+
     /// # Satety
     ///
     /// `token_id` must be smaller than `scorer.tag_weight.len()`.
@@ -147,7 +220,7 @@ impl CharScorer {
         scores: &mut [i32],
     ) {
         match self {
-            Self::Boundary(_) => panic!("unsupported"),
+            Self::Boundary(_) | Self::BoundaryLite(_) => panic!("unsupported"),
             Self::BoundaryTag(scorer) => scorer.add_tag_scores(token_id, pos, sentence, scores),
         }
     }
@@ -400,6 +473,252 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_scores_lite_1() {
+        // input:  我  ら  は  全  世  界  の  国  民
+        // n-grams:
+        //   我ら:    3   4   5
+        //   全世界:          6   7   8   9
+        //   国民:                       10  11  12
+        //   世界:           15  16  17  18  19
+        //   界:             20  21  22  23  24  25
+        // dict:
+        //   全世界:         26  27  28  29
+        //   世界:               30  31  32
+        //   世:                 33  34
+        let scorer = CharScorerBoundaryLite::new(
+            NgramModel(vec![
+                NgramData {
+                    ngram: "我ら".into(),
+                    weights: vec![1, 2, 3, 4, 5],
+                },
+                NgramData {
+                    ngram: "全世界".into(),
+                    weights: vec![6, 7, 8, 9],
+                },
+                NgramData {
+                    ngram: "国民".into(),
+                    weights: vec![10, 11, 12, 13, 14],
+                },
+                NgramData {
+                    ngram: "世界".into(),
+                    weights: vec![15, 16, 17, 18, 19],
+                },
+                NgramData {
+                    ngram: "界".into(),
+                    weights: vec![20, 21, 22, 23, 24, 25],
+                },
+            ]),
+            DictModel(vec![
+                WordWeightRecord {
+                    word: "全世界".into(),
+                    weights: vec![26, 27, 28, 29],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世界".into(),
+                    weights: vec![30, 31, 32],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世".into(),
+                    weights: vec![33, 34],
+                    comment: "".into(),
+                },
+            ]),
+            3,
+        )
+        .unwrap();
+        let mut sentence = Sentence::from_raw("我らは全世界の国民").unwrap();
+        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
+        sentence.boundary_scores.clear();
+        sentence
+            .boundary_scores
+            .resize(sentence.score_padding * 2 + sentence.len() - 1, 1);
+        scorer.add_scores(&mut sentence);
+        assert_eq!(
+            &[4, 5, 73, 135, 141, 122, 55, 38],
+            sentence.boundary_scores(),
+        );
+    }
+
+    #[test]
+    fn test_add_scores_lite_2() {
+        // input:  我  ら  は  全  世  界  の  国  民
+        // n-grams:
+        //   我ら:    2   3
+        //   全世界:              4   5
+        //   国民:                            6   7
+        //   世界:                9  10  11
+        //   界:                 12  13  14  15
+        // dict:
+        //   全世界:         16  17  18  19
+        //   世界:               20  21  22
+        //   世:                 23  24
+        let scorer = CharScorerBoundaryLite::new(
+            NgramModel(vec![
+                NgramData {
+                    ngram: "我ら".into(),
+                    weights: vec![1, 2, 3],
+                },
+                NgramData {
+                    ngram: "全世界".into(),
+                    weights: vec![4, 5],
+                },
+                NgramData {
+                    ngram: "国民".into(),
+                    weights: vec![6, 7, 8],
+                },
+                NgramData {
+                    ngram: "世界".into(),
+                    weights: vec![9, 10, 11],
+                },
+                NgramData {
+                    ngram: "界".into(),
+                    weights: vec![12, 13, 14, 15],
+                },
+            ]),
+            DictModel(vec![
+                WordWeightRecord {
+                    word: "全世界".into(),
+                    weights: vec![16, 17, 18, 19],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世界".into(),
+                    weights: vec![20, 21, 22],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世".into(),
+                    weights: vec![23, 24],
+                    comment: "".into(),
+                },
+            ]),
+            2,
+        )
+        .unwrap();
+        let mut sentence = Sentence::from_raw("我らは全世界の国民").unwrap();
+        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
+        sentence.boundary_scores.clear();
+        sentence
+            .boundary_scores
+            .resize(sentence.score_padding * 2 + sentence.len() - 1, 2);
+        scorer.add_scores(&mut sentence);
+        assert_eq!(&[4, 5, 18, 87, 93, 68, 23, 9], sentence.boundary_scores(),);
+    }
+
+    #[test]
+    fn test_add_scores_lite_3() {
+        // input:  我  ら  は  全  世  界  の  国  民
+        // n-grams:
+        //   我ら:    3   4   5
+        //   全世界:          6   7   8   9
+        //   国民:                       10  11  12
+        //   世界:           15  16  17  18  19
+        //   界:             20  21  22  23  24  25
+        // dict:
+        //   全世界:         26  27  28  29
+        //   世界:               30  31  32
+        //   世:                 33  34
+        //   世界の国民:         35  36  37  38  39
+        //   は全世界:   41  42  43  44  45
+        let scorer = CharScorerBoundaryLite::new(
+            NgramModel(vec![
+                NgramData {
+                    ngram: "我ら".into(),
+                    weights: vec![1, 2, 3, 4, 5],
+                },
+                NgramData {
+                    ngram: "全世界".into(),
+                    weights: vec![6, 7, 8, 9],
+                },
+                NgramData {
+                    ngram: "国民".into(),
+                    weights: vec![10, 11, 12, 13, 14],
+                },
+                NgramData {
+                    ngram: "世界".into(),
+                    weights: vec![15, 16, 17, 18, 19],
+                },
+                NgramData {
+                    ngram: "界".into(),
+                    weights: vec![20, 21, 22, 23, 24, 25],
+                },
+            ]),
+            DictModel(vec![
+                WordWeightRecord {
+                    word: "全世界".into(),
+                    weights: vec![26, 27, 28, 29],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世界".into(),
+                    weights: vec![30, 31, 32],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世".into(),
+                    weights: vec![33, 34],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "世界の国民".into(),
+                    weights: vec![35, 36, 37, 38, 39, 40],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "は全世界".into(),
+                    weights: vec![41, 42, 43, 44, 45],
+                    comment: "".into(),
+                },
+            ]),
+            3,
+        )
+        .unwrap();
+        let mut sentence = Sentence::from_raw("我らは全世界の国民").unwrap();
+        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
+        sentence.boundary_scores.clear();
+        sentence
+            .boundary_scores
+            .resize(sentence.score_padding * 2 + sentence.len() - 1, 3);
+        scorer.add_scores(&mut sentence);
+        assert_eq!(
+            &[6, 48, 117, 215, 223, 206, 95, 79],
+            sentence.boundary_scores(),
+        );
+    }
+
+    #[test]
+    fn test_select_lite_scorer() {
+        let create_scorer = |word: &str| {
+            CharScorer::new(
+                NgramModel(vec![NgramData {
+                    ngram: "我ら".into(),
+                    weights: vec![1, 2, 3, 4, 5],
+                }]),
+                DictModel(vec![WordWeightRecord {
+                    word: word.into(),
+                    weights: vec![1; word.chars().count() + 1],
+                    comment: "".into(),
+                }]),
+                2,
+                #[cfg(feature = "tag-prediction")]
+                vec![],
+            )
+            .unwrap()
+            .unwrap()
+        };
+        assert!(matches!(
+            create_scorer("全世界"),
+            CharScorer::BoundaryLite(_)
+        ));
+        assert!(matches!(
+            create_scorer("じゅげむじゅげむごこうのすりきれ"),
+            CharScorer::Boundary(_)
+        ));
+    }
+
     #[cfg(feature = "tag-prediction")]
     #[test]
     fn test_add_scores_with_tags() {
@@ -12,12 +12,13 @@ use daachorse::charwise::CharwiseDoubleArrayAhoCorasick;
 #[cfg(not(feature = "charwise-pma"))]
 use daachorse::DoubleArrayAhoCorasick;
 
-use crate::char_scorer::CharWeightMerger;
+use crate::char_scorer::merge_weights;
 use crate::dict_model::DictModel;
 use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::NgramModel;
 use crate::predictor::{PositionalWeight, WeightVector};
 use crate::sentence::Sentence;
+use crate::utils::{HeapBytes, ScorerHeapBytes};
 
 pub struct CharScorerBoundary {
     #[cfg(not(feature = "charwise-pma"))]
@@ -58,24 +59,9 @@ impl CharScorerBoundary {
         dict_model: DictModel,
         window_size: u8,
     ) -> Result<Self> {
-        let mut merger = CharWeightMerger::default();
-        for d in ngram_model.0 {
-            let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
-            merger.add(d.ngram, weight);
-        }
-        for d in dict_model.0 {
-            let word_len = d.word.chars().count();
-            let word_len = i16::try_from(word_len).map_err(|_| {
-                VaporettoError::invalid_model(
-                    "words must be shorter than or equal to 32767 characters",
-                )
-            })?;
-            let weight = PositionalWeight::new(-word_len, d.weights);
-            merger.add(d.word, weight);
-        }
         let mut ngrams = vec![];
         let mut weights = vec![];
-        for (ngram, weight) in merger.merge() {
+        for (ngram, weight) in merge_weights(ngram_model, dict_model, window_size)? {
             ngrams.push(ngram);
             weights.push(weight.into());
         }
@@ -88,6 +74,14 @@ impl CharScorerBoundary {
         Ok(Self { pma, weights })
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: self.pma.heap_bytes(),
+            weights: self.weights.heap_bytes(),
+            tag_weights: 0,
+        }
+    }
+
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
     pub fn add_scores(&self, sentence: &mut Sentence) {
@@ -111,4 +105,21 @@ impl CharScorerBoundary {
             );
         }
     }
+
+    #[inline]
+    pub fn mark_covered_chars(&self, sentence: &Sentence, covered: &mut [bool]) {
+        #[cfg(not(feature = "charwise-pma"))]
+        let it = self
+            .pma
+            .find_overlapping_no_suffix_iter(sentence.text.as_bytes());
+        #[cfg(feature = "charwise-pma")]
+        let it = self.pma.find_overlapping_no_suffix_iter(&sentence.text);
+        for m in it {
+            debug_assert!(sentence.text.is_char_boundary(m.start()));
+            debug_assert!(sentence.text.is_char_boundary(m.end()));
+            let start = unsafe { sentence.str_to_char_pos(m.start()) };
+            let end = unsafe { sentence.str_to_char_pos(m.end()) };
+            covered[start..end].fill(true);
+        }
+    }
 }
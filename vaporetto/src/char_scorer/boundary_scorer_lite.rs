@@ -0,0 +1,128 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
+use hashbrown::HashMap;
+
+use crate::char_scorer::merge_weights;
+use crate::dict_model::DictModel;
+use crate::errors::{Result, VaporettoError};
+use crate::ngram_model::NgramModel;
+use crate::predictor::{PositionalWeight, WeightVector};
+use crate::sentence::Sentence;
+use crate::utils::{HeapBytes, ScorerHeapBytes};
+
+/// Character scorer using a hash map instead of the automaton.
+///
+/// For each position, this scorer looks up substrings from the longest one, so it is only
+/// efficient for tiny models.
+pub struct CharScorerBoundaryLite {
+    map: HashMap<String, u32>,
+    weights: Vec<PositionalWeight<WeightVector>>,
+    max_len: usize,
+}
+
+impl CharScorerBoundaryLite {
+    fn from_ngrams(
+        ngrams: Vec<String>,
+        weights: Vec<PositionalWeight<WeightVector>>,
+    ) -> Result<Self> {
+        let mut map = HashMap::with_capacity(ngrams.len());
+        let mut max_len = 0;
+        for (i, ngram) in ngrams.into_iter().enumerate() {
+            max_len = max_len.max(ngram.chars().count());
+            let i = u32::try_from(i)
+                .map_err(|_| VaporettoError::invalid_model("too many character n-grams"))?;
+            map.insert(ngram, i);
+        }
+        Ok(Self {
+            map,
+            weights,
+            max_len,
+        })
+    }
+
+    pub fn new(
+        ngram_model: NgramModel<String>,
+        dict_model: DictModel,
+        window_size: u8,
+    ) -> Result<Self> {
+        let mut ngrams = vec![];
+        let mut weights = vec![];
+        for (ngram, weight) in merge_weights(ngram_model, dict_model, window_size)? {
+            ngrams.push(ngram);
+            weights.push(weight.into());
+        }
+        Self::from_ngrams(ngrams, weights)
+    }
+
+    /// Returns the start position and the ID of the longest n-gram ending at `end`.
+    #[inline(always)]
+    fn longest_match(&self, sentence: &Sentence, end: usize) -> Option<(usize, u32)> {
+        (end.saturating_sub(self.max_len)..end).find_map(|start| {
+            self.map
+                .get(sentence.text_substring(start, end))
+                .map(|&i| (start, i))
+        })
+    }
+
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: self.map.heap_bytes(),
+            weights: self.weights.heap_bytes(),
+            tag_weights: 0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    #[inline(always)]
+    pub fn add_scores(&self, sentence: &mut Sentence) {
+        for end in 1..=sentence.len() {
+            if let Some((_, i)) = self.longest_match(sentence, end) {
+                self.weights[usize::try_from(i).unwrap()].add_score(
+                    (end + sentence.score_padding - 1) as isize,
+                    &mut sentence.boundary_scores,
+                );
+            }
+        }
+    }
+
+    #[inline]
+    pub fn mark_covered_chars(&self, sentence: &Sentence, covered: &mut [bool]) {
+        for end in 1..=sentence.len() {
+            if let Some((start, _)) = self.longest_match(sentence, end) {
+                covered[start..end].fill(true);
+            }
+        }
+    }
+}
+
+impl Decode for CharScorerBoundaryLite {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let ngrams: Vec<String> = Decode::decode(decoder)?;
+        let weights: Vec<PositionalWeight<WeightVector>> = Decode::decode(decoder)?;
+        if ngrams.len() != weights.len() {
+            return Err(DecodeError::Other("invalid character n-grams"));
+        }
+        Self::from_ngrams(ngrams, weights)
+            .map_err(|_| DecodeError::Other("invalid character n-grams"))
+    }
+}
+bincode::impl_borrow_decode!(CharScorerBoundaryLite);
+
+impl Encode for CharScorerBoundaryLite {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        let mut ngrams = vec![String::new(); self.map.len()];
+        for (ngram, &i) in &self.map {
+            ngrams[usize::try_from(i).unwrap()].clone_from(ngram);
+        }
+        Encode::encode(&ngrams, encoder)?;
+        Encode::encode(&self.weights, encoder)?;
+        Ok(())
+    }
+}
@@ -13,12 +13,12 @@ use daachorse::charwise::CharwiseDoubleArrayAhoCorasick;
 use daachorse::DoubleArrayAhoCorasick;
 
 use crate::char_scorer::CharWeightMerger;
-use crate::dict_model::DictModel;
+use crate::dict_model::{DictModel, MAX_WORD_LEN};
 use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::{NgramModel, TagNgramModel};
 use crate::predictor::{PositionalWeight, PositionalWeightWithTag, WeightVector};
 use crate::sentence::Sentence;
-use crate::utils::{SerializableHashMap, SplitMix64Builder};
+use crate::utils::{HeapBytes, ScorerHeapBytes, SerializableHashMap, SplitMix64Builder};
 
 pub struct CharScorerBoundaryTag {
     #[cfg(not(feature = "charwise-pma"))]
@@ -73,9 +73,9 @@ impl CharScorerBoundaryTag {
         for d in dict_model.0 {
             let word_len = d.word.chars().count();
             let word_len = i16::try_from(word_len).map_err(|_| {
-                VaporettoError::invalid_model(
-                    "words must be shorter than or equal to 32767 characters",
-                )
+                VaporettoError::invalid_model(format!(
+                    "words must be shorter than or equal to {MAX_WORD_LEN} characters"
+                ))
             })?;
             let weight = PositionalWeightWithTag::with_boundary(-word_len, d.weights);
             merger.add(d.word, weight);
@@ -95,11 +95,12 @@ impl CharScorerBoundaryTag {
         let mut ngrams = vec![];
         let mut weights = vec![];
         for (i, (ngram, weight)) in merger.merge().into_iter().enumerate() {
+            let i = u32::try_from(i)
+                .map_err(|_| VaporettoError::invalid_argument("model", "too many n-grams"))?;
             ngrams.push(ngram);
             weights.push(weight.weight.map(|w| w.into()));
             for ((token_id, rel_position), weight) in weight.tag_info {
-                tag_weight[token_id][usize::from(rel_position)]
-                    .insert(u32::try_from(i).unwrap(), weight.into());
+                tag_weight[token_id][usize::from(rel_position)].insert(i, weight.into());
             }
         }
         #[cfg(not(feature = "charwise-pma"))]
@@ -115,6 +116,14 @@ impl CharScorerBoundaryTag {
         })
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: self.pma.heap_bytes(),
+            weights: self.weights.heap_bytes(),
+            tag_weights: self.tag_weight.heap_bytes(),
+        }
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
@@ -146,6 +155,23 @@ impl CharScorerBoundaryTag {
         }
     }
 
+    #[inline]
+    pub fn mark_covered_chars(&self, sentence: &Sentence, covered: &mut [bool]) {
+        #[cfg(not(feature = "charwise-pma"))]
+        let it = self
+            .pma
+            .find_overlapping_no_suffix_iter(sentence.text.as_bytes());
+        #[cfg(feature = "charwise-pma")]
+        let it = self.pma.find_overlapping_no_suffix_iter(&sentence.text);
+        for m in it {
+            debug_assert!(sentence.text.is_char_boundary(m.start()));
+            debug_assert!(sentence.text.is_char_boundary(m.end()));
+            let start = unsafe { sentence.str_to_char_pos(m.start()) };
+            let end = unsafe { sentence.str_to_char_pos(m.end()) };
+            covered[start..end].fill(true);
+        }
+    }
+
     /// # Satety
     ///
     /// `token_id` must be smaller than `scorer.tag_weight.len()`.
@@ -1,9 +1,17 @@
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 use bincode::{Decode, Encode};
+use hashbrown::HashMap;
 
 use crate::errors::{Result, VaporettoError};
+use crate::utils::HeapBytes;
+
+/// The maximum number of characters in a dictionary word.
+///
+/// Relative positions of weights of a word are stored as `i16`.
+pub const MAX_WORD_LEN: u16 = i16::MAX.unsigned_abs();
 
 #[cfg(feature = "kytea")]
 #[derive(Clone, Copy, Default)]
@@ -21,6 +29,12 @@ pub struct WordWeightRecord {
     pub(crate) comment: String,
 }
 
+impl HeapBytes for WordWeightRecord {
+    fn heap_bytes(&self) -> usize {
+        self.word.heap_bytes() + self.weights.heap_bytes() + self.comment.heap_bytes()
+    }
+}
+
 impl WordWeightRecord {
     /// Creates a new word weight record.
     ///
@@ -64,6 +78,91 @@ impl WordWeightRecord {
     }
 }
 
+/// Iterator over dictionary words narrowed down by patterns.
+///
+/// This iterator is created by [`Model::dictionary_entries()`](crate::Model::dictionary_entries).
+/// Patterns are combined with AND, and the words are yielded in the order of the dictionary.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{ModelBuilder, WordWeightRecord};
+///
+/// let mut builder = ModelBuilder::new(3, 3);
+/// for word in ["火星", "火星人", "火星猫", "土星"] {
+///     let len = word.chars().count();
+///     builder
+///         .add_dict_word(WordWeightRecord::new(word.into(), vec![1; len + 1], "".into()).unwrap())
+///         .unwrap();
+/// }
+/// let model = builder.build();
+///
+/// let mut words: Vec<_> = model
+///     .dictionary_entries()
+///     .with_prefix("火星")
+///     .matching(|word| word.chars().count() == 3)
+///     .map(|record| record.get_word())
+///     .collect();
+/// words.sort_unstable();
+/// assert_eq!(vec!["火星人", "火星猫"], words);
+/// ```
+pub struct DictEntries<'a, 'p> {
+    records: core::slice::Iter<'a, WordWeightRecord>,
+    #[allow(clippy::type_complexity)]
+    patterns: Vec<Box<dyn Fn(&str) -> bool + 'p>>,
+}
+
+impl<'a, 'p> DictEntries<'a, 'p> {
+    fn new(records: &'a [WordWeightRecord]) -> Self {
+        Self {
+            records: records.iter(),
+            patterns: vec![],
+        }
+    }
+
+    /// Keeps words starting with `prefix`.
+    pub fn with_prefix(self, prefix: &'p str) -> Self {
+        self.matching(move |word| word.starts_with(prefix))
+    }
+
+    /// Keeps words ending with `suffix`.
+    pub fn with_suffix(self, suffix: &'p str) -> Self {
+        self.matching(move |word| word.ends_with(suffix))
+    }
+
+    /// Keeps words containing `pattern`.
+    pub fn containing(self, pattern: &'p str) -> Self {
+        self.matching(move |word| word.contains(pattern))
+    }
+
+    /// Keeps words for which `f` returns `true`, e.g., words matching a regular expression.
+    pub fn matching<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'p,
+    {
+        self.patterns.push(Box::new(f));
+        self
+    }
+}
+
+impl<'a> Iterator for DictEntries<'a, '_> {
+    type Item = &'a WordWeightRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let patterns = &self.patterns;
+        self.records
+            .find(|record| patterns.iter().all(|f| f(&record.word)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.patterns.is_empty() {
+            self.records.size_hint()
+        } else {
+            (0, self.records.size_hint().1)
+        }
+    }
+}
+
 #[derive(Debug, Decode, Encode)]
 pub struct DictModel(pub(crate) Vec<WordWeightRecord>);
 
@@ -75,4 +174,164 @@ impl DictModel {
     pub fn dictionary(&self) -> &[WordWeightRecord] {
         &self.0
     }
+
+    pub fn iter(&self) -> DictEntries<'_, 'static> {
+        DictEntries::new(&self.0)
+    }
+
+    /// Limits the number of dictionary words applied at each position.
+    ///
+    /// When a word matches, the weights of all dictionary words that are suffixes of it are also
+    /// added. If there are more than `max_matches` such words, only the ones with the largest
+    /// absolute weights are kept, and the weights of the matched word are adjusted to cancel the
+    /// others. Returns the number of words whose matches were capped.
+    pub fn limit_matches(&mut self, max_matches: usize) -> Result<usize> {
+        if max_matches == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "max_matches",
+                "must be at least 1",
+            ));
+        }
+        let word_ids: HashMap<&str, usize> = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (d.word.as_str(), i))
+            .collect();
+
+        // Words are visited from shorter to longer, so merged weights of suffixes are always
+        // available when a word is processed. If a word is not capped, none of its suffixes are
+        // capped either, so the merged weights are simply the sum of the original weights.
+        let mut order: Vec<usize> = (0..self.0.len()).collect();
+        order.sort_unstable_by_key(|&i| self.0[i].weights.len());
+
+        let mut merged: Vec<Vec<i32>> = vec![vec![]; self.0.len()];
+        let mut new_weights = vec![];
+        for i in order {
+            let word = &self.0[i].word;
+            let mut chain = vec![i];
+            for (j, _) in word.char_indices().skip(1) {
+                if let Some(&k) = word_ids.get(&word[j..]) {
+                    chain.push(k);
+                }
+            }
+            let longest_suffix = chain.get(1).copied();
+            let capped = chain.len() > max_matches;
+            if capped {
+                chain.sort_by_key(|&k| {
+                    core::cmp::Reverse(
+                        self.0[k]
+                            .weights
+                            .iter()
+                            .map(|&w| i64::from(w).abs())
+                            .sum::<i64>(),
+                    )
+                });
+                chain.truncate(max_matches);
+            }
+            let len = self.0[i].weights.len();
+            let mut total = vec![0; len];
+            for &k in &chain {
+                let ws = &self.0[k].weights;
+                for (y, x) in total[len - ws.len()..].iter_mut().zip(ws) {
+                    *y += *x;
+                }
+            }
+            if capped {
+                let mut own = total.clone();
+                if let Some(k) = longest_suffix {
+                    let ws = &merged[k];
+                    for (y, x) in own[len - ws.len()..].iter_mut().zip(ws) {
+                        *y -= *x;
+                    }
+                }
+                new_weights.push((i, own));
+            }
+            merged[i] = total;
+        }
+        drop(word_ids);
+        let n_capped = new_weights.len();
+        for (i, weights) in new_weights {
+            self.0[i].weights = weights;
+        }
+        Ok(n_capped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_dict(weights: [Vec<i32>; 3]) -> DictModel {
+        let [w1, w2, w3] = weights;
+        DictModel(vec![
+            WordWeightRecord {
+                word: "全世界".into(),
+                weights: w1,
+                comment: "".into(),
+            },
+            WordWeightRecord {
+                word: "世界".into(),
+                weights: w2,
+                comment: "".into(),
+            },
+            WordWeightRecord {
+                word: "界".into(),
+                weights: w3,
+                comment: "".into(),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_dict_entries() {
+        let dict = create_test_dict([vec![1, 1, 1, 1], vec![10, 10, 10], vec![3, 3]]);
+        fn words<'a>(entries: DictEntries<'a, '_>) -> Vec<&'a str> {
+            entries.map(|r| r.get_word()).collect()
+        }
+        assert_eq!(vec!["全世界", "世界", "界"], words(dict.iter()));
+        assert_eq!(3, dict.iter().size_hint().0);
+        assert_eq!(vec!["全世界", "世界"], words(dict.iter().containing("世")));
+        assert_eq!(
+            vec!["全世界"],
+            words(dict.iter().with_prefix("全").with_suffix("界"))
+        );
+        assert_eq!(
+            vec!["世界"],
+            words(dict.iter().with_suffix("界").matching(|w| w.len() == 6)),
+        );
+        assert!(words(dict.iter().with_prefix("世").containing("全")).is_empty());
+    }
+
+    #[test]
+    fn test_limit_matches_drop_longest() {
+        let mut dict = create_test_dict([vec![1, 1, 1, 1], vec![10, 10, 10], vec![3, 3]]);
+        assert_eq!(1, dict.limit_matches(2).unwrap());
+        assert_eq!(&[0, 0, 0, 0], dict.0[0].get_weights());
+        assert_eq!(&[10, 10, 10], dict.0[1].get_weights());
+        assert_eq!(&[3, 3], dict.0[2].get_weights());
+    }
+
+    #[test]
+    fn test_limit_matches_drop_suffix() {
+        let mut dict = create_test_dict([vec![20, 20, 20, 20], vec![10, 10, 10], vec![3, 3]]);
+        assert_eq!(1, dict.limit_matches(2).unwrap());
+        // 全世界 + 世界 + 界 = [20, 30, 30, 30] after the adjustment, i.e., 界 is cancelled.
+        assert_eq!(&[20, 20, 17, 17], dict.0[0].get_weights());
+        assert_eq!(&[10, 10, 10], dict.0[1].get_weights());
+        assert_eq!(&[3, 3], dict.0[2].get_weights());
+    }
+
+    #[test]
+    fn test_limit_matches_not_capped() {
+        let mut dict = create_test_dict([vec![1, 1, 1, 1], vec![10, 10, 10], vec![3, 3]]);
+        assert_eq!(0, dict.limit_matches(3).unwrap());
+        assert_eq!(&[1, 1, 1, 1], dict.0[0].get_weights());
+    }
+
+    #[test]
+    fn test_limit_matches_zero() {
+        let mut dict = create_test_dict([vec![1, 1, 1, 1], vec![10, 10, 10], vec![3, 3]]);
+        assert!(dict.limit_matches(0).is_err());
+    }
 }
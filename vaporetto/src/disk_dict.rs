@@ -0,0 +1,644 @@
+//! Dictionary stored on disk.
+//!
+//! Words of this dictionary are kept in a file and loaded block by block on demand, so a lexicon
+//! much larger than memory can be used at the cost of latency. Only the first word of each block
+//! and a limited number of recently used blocks are held in memory.
+//!
+//! The file consists of the following parts, where all integers are in little endian:
+//!
+//! * Magic bytes `VPDDICT1`.
+//! * Blocks of records sorted by words, where each block is the number of records (`u32`)
+//!   followed by records. Each record is the byte length of the word (`u32`), the word, the
+//!   number of weights (`u32`), and the weights (`i32`).
+//! * The index, which is the maximum byte length of words (`u32`), the number of blocks (`u64`),
+//!   and the offset (`u64`), the byte length of the first word (`u32`), and the first word of
+//!   each block.
+//! * The offset of the index (`u64`).
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use hashbrown::HashMap;
+
+use crate::dict_model::WordWeightRecord;
+use crate::errors::{Result, VaporettoError};
+use crate::sentence::Sentence;
+
+const MAGIC: &[u8; 8] = b"VPDDICT1";
+
+trait ReadSeek: Read + Seek + Send {}
+
+impl<T> ReadSeek for T where T: Read + Seek + Send {}
+
+type Block = Vec<(String, Vec<i32>)>;
+
+/// Reads a block at the current position of the reader.
+fn read_block<R: Read + ?Sized>(rdr: &mut R) -> Result<Block> {
+    let n_records = read_u32(rdr)?;
+    let mut block = Vec::with_capacity(usize::try_from(n_records)?);
+    for _ in 0..n_records {
+        let word = read_str(rdr)?;
+        let n_weights = usize::try_from(read_u32(rdr)?)?;
+        if n_weights != word.chars().count() + 1 {
+            return Err(VaporettoError::invalid_model(
+                "the number of weights does not match the length of the word",
+            ));
+        }
+        let mut weights = Vec::with_capacity(n_weights);
+        for _ in 0..n_weights {
+            weights.push(read_i32(rdr)?);
+        }
+        block.push((word, weights));
+    }
+    Ok(block)
+}
+
+/// Locks the mutex. The data is still used after a panic because it is never left in an
+/// inconsistent state.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct BlockCache {
+    // Blocks with the time of the last use.
+    blocks: HashMap<usize, (u64, Arc<Block>)>,
+    // Block indices keyed by the time of the last use. The least recently used block is evicted
+    // first.
+    lru: BTreeMap<u64, usize>,
+    clock: u64,
+    capacity: usize,
+}
+
+impl BlockCache {
+    fn get(&mut self, block_id: usize) -> Option<Arc<Block>> {
+        let (last_used, block) = self.blocks.get_mut(&block_id)?;
+        self.lru.remove(last_used);
+        self.clock += 1;
+        *last_used = self.clock;
+        self.lru.insert(self.clock, block_id);
+        Some(Arc::clone(block))
+    }
+
+    fn insert(&mut self, block_id: usize, block: Arc<Block>) {
+        if let Some((last_used, _)) = self.blocks.insert(block_id, (self.clock + 1, block)) {
+            // Another thread has loaded the same block.
+            self.lru.remove(&last_used);
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, block_id);
+        self.shrink();
+    }
+
+    fn shrink(&mut self) {
+        while self.blocks.len() > self.capacity.max(1) {
+            let Some((_, evicted)) = self.lru.pop_first() else {
+                break;
+            };
+            self.blocks.remove(&evicted);
+        }
+    }
+}
+
+fn read_u32<R: Read + ?Sized>(rdr: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    rdr.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32<R: Read + ?Sized>(rdr: &mut R) -> Result<i32> {
+    let mut buf = [0; 4];
+    rdr.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read + ?Sized>(rdr: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    rdr.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_str<R: Read + ?Sized>(rdr: &mut R) -> Result<String> {
+    let len = usize::try_from(read_u32(rdr)?)?;
+    let mut buf = vec![0; len];
+    rdr.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn write_str<W: Write>(wtr: &mut W, s: &str) -> Result<()> {
+    wtr.write_all(&u32::try_from(s.len())?.to_le_bytes())?;
+    wtr.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+/// Dictionary whose words are loaded from a file on demand.
+///
+/// Words are applied in the same way as words of the user dictionary (see
+/// [`Predictor::add_dict_word()`](crate::Predictor::add_dict_word)). Use
+/// [`Predictor::add_disk_dictionary()`](crate::Predictor::add_disk_dictionary) to register this
+/// dictionary.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use vaporetto::{DiskDictionary, WordWeightRecord};
+///
+/// let records = vec![
+///     WordWeightRecord::new("火星猫".into(), vec![1000, -1000, -1000, 1000], "".into())?,
+///     WordWeightRecord::new("地球".into(), vec![1000, -1000, 1000], "".into())?,
+/// ];
+/// let mut data = vec![];
+/// DiskDictionary::write(records, 1, &mut data)?;
+///
+/// let dict = DiskDictionary::new(Cursor::new(data))?;
+/// assert_eq!(2, dict.n_blocks());
+/// assert_eq!(Some(vec![1000, -1000, 1000]), dict.get("地球")?);
+/// assert_eq!(None, dict.get("火星")?);
+/// # Ok::<(), vaporetto::errors::VaporettoError>(())
+/// ```
+pub struct DiskDictionary {
+    first_words: Vec<String>,
+    offsets: Vec<u64>,
+    max_word_len: usize,
+    // The reader is locked only while a block is loaded, so lookups of cached blocks do not wait
+    // for reading the file.
+    rdr: Mutex<Box<dyn ReadSeek>>,
+    cache: Mutex<BlockCache>,
+    error: Mutex<Option<VaporettoError>>,
+}
+
+impl DiskDictionary {
+    /// The default number of blocks cached in memory.
+    pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+    /// Writes records in the format of this dictionary.
+    ///
+    /// Records are sorted by words, and if words are duplicated, the first one is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - Records of the dictionary.
+    /// * `block_len` - The number of records in each block. A larger value reduces the memory
+    ///   usage of the index but increases the cost of loading a block.
+    /// * `wtr` - A writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when `block_len` is zero or writing fails.
+    pub fn write<W>(mut records: Vec<WordWeightRecord>, block_len: usize, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        if block_len == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "block_len",
+                "must be at least 1",
+            ));
+        }
+        records.sort_by(|a, b| a.word.cmp(&b.word));
+        records.dedup_by(|b, a| a.word == b.word);
+
+        let mut wtr = BufWriter::new(wtr);
+        wtr.write_all(MAGIC)?;
+        let mut offset = u64::try_from(MAGIC.len())?;
+        let mut index = vec![];
+        for block in records.chunks(block_len) {
+            index.push((offset, block[0].word.as_str()));
+            let mut buf = vec![];
+            buf.extend_from_slice(&u32::try_from(block.len())?.to_le_bytes());
+            for record in block {
+                write_str(&mut buf, &record.word)?;
+                buf.extend_from_slice(&u32::try_from(record.weights.len())?.to_le_bytes());
+                for w in &record.weights {
+                    buf.extend_from_slice(&w.to_le_bytes());
+                }
+            }
+            wtr.write_all(&buf)?;
+            offset += u64::try_from(buf.len())?;
+        }
+        let max_word_len = records.iter().map(|r| r.word.len()).max().unwrap_or(0);
+        wtr.write_all(&u32::try_from(max_word_len)?.to_le_bytes())?;
+        wtr.write_all(&u64::try_from(index.len())?.to_le_bytes())?;
+        for (block_offset, first_word) in index {
+            wtr.write_all(&block_offset.to_le_bytes())?;
+            write_str(&mut wtr, first_word)?;
+        }
+        wtr.write_all(&offset.to_le_bytes())?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Creates a dictionary reading blocks from the given reader.
+    ///
+    /// All blocks are read once to check that they are valid, so reading them during prediction
+    /// can only fail if the data is changed later or an I/O error occurs. Only the index is kept
+    /// in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when the data is invalid.
+    pub fn new<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let mut magic = [0; 8];
+        rdr.seek(SeekFrom::Start(0))?;
+        rdr.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(VaporettoError::invalid_model(
+                "the data is not a disk dictionary",
+            ));
+        }
+        rdr.seek(SeekFrom::End(-8))?;
+        let index_offset = read_u64(&mut rdr)?;
+        rdr.seek(SeekFrom::Start(index_offset))?;
+        let max_word_len = usize::try_from(read_u32(&mut rdr)?)?;
+        let n_blocks = usize::try_from(read_u64(&mut rdr)?)?;
+        let mut first_words = Vec::with_capacity(n_blocks);
+        let mut offsets = Vec::with_capacity(n_blocks);
+        for _ in 0..n_blocks {
+            offsets.push(read_u64(&mut rdr)?);
+            first_words.push(read_str(&mut rdr)?);
+        }
+        if first_words.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(VaporettoError::invalid_model("blocks are not sorted"));
+        }
+
+        // Blocks are stored contiguously between the magic bytes and the index.
+        rdr.seek(SeekFrom::Start(u64::try_from(MAGIC.len())?))?;
+        for (i, &offset) in offsets.iter().enumerate() {
+            if rdr.stream_position()? != offset {
+                return Err(VaporettoError::invalid_model("invalid block offset"));
+            }
+            let block = read_block(&mut rdr)?;
+            let next_word = first_words.get(i + 1);
+            if block.first().map(|(w, _)| w) != Some(&first_words[i])
+                || block.windows(2).any(|w| w[0].0 >= w[1].0)
+                || block
+                    .last()
+                    .zip(next_word)
+                    .is_some_and(|((w, _), next)| w >= next)
+            {
+                return Err(VaporettoError::invalid_model("words are not sorted"));
+            }
+            if block
+                .iter()
+                .any(|(w, _)| w.is_empty() || w.len() > max_word_len)
+            {
+                return Err(VaporettoError::invalid_model("invalid word length"));
+            }
+        }
+        if rdr.stream_position()? != index_offset {
+            return Err(VaporettoError::invalid_model("invalid block offset"));
+        }
+
+        Ok(Self {
+            first_words,
+            offsets,
+            max_word_len,
+            rdr: Mutex::new(Box::new(rdr)),
+            cache: Mutex::new(BlockCache {
+                blocks: HashMap::new(),
+                lru: BTreeMap::new(),
+                clock: 0,
+                capacity: Self::DEFAULT_CACHE_CAPACITY,
+            }),
+            error: Mutex::new(None),
+        })
+    }
+
+    /// Opens a dictionary file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when the file cannot be opened or is invalid.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Sets the maximum number of blocks cached in memory. The default is
+    /// [`DiskDictionary::DEFAULT_CACHE_CAPACITY`].
+    ///
+    /// When the cache is full, the least recently used block is evicted.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        let cache = self.cache.get_mut().unwrap_or_else(PoisonError::into_inner);
+        cache.capacity = capacity;
+        cache.shrink();
+    }
+
+    /// Returns the number of blocks.
+    pub fn n_blocks(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns the weights of the given word.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when reading a block fails.
+    pub fn get(&self, word: &str) -> Result<Option<Vec<i32>>> {
+        let mut blocks = HashMap::new();
+        Ok(self
+            .find(&mut blocks, word)?
+            .and_then(|(found, weights)| found.then(|| weights.to_vec())))
+    }
+
+    /// Returns the last error of reading the file during prediction and clears it.
+    ///
+    /// Prediction cannot fail, so if reading a block fails, words of this dictionary are ignored
+    /// in the sentence and the error is kept until this function is called.
+    pub fn take_error(&self) -> Option<VaporettoError> {
+        lock(&self.error).take()
+    }
+
+    /// Returns the block from the cache, or loads it from the file.
+    fn block(&self, block_id: usize) -> Result<Arc<Block>> {
+        let cached = lock(&self.cache).get(block_id);
+        if let Some(block) = cached {
+            return Ok(block);
+        }
+        let block = {
+            let mut rdr = lock(&self.rdr);
+            rdr.seek(SeekFrom::Start(self.offsets[block_id]))?;
+            Arc::new(read_block(&mut *rdr)?)
+        };
+        lock(&self.cache).insert(block_id, Arc::clone(&block));
+        Ok(block)
+    }
+
+    /// Searches the word and returns whether it is found and its weights, or returns `None` if
+    /// no word starts with `word`.
+    ///
+    /// `blocks` holds the blocks used by the caller so that the cache is locked only once for
+    /// each block.
+    fn find<'c>(
+        &self,
+        blocks: &'c mut HashMap<usize, Arc<Block>>,
+        word: &str,
+    ) -> Result<Option<(bool, &'c [i32])>> {
+        let Some(block_id) = self
+            .first_words
+            .partition_point(|w| w.as_str() <= word)
+            .checked_sub(1)
+        else {
+            return Ok(self
+                .first_words
+                .first()
+                .filter(|w| w.starts_with(word))
+                .map(|_| (false, &[][..])));
+        };
+        if !blocks.contains_key(&block_id) {
+            let block = self.block(block_id)?;
+            blocks.insert(block_id, block);
+        }
+        let block = &blocks[&block_id];
+        let i = block.partition_point(|(w, _)| w.as_str() < word);
+        if let Some((w, weights)) = block.get(i) {
+            if w == word {
+                return Ok(Some((true, weights)));
+            }
+            return Ok(w.starts_with(word).then_some((false, &[][..])));
+        }
+        Ok(self
+            .first_words
+            .get(block_id + 1)
+            .filter(|w| w.starts_with(word))
+            .map(|_| (false, &[][..])))
+    }
+
+    /// Calls `f` with the start position and the weights of each word of this dictionary
+    /// appearing in the text.
+    fn for_each_word<F>(&self, text: &str, mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, &[i32]),
+    {
+        let mut blocks = HashMap::new();
+        for (start, (start_byte, _)) in text.char_indices().enumerate() {
+            let rest = &text[start_byte..];
+            let ends = rest.char_indices().skip(1).map(|(i, _)| i);
+            for end_byte in ends.chain([rest.len()]) {
+                if end_byte > self.max_word_len {
+                    break;
+                }
+                match self.find(&mut blocks, &rest[..end_byte])? {
+                    Some((true, weights)) => f(start, weights),
+                    Some((false, _)) => (),
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds weights of words in the sentence to the boundary scores.
+    ///
+    /// If reading the file fails, nothing is added and the error is kept for
+    /// [`DiskDictionary::take_error()`].
+    pub(crate) fn add_scores(&self, sentence: &mut Sentence) {
+        let n_boundaries = sentence.boundaries.len();
+        let mut scores = vec![0; n_boundaries];
+        let result = self.for_each_word(&sentence.text, |start, weights| {
+            for (j, &w) in weights.iter().enumerate() {
+                // The j-th weight is of the boundary before the (start + j)-th character.
+                if let Some(b) = (start + j).checked_sub(1).filter(|&b| b < n_boundaries) {
+                    scores[b] += w;
+                }
+            }
+        });
+        if let Err(e) = result {
+            *lock(&self.error) = Some(e);
+            return;
+        }
+        let padding = sentence.score_padding;
+        for (y, w) in sentence.boundary_scores[padding..padding + n_boundaries]
+            .iter_mut()
+            .zip(scores)
+        {
+            *y += w;
+        }
+    }
+
+    /// Marks characters covered by words of this dictionary.
+    ///
+    /// If reading the file fails, nothing is marked and the error is kept for
+    /// [`DiskDictionary::take_error()`].
+    pub(crate) fn mark_covered_chars(&self, sentence: &Sentence, covered: &mut [bool]) {
+        let mut marked = vec![false; covered.len()];
+        let result = self.for_each_word(&sentence.text, |start, weights| {
+            // Words have one more weight than characters.
+            marked[start..start + weights.len() - 1].fill(true);
+        });
+        if let Err(e) = result {
+            *lock(&self.error) = Some(e);
+            return;
+        }
+        for (c, m) in covered.iter_mut().zip(marked) {
+            *c |= m;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn build(words: &[(&str, Vec<i32>)], block_len: usize) -> DiskDictionary {
+        let records = words
+            .iter()
+            .map(|(w, weights)| WordWeightRecord::new(w.to_string(), weights.clone(), "".into()))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let mut data = vec![];
+        DiskDictionary::write(records, block_len, &mut data).unwrap();
+        DiskDictionary::new(Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn test_get() {
+        let dict = build(
+            &[
+                ("火星猫", vec![1, 2, 3, 4]),
+                ("火星", vec![5, 6, 7]),
+                ("地球", vec![8, 9, 10]),
+                ("火星", vec![0, 0, 0]),
+                ("人", vec![11, 12]),
+            ],
+            2,
+        );
+        assert_eq!(2, dict.n_blocks());
+        assert_eq!(Some(vec![5, 6, 7]), dict.get("火星").unwrap());
+        assert_eq!(Some(vec![1, 2, 3, 4]), dict.get("火星猫").unwrap());
+        assert_eq!(Some(vec![11, 12]), dict.get("人").unwrap());
+        assert_eq!(None, dict.get("火").unwrap());
+        assert_eq!(None, dict.get("猫").unwrap());
+        assert_eq!(None, dict.get("").unwrap());
+    }
+
+    #[test]
+    fn test_cache_capacity() {
+        let mut dict = build(
+            &[
+                ("あ", vec![1, 1]),
+                ("い", vec![2, 2]),
+                ("う", vec![3, 3]),
+                ("え", vec![4, 4]),
+            ],
+            1,
+        );
+        dict.set_cache_capacity(2);
+        for (w, x) in [("あ", 1), ("い", 2), ("う", 3), ("え", 4), ("あ", 1)] {
+            assert_eq!(Some(vec![x, x]), dict.get(w).unwrap());
+            assert!(dict.cache.lock().unwrap().blocks.len() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_lru() {
+        let mut dict = build(
+            &[("あ", vec![1, 1]), ("い", vec![2, 2]), ("う", vec![3, 3])],
+            1,
+        );
+        dict.set_cache_capacity(2);
+        for w in ["あ", "い", "あ", "う"] {
+            dict.get(w).unwrap();
+        }
+        let blocks = &dict.cache.get_mut().unwrap().blocks;
+        assert!(blocks.contains_key(&0));
+        assert!(!blocks.contains_key(&1));
+        assert!(blocks.contains_key(&2));
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(DiskDictionary::new(Cursor::new(b"VPDDICT0".to_vec())).is_err());
+        assert!(DiskDictionary::write(vec![], 0, vec![]).is_err());
+
+        let records = vec![
+            WordWeightRecord::new("火星".into(), vec![1, 2, 3], "".into()).unwrap(),
+            WordWeightRecord::new("猫".into(), vec![4, 5], "".into()).unwrap(),
+        ];
+        let mut data = vec![];
+        DiskDictionary::write(records, 1, &mut data).unwrap();
+        assert!(DiskDictionary::new(Cursor::new(data.clone())).is_ok());
+        // The number of weights of 火星 is broken.
+        let pos = MAGIC.len() + 4 + 4 + "火星".len();
+        data[pos] = 2;
+        assert!(DiskDictionary::new(Cursor::new(data)).is_err());
+    }
+
+    /// Reader failing after `n_reads` calls of `read()`.
+    struct FailingReader {
+        inner: Cursor<Vec<u8>>,
+        n_reads: Arc<AtomicUsize>,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self
+                .n_reads
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_err()
+            {
+                return Err(std::io::Error::other("failed"));
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_read_error() {
+        let records = vec![
+            WordWeightRecord::new("火星".into(), vec![1, 2, 3], "".into()).unwrap(),
+            WordWeightRecord::new("猫".into(), vec![4, 5], "".into()).unwrap(),
+        ];
+        let mut data = vec![];
+        DiskDictionary::write(records, 1, &mut data).unwrap();
+        let n_reads = Arc::new(AtomicUsize::new(usize::MAX));
+        let dict = DiskDictionary::new(FailingReader {
+            inner: Cursor::new(data),
+            n_reads: Arc::clone(&n_reads),
+        })
+        .unwrap();
+        n_reads.store(0, Ordering::Relaxed);
+
+        assert!(dict.get("猫").is_err());
+        let mut s = Sentence::from_raw("火星猫").unwrap();
+        let mut covered = vec![false; 3];
+        dict.mark_covered_chars(&s, &mut covered);
+        assert_eq!(vec![false; 3], covered);
+        assert!(dict.take_error().is_some());
+        assert!(dict.take_error().is_none());
+        s.boundary_scores.resize(s.score_padding * 2 + 2, 0);
+        dict.add_scores(&mut s);
+        assert!(s.boundary_scores.iter().all(|&y| y == 0));
+        assert!(dict.take_error().is_some());
+
+        n_reads.store(usize::MAX, Ordering::Relaxed);
+        dict.mark_covered_chars(&s, &mut covered);
+        assert_eq!(vec![true, true, true], covered);
+        assert!(dict.take_error().is_none());
+    }
+
+    #[test]
+    fn test_empty() {
+        let dict = build(&[], 4);
+        assert_eq!(0, dict.n_blocks());
+        assert_eq!(None, dict.get("火星").unwrap());
+    }
+}
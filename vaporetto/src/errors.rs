@@ -19,6 +19,9 @@ pub enum VaporettoError {
     /// The error variant for [`InvalidArgumentError`].
     InvalidArgument(InvalidArgumentError),
 
+    /// The error variant for [`ModelVersionError`].
+    ModelVersion(ModelVersionError),
+
     /// The error variant for [`FromUtf8Error`](alloc::string::FromUtf8Error).
     UTF8Error(alloc::string::FromUtf8Error),
 
@@ -61,6 +64,7 @@ impl fmt::Display for VaporettoError {
         match self {
             Self::InvalidModel(e) => e.fmt(f),
             Self::InvalidArgument(e) => e.fmt(f),
+            Self::ModelVersion(e) => e.fmt(f),
             Self::UTF8Error(e) => e.fmt(f),
             Self::CastError(e) => e.fmt(f),
             Self::DecodeError(e) => e.fmt(f),
@@ -110,6 +114,63 @@ impl fmt::Display for InvalidArgumentError {
 #[cfg(feature = "std")]
 impl Error for InvalidArgumentError {}
 
+/// Error used when the model requires another version of Vaporetto.
+#[derive(Debug)]
+pub struct ModelVersionError {
+    /// Format version of the model.
+    pub(crate) format_version: String,
+
+    /// Version of Vaporetto that wrote the model, if the model header could be read.
+    pub(crate) required_version: Option<String>,
+}
+
+impl ModelVersionError {
+    /// Returns the format version of the model.
+    pub fn format_version(&self) -> &str {
+        &self.format_version
+    }
+
+    /// Returns the version of Vaporetto required to read the model.
+    ///
+    /// If the model is written in an unknown format, its header cannot be read, and [`None`] is
+    /// returned.
+    pub fn required_version(&self) -> Option<&str> {
+        self.required_version.as_deref()
+    }
+}
+
+impl fmt::Display for ModelVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ModelVersionError: the model of format version {} requires ",
+            self.format_version,
+        )?;
+        match &self.required_version {
+            Some(version) => write!(f, "vaporetto {version} or later")?,
+            None => write!(
+                f,
+                "a version of vaporetto newer than {}",
+                env!("CARGO_PKG_VERSION"),
+            )?,
+        }
+        write!(
+            f,
+            " (supported format versions: {})",
+            crate::SUPPORTED_MODEL_FORMAT_VERSIONS.join(", "),
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ModelVersionError {}
+
+impl From<ModelVersionError> for VaporettoError {
+    fn from(error: ModelVersionError) -> Self {
+        Self::ModelVersion(error)
+    }
+}
+
 impl From<alloc::string::FromUtf8Error> for VaporettoError {
     fn from(error: alloc::string::FromUtf8Error) -> Self {
         Self::UTF8Error(error)
@@ -0,0 +1,886 @@
+//! Conversion of models generated by [KyTea](http://www.phontron.com/kytea/).
+//!
+//! Models are converted at runtime by [`Model::from_kytea_reader()`] or by [`KyteaModel`] and the
+//! [`TryFrom`] implementation of [`Model`].
+//!
+//! Character and character type n-gram features of local tag models, which KyTea trains for
+//! each word having multiple tag candidates, are converted into [`TagModel`]s. N-grams that do
+//! not touch the right side of the word, except those adjacent to its left side, cannot be
+//! represented and are dropped.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::dict_model::{DictModel, DictWeight, WordWeightRecord};
+use crate::errors::{Result, VaporettoError};
+use crate::model::{Model, TagModel};
+use crate::ngram_model::{NgramData, NgramModel};
+use crate::sentence::CharacterType;
+use crate::utils;
+
+struct KyteaConfig {
+    _model_tag: String,
+    _do_ws: bool,
+    _do_tags: bool,
+    n_tags: u32,
+    char_w: u8,
+    _char_n: u8,
+    type_w: u8,
+    _type_n: u8,
+    dict_n: u8,
+    _bias: bool,
+    _epsilon: f64,
+    _solver_type: u8,
+    char_map: Vec<char>,
+}
+
+impl KyteaConfig {
+    fn read<R>(mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let mut model_tag = String::new();
+        rdr.read_line(&mut model_tag)?;
+        let do_ws = utils::read_u8(&mut rdr)? != 0;
+        let do_tags = utils::read_u8(&mut rdr)? != 0;
+        let n_tags = utils::read_u32(&mut rdr)?;
+        let char_w = utils::read_u8(&mut rdr)?;
+        let char_n = utils::read_u8(&mut rdr)?;
+        let type_w = utils::read_u8(&mut rdr)?;
+        let type_n = utils::read_u8(&mut rdr)?;
+        let dict_n = utils::read_u8(&mut rdr)?;
+        let bias = utils::read_u8(&mut rdr)? != 0;
+        let epsilon = utils::read_f64(&mut rdr)?;
+        let solver_type = utils::read_u8(&mut rdr)?;
+        let mut char_map = vec![];
+        rdr.read_until(0, &mut char_map)?;
+        let char_map: Vec<char> = String::from_utf8(char_map)?.chars().collect();
+        Ok(Self {
+            _model_tag: model_tag,
+            _do_ws: do_ws,
+            _do_tags: do_tags,
+            n_tags,
+            char_w,
+            _char_n: char_n,
+            type_w,
+            _type_n: type_n,
+            dict_n,
+            _bias: bias,
+            _epsilon: epsilon,
+            _solver_type: solver_type,
+            char_map,
+        })
+    }
+}
+
+trait Readable: Sized {
+    fn read<R>(config: &KyteaConfig, rdr: R) -> Result<Self>
+    where
+        R: BufRead;
+}
+
+impl Readable for i16 {
+    fn read<R>(_config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        Ok(utils::read_i16(&mut rdr)?)
+    }
+}
+
+impl Readable for f64 {
+    fn read<R>(_config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        Ok(utils::read_f64(&mut rdr)?)
+    }
+}
+
+impl Readable for char {
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let cidx = usize::from(utils::read_u16(&mut rdr)?);
+        Ok(config.char_map[cidx - 1])
+    }
+}
+
+impl<T> Readable for Vec<T>
+where
+    T: Readable,
+{
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let size = utils::read_u32(&mut rdr)?;
+        let mut result = Self::with_capacity(size as usize);
+        for _ in 0..size {
+            result.push(T::read(config, &mut rdr)?);
+        }
+        Ok(result)
+    }
+}
+
+impl Readable for String {
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let size = utils::read_u32(&mut rdr)?;
+        let mut result = Self::new();
+        for _ in 0..size {
+            let cidx = usize::from(utils::read_u16(&mut rdr)?);
+            result.push(config.char_map[cidx - 1]);
+        }
+        Ok(result)
+    }
+}
+
+struct State {
+    _failure: u32,
+    gotos: Vec<(char, u32)>,
+    outputs: Vec<u32>,
+    is_branch: bool,
+}
+
+struct Dictionary<T>
+where
+    T: Readable,
+{
+    n_dicts: u8,
+    states: Vec<State>,
+    entries: Vec<T>,
+}
+
+impl<T> Dictionary<T>
+where
+    T: Readable,
+{
+    fn dump_items(&self) -> Vec<(Vec<char>, &T)> {
+        let mut result = vec![];
+        let mut stack = vec![(0, vec![])];
+        while let Some((idx, word)) = stack.pop() {
+            let state = &self.states[idx];
+            if state.is_branch {
+                result.push((word.clone(), &self.entries[state.outputs[0] as usize]));
+            }
+            for &(c, next_idx) in state.gotos.iter().rev() {
+                let mut word = word.clone();
+                word.push(c);
+                stack.push((next_idx as usize, word));
+            }
+        }
+        result
+    }
+}
+
+impl<T> Dictionary<T>
+where
+    T: Readable,
+{
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Option<Self>>
+    where
+        R: BufRead,
+    {
+        let n_dicts = utils::read_u8(&mut rdr)?;
+        let n_states = utils::read_u32(&mut rdr)? as usize;
+        if n_states == 0 {
+            return Ok(None);
+        }
+        let mut states = Vec::with_capacity(n_states);
+        for _ in 0..n_states {
+            let failure = utils::read_u32(&mut rdr)?;
+            let n_gotos = utils::read_u32(&mut rdr)?;
+            let mut gotos = vec![];
+            for _ in 0..n_gotos {
+                let k = char::read(config, &mut rdr)?;
+                let v = utils::read_u32(&mut rdr)?;
+                gotos.push((k, v));
+            }
+            gotos.sort_unstable();
+            let n_outputs = utils::read_u32(&mut rdr)? as usize;
+            let mut outputs = Vec::with_capacity(n_outputs);
+            for _ in 0..n_outputs {
+                outputs.push(utils::read_u32(&mut rdr)?);
+            }
+            let is_branch = utils::read_u8(&mut rdr)? != 0;
+            states.push(State {
+                _failure: failure,
+                gotos,
+                outputs,
+                is_branch,
+            });
+        }
+        let n_entries = utils::read_u32(&mut rdr)? as usize;
+        let mut entries = Vec::with_capacity(n_entries);
+        for _ in 0..n_entries {
+            entries.push(T::read(config, &mut rdr)?);
+        }
+        Ok(Some(Self {
+            n_dicts,
+            states,
+            entries,
+        }))
+    }
+}
+
+struct FeatureLookup<T>
+where
+    T: Readable,
+{
+    char_dict: Option<Dictionary<Vec<T>>>,
+    type_dict: Option<Dictionary<Vec<T>>>,
+    _self_dict: Option<Dictionary<Vec<T>>>,
+    dict_vec: Vec<T>,
+    biases: Vec<T>,
+    _tag_dict_vec: Vec<T>,
+    _tag_unk_vec: Vec<T>,
+}
+
+impl<T> FeatureLookup<T>
+where
+    T: Readable,
+{
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Option<Self>>
+    where
+        R: BufRead,
+    {
+        let active = utils::read_u8(&mut rdr)?;
+        if active == 0 {
+            return Ok(None);
+        }
+        let char_dict = Dictionary::read(config, &mut rdr)?;
+        let type_dict = Dictionary::read(config, &mut rdr)?;
+        let self_dict = Dictionary::read(config, &mut rdr)?;
+        let dict_vec = Vec::<T>::read(config, &mut rdr)?;
+        let biases = Vec::<T>::read(config, &mut rdr)?;
+        let tag_dict_vec = Vec::<T>::read(config, &mut rdr)?;
+        let tag_unk_vec = Vec::<T>::read(config, &mut rdr)?;
+        Ok(Some(Self {
+            char_dict,
+            type_dict,
+            _self_dict: self_dict,
+            dict_vec,
+            biases,
+            _tag_dict_vec: tag_dict_vec,
+            _tag_unk_vec: tag_unk_vec,
+        }))
+    }
+}
+
+struct LinearModel {
+    _add_features: bool,
+    _solver_type: u8,
+    labels: Vec<i32>,
+    _bias: bool,
+    _multiplier: f64,
+    feature_lookup: Option<FeatureLookup<i16>>,
+}
+
+impl Readable for Option<LinearModel> {
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let n_classes = utils::read_u32(&mut rdr)?;
+        if n_classes == 0 {
+            return Ok(None);
+        }
+        let add_features = false;
+        let solver_type = utils::read_u8(&mut rdr)?;
+        let mut labels = vec![];
+        for _ in 0..n_classes {
+            labels.push(utils::read_i32(&mut rdr)?);
+        }
+        let bias = utils::read_u8(&mut rdr)? != 0;
+        let multiplier = utils::read_f64(&mut rdr)?;
+        let feature_lookup = FeatureLookup::read(config, &mut rdr)?;
+        Ok(Some(LinearModel {
+            _add_features: add_features,
+            _solver_type: solver_type,
+            labels,
+            _bias: bias,
+            _multiplier: multiplier,
+            feature_lookup,
+        }))
+    }
+}
+
+struct ModelTagEntry {
+    _word: String,
+    tags: Vec<Vec<String>>,
+    _tags_in_dicts: Vec<Vec<u8>>,
+    in_dict: u8,
+    tag_models: Vec<Option<LinearModel>>,
+}
+
+impl Readable for ModelTagEntry {
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let word = String::read(config, &mut rdr)?;
+        let mut tags = Vec::with_capacity(config.n_tags as usize);
+        let mut tags_in_dicts = Vec::with_capacity(config.n_tags as usize);
+        for _ in 0..config.n_tags {
+            let size = utils::read_u32(&mut rdr)? as usize;
+            let mut t = Vec::with_capacity(size);
+            let mut td = Vec::with_capacity(size);
+            for _ in 0..size {
+                t.push(String::read(config, &mut rdr)?);
+                td.push(utils::read_u8(&mut rdr)?);
+            }
+            tags.push(t);
+            tags_in_dicts.push(td);
+        }
+        let in_dict = utils::read_u8(&mut rdr)?;
+        let mut tag_models = Vec::with_capacity(config.n_tags as usize);
+        for _ in 0..config.n_tags {
+            tag_models.push(Option::<LinearModel>::read(config, &mut rdr)?);
+        }
+        Ok(Self {
+            _word: word,
+            tags,
+            _tags_in_dicts: tags_in_dicts,
+            in_dict,
+            tag_models,
+        })
+    }
+}
+
+struct ProbTagEntry {
+    _word: String,
+    _tags: Vec<Vec<String>>,
+    _probs: Vec<Vec<f64>>,
+}
+
+impl Readable for ProbTagEntry {
+    fn read<R>(config: &KyteaConfig, mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let word = String::read(config, &mut rdr)?;
+        let mut tags = Vec::with_capacity(config.n_tags as usize);
+        let mut probs = Vec::with_capacity(config.n_tags as usize);
+        for _ in 0..config.n_tags {
+            let size = utils::read_u32(&mut rdr)? as usize;
+            let mut t = Vec::with_capacity(size);
+            let mut p = Vec::with_capacity(size);
+            for _ in 0..size {
+                t.push(String::read(config, &mut rdr)?);
+                p.push(utils::read_f64(&mut rdr)?);
+            }
+            tags.push(t);
+            probs.push(p);
+        }
+        Ok(Self {
+            _word: word,
+            _tags: tags,
+            _probs: probs,
+        })
+    }
+}
+
+/// Model data created by KyTea.
+#[cfg_attr(docsrs, doc(cfg(feature = "kytea")))]
+pub struct KyteaModel {
+    config: KyteaConfig,
+    wordseg_model: Option<LinearModel>,
+    global_tags: Vec<Vec<String>>,
+    global_models: Vec<Option<LinearModel>>,
+    dict: Option<Dictionary<ModelTagEntry>>,
+    _subword_dict: Option<Dictionary<ProbTagEntry>>,
+}
+
+impl KyteaModel {
+    /// Creates a model from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `rdr` - A data source.
+    ///
+    /// # Errors
+    ///
+    /// When `rdr` generates an error, it will be returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// use vaporetto::{KyteaModel, Model, Predictor, Sentence};
+    ///
+    /// let f = BufReader::new(File::open("../resources/kytea-model.bin").unwrap());
+    /// let model = KyteaModel::read(f).unwrap();
+    /// let model = Model::try_from(model).unwrap();
+    ///
+    /// let predictor = Predictor::new(model, false).unwrap();
+    ///
+    /// let mut buf = String::new();
+    ///
+    /// let mut s = Sentence::default();
+    ///
+    /// s.update_raw("まぁ社長は火星猫だ").unwrap();
+    /// predictor.predict(&mut s);
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+    /// ```
+    pub fn read<R>(mut rdr: R) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let config = KyteaConfig::read(&mut rdr)?;
+
+        let wordseg_model = Option::<LinearModel>::read(&config, &mut rdr)?;
+
+        let mut global_tags = Vec::with_capacity(config.n_tags as usize);
+        let mut global_models = Vec::with_capacity(config.n_tags as usize);
+
+        for _ in 0..config.n_tags {
+            global_tags.push(Vec::<String>::read(&config, &mut rdr)?);
+            global_models.push(Option::<LinearModel>::read(&config, &mut rdr)?);
+        }
+
+        let dict = Dictionary::<ModelTagEntry>::read(&config, &mut rdr)?;
+        let subword_dict = Dictionary::<ProbTagEntry>::read(&config, &mut rdr)?;
+
+        Ok(Self {
+            config,
+            wordseg_model,
+            global_tags,
+            global_models,
+            dict,
+            _subword_dict: subword_dict,
+        })
+    }
+
+    /// Returns `true` if the model contains global tag models, which predict tags of words
+    /// without their local tag models.
+    ///
+    /// Vaporetto cannot represent global tag models, so the conversion into [`Model`] fails if
+    /// this function returns `true`. Use [`KyteaModel::remove_global_tag_models()`] to convert
+    /// the model without them.
+    pub fn has_global_tag_models(&self) -> bool {
+        self.global_models
+            .iter()
+            .flatten()
+            .any(|m| m.feature_lookup.is_some())
+    }
+
+    /// Removes global tag models.
+    ///
+    /// Tags of words without local tag models are not predicted by the converted model.
+    pub fn remove_global_tag_models(&mut self) {
+        self.global_tags.clear();
+        self.global_models.clear();
+    }
+}
+
+impl Model {
+    /// Reads a KyTea model from a reader and converts it into a Vaporetto model.
+    ///
+    /// The reader is buffered internally. Tags of dictionary words are converted with their local
+    /// tag models.
+    ///
+    /// # Errors
+    ///
+    /// When `rdr` generates an error, it will be returned as is. If the KyTea model does not
+    /// contain a word segmentation model or contains global tag models, an error variant will be
+    /// returned. To convert a model with global tag models, read it by [`KyteaModel::read()`]
+    /// and remove them by [`KyteaModel::remove_global_tag_models()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, Predictor, Sentence};
+    ///
+    /// let model = Model::from_kytea_reader(File::open("../resources/kytea-model.bin")?)?;
+    /// let predictor = Predictor::new(model, false)?;
+    ///
+    /// let mut s = Sentence::from_raw("まぁ社長は火星猫だ")?;
+    /// predictor.predict(&mut s);
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_kytea_reader<R>(rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::try_from(KyteaModel::read(BufReader::new(rdr))?)
+    }
+}
+
+impl TryFrom<KyteaModel> for Model {
+    type Error = VaporettoError;
+
+    fn try_from(model: KyteaModel) -> Result<Self> {
+        if model.has_global_tag_models() {
+            return Err(VaporettoError::invalid_model(
+                "global tag models cannot be converted. Remove them in advance.",
+            ));
+        }
+        let config = &model.config;
+        let wordseg_model = model
+            .wordseg_model
+            .ok_or_else(|| VaporettoError::invalid_model("no word segmentation model."))?;
+        let feature_lookup = wordseg_model
+            .feature_lookup
+            .ok_or_else(|| VaporettoError::invalid_model("no lookup data."))?;
+        let bias = i32::from(
+            *feature_lookup
+                .biases
+                .first()
+                .ok_or_else(|| VaporettoError::invalid_model("no bias."))?,
+        );
+        let char_dict = feature_lookup
+            .char_dict
+            .ok_or_else(|| VaporettoError::invalid_model("no character dictionary."))?;
+        let type_dict = feature_lookup
+            .type_dict
+            .ok_or_else(|| VaporettoError::invalid_model("no type dictionary."))?;
+
+        let mut char_ngrams = vec![];
+        for (char_ngram, v) in char_dict.dump_items() {
+            let weights = ngram_weights(v, config.char_w, char_ngram.len())?;
+            char_ngrams.push(NgramData {
+                ngram: char_ngram.into_iter().collect(),
+                weights,
+            });
+        }
+
+        let mut type_ngrams = vec![];
+        for (type_ngram, v) in type_dict.dump_items() {
+            let weights = ngram_weights(v, config.type_w, type_ngram.len())?;
+            let Some(ngram) = convert_type_ngram(&type_ngram)? else {
+                continue;
+            };
+            type_ngrams.push(NgramData { ngram, weights });
+        }
+
+        let mut dict = vec![];
+        let mut tag_models = vec![];
+        if let Some(kytea_dict) = model.dict {
+            for (w, data) in kytea_dict.dump_items() {
+                if let Some(tag_model) = convert_tag_model(config, &w, data)? {
+                    tag_models.push(tag_model);
+                }
+                let idx = std::cmp::min(w.len(), usize::from(config.dict_n))
+                    .checked_sub(1)
+                    .ok_or_else(|| VaporettoError::invalid_model("invalid dictionary word."))?;
+                let mut dict_weight = DictWeight::default();
+                for j in 0..usize::from(kytea_dict.n_dicts) {
+                    if (data.in_dict >> j) & 1 == 1 {
+                        let offset = 3 * usize::from(config.dict_n) * j + 3 * idx;
+                        let v =
+                            feature_lookup
+                                .dict_vec
+                                .get(offset..offset + 3)
+                                .ok_or_else(|| {
+                                    VaporettoError::invalid_model(
+                                        "dictionary weights are too short.",
+                                    )
+                                })?;
+                        dict_weight.left += i32::from(v[0]);
+                        dict_weight.inside += i32::from(v[1]);
+                        dict_weight.right += i32::from(v[2]);
+                    }
+                }
+                let mut weights = vec![dict_weight.inside; w.len() + 1];
+                *weights.first_mut().unwrap() = dict_weight.left;
+                *weights.last_mut().unwrap() = dict_weight.right;
+                dict.push(WordWeightRecord {
+                    word: w.into_iter().collect(),
+                    weights,
+                    comment: "".to_string(),
+                });
+            }
+        }
+
+        Ok(Self::new(
+            NgramModel(char_ngrams),
+            NgramModel(type_ngrams),
+            DictModel::new(dict),
+            bias,
+            config.char_w,
+            config.type_w,
+            tag_models,
+        ))
+    }
+}
+
+/// Returns the weights of an n-gram of the given length in the window of the given size.
+fn ngram_weights(v: &[i16], window_size: u8, ngram_len: usize) -> Result<Vec<i32>> {
+    (usize::from(window_size) * 2 + 1)
+        .checked_sub(ngram_len)
+        .and_then(|weight_size| v.get(..weight_size))
+        .map(|v| v.iter().map(|&w| i32::from(w)).collect())
+        .ok_or_else(|| {
+            VaporettoError::invalid_model(format!(
+                "the weights of an n-gram of length {ngram_len} are too short."
+            ))
+        })
+}
+
+/// Converts a KyTea character type n-gram into type IDs.
+///
+/// Returns `None` if the n-gram contains the invalid type `0x04`.
+fn convert_type_ngram(type_ngram: &[char]) -> Result<Option<Vec<u8>>> {
+    let mut ngram = Vec::with_capacity(type_ngram.len());
+    for &t in type_ngram {
+        ngram.push(match t {
+            'D' => CharacterType::Digit as u8,
+            'R' => CharacterType::Roman as u8,
+            'H' => CharacterType::Hiragana as u8,
+            'T' => CharacterType::Katakana as u8,
+            'K' => CharacterType::Kanji as u8,
+            'O' => CharacterType::Other as u8,
+            // https://github.com/daac-tools/vaporetto/issues/110
+            // Some models distributed on KyTea's web site contain the invalid character
+            // type `0x04`. The following supports them.
+            '\x04' => return Ok(None),
+            t => {
+                return Err(VaporettoError::invalid_model(format!(
+                    "unsupported character type: {}",
+                    u32::from(t)
+                )));
+            }
+        });
+    }
+    Ok(Some(ngram))
+}
+
+/// Adds n-gram weights of a local tag model of KyTea to `weights`.
+///
+/// KyTea looks up n-grams in the string concatenating `window` characters on each side of the
+/// word, and the `j`-th weight vector of an n-gram corresponds to the occurrence ending at the
+/// `(2 * window - 1 - j)`-th character of the string. N-grams in the right context are mapped to
+/// positions relative to the end of the word, and n-grams adjacent to the left of the word are
+/// joined with the word. Other n-grams cannot be represented and are dropped.
+fn add_tag_ngram_weights<T>(
+    dict: &Dictionary<Vec<i16>>,
+    word: &[T],
+    window: usize,
+    n_vecs: usize,
+    mut convert_ngram: impl FnMut(&[char]) -> Result<Option<Vec<T>>>,
+    mut add: impl FnMut(&mut Vec<i32>, &[i16]),
+    weights: &mut BTreeMap<(Vec<T>, u8), Vec<i32>>,
+) -> Result<()>
+where
+    T: Clone + Ord,
+{
+    for (ngram, v) in dict.dump_items() {
+        let n = ngram.len();
+        if n > window {
+            continue;
+        }
+        let Some(ngram) = convert_ngram(&ngram)? else {
+            continue;
+        };
+        for (j, vec) in v.chunks(n_vecs).take(2 * window - n + 1).enumerate() {
+            if vec.iter().all(|&w| w == 0) {
+                continue;
+            }
+            let end = 2 * window - 1 - j;
+            let key = if end + 1 >= window + n {
+                // The n-gram is in the right context.
+                (ngram.clone(), u8::try_from(end + 1 - window).unwrap())
+            } else if end + 1 == window {
+                let mut joined = ngram.clone();
+                joined.extend_from_slice(word);
+                (joined, 0)
+            } else {
+                continue;
+            };
+            add(weights.entry(key).or_default(), vec);
+        }
+    }
+    Ok(())
+}
+
+/// Converts tag candidates of a dictionary word and its local tag models into [`TagModel`].
+///
+/// Dictionary features of tag models are not converted. Returns `None` if the word has no tag or a tag without candidates is followed by another tag.
+fn convert_tag_model(
+    config: &KyteaConfig,
+    word: &[char],
+    entry: &ModelTagEntry,
+) -> Result<Option<TagModel>> {
+    let mut tags = entry.tags.clone();
+    while tags.last().is_some_and(Vec::is_empty) {
+        tags.pop();
+    }
+    if tags.is_empty() || tags.iter().any(Vec::is_empty) {
+        return Ok(None);
+    }
+    let n_classes = tags.iter().map(Vec::len).filter(|&n| n >= 2).sum();
+    let word_types: Vec<u8> = word
+        .iter()
+        .map(|&c| CharacterType::get_type(c) as u8)
+        .collect();
+    let mut bias = vec![0; n_classes];
+    let mut char_weights = BTreeMap::new();
+    let mut type_weights = BTreeMap::new();
+    let mut offset = 0;
+    for (cands, model) in tags.iter().zip(&entry.tag_models) {
+        if cands.len() < 2 {
+            continue;
+        }
+        let Some((model, lookup)) = model
+            .as_ref()
+            .and_then(|m| m.feature_lookup.as_ref().map(|lookup| (m, lookup)))
+        else {
+            offset += cands.len();
+            continue;
+        };
+        // Each weight vector of the classifier adds scores of the candidate of its label. A
+        // binary classifier has only one vector for the first label.
+        let n_vecs = lookup.biases.len();
+        if n_vecs == 0 || (n_vecs != model.labels.len() && (n_vecs, model.labels.len()) != (1, 2)) {
+            return Err(VaporettoError::invalid_model(
+                "the number of weight vectors does not match the number of labels",
+            ));
+        }
+        let mut classes = Vec::with_capacity(n_vecs);
+        for &label in &model.labels[..n_vecs] {
+            let cand = usize::try_from(label - 1)
+                .ok()
+                .filter(|&i| i < cands.len())
+                .ok_or_else(|| VaporettoError::invalid_model("invalid label of a tag model"))?;
+            classes.push(offset + cand);
+        }
+        let add = |dst: &mut Vec<i32>, vec: &[i16]| {
+            dst.resize(n_classes, 0);
+            for (&i, &w) in classes.iter().zip(vec) {
+                dst[i] += i32::from(w);
+            }
+        };
+        add(&mut bias, &lookup.biases);
+        if let Some(char_dict) = lookup.char_dict.as_ref() {
+            add_tag_ngram_weights(
+                char_dict,
+                word,
+                usize::from(config.char_w),
+                n_vecs,
+                |ngram| Ok(Some(ngram.to_vec())),
+                add,
+                &mut char_weights,
+            )?;
+        }
+        if let Some(type_dict) = lookup.type_dict.as_ref() {
+            add_tag_ngram_weights(
+                type_dict,
+                &word_types,
+                usize::from(config.type_w),
+                n_vecs,
+                convert_type_ngram,
+                add,
+                &mut type_weights,
+            )?;
+        }
+        offset += cands.len();
+    }
+    let mut tag_model = TagModel::new(word.iter().collect(), tags, bias)?;
+    for ((ngram, rel_position), weights) in char_weights {
+        tag_model.add_char_ngram(ngram.into_iter().collect(), rel_position, weights)?;
+    }
+    for ((ngram, rel_position), weights) in type_weights {
+        tag_model.add_type_ngram(ngram, rel_position, weights)?;
+    }
+    Ok(Some(tag_model))
+}
+
+#[cfg(all(test, feature = "tag-prediction"))]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    use crate::{Predictor, Sentence};
+
+    fn read_with_global_tag_model() -> KyteaModel {
+        let mut model = KyteaModel::read(BufReader::new(
+            File::open("../resources/kytea-model.bin").unwrap(),
+        ))
+        .unwrap();
+        assert!(!model.has_global_tag_models());
+        model.global_tags.push(vec!["名詞".into(), "動詞".into()]);
+        model.global_models.push(Some(LinearModel {
+            _add_features: false,
+            _solver_type: 0,
+            labels: vec![1, 2],
+            _bias: true,
+            _multiplier: 1.0,
+            feature_lookup: Some(FeatureLookup {
+                char_dict: None,
+                type_dict: None,
+                _self_dict: None,
+                dict_vec: vec![],
+                biases: vec![1],
+                _tag_dict_vec: vec![],
+                _tag_unk_vec: vec![],
+            }),
+        }));
+        model
+    }
+
+    #[test]
+    fn test_global_tag_models() {
+        let model = read_with_global_tag_model();
+        assert!(model.has_global_tag_models());
+        assert!(Model::try_from(model).is_err());
+
+        let mut model = read_with_global_tag_model();
+        model.remove_global_tag_models();
+        assert!(!model.has_global_tag_models());
+        assert!(Model::try_from(model).is_ok());
+    }
+
+    #[test]
+    fn test_ngram_weights() {
+        assert_eq!(vec![1, 2, 3], ngram_weights(&[1, 2, 3, 4], 2, 2).unwrap());
+        assert!(ngram_weights(&[1, 2, 3, 4], 2, 6).is_err());
+        assert!(ngram_weights(&[1, 2], 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_convert_local_tag_model() {
+        let model =
+            Model::from_kytea_reader(File::open("../resources/kytea-model.bin").unwrap()).unwrap();
+        let tag_model = model
+            .0
+            .tag_models
+            .iter()
+            .find(|m| m.token == "まぁ")
+            .unwrap();
+        assert_eq!(vec![0, 0], tag_model.bias);
+        assert_eq!(2, tag_model.char_ngram_model.0.len());
+
+        let predictor = Predictor::new(model, true).unwrap();
+        let mut buf = String::new();
+        let mut s = Sentence::from_raw("まぁ良いだろう").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ/副詞/マー 良い/形容詞/ヨイ だろう/助動詞/ダロー", buf);
+
+        s.update_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!(
+            "まぁ/名詞/マー 社長/名詞/シャチョー は/助詞/ワ 火星/名詞/カセー 猫/名詞/ネコ だ/助動詞/ダ",
+            buf
+        );
+    }
+}
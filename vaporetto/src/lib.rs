@@ -47,7 +47,24 @@ assert_eq!(
 //!
 //! Tag prediction requires **crate feature** `tag-prediction`.
 //!
+//! Batch prediction using multiple threads requires **crate feature** `rayon`. For more details,
+//! see [`Predictor::predict_batch()`]. To tokenize a large document using multiple threads
+//! without a thread pool, see [`Predictor::predict_document()`].
+//!
+//! Loading a model from an asynchronous reader requires **crate feature** `tokio`. For more
+//! details, see [`Model::read_async()`].
+//!
 //! Training requires **crate feature** `train`. For more details, see [`Trainer`].
+//!
+//! This crate builds on stable Rust 1.75 or later. Only **crate feature** `portable-simd`
+//! requires nightly Rust.
+//!
+//! ## API stability
+//!
+//! Items re-exported at the crate root and in [`prelude`] follow semantic versioning. Scorers
+//! and other internal data structures are private and may change in any release; only their
+//! effects through [`Predictor`] and [`Model`] are covered. The binary model format is versioned
+//! separately; see [`MODEL_FORMAT_VERSION`].
 
 #![deny(missing_docs)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -60,16 +77,31 @@ compile_error!("`alloc` feature is currently required to build this crate");
 #[macro_use]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+mod atomic_write;
+mod attribution;
+mod calibration;
+mod char_class;
 mod char_scorer;
 mod dict_model;
+#[cfg(feature = "std")]
+mod disk_dict;
 mod model;
 mod ngram_model;
+mod offset_map;
 mod predictor;
 mod sentence;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod timings;
 mod type_scorer;
 mod utils;
 
+pub mod active;
 pub mod errors;
+pub mod metrics;
+pub mod prelude;
 
 #[cfg(feature = "train")]
 mod tag_trainer;
@@ -77,18 +109,38 @@ mod tag_trainer;
 mod trainer;
 
 #[cfg(feature = "kytea")]
-mod kytea_model;
-
-pub use dict_model::WordWeightRecord;
-pub use model::Model;
-pub use predictor::Predictor;
-pub use sentence::{CharacterBoundary, CharacterType, Sentence, Token, TokenIterator};
+#[cfg_attr(docsrs, doc(cfg(feature = "kytea")))]
+pub mod kytea;
+
+#[cfg(feature = "std")]
+pub use atomic_write::write_atomically;
+pub use attribution::{Attribution, FeatureKind};
+pub use calibration::PlattScaling;
+pub use char_class::{CharCategory, Script, ScriptCategory, TypeScheme};
+pub use dict_model::{DictEntries, WordWeightRecord};
+#[cfg(feature = "std")]
+pub use disk_dict::DiskDictionary;
+pub use model::{
+    Model, ModelBuilder, ModelCapabilities, PruneReport, TagModel, TagModelReport,
+    MODEL_FORMAT_VERSION, SUPPORTED_MODEL_FORMAT_VERSIONS,
+};
+pub use offset_map::OffsetMap;
+pub use predictor::{MemoryUsage, Predictor, ScoreComponents};
+pub use sentence::{
+    CharTypeRanges, CharacterBoundary, CharacterType, InvisibleChars, Sentence, SentenceKey, Token,
+    TokenIterator, TokenizedFormat,
+};
+
+#[cfg(feature = "std")]
+pub use stream::StreamTokenizer;
+#[cfg(feature = "std")]
+pub use timings::StageTimings;
 
 #[cfg(feature = "train")]
 pub use trainer::{SolverType, Trainer};
 
 #[cfg(feature = "kytea")]
-pub use kytea_model::KyteaModel;
+pub use kytea::KyteaModel;
 
 /// Version number of this library.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
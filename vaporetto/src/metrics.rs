@@ -0,0 +1,393 @@
+//! Evaluation metrics of word segmentation and tagging.
+//!
+//! [`Evaluator`] compares predicted sentences with gold sentences and computes precision,
+//! recall, and F1 of boundaries and words and accuracy of tags. Confidence intervals are
+//! estimated by bootstrap resampling of sentences.
+
+use core::ops::AddAssign;
+
+use alloc::vec::Vec;
+
+use crate::errors::{Result, VaporettoError};
+use crate::sentence::{CharacterBoundary, Sentence};
+use crate::utils::SplitMix64Rng;
+
+/// Returns `num / den`, or 0 if `den` is 0.
+#[allow(clippy::cast_precision_loss)]
+fn ratio(num: usize, den: usize) -> f64 {
+    if den == 0 {
+        0.0
+    } else {
+        num as f64 / den as f64
+    }
+}
+
+fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+/// Returns the mean and the sample standard deviation of the values.
+///
+/// The mean is 0 if `values` is empty, and the standard deviation is 0 if `values` has less than
+/// two values.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[allow(clippy::cast_precision_loss)]
+pub fn mean_std(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var.sqrt())
+}
+
+/// Counts of correct and incorrect predictions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// The number of boundaries correctly predicted as word boundaries.
+    pub boundary_tp: usize,
+
+    /// The number of boundaries correctly predicted as non-word boundaries.
+    pub boundary_tn: usize,
+
+    /// The number of boundaries incorrectly predicted as word boundaries.
+    pub boundary_fp: usize,
+
+    /// The number of boundaries incorrectly predicted as non-word boundaries.
+    pub boundary_fn: usize,
+
+    /// The number of words in gold sentences.
+    pub gold_words: usize,
+
+    /// The number of words in predicted sentences.
+    pub sys_words: usize,
+
+    /// The number of predicted words that match gold words.
+    pub correct_words: usize,
+
+    /// The number of correct words whose tags also match the gold tags.
+    ///
+    /// Only tags annotated in gold sentences are compared.
+    pub correct_tagged_words: usize,
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.boundary_tp += other.boundary_tp;
+        self.boundary_tn += other.boundary_tn;
+        self.boundary_fp += other.boundary_fp;
+        self.boundary_fn += other.boundary_fn;
+        self.gold_words += other.gold_words;
+        self.sys_words += other.sys_words;
+        self.correct_words += other.correct_words;
+        self.correct_tagged_words += other.correct_tagged_words;
+    }
+}
+
+impl Counts {
+    /// Compares a predicted sentence with a gold sentence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when the sentences have different numbers of characters.
+    pub fn new(gold: &Sentence, sys: &Sentence) -> Result<Self> {
+        if gold.boundaries().len() != sys.boundaries().len() {
+            return Err(VaporettoError::invalid_argument(
+                "sys",
+                "the number of characters differs from the gold sentence",
+            ));
+        }
+        let mut counts = Self::default();
+        for (&g, &s) in gold.boundaries().iter().zip(sys.boundaries()) {
+            match (g, s) {
+                (CharacterBoundary::WordBoundary, CharacterBoundary::WordBoundary) => {
+                    counts.boundary_tp += 1;
+                }
+                (CharacterBoundary::WordBoundary, _) => counts.boundary_fn += 1,
+                (_, CharacterBoundary::WordBoundary) => counts.boundary_fp += 1,
+                _ => counts.boundary_tn += 1,
+            }
+        }
+
+        let gold_tokens: Vec<_> = gold.iter_tokens().collect();
+        let sys_tokens: Vec<_> = sys.iter_tokens().collect();
+        counts.gold_words = gold_tokens.len();
+        counts.sys_words = sys_tokens.len();
+        let mut j = 0;
+        for g in &gold_tokens {
+            while j < sys_tokens.len() && sys_tokens[j].start() < g.start() {
+                j += 1;
+            }
+            let Some(s) = sys_tokens.get(j) else {
+                break;
+            };
+            if s.start() != g.start() || s.end() != g.end() {
+                continue;
+            }
+            counts.correct_words += 1;
+            let sys_tags = s.tags();
+            let is_tag_match = g.tags().iter().enumerate().all(|(i, tag)| {
+                tag.as_ref().map_or(true, |tag| {
+                    sys_tags.get(i).and_then(Option::as_ref) == Some(tag)
+                })
+            });
+            if is_tag_match {
+                counts.correct_tagged_words += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Returns the precision of word boundaries.
+    pub fn boundary_precision(&self) -> f64 {
+        ratio(self.boundary_tp, self.boundary_tp + self.boundary_fp)
+    }
+
+    /// Returns the recall of word boundaries.
+    pub fn boundary_recall(&self) -> f64 {
+        ratio(self.boundary_tp, self.boundary_tp + self.boundary_fn)
+    }
+
+    /// Returns the F1 score of word boundaries.
+    pub fn boundary_f1(&self) -> f64 {
+        f1(self.boundary_precision(), self.boundary_recall())
+    }
+
+    /// Returns the precision of words.
+    ///
+    /// If `with_tags` is true, words are correct only if their tags are also correct.
+    pub fn word_precision(&self, with_tags: bool) -> f64 {
+        ratio(self.correct(with_tags), self.sys_words)
+    }
+
+    /// Returns the recall of words.
+    ///
+    /// If `with_tags` is true, words are correct only if their tags are also correct.
+    pub fn word_recall(&self, with_tags: bool) -> f64 {
+        ratio(self.correct(with_tags), self.gold_words)
+    }
+
+    /// Returns the F1 score of words.
+    ///
+    /// If `with_tags` is true, words are correct only if their tags are also correct.
+    pub fn word_f1(&self, with_tags: bool) -> f64 {
+        f1(self.word_precision(with_tags), self.word_recall(with_tags))
+    }
+
+    /// Returns the accuracy of tags of correctly segmented words.
+    pub fn tag_accuracy(&self) -> f64 {
+        ratio(self.correct_tagged_words, self.correct_words)
+    }
+
+    const fn correct(&self, with_tags: bool) -> usize {
+        if with_tags {
+            self.correct_tagged_words
+        } else {
+            self.correct_words
+        }
+    }
+}
+
+/// Evaluator accumulating counts of each sentence.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::metrics::Evaluator;
+/// use vaporetto::Sentence;
+///
+/// let gold = Sentence::from_tokenized("火星/名詞 猫/名詞 だ/助動詞")?;
+/// let sys = Sentence::from_tokenized("火星猫/名詞 だ/助動詞")?;
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.add(&gold, &sys)?;
+/// let counts = evaluator.total();
+/// assert_eq!(0.5, counts.boundary_recall());
+/// assert_eq!(0.5, counts.word_precision(false));
+/// assert_eq!(1.0, counts.tag_accuracy());
+///
+/// let (lower, upper) = evaluator.bootstrap(|c| c.word_f1(false), 100, 0.95, 0);
+/// assert!(lower <= upper);
+/// # Ok::<(), vaporetto::errors::VaporettoError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Evaluator {
+    sentences: Vec<Counts>,
+}
+
+impl Evaluator {
+    /// Creates a new evaluator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compares a predicted sentence with a gold sentence and stores the counts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when the sentences have different numbers of characters.
+    pub fn add(&mut self, gold: &Sentence, sys: &Sentence) -> Result<()> {
+        self.sentences.push(Counts::new(gold, sys)?);
+        Ok(())
+    }
+
+    /// Returns the number of stored sentences.
+    pub fn n_sentences(&self) -> usize {
+        self.sentences.len()
+    }
+
+    /// Returns the sum of counts of all sentences.
+    pub fn total(&self) -> Counts {
+        let mut total = Counts::default();
+        for &counts in &self.sentences {
+            total += counts;
+        }
+        total
+    }
+
+    /// Estimates the confidence interval of the metric by the bootstrap percentile method.
+    ///
+    /// Sentences are resampled with replacement `n_resamples` times, and the lower and upper
+    /// bounds of the central `confidence` interval of the metric are returned. If no sentence
+    /// is stored or `n_resamples` is 0, the metric of the total counts is returned for both
+    /// bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - A function computing the metric from counts.
+    /// * `n_resamples` - The number of resamples.
+    /// * `confidence` - The confidence level in `[0, 1]`, e.g., 0.95.
+    /// * `seed` - A seed of the random number generator.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        clippy::suboptimal_flops
+    )]
+    pub fn bootstrap<F>(
+        &self,
+        metric: F,
+        n_resamples: usize,
+        confidence: f64,
+        seed: u64,
+    ) -> (f64, f64)
+    where
+        F: Fn(&Counts) -> f64,
+    {
+        if self.sentences.is_empty() || n_resamples == 0 {
+            let value = metric(&self.total());
+            return (value, value);
+        }
+        let mut rng = SplitMix64Rng::new(seed);
+        let mut values = Vec::with_capacity(n_resamples);
+        for _ in 0..n_resamples {
+            let mut counts = Counts::default();
+            for _ in 0..self.sentences.len() {
+                counts += self.sentences[rng.below(self.sentences.len())];
+            }
+            values.push(metric(&counts));
+        }
+        values.sort_unstable_by(f64::total_cmp);
+        let alpha = (1.0 - confidence.clamp(0.0, 1.0)) / 2.0;
+        let last = (n_resamples - 1) as f64;
+        // Rounds to the nearest index. `f64::round()` and `f64::mul_add()` are unavailable in
+        // no_std.
+        let lower = (alpha * last + 0.5) as usize;
+        let upper = ((1.0 - alpha) * last + 0.5) as usize;
+        (values[lower], values[upper])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let gold =
+            Sentence::from_tokenized("この/連体詞 人/名詞 は/助詞 火星/名詞 人/接尾辞").unwrap();
+        let sys =
+            Sentence::from_tokenized("この/連体詞 人/名詞 は火/名詞 星/名詞 人/名詞").unwrap();
+        let counts = Counts::new(&gold, &sys).unwrap();
+        assert_eq!(
+            Counts {
+                boundary_tp: 3,
+                boundary_tn: 1,
+                boundary_fp: 1,
+                boundary_fn: 1,
+                gold_words: 5,
+                sys_words: 5,
+                correct_words: 3,
+                correct_tagged_words: 2,
+            },
+            counts,
+        );
+        assert_eq!(0.75, counts.boundary_precision());
+        assert_eq!(0.75, counts.boundary_recall());
+        assert_eq!(0.6, counts.word_f1(false));
+        assert_eq!(0.4, counts.word_recall(true));
+        assert_eq!(2.0 / 3.0, counts.tag_accuracy());
+    }
+
+    #[test]
+    fn test_counts_partial_tags() {
+        let gold = Sentence::from_tokenized("火星/名詞 猫 だ/助動詞").unwrap();
+        let sys = Sentence::from_tokenized("火星/名詞 猫/名詞 だ").unwrap();
+        let counts = Counts::new(&gold, &sys).unwrap();
+        assert_eq!(3, counts.correct_words);
+        assert_eq!(2, counts.correct_tagged_words);
+    }
+
+    #[test]
+    fn test_counts_length_mismatch() {
+        let gold = Sentence::from_tokenized("火星 猫").unwrap();
+        let sys = Sentence::from_tokenized("火星").unwrap();
+        assert!(Counts::new(&gold, &sys).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_mean_std() {
+        assert_eq!((0.0, 0.0), mean_std(&[]));
+        assert_eq!((0.5, 0.0), mean_std(&[0.5]));
+        let (mean, std) = mean_std(&[1.0, 2.0, 3.0, 4.0]);
+        assert!((mean - 2.5).abs() < 1e-12);
+        assert!((std - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bootstrap() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(
+            (0.0, 0.0),
+            evaluator.bootstrap(Counts::boundary_f1, 10, 0.95, 0)
+        );
+        let gold = Sentence::from_tokenized("火星 猫").unwrap();
+        evaluator.add(&gold, &gold).unwrap();
+        let sys = Sentence::from_tokenized("火 星猫").unwrap();
+        evaluator.add(&gold, &sys).unwrap();
+        assert_eq!(2, evaluator.n_sentences());
+
+        let (lower, upper) = evaluator.bootstrap(Counts::boundary_f1, 1000, 0.9, 1);
+        assert_eq!(0.0, lower);
+        assert_eq!(1.0, upper);
+        assert_eq!(
+            (lower, upper),
+            evaluator.bootstrap(Counts::boundary_f1, 1000, 0.9, 1)
+        );
+        let total = evaluator.total().boundary_f1();
+        assert_eq!(
+            (total, total),
+            evaluator.bootstrap(Counts::boundary_f1, 0, 0.9, 1)
+        );
+    }
+}
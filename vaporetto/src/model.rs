@@ -1,18 +1,167 @@
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 #[cfg(feature = "std")]
 use std::io::{Read, Write};
 
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
 use bincode::{Decode, Encode};
+use hashbrown::HashSet;
 
-use crate::dict_model::{DictModel, WordWeightRecord};
-use crate::errors::{Result, VaporettoError};
-use crate::ngram_model::{NgramModel, TagNgramModel};
+use crate::calibration::PlattScaling;
+use crate::dict_model::{DictEntries, DictModel, WordWeightRecord, MAX_WORD_LEN};
+use crate::errors::{ModelVersionError, Result, VaporettoError};
+use crate::ngram_model::{NgramData, NgramModel, TagNgramData, TagNgramModel, TagWeight};
+use crate::sentence::CharTypeRanges;
 use crate::utils::VecWriter;
 
+/// Version of the model format written by this crate.
+///
+/// Applications can compare this value with [`Model::format_version()`] of model files before
+/// loading them.
+///
+/// Format versions are numbered independently of this crate. Models of the current format also
+/// record the version of this crate that wrote them, which is reported by
+/// [`ModelVersionError`](crate::errors::ModelVersionError) when the model cannot be read.
+pub const MODEL_FORMAT_VERSION: &str = "0.6.0";
+
+/// Versions of the model format that this crate can read.
+pub const SUPPORTED_MODEL_FORMAT_VERSIONS: &[&str] = &["0.5.0", "0.6.0"];
+
+/// Prefix of magic numbers.
+const MODEL_MAGIC_PREFIX: &[u8] = b"VaporettoTokenizer ";
+
 /// Magic number.
-const MODEL_MAGIC: &[u8] = b"VaporettoTokenizer 0.5.0\n";
+const MODEL_MAGIC: &[u8] = b"VaporettoTokenizer 0.6.0\n";
+
+/// Magic number of models without metadata.
+const MODEL_MAGIC_0_5: &[u8] = b"VaporettoTokenizer 0.5.0\n";
+
+/// Layouts of model files distinguished by magic numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FormatVersion {
+    /// Model data only.
+    V0_5,
+
+    /// Header, model data, and metadata.
+    V0_6,
+}
+
+/// Parses a version string of the form `major.minor.patch`.
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut it = version.split('.').map(|v| v.parse().ok());
+    let version = (it.next()??, it.next()??, it.next()??);
+    it.next().is_none().then_some(version)
+}
+
+/// Set of optional data used by a model.
+///
+/// Capabilities are recorded in the header of model files. A runtime rejects a model using a
+/// capability unknown to it with a [`ModelVersionError`], which tells the version of this crate
+/// required to read the model, instead of failing in the middle of decoding.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{Model, ModelCapabilities};
+///
+/// let data = std::fs::read("../resources/model.bin").unwrap();
+/// let (model, _) = Model::read_slice(&data).unwrap();
+/// assert!(!model.capabilities().contains(ModelCapabilities::CALIBRATION));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ModelCapabilities(u64);
+
+impl ModelCapabilities {
+    /// The model predicts tags.
+    pub const TAGS: Self = Self(1 << 0);
+
+    /// The model has a normalization name.
+    pub const NORMALIZATION: Self = Self(1 << 1);
+
+    /// The model has user-defined character types.
+    pub const CHAR_TYPE_RANGES: Self = Self(1 << 2);
+
+    /// The model has a calibration of boundary scores.
+    pub const CALIBRATION: Self = Self(1 << 3);
+
+    /// The model has transition weights between tags.
+    pub const TAG_TRANSITIONS: Self = Self(1 << 4);
+
+    /// The model has a recommended pipeline of filters.
+    pub const PIPELINE: Self = Self(1 << 5);
+
+    /// All capabilities known to this crate.
+    const KNOWN: Self = Self((1 << 6) - 1);
+
+    /// Returns the empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw bits.
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if all the capabilities of `other` are contained.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the union of the two sets.
+    #[must_use]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns capabilities unknown to this crate.
+    const fn unknown(self) -> Self {
+        Self(self.0 & !Self::KNOWN.0)
+    }
+}
+
+impl core::ops::BitOr for ModelCapabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+/// Header added in the 0.6.0 format.
+#[derive(Debug, Decode, Encode)]
+struct ModelHeader {
+    /// Bits of [`ModelCapabilities`].
+    capabilities: u64,
+
+    /// Version of this crate that wrote the model, which can read all the capabilities used.
+    required_version: String,
+}
+
+impl ModelHeader {
+    fn new(capabilities: ModelCapabilities) -> Self {
+        Self {
+            capabilities: capabilities.bits(),
+            required_version: env!("CARGO_PKG_VERSION").into(),
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if ModelCapabilities(self.capabilities).unknown() == ModelCapabilities::empty() {
+            Ok(())
+        } else {
+            Err(ModelVersionError {
+                format_version: MODEL_FORMAT_VERSION.into(),
+                required_version: Some(self.required_version.clone()),
+            }
+            .into())
+        }
+    }
+}
 
 // For each token, a model is trained for every tag independently, but the scores of all tags are
 // calculated in parallel during prediction.
@@ -47,15 +196,229 @@ pub struct TagModel {
 }
 
 impl TagModel {
+    /// Creates a new tag model of the given token without n-gram weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - A token string.
+    /// * `tags` - A list of candidates of each tag.
+    /// * `bias` - A bias of the score array. This is a concatenation of biases of all candidates
+    ///   of tags having two or more candidates.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the token or any candidate set is empty, or the length
+    /// of `bias` does not match the number of candidates.
+    pub fn new(token: String, tags: Vec<Vec<String>>, bias: Vec<i32>) -> Result<Self> {
+        if token.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "token",
+                "must not be empty",
+            ));
+        }
+        if tags.iter().any(|cands| cands.is_empty()) {
+            return Err(VaporettoError::invalid_argument(
+                "tags",
+                "each tag must have at least one candidate",
+            ));
+        }
+        let tag_model = Self {
+            token,
+            tags,
+            char_ngram_model: TagNgramModel(vec![]),
+            type_ngram_model: TagNgramModel(vec![]),
+            bias,
+        };
+        if tag_model.bias.len() != tag_model.n_classes() {
+            return Err(VaporettoError::invalid_argument(
+                "bias",
+                "does not match the number of tag candidates",
+            ));
+        }
+        Ok(tag_model)
+    }
+
+    /// Adds weights of a character n-gram.
+    ///
+    /// # Arguments
+    ///
+    /// * `ngram` - A character n-gram.
+    /// * `rel_position` - The position of the n-gram relative to the end of the token.
+    /// * `weights` - Weights of the score array.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the n-gram is empty or the length of `weights` does
+    /// not match the number of candidates.
+    pub fn add_char_ngram(
+        &mut self,
+        ngram: String,
+        rel_position: u8,
+        weights: Vec<i32>,
+    ) -> Result<()> {
+        if ngram.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "ngram",
+                "must not be empty",
+            ));
+        }
+        self.check_weights(&weights)?;
+        Self::push_weight(
+            &mut self.char_ngram_model,
+            ngram,
+            TagWeight {
+                rel_position,
+                weights,
+            },
+        );
+        Ok(())
+    }
+
+    /// Adds weights of a character type n-gram.
+    ///
+    /// # Arguments
+    ///
+    /// * `ngram` - A character type n-gram. Each element is a type ID.
+    /// * `rel_position` - The position of the n-gram relative to the end of the token.
+    /// * `weights` - Weights of the score array.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the n-gram is empty, contains 0, or the length of
+    /// `weights` does not match the number of candidates.
+    pub fn add_type_ngram(
+        &mut self,
+        ngram: Vec<u8>,
+        rel_position: u8,
+        weights: Vec<i32>,
+    ) -> Result<()> {
+        check_type_ngram(&ngram)?;
+        self.check_weights(&weights)?;
+        Self::push_weight(
+            &mut self.type_ngram_model,
+            ngram,
+            TagWeight {
+                rel_position,
+                weights,
+            },
+        );
+        Ok(())
+    }
+
+    fn check_weights(&self, weights: &[i32]) -> Result<()> {
+        if weights.len() != self.n_classes() {
+            return Err(VaporettoError::invalid_argument(
+                "weights",
+                "does not match the number of tag candidates",
+            ));
+        }
+        Ok(())
+    }
+
+    fn push_weight<T>(model: &mut TagNgramModel<T>, ngram: T, weight: TagWeight)
+    where
+        T: PartialEq,
+    {
+        if let Some(d) = model.0.iter_mut().find(|d| d.ngram == ngram) {
+            d.weights.push(weight);
+        } else {
+            model.0.push(TagNgramData {
+                ngram,
+                weights: vec![weight],
+            });
+        }
+    }
+
     /// Returns the token string.
     pub fn token(&self) -> &str {
         &self.token
     }
+
+    /// Returns the length of the score array, i.e., the total number of candidates of tags
+    /// having two or more candidates.
+    fn n_classes(&self) -> usize {
+        self.tags
+            .iter()
+            .filter(|cands| cands.len() >= 2)
+            .map(|cands| cands.len())
+            .sum()
+    }
+}
+
+/// Inconsistencies between tag models and the boundary model.
+///
+/// This is returned by [`Model::check_tag_models()`] and [`Model::prune_tag_models()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TagModelReport {
+    /// The number of tag models whose biases do not match the number of tag candidates.
+    pub invalid_biases: usize,
+
+    /// The number of tag n-gram weights that do not match the number of tag candidates.
+    pub invalid_weights: usize,
+
+    /// The number of tag character n-grams containing characters that never appear in the
+    /// boundary model.
+    pub dangling_char_ngrams: usize,
+}
+
+impl TagModelReport {
+    /// Returns `true` if the tag models can be used for prediction.
+    ///
+    /// Dangling n-grams do not break prediction, so they are not considered.
+    pub const fn is_valid(&self) -> bool {
+        self.invalid_biases == 0 && self.invalid_weights == 0
+    }
+}
+
+/// Statistics of entries removed by [`Model::prune()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// The number of removed character n-grams.
+    pub removed_char_ngrams: usize,
+
+    /// The number of removed character type n-grams.
+    pub removed_type_ngrams: usize,
+
+    /// The number of removed dictionary words.
+    pub removed_dict_words: usize,
+
+    /// The total number of removed non-zero weights.
+    pub removed_weights: usize,
+
+    /// The sum of absolute values of removed weights.
+    pub removed_weight_sum: u64,
+}
+
+impl PruneReport {
+    /// Returns the expected perturbation of a boundary score caused by a removed entry, i.e.,
+    /// the mean absolute value of removed non-zero weights.
+    ///
+    /// Each matched entry changes a boundary score by less than the threshold, so the change of
+    /// a score is bounded by the threshold times the number of removed entries matched around
+    /// the boundary.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn expected_perturbation(&self) -> f64 {
+        if self.removed_weights == 0 {
+            0.0
+        } else {
+            self.removed_weight_sum as f64 / self.removed_weights as f64
+        }
+    }
+
+    fn add_weights(&mut self, weights: &[i32]) {
+        for &w in weights {
+            if w != 0 {
+                self.removed_weights += 1;
+                self.removed_weight_sum += u64::from(w.unsigned_abs());
+            }
+        }
+    }
 }
 
 /// Model data.
 #[derive(Debug)]
-pub struct Model(pub(crate) ModelData);
+pub struct Model(pub(crate) ModelData, pub(crate) ModelMetadata);
 
 #[derive(Debug, Decode, Encode)]
 pub struct ModelData {
@@ -69,6 +432,269 @@ pub struct ModelData {
     pub(crate) tag_models: Vec<TagModel>,
 }
 
+/// Additional information added in the 0.6.0 format.
+///
+/// Models of the old format do not contain this data, so all fields must have their default
+/// values when the information is not known.
+#[derive(Debug, Default, Decode, Encode)]
+pub struct ModelMetadata {
+    /// Name of the character normalization applied to the training corpus.
+    pub(crate) normalization: Option<String>,
+
+    /// User-defined character types.
+    pub(crate) char_type_ranges: CharTypeRanges,
+
+    /// Calibration of boundary scores.
+    pub(crate) calibration: Option<PlattScaling>,
+
+    /// Transition weights between tags of adjacent tokens for each tag level.
+    pub(crate) tag_transitions: Vec<Vec<(String, String, i32)>>,
+
+    /// Names of filters recommended to be applied with the model.
+    pub(crate) pipeline: Vec<String>,
+}
+
+fn check_type_ngram(ngram: &[u8]) -> Result<()> {
+    if ngram.is_empty() {
+        return Err(VaporettoError::invalid_argument(
+            "ngram",
+            "must not be empty",
+        ));
+    }
+    if ngram.contains(&0) {
+        return Err(VaporettoError::invalid_argument(
+            "ngram",
+            "type IDs must not be 0",
+        ));
+    }
+    Ok(())
+}
+
+/// Builder of [`Model`]s.
+///
+/// This builder checks invariants of the model so that the built model can always be used by
+/// [`Predictor`](crate::Predictor).
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{CharacterType, ModelBuilder, Predictor, Sentence, WordWeightRecord};
+///
+/// let mut builder = ModelBuilder::new(2, 1);
+/// builder.set_bias(-100);
+/// // The weight length must be `char_window_size * 2 - n + 1`.
+/// builder.add_char_ngram("火星".into(), vec![0, 0, 200]).unwrap();
+/// builder
+///     .add_type_ngram(vec![CharacterType::Kanji as u8], vec![0, 50])
+///     .unwrap();
+/// builder
+///     .add_dict_word(WordWeightRecord::new("猫".into(), vec![300, 300], "".into()).unwrap())
+///     .unwrap();
+/// let model = builder.build();
+///
+/// let predictor = Predictor::new(model, false).unwrap();
+/// let mut s = Sentence::from_raw("火星猫").unwrap();
+/// predictor.predict(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("火星 猫", buf);
+/// ```
+#[derive(Debug, Default)]
+pub struct ModelBuilder {
+    char_window_size: u8,
+    type_window_size: u8,
+    char_ngrams: BTreeMap<String, Vec<i32>>,
+    type_ngrams: BTreeMap<Vec<u8>, Vec<i32>>,
+    dict: BTreeMap<String, WordWeightRecord>,
+    bias: i32,
+    tag_models: BTreeMap<String, TagModel>,
+}
+
+impl ModelBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `char_window_size` - The character window size.
+    /// * `type_window_size` - The character type window size.
+    pub fn new(char_window_size: u8, type_window_size: u8) -> Self {
+        Self {
+            char_window_size,
+            type_window_size,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the bias of word boundaries.
+    pub fn set_bias(&mut self, bias: i32) {
+        self.bias = bias;
+    }
+
+    /// Sets weights of a character n-gram.
+    ///
+    /// The `i`-th weight is added to the `i`-th boundary from the position `char_window_size`
+    /// characters before the start of the n-gram, so the length of `weights` must be
+    /// `char_window_size * 2 - n + 1`, where `n` is the length of the n-gram in characters.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the n-gram is empty, longer than the window, already
+    /// added, or the length of `weights` is invalid.
+    pub fn add_char_ngram(&mut self, ngram: String, weights: Vec<i32>) -> Result<()> {
+        let len = ngram.chars().count();
+        Self::check_ngram_weights(len, self.char_window_size, &weights)?;
+        if self.char_ngrams.contains_key(&ngram) {
+            return Err(VaporettoError::invalid_argument("ngram", "already added"));
+        }
+        self.char_ngrams.insert(ngram, weights);
+        Ok(())
+    }
+
+    /// Sets weights of a character type n-gram.
+    ///
+    /// The length of `weights` must be `type_window_size * 2 - n + 1`, where `n` is the length of
+    /// the n-gram. See also [`ModelBuilder::add_char_ngram()`].
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the n-gram is empty, contains 0, longer than the
+    /// window, already added, or the length of `weights` is invalid.
+    pub fn add_type_ngram(&mut self, ngram: Vec<u8>, weights: Vec<i32>) -> Result<()> {
+        check_type_ngram(&ngram)?;
+        Self::check_ngram_weights(ngram.len(), self.type_window_size, &weights)?;
+        if self.type_ngrams.contains_key(&ngram) {
+            return Err(VaporettoError::invalid_argument("ngram", "already added"));
+        }
+        self.type_ngrams.insert(ngram, weights);
+        Ok(())
+    }
+
+    fn check_ngram_weights(len: usize, window_size: u8, weights: &[i32]) -> Result<()> {
+        let window_size = usize::from(window_size);
+        if len == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "ngram",
+                "must not be empty",
+            ));
+        }
+        if len > window_size * 2 {
+            return Err(VaporettoError::invalid_argument(
+                "ngram",
+                "must be shorter than or equal to twice the window size",
+            ));
+        }
+        if weights.len() != window_size * 2 - len + 1 {
+            return Err(VaporettoError::invalid_argument(
+                "weights",
+                "does not match the window size and the length of the n-gram",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds a dictionary word.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the word is empty, longer than 32767 characters,
+    /// already added, or the number of weights is not the number of characters plus one.
+    pub fn add_dict_word(&mut self, record: WordWeightRecord) -> Result<()> {
+        let len = record.word.chars().count();
+        if len == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "record",
+                "word must not be empty",
+            ));
+        }
+        if len > usize::from(MAX_WORD_LEN) {
+            return Err(VaporettoError::invalid_argument(
+                "record",
+                format!("words must be shorter than or equal to {MAX_WORD_LEN} characters"),
+            ));
+        }
+        if record.weights.len() != len + 1 {
+            return Err(VaporettoError::invalid_argument(
+                "record",
+                "weights does not match the length of the word",
+            ));
+        }
+        if self.dict.contains_key(&record.word) {
+            return Err(VaporettoError::invalid_argument("record", "already added"));
+        }
+        self.dict.insert(record.word.clone(), record);
+        Ok(())
+    }
+
+    /// Adds a tag model.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if a model of the same token is already added, or
+    /// relative positions of n-grams exceed the window sizes.
+    pub fn add_tag_model(&mut self, tag_model: TagModel) -> Result<()> {
+        if tag_model
+            .char_ngram_model
+            .0
+            .iter()
+            .flat_map(|d| &d.weights)
+            .any(|w| w.rel_position > self.char_window_size)
+        {
+            return Err(VaporettoError::invalid_argument(
+                "tag_model",
+                "relative positions must be smaller than or equal to the character window size",
+            ));
+        }
+        if tag_model
+            .type_ngram_model
+            .0
+            .iter()
+            .flat_map(|d| &d.weights)
+            .any(|w| w.rel_position > self.type_window_size)
+        {
+            return Err(VaporettoError::invalid_argument(
+                "tag_model",
+                "relative positions must be smaller than or equal to the type window size",
+            ));
+        }
+        if self.tag_models.contains_key(&tag_model.token) {
+            return Err(VaporettoError::invalid_argument(
+                "tag_model",
+                "already added",
+            ));
+        }
+        self.tag_models.insert(tag_model.token.clone(), tag_model);
+        Ok(())
+    }
+
+    /// Builds a model.
+    ///
+    /// This never fails because all entries are checked when they are added.
+    pub fn build(self) -> Model {
+        Model(
+            ModelData {
+                char_ngram_model: NgramModel(
+                    self.char_ngrams
+                        .into_iter()
+                        .map(|(ngram, weights)| NgramData { ngram, weights })
+                        .collect(),
+                ),
+                type_ngram_model: NgramModel(
+                    self.type_ngrams
+                        .into_iter()
+                        .map(|(ngram, weights)| NgramData { ngram, weights })
+                        .collect(),
+                ),
+                dict_model: DictModel::new(self.dict.into_values().collect()),
+                bias: self.bias,
+                char_window_size: self.char_window_size,
+                type_window_size: self.type_window_size,
+                tag_models: self.tag_models.into_values().collect(),
+            },
+            ModelMetadata::default(),
+        )
+    }
+}
+
 impl Model {
     #[cfg(any(feature = "train", feature = "kytea", test))]
     pub(crate) const fn new(
@@ -80,15 +706,24 @@ impl Model {
         type_window_size: u8,
         tag_models: Vec<TagModel>,
     ) -> Self {
-        Self(ModelData {
-            char_ngram_model,
-            type_ngram_model,
-            dict_model,
-            bias,
-            char_window_size,
-            type_window_size,
-            tag_models,
-        })
+        Self(
+            ModelData {
+                char_ngram_model,
+                type_ngram_model,
+                dict_model,
+                bias,
+                char_window_size,
+                type_window_size,
+                tag_models,
+            },
+            ModelMetadata {
+                normalization: None,
+                char_type_ranges: CharTypeRanges::new(),
+                calibration: None,
+                tag_transitions: vec![],
+                pipeline: vec![],
+            },
+        )
     }
 
     /// Exports the model data into a [`Vec`].
@@ -99,7 +734,9 @@ impl Model {
     pub fn to_vec(&self) -> Result<Vec<u8>> {
         let mut wtr = VecWriter(MODEL_MAGIC.to_vec());
         let config = bincode::config::standard();
+        bincode::encode_into_writer(ModelHeader::new(self.capabilities()), &mut wtr, config)?;
         bincode::encode_into_writer(&self.0, &mut wtr, config)?;
+        bincode::encode_into_writer(&self.1, &mut wtr, config)?;
         Ok(wtr.0)
     }
 
@@ -115,29 +752,204 @@ impl Model {
     {
         wtr.write_all(MODEL_MAGIC)?;
         let config = bincode::config::standard();
+        bincode::encode_into_std_write(ModelHeader::new(self.capabilities()), &mut wtr, config)?;
         bincode::encode_into_std_write(&self.0, &mut wtr, config)?;
+        bincode::encode_into_std_write(&self.1, &mut wtr, config)?;
         Ok(())
     }
 
+    /// Returns the capabilities used by the model.
+    ///
+    /// The capabilities are recorded in the header of model files written by [`Model::write()`].
+    pub fn capabilities(&self) -> ModelCapabilities {
+        let flags = [
+            (!self.0.tag_models.is_empty(), ModelCapabilities::TAGS),
+            (
+                self.1.normalization.is_some(),
+                ModelCapabilities::NORMALIZATION,
+            ),
+            (
+                !self.1.char_type_ranges.is_empty(),
+                ModelCapabilities::CHAR_TYPE_RANGES,
+            ),
+            (self.1.calibration.is_some(), ModelCapabilities::CALIBRATION),
+            (
+                !self.1.tag_transitions.is_empty(),
+                ModelCapabilities::TAG_TRANSITIONS,
+            ),
+            (!self.1.pipeline.is_empty(), ModelCapabilities::PIPELINE),
+        ];
+        flags
+            .into_iter()
+            .filter(|&(yes, _)| yes)
+            .fold(ModelCapabilities::empty(), |acc, (_, c)| acc | c)
+    }
+
+    /// Exports the model data into a file atomically.
+    ///
+    /// The model is written into a temporary file, which is flushed to the disk and renamed to
+    /// `path`, so an interrupted run never leaves a truncated model. See
+    /// [`write_atomically()`](crate::write_atomically) for writing compressed models.
+    ///
+    /// # Errors
+    ///
+    /// When writing the file fails, an error variant will be returned.
+    /// When bincode generates an error, it will be returned as is.
+    #[cfg(feature = "std")]
+    pub fn write_to_path<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        crate::write_atomically(path, |f| {
+            let mut wtr = std::io::BufWriter::new(f);
+            self.write(&mut wtr)?;
+            wtr.flush()?;
+            Ok(())
+        })
+    }
+
+    /// Exports the model data in the format of the given older version so that the model can be
+    /// read by older runtimes.
+    ///
+    /// The following versions are supported:
+    ///
+    /// * `"0.6"` - The current format. This is the same as [`Model::write()`].
+    /// * `"0.5"` - The format without metadata. The normalization name, user-defined character
+    ///   types, calibration, tag transitions, and the pipeline are dropped, so prediction results
+    ///   can change if the model has user-defined character types.
+    ///
+    /// # Errors
+    ///
+    /// If the version is not supported, an error variant will be returned.
+    /// When bincode generates an error, it will be returned as is.
+    #[cfg(feature = "std")]
+    pub fn write_downgraded<W>(&self, mut wtr: W, version: &str) -> Result<()>
+    where
+        W: Write,
+    {
+        match version {
+            "0.6" => self.write(wtr),
+            "0.5" => {
+                wtr.write_all(MODEL_MAGIC_0_5)?;
+                let config = bincode::config::standard();
+                bincode::encode_into_std_write(&self.0, &mut wtr, config)?;
+                Ok(())
+            }
+            _ => Err(VaporettoError::invalid_argument(
+                "version",
+                "must be 0.5 or 0.6",
+            )),
+        }
+    }
+
+    /// Returns the format version of the given model data by inspecting its header.
+    ///
+    /// Only the header is read, so this function can be used to check compatibility of model
+    /// files fetched at runtime before loading them. The returned version can be compared with
+    /// [`MODEL_FORMAT_VERSION`] and [`SUPPORTED_MODEL_FORMAT_VERSIONS`].
+    ///
+    /// # Errors
+    ///
+    /// If the data is not a model of Vaporetto, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{Model, SUPPORTED_MODEL_FORMAT_VERSIONS};
+    ///
+    /// let data = std::fs::read("../resources/model.bin").unwrap();
+    /// let version = Model::format_version(&data).unwrap();
+    /// assert!(SUPPORTED_MODEL_FORMAT_VERSIONS.contains(&version));
+    ///
+    /// assert!(Model::format_version(b"foo").is_err());
+    /// ```
+    pub fn format_version(data: &[u8]) -> Result<&str> {
+        data.strip_prefix(MODEL_MAGIC_PREFIX)
+            .and_then(|rest| {
+                let len = rest.iter().position(|&b| b == b'\n')?;
+                core::str::from_utf8(&rest[..len]).ok()
+            })
+            .ok_or_else(|| VaporettoError::invalid_model("not a model of Vaporetto"))
+    }
+
+    fn magic_error(data: &[u8]) -> VaporettoError {
+        match Self::format_version(data) {
+            Ok(version) => match (parse_version(version), parse_version(MODEL_FORMAT_VERSION)) {
+                (Some(found), Some(current)) if found > current => ModelVersionError {
+                    format_version: version.into(),
+                    // The header of an unknown format cannot be read.
+                    required_version: None,
+                }
+                .into(),
+                _ => VaporettoError::invalid_model(format!(
+                    "unsupported model format version {version} (supported versions: {})",
+                    SUPPORTED_MODEL_FORMAT_VERSIONS.join(", "),
+                )),
+            },
+            Err(e) => e,
+        }
+    }
+
+    /// Checks the magic number and returns the layout of the model.
+    fn parse_magic(magic: &[u8]) -> Result<FormatVersion> {
+        if magic == MODEL_MAGIC {
+            Ok(FormatVersion::V0_6)
+        } else if magic == MODEL_MAGIC_0_5 {
+            Ok(FormatVersion::V0_5)
+        } else {
+            Err(Self::magic_error(magic))
+        }
+    }
+
     /// Creates a model from a slice and returns a tuple of the model and the remaining slice.
     ///
     /// # Errors
     ///
+    /// If the model is written in a newer format or uses capabilities unknown to this crate,
+    /// [`VaporettoError::ModelVersion`] will be returned.
     /// When bincode generates an error, it will be returned as is.
     pub fn read_slice(slice: &[u8]) -> Result<(Self, &[u8])> {
-        if &slice[..MODEL_MAGIC.len()] != MODEL_MAGIC {
-            return Err(VaporettoError::invalid_model("model version mismatch"));
-        }
+        let magic = slice
+            .get(..MODEL_MAGIC.len())
+            .ok_or_else(|| Self::magic_error(slice))?;
+        let version = Self::parse_magic(magic)?;
         let config = bincode::config::standard();
-        let (data, size) = bincode::decode_from_slice(&slice[MODEL_MAGIC.len()..], config)?;
-        Ok((Self(data), &slice[MODEL_MAGIC.len() + size..]))
+        let mut slice = &slice[MODEL_MAGIC.len()..];
+        if version == FormatVersion::V0_6 {
+            let (header, size) = bincode::decode_from_slice::<ModelHeader, _>(slice, config)?;
+            header.check()?;
+            slice = &slice[size..];
+        }
+        let (data, size) = bincode::decode_from_slice(slice, config)?;
+        let slice = &slice[size..];
+        if version == FormatVersion::V0_5 {
+            Ok((Self(data, ModelMetadata::default()), slice))
+        } else {
+            let (metadata, size) = bincode::decode_from_slice(slice, config)?;
+            Ok((Self(data, metadata), &slice[size..]))
+        }
     }
 
     /// Creates a model from a reader.
     ///
     /// # Errors
     ///
+    /// If the model is written in a newer format or uses capabilities unknown to this crate,
+    /// [`VaporettoError::ModelVersion`] will be returned.
     /// When bincode generates an error, it will be returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{errors::VaporettoError, Model};
+    ///
+    /// let data = b"VaporettoTokenizer 9.9.9\n";
+    /// let Err(VaporettoError::ModelVersion(e)) = Model::read(&data[..]) else {
+    ///     panic!();
+    /// };
+    /// assert_eq!("9.9.9", e.format_version());
+    /// assert_eq!(None, e.required_version());
+    /// ```
     #[cfg(feature = "std")]
     pub fn read<R>(mut rdr: R) -> Result<Self>
     where
@@ -145,11 +957,74 @@ impl Model {
     {
         let mut magic = [0; MODEL_MAGIC.len()];
         rdr.read_exact(&mut magic)?;
-        if magic != MODEL_MAGIC {
-            return Err(VaporettoError::invalid_model("model version mismatch"));
-        }
+        let version = Self::parse_magic(&magic)?;
         let config = bincode::config::standard();
-        Ok(Self(bincode::decode_from_std_read(&mut rdr, config)?))
+        if version == FormatVersion::V0_6 {
+            let header: ModelHeader = bincode::decode_from_std_read(&mut rdr, config)?;
+            header.check()?;
+        }
+        let data = bincode::decode_from_std_read(&mut rdr, config)?;
+        let metadata = if version == FormatVersion::V0_6 {
+            bincode::decode_from_std_read(&mut rdr, config)?
+        } else {
+            ModelMetadata::default()
+        };
+        Ok(Self(data, metadata))
+    }
+
+    /// Creates a model from an asynchronous reader.
+    ///
+    /// Only reading is asynchronous. The reader is read until the end of the stream without
+    /// blocking the executor, but the model is then decoded from the read data synchronously in
+    /// the current task. Like [`Predictor::new()`](crate::Predictor::new), decoding is CPU-bound
+    /// and may block the executor thread for a while for large models.
+    ///
+    /// # Errors
+    ///
+    /// When the reader or bincode generates an error, it will be returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use vaporetto::{Model, Predictor};
+    ///
+    /// # async fn load() -> Result<Predictor, Box<dyn std::error::Error>> {
+    /// let f = tokio::fs::File::open("path/to/model.bin").await?;
+    /// let model = Model::read_async(tokio::io::BufReader::new(f)).await?;
+    /// let predictor = Predictor::new(model, true)?;
+    /// # Ok(predictor)
+    /// # }
+    /// ```
+    ///
+    /// To keep the executor responsive while loading a large model, read the data
+    /// asynchronously and move decoding to a blocking thread instead:
+    ///
+    /// ```no_run
+    /// use vaporetto::{Model, Predictor};
+    ///
+    /// # async fn load() -> Result<Predictor, Box<dyn std::error::Error>> {
+    /// let data = tokio::fs::read("path/to/model.bin").await?;
+    /// let predictor = tokio::task::spawn_blocking(move || {
+    ///     let (model, _) = Model::read_slice(&data)?;
+    ///     Predictor::new(model, true)
+    /// })
+    /// .await??;
+    /// # Ok(predictor)
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub async fn read_async<R>(mut rdr: R) -> Result<Self>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut magic = [0; MODEL_MAGIC.len()];
+        rdr.read_exact(&mut magic).await?;
+        Self::parse_magic(&magic)?;
+        let mut data = magic.to_vec();
+        rdr.read_to_end(&mut data).await?;
+        let (model, _) = Self::read_slice(&data)?;
+        Ok(model)
     }
 
     /// Returns the slice of dictionary words.
@@ -157,13 +1032,953 @@ impl Model {
         self.0.dict_model.dictionary()
     }
 
+    /// Returns an iterator over dictionary words that can be narrowed down by patterns.
+    ///
+    /// See [`DictEntries`](crate::DictEntries) for the available patterns.
+    pub fn dictionary_entries(&self) -> DictEntries<'_, 'static> {
+        self.0.dict_model.iter()
+    }
+
     /// Replaces the dictionary with the given data.
     pub fn replace_dictionary(&mut self, dict: Vec<WordWeightRecord>) {
         self.0.dict_model = DictModel::new(dict);
     }
 
+    /// Adds the given words to the dictionary.
+    ///
+    /// Words already in the dictionary keep their existing weights, and only the first of
+    /// duplicated words in `dict` is added. Returns the number of added words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{ModelBuilder, WordWeightRecord};
+    ///
+    /// let mut builder = ModelBuilder::new(3, 3);
+    /// builder
+    ///     .add_dict_word(WordWeightRecord::new("猫".into(), vec![300, 300], "".into()).unwrap())
+    ///     .unwrap();
+    /// let mut model = builder.build();
+    /// let n_added = model.merge_dict(vec![
+    ///     WordWeightRecord::new("猫".into(), vec![100, 100], "".into()).unwrap(),
+    ///     WordWeightRecord::new("火星".into(), vec![100, -100, 100], "".into()).unwrap(),
+    /// ]);
+    /// assert_eq!(1, n_added);
+    /// assert_eq!(&[300, 300], model.dictionary()[0].get_weights());
+    /// assert_eq!("火星", model.dictionary()[1].get_word());
+    /// ```
+    pub fn merge_dict(&mut self, dict: Vec<WordWeightRecord>) -> usize {
+        let records = &mut self.0.dict_model.0;
+        let n_words = records.len();
+        let mut words: HashSet<String> = records.iter().map(|r| r.word.clone()).collect();
+        for record in dict {
+            if words.insert(record.word.clone()) {
+                records.push(record);
+            }
+        }
+        records.len() - n_words
+    }
+
+    /// Limits the number of dictionary words applied at each position.
+    ///
+    /// Adversarial or noisy dictionaries can contain many words that are suffixes of each other,
+    /// and all of them are applied when the longest one matches. This function keeps at most
+    /// `max_matches` words with the largest absolute weights for each match and returns the
+    /// number of words whose matches were capped.
+    ///
+    /// # Errors
+    ///
+    /// If `max_matches` is 0, an error variant will be returned.
+    pub fn limit_dictionary_matches(&mut self, max_matches: usize) -> Result<usize> {
+        self.0.dict_model.limit_matches(max_matches)
+    }
+
+    /// Returns the name of the character normalization applied to the training corpus.
+    ///
+    /// `None` is returned if the model does not record it.
+    pub fn normalization(&self) -> Option<&str> {
+        self.1.normalization.as_deref()
+    }
+
+    /// Records the name of the character normalization applied to the training corpus.
+    ///
+    /// The name is an arbitrary string, but applications should agree on it to check the
+    /// compatibility with [`Model::assert_normalization()`].
+    pub fn set_normalization(&mut self, name: Option<String>) {
+        self.1.normalization = name;
+    }
+
+    /// Checks that the given normalization matches the one recorded in the model.
+    ///
+    /// This function always succeeds if the model does not record the normalization.
+    ///
+    /// # Errors
+    ///
+    /// If the model is trained with another normalization, an error variant will be returned.
+    pub fn assert_normalization(&self, name: &str) -> Result<()> {
+        match self.normalization() {
+            Some(expected) if expected != name => Err(VaporettoError::invalid_argument(
+                "name",
+                format!("the model expects `{expected}` normalization, but `{name}` is given"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a warning message if the given normalization does not match the one recorded in
+    /// the model.
+    ///
+    /// This is a non-fatal version of [`Model::assert_normalization()`] for applications that
+    /// proceed with the mismatched normalization.
+    pub fn normalization_warning(&self, name: &str) -> Option<String> {
+        self.assert_normalization(name).err().map(|_| {
+            format!(
+                "the model is trained with `{}` normalization, but `{name}` is applied",
+                self.normalization().unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Returns user-defined character types.
+    pub const fn char_type_ranges(&self) -> &CharTypeRanges {
+        &self.1.char_type_ranges
+    }
+
+    /// Sets user-defined character types applied before prediction.
+    ///
+    /// The model must be trained with sentences processed by
+    /// [`Sentence::apply_char_type_ranges()`](crate::Sentence::apply_char_type_ranges) with the
+    /// same ranges.
+    pub fn set_char_type_ranges(&mut self, ranges: CharTypeRanges) {
+        self.1.char_type_ranges = ranges;
+    }
+
+    /// Returns the calibration of boundary scores.
+    pub const fn calibration(&self) -> Option<&PlattScaling> {
+        self.1.calibration.as_ref()
+    }
+
+    /// Sets the calibration used by
+    /// [`Sentence::boundary_probabilities()`](crate::Sentence::boundary_probabilities).
+    pub fn set_calibration(&mut self, calibration: Option<PlattScaling>) {
+        self.1.calibration = calibration;
+    }
+
+    /// Returns transition weights between tags. The `i`-th element holds the weights of the
+    /// `i`-th tag level. See [`Model::set_tag_transitions()`].
+    pub fn tag_transitions(&self) -> &[Vec<(String, String, i32)>] {
+        &self.1.tag_transitions
+    }
+
+    /// Sets transition weights between tags of the given level used by
+    /// [`Sentence::fill_tags_joint()`](crate::Sentence::fill_tags_joint).
+    ///
+    /// Each element is a tuple of the tag of the preceding token, the tag of the following token,
+    /// and the weight added to the score of the tag sequence. Pairs not contained in the list
+    /// have a weight of 0. An empty list removes transitions of the level.
+    pub fn set_tag_transitions(&mut self, level: usize, transitions: Vec<(String, String, i32)>) {
+        if self.1.tag_transitions.len() <= level {
+            self.1.tag_transitions.resize(level + 1, vec![]);
+        }
+        self.1.tag_transitions[level] = transitions;
+        while self.1.tag_transitions.last().is_some_and(Vec::is_empty) {
+            self.1.tag_transitions.pop();
+        }
+    }
+
+    /// Returns names of filters recommended to be applied with the model, in the order of
+    /// application.
+    ///
+    /// The names are interpreted by applications, e.g., `PipelineBuilder::spec()` of
+    /// vaporetto_rules.
+    pub fn pipeline(&self) -> &[String] {
+        &self.1.pipeline
+    }
+
+    /// Records names of filters recommended to be applied with the model.
+    pub fn set_pipeline(&mut self, pipeline: Vec<String>) {
+        self.1.pipeline = pipeline;
+    }
+
+    fn boundary_chars(&self) -> HashSet<char> {
+        let mut chars = HashSet::new();
+        for d in &self.0.char_ngram_model.0 {
+            chars.extend(d.ngram.chars());
+        }
+        for d in &self.0.dict_model.0 {
+            chars.extend(d.word.chars());
+        }
+        chars
+    }
+
+    /// Checks that tag models are consistent with their tag candidates and the boundary model.
+    ///
+    /// Models converted from other formats may contain tag n-grams referencing characters
+    /// absent from the boundary model or weights whose lengths do not match tag candidates.
+    pub fn check_tag_models(&self) -> TagModelReport {
+        let boundary_chars = self.boundary_chars();
+        let mut report = TagModelReport::default();
+        for tag_model in &self.0.tag_models {
+            let n_classes = tag_model.n_classes();
+            if tag_model.bias.len() != n_classes {
+                report.invalid_biases += 1;
+            }
+            for d in &tag_model.char_ngram_model.0 {
+                if !d.ngram.chars().all(|c| boundary_chars.contains(&c)) {
+                    report.dangling_char_ngrams += 1;
+                }
+                report.invalid_weights += d
+                    .weights
+                    .iter()
+                    .filter(|w| w.weights.len() != n_classes)
+                    .count();
+            }
+            for d in &tag_model.type_ngram_model.0 {
+                report.invalid_weights += d
+                    .weights
+                    .iter()
+                    .filter(|w| w.weights.len() != n_classes)
+                    .count();
+            }
+        }
+        report
+    }
+
+    /// Fixes inconsistencies found by [`Model::check_tag_models()`] and returns the numbers of
+    /// fixed entries.
+    ///
+    /// Invalid weights and dangling n-grams are removed, and invalid biases are truncated or
+    /// padded with zeros.
+    pub fn prune_tag_models(&mut self) -> TagModelReport {
+        self.fix_tag_models(true)
+    }
+
+    /// Fixes inconsistencies of tag models like [`Model::prune_tag_models()`].
+    ///
+    /// If `prune_dangling` is false, dangling character n-grams are only counted because the tag
+    /// predictor can still use them.
+    pub(crate) fn fix_tag_models(&mut self, prune_dangling: bool) -> TagModelReport {
+        let boundary_chars = self.boundary_chars();
+        let mut report = TagModelReport::default();
+        for tag_model in &mut self.0.tag_models {
+            let n_classes = tag_model.n_classes();
+            if tag_model.bias.len() != n_classes {
+                tag_model.bias.resize(n_classes, 0);
+                report.invalid_biases += 1;
+            }
+            tag_model.char_ngram_model.0.retain_mut(|d| {
+                if !d.ngram.chars().all(|c| boundary_chars.contains(&c)) {
+                    report.dangling_char_ngrams += 1;
+                    if prune_dangling {
+                        return false;
+                    }
+                }
+                let len = d.weights.len();
+                d.weights.retain(|w| w.weights.len() == n_classes);
+                report.invalid_weights += len - d.weights.len();
+                !d.weights.is_empty()
+            });
+            tag_model.type_ngram_model.0.retain_mut(|d| {
+                let len = d.weights.len();
+                d.weights.retain(|w| w.weights.len() == n_classes);
+                report.invalid_weights += len - d.weights.len();
+                !d.weights.is_empty()
+            });
+        }
+        report
+    }
+
+    /// Removes character n-grams, character type n-grams, and dictionary words whose absolute
+    /// weights are all less than `threshold`.
+    ///
+    /// This reduces the model size at the cost of a small accuracy loss. Tag models are not
+    /// changed.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::Model;
+
+let mut model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let report = model.prune(1000);
+
+assert_eq!(1, report.removed_char_ngrams);
+assert!(report.expected_perturbation() < 1000.0);
+# Ok(())
+# }
+```
+"
+    )]
+    pub fn prune(&mut self, threshold: i32) -> PruneReport {
+        let threshold = threshold.unsigned_abs();
+        let is_small = |weights: &[i32]| weights.iter().all(|w| w.unsigned_abs() < threshold);
+        let mut report = PruneReport::default();
+        self.0.char_ngram_model.0.retain(|d| {
+            if is_small(&d.weights) {
+                report.removed_char_ngrams += 1;
+                report.add_weights(&d.weights);
+                false
+            } else {
+                true
+            }
+        });
+        self.0.type_ngram_model.0.retain(|d| {
+            if is_small(&d.weights) {
+                report.removed_type_ngrams += 1;
+                report.add_weights(&d.weights);
+                false
+            } else {
+                true
+            }
+        });
+        self.0.dict_model.0.retain(|d| {
+            if is_small(&d.weights) {
+                report.removed_dict_words += 1;
+                report.add_weights(&d.weights);
+                false
+            } else {
+                true
+            }
+        });
+        report
+    }
+
     /// Returns the slice of tag models.
     pub fn tag_models(&self) -> &[TagModel] {
         &self.0.tag_models
     }
+
+    /// Removes all tag models and returns the number of removed models.
+    pub fn remove_tag_models(&mut self) -> usize {
+        let n = self.0.tag_models.len();
+        self.0.tag_models.clear();
+        n
+    }
+
+    /// Writes the character n-grams and their weights in TSV format.
+    ///
+    /// The first line is the header `ngram`, `first`, `last`, and `weights`, and each of the
+    /// following lines contains an n-gram:
+    ///
+    /// * `ngram` - The n-gram. Backslashes, tabs, and line breaks are escaped as `\\`, `\t`,
+    ///   `\n`, and `\r`.
+    /// * `first` and `last` - The range of the boundaries that the weights are added to, relative
+    ///   to the n-gram. The boundary `k` is placed after the `k`-th character of the n-gram
+    ///   (0-origin), e.g., `-1` is the boundary just before the n-gram, and `0` is the one
+    ///   between its first and second characters.
+    /// * `weights` - Space-separated weights added to the boundaries from `first` to `last`.
+    ///
+    /// # Errors
+    ///
+    /// When writing fails, an error variant will be returned.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn dump_char_ngrams<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        write_ngram_tsv(
+            wtr,
+            self.0.char_window_size,
+            self.0
+                .char_ngram_model
+                .0
+                .iter()
+                .map(|d| (escape_tsv(&d.ngram), d.ngram.chars().count(), &d.weights)),
+        )
+    }
+
+    /// Writes the character type n-grams and their weights in TSV format.
+    ///
+    /// The format is the same as [`Model::dump_char_ngrams()`]. Character types in n-grams are
+    /// represented by `D` (digit), `R` (roman), `H` (hiragana), `T` (katakana), `K` (kanji), and
+    /// `O` (other), and user-defined types are represented by their IDs in angle brackets, e.g.,
+    /// `<7>`.
+    ///
+    /// # Errors
+    ///
+    /// When writing fails, an error variant will be returned.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn dump_type_ngrams<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        write_ngram_tsv(
+            wtr,
+            self.0.type_window_size,
+            self.0
+                .type_ngram_model
+                .0
+                .iter()
+                .map(|d| (type_ngram_to_string(&d.ngram), d.ngram.len(), &d.weights)),
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+fn escape_tsv(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(feature = "std")]
+fn type_ngram_to_string(ngram: &[u8]) -> String {
+    let mut result = String::new();
+    for &t in ngram {
+        match t {
+            1 => result.push('D'),
+            2 => result.push('R'),
+            3 => result.push('H'),
+            4 => result.push('T'),
+            5 => result.push('K'),
+            6 => result.push('O'),
+            t => result.push_str(&format!("<{t}>")),
+        }
+    }
+    result
+}
+
+/// Writes n-grams in the format of [`Model::dump_char_ngrams()`].
+#[cfg(feature = "std")]
+fn write_ngram_tsv<'a, W, I>(mut wtr: W, window_size: u8, ngrams: I) -> Result<()>
+where
+    W: Write,
+    I: Iterator<Item = (String, usize, &'a Vec<i32>)>,
+{
+    writeln!(wtr, "ngram\tfirst\tlast\tweights")?;
+    for (ngram, len, weights) in ngrams {
+        // The `pos`-th weight is added to the boundary `len + pos - window_size - 1`.
+        let first = isize::try_from(len)? - isize::from(window_size) - 1;
+        let last = first + isize::try_from(weights.len())? - 1;
+        write!(wtr, "{ngram}\t{first}\t{last}\t")?;
+        for (i, w) in weights.iter().enumerate() {
+            if i != 0 {
+                write!(wtr, " ")?;
+            }
+            write!(wtr, "{w}")?;
+        }
+        writeln!(wtr)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::ToString;
+
+    fn create_test_model() -> Model {
+        Model::new(
+            NgramModel(vec![NgramData {
+                ngram: "この人".into(),
+                weights: vec![1, -2, 3, 4],
+            }]),
+            NgramModel(vec![]),
+            DictModel(vec![]),
+            -5,
+            3,
+            3,
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_read_write_metadata() {
+        let mut model = create_test_model();
+        model.set_normalization(Some("kytea-fullwidth".into()));
+        model.set_calibration(Some(PlattScaling::new(-0.5, 0.25)));
+        model.set_pipeline(vec!["kytea-fullwidth".into(), "wsconst:D".into()]);
+        let data = model.to_vec().unwrap();
+        let (model, rest) = Model::read_slice(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(Some("kytea-fullwidth"), model.normalization());
+        assert_eq!(Some(&PlattScaling::new(-0.5, 0.25)), model.calibration());
+        assert_eq!(&["kytea-fullwidth", "wsconst:D"], model.pipeline());
+        assert_eq!(-5, model.0.bias);
+    }
+
+    #[test]
+    fn test_set_tag_transitions() {
+        let mut model = create_test_model();
+        model.set_tag_transitions(1, vec![("名詞".into(), "助詞".into(), 3)]);
+        assert_eq!(2, model.tag_transitions().len());
+        assert!(model.tag_transitions()[0].is_empty());
+        let data = model.to_vec().unwrap();
+        let (mut model, _) = Model::read_slice(&data).unwrap();
+        assert_eq!(
+            &[("名詞".to_string(), "助詞".to_string(), 3)],
+            model.tag_transitions()[1].as_slice()
+        );
+        model.set_tag_transitions(1, vec![]);
+        assert!(model.tag_transitions().is_empty());
+    }
+
+    #[test]
+    fn test_read_legacy_format() {
+        let model = create_test_model();
+        let mut data = MODEL_MAGIC_0_5.to_vec();
+        data.extend(bincode::encode_to_vec(&model.0, bincode::config::standard()).unwrap());
+        data.push(42);
+        let (model, rest) = Model::read_slice(&data).unwrap();
+        assert_eq!(&[42], rest);
+        assert_eq!(None, model.normalization());
+        assert_eq!(-5, model.0.bias);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_read_async() {
+        let mut model = create_test_model();
+        model.set_pipeline(vec!["kytea-fullwidth".into()]);
+        let data = model.to_vec().unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let model = rt.block_on(Model::read_async(data.as_slice())).unwrap();
+        assert_eq!(&["kytea-fullwidth"], model.pipeline());
+        assert_eq!(-5, model.0.bias);
+
+        assert!(rt
+            .block_on(Model::read_async(&b"VaporettoTokenizer 0.4.0\n"[..]))
+            .is_err());
+        assert!(rt.block_on(Model::read_async(&data[..10])).is_err());
+    }
+
+    #[test]
+    fn test_read_invalid_magic() {
+        assert!(Model::read_slice(b"VaporettoTokenizer 0.4.0\n").is_err());
+        assert!(Model::read_slice(b"Vaporetto").is_err());
+    }
+
+    #[test]
+    fn test_assert_normalization() {
+        let mut model = create_test_model();
+        assert!(model.assert_normalization("none").is_ok());
+        model.set_normalization(Some("kytea-fullwidth".into()));
+        assert!(model.assert_normalization("kytea-fullwidth").is_ok());
+        assert!(model.assert_normalization("none").is_err());
+        assert_eq!(None, model.normalization_warning("kytea-fullwidth"));
+        assert_eq!(
+            Some(
+                "the model is trained with `kytea-fullwidth` normalization, but `none` is applied"
+                    .into()
+            ),
+            model.normalization_warning("none"),
+        );
+    }
+
+    fn create_test_model_with_tags(bias: Vec<i32>, weights: Vec<i32>) -> Model {
+        let mut model = create_test_model();
+        model.0.tag_models.push(TagModel {
+            token: "人".into(),
+            tags: vec![
+                vec!["名詞".into(), "接尾辞".into()],
+                vec!["ジン".into(), "ヒト".into()],
+                vec!["*".into()],
+            ],
+            char_ngram_model: TagNgramModel(vec![
+                TagNgramData {
+                    ngram: "この".into(),
+                    weights: vec![TagWeight {
+                        rel_position: 0,
+                        weights: weights.clone(),
+                    }],
+                },
+                TagNgramData {
+                    ngram: "は地球".into(),
+                    weights: vec![TagWeight {
+                        rel_position: 0,
+                        weights: vec![1, 2, 3, 4],
+                    }],
+                },
+            ]),
+            type_ngram_model: TagNgramModel(vec![TagNgramData {
+                ngram: vec![1, 2],
+                weights: vec![
+                    TagWeight {
+                        rel_position: 0,
+                        weights,
+                    },
+                    TagWeight {
+                        rel_position: 1,
+                        weights: vec![1, 2, 3, 4],
+                    },
+                ],
+            }]),
+            bias,
+        });
+        model
+    }
+
+    #[test]
+    fn test_check_tag_models() {
+        let model = create_test_model_with_tags(vec![1, 2, 3, 4], vec![5, 6, 7, 8]);
+        assert_eq!(
+            TagModelReport {
+                invalid_biases: 0,
+                invalid_weights: 0,
+                dangling_char_ngrams: 1,
+            },
+            model.check_tag_models(),
+        );
+        assert!(model.check_tag_models().is_valid());
+
+        let model = create_test_model_with_tags(vec![1, 2, 3], vec![5, 6, 7, 8, 9]);
+        assert_eq!(
+            TagModelReport {
+                invalid_biases: 1,
+                invalid_weights: 2,
+                dangling_char_ngrams: 1,
+            },
+            model.check_tag_models(),
+        );
+        assert!(!model.check_tag_models().is_valid());
+    }
+
+    #[test]
+    fn test_prune_tag_models() {
+        let mut model = create_test_model_with_tags(vec![1, 2, 3], vec![5, 6, 7, 8, 9]);
+        assert_eq!(
+            TagModelReport {
+                invalid_biases: 1,
+                invalid_weights: 2,
+                dangling_char_ngrams: 1,
+            },
+            model.prune_tag_models(),
+        );
+        assert_eq!(TagModelReport::default(), model.check_tag_models());
+        let tag_model = &model.tag_models()[0];
+        assert_eq!(vec![1, 2, 3, 0], tag_model.bias);
+        assert!(tag_model.char_ngram_model.0.is_empty());
+        assert_eq!(1, tag_model.type_ngram_model.0.len());
+        assert_eq!(1, tag_model.type_ngram_model.0[0].weights[0].rel_position);
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predictor_invalid_tag_models() {
+        use crate::{Predictor, Sentence};
+
+        // Every character is a token.
+        let mut model = create_test_model_with_tags(vec![1, 2, 3], vec![5, 6, 7, 8]);
+        model.0.bias = 10;
+        let predictor = Predictor::new(model, true).unwrap();
+        assert_eq!(
+            TagModelReport {
+                invalid_biases: 1,
+                invalid_weights: 0,
+                dangling_char_ngrams: 1,
+            },
+            predictor.tag_model_report(),
+        );
+        let mut s = Sentence::from_raw("この人は地球人").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("こ の 人/接尾辞/ジン/* は 地 球 人/接尾辞/ジン/*", buf);
+
+        // A weight vector of the wrong length does not disable the whole tag model.
+        let mut model = create_test_model_with_tags(vec![1, 2, 3, 4], vec![5, 6, 7]);
+        model.0.bias = 10;
+        let predictor = Predictor::new(model, true).unwrap();
+        assert_eq!(
+            TagModelReport {
+                invalid_biases: 0,
+                invalid_weights: 2,
+                dangling_char_ngrams: 1,
+            },
+            predictor.tag_model_report(),
+        );
+        let mut s = Sentence::from_raw("この人は地球人").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("こ の 人/接尾辞/ヒト/* は 地 球 人/接尾辞/ヒト/*", buf);
+
+        let model = create_test_model_with_tags(vec![1, 2, 3], vec![5, 6, 7, 8]);
+        let predictor = Predictor::new(model, false).unwrap();
+        assert_eq!(TagModelReport::default(), predictor.tag_model_report());
+
+        let mut model = create_test_model_with_tags(vec![1, 2, 3], vec![5, 6, 7, 8]);
+        model.prune_tag_models();
+        let predictor = Predictor::new(model, true).unwrap();
+        assert!(predictor.tag_model_report().is_valid());
+    }
+
+    #[test]
+    fn test_model_builder() {
+        let mut builder = ModelBuilder::new(2, 1);
+        builder.set_bias(-3);
+        builder.add_char_ngram("ab".into(), vec![1, 2, 3]).unwrap();
+        builder.add_type_ngram(vec![1], vec![4, 5]).unwrap();
+        let mut tag_model =
+            TagModel::new("ab".into(), vec![vec!["X".into(), "Y".into()]], vec![1, 2]).unwrap();
+        tag_model.add_char_ngram("a".into(), 2, vec![3, 4]).unwrap();
+        tag_model.add_char_ngram("a".into(), 1, vec![5, 6]).unwrap();
+        builder.add_tag_model(tag_model).unwrap();
+        let model = builder.build();
+
+        assert_eq!(-3, model.0.bias);
+        assert_eq!(1, model.0.char_ngram_model.0.len());
+        assert_eq!(1, model.0.type_ngram_model.0.len());
+        assert_eq!(1, model.tag_models().len());
+        assert_eq!(2, model.tag_models()[0].char_ngram_model.0[0].weights.len());
+        assert!(model.check_tag_models().is_valid());
+    }
+
+    #[test]
+    fn test_model_builder_invalid() {
+        let mut builder = ModelBuilder::new(2, 1);
+        assert!(builder.add_char_ngram("".into(), vec![]).is_err());
+        assert!(builder.add_char_ngram("abcde".into(), vec![]).is_err());
+        assert!(builder.add_char_ngram("ab".into(), vec![1, 2]).is_err());
+        builder.add_char_ngram("ab".into(), vec![1, 2, 3]).unwrap();
+        assert!(builder.add_char_ngram("ab".into(), vec![1, 2, 3]).is_err());
+        assert!(builder.add_type_ngram(vec![0], vec![1, 2]).is_err());
+        assert!(builder
+            .add_dict_word(WordWeightRecord::new("".into(), vec![1], "".into()).unwrap())
+            .is_err());
+        builder
+            .add_dict_word(WordWeightRecord::new("a".into(), vec![1, 2], "".into()).unwrap())
+            .unwrap();
+        assert!(builder
+            .add_dict_word(WordWeightRecord::new("a".into(), vec![3, 4], "".into()).unwrap())
+            .is_err());
+        // Records built without WordWeightRecord::new() are also checked.
+        assert!(builder
+            .add_dict_word(WordWeightRecord {
+                word: "b".into(),
+                weights: vec![1],
+                comment: "".into(),
+            })
+            .is_err());
+
+        let mut tag_model =
+            TagModel::new("ab".into(), vec![vec!["X".into(), "Y".into()]], vec![1, 2]).unwrap();
+        assert!(tag_model.add_char_ngram("a".into(), 0, vec![1]).is_err());
+        assert!(tag_model.add_type_ngram(vec![0], 0, vec![1, 2]).is_err());
+        tag_model.add_char_ngram("a".into(), 3, vec![1, 2]).unwrap();
+        assert!(builder.add_tag_model(tag_model).is_err());
+    }
+
+    #[test]
+    fn test_tag_model_new_invalid() {
+        assert!(TagModel::new("".into(), vec![], vec![]).is_err());
+        assert!(TagModel::new("a".into(), vec![vec![]], vec![]).is_err());
+        assert!(TagModel::new("a".into(), vec![vec!["X".into(), "Y".into()]], vec![1]).is_err());
+        assert!(TagModel::new("a".into(), vec![vec!["X".into()]], vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut model = Model::new(
+            NgramModel(vec![
+                NgramData {
+                    ngram: "この人".into(),
+                    weights: vec![1, -2, 3, 4],
+                },
+                NgramData {
+                    ngram: "人".into(),
+                    weights: vec![0, -10, 3],
+                },
+            ]),
+            NgramModel(vec![NgramData {
+                ngram: b"KH".to_vec(),
+                weights: vec![-4, 4],
+            }]),
+            DictModel(vec![
+                WordWeightRecord {
+                    word: "人".into(),
+                    weights: vec![5, 0],
+                    comment: "".into(),
+                },
+                WordWeightRecord {
+                    word: "この".into(),
+                    weights: vec![2, 1, 3],
+                    comment: "".into(),
+                },
+            ]),
+            -5,
+            3,
+            3,
+            vec![],
+        );
+        let report = model.prune(5);
+        assert_eq!(
+            PruneReport {
+                removed_char_ngrams: 1,
+                removed_type_ngrams: 1,
+                removed_dict_words: 1,
+                removed_weights: 9,
+                removed_weight_sum: 24,
+            },
+            report,
+        );
+        #[cfg(feature = "std")]
+        assert!((report.expected_perturbation() - 24.0 / 9.0).abs() < 1e-9);
+        assert_eq!(1, model.0.char_ngram_model.0.len());
+        assert_eq!("人", model.0.char_ngram_model.0[0].ngram);
+        assert!(model.0.type_ngram_model.0.is_empty());
+        assert_eq!(1, model.dictionary().len());
+        assert_eq!("人", model.dictionary()[0].word);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_downgraded() {
+        let mut model = create_test_model();
+        model.set_normalization(Some("kytea-fullwidth".into()));
+
+        let mut data = vec![];
+        model.write_downgraded(&mut data, "0.5").unwrap();
+        assert!(data.starts_with(MODEL_MAGIC_0_5));
+        let (model_read, rest) = Model::read_slice(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(None, model_read.normalization());
+
+        let mut data = vec![];
+        model.write_downgraded(&mut data, "0.6").unwrap();
+        assert_eq!(model.to_vec().unwrap(), data);
+
+        assert!(model.write_downgraded(&mut vec![], "0.4").is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dump_ngrams() {
+        let model = Model::new(
+            NgramModel(vec![
+                NgramData {
+                    ngram: "この人".into(),
+                    weights: vec![1, -2, 3, 4],
+                },
+                NgramData {
+                    ngram: "\t".into(),
+                    weights: vec![1, 2, 3, 4, 5, 6],
+                },
+            ]),
+            NgramModel(vec![NgramData {
+                ngram: vec![5, 7],
+                weights: vec![5, 6, 7, 8, 9],
+            }]),
+            DictModel(vec![]),
+            -5,
+            3,
+            3,
+            vec![],
+        );
+
+        let mut data = vec![];
+        model.dump_char_ngrams(&mut data).unwrap();
+        assert_eq!(
+            "ngram\tfirst\tlast\tweights\nこの人\t-1\t2\t1 -2 3 4\n\\t\t-3\t2\t1 2 3 4 5 6\n",
+            String::from_utf8(data).unwrap(),
+        );
+
+        let mut data = vec![];
+        model.dump_type_ngrams(&mut data).unwrap();
+        assert_eq!(
+            "ngram\tfirst\tlast\tweights\nK<7>\t-2\t2\t5 6 7 8 9\n",
+            String::from_utf8(data).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_to_path() {
+        let model = create_test_model();
+        let path = std::env::temp_dir().join(format!("vaporetto-model-{}.bin", std::process::id()));
+        model.write_to_path(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(model.to_vec().unwrap(), data);
+    }
+
+    #[test]
+    fn test_format_version() {
+        assert_eq!(
+            MODEL_FORMAT_VERSION,
+            Model::format_version(&create_test_model().to_vec().unwrap()).unwrap(),
+        );
+        assert_eq!("0.5.0", Model::format_version(MODEL_MAGIC_0_5).unwrap());
+        assert!(SUPPORTED_MODEL_FORMAT_VERSIONS.contains(&MODEL_FORMAT_VERSION));
+        assert!(Model::format_version(b"VaporettoTokenizer 0.6.0").is_err());
+        assert!(Model::format_version(b"KyTea").is_err());
+
+        assert_eq!(
+            format!(
+                "ModelVersionError: the model of format version 9.9.9 requires a version of vaporetto newer than {} (supported format versions: 0.5.0, 0.6.0)",
+                env!("CARGO_PKG_VERSION"),
+            ),
+            Model::read_slice(b"VaporettoTokenizer 9.9.9\n????")
+                .err()
+                .unwrap()
+                .to_string(),
+        );
+        assert_eq!(
+            "InvalidModelError: unsupported model format version 0.4.0 (supported versions: 0.5.0, 0.6.0)",
+            Model::read_slice(b"VaporettoTokenizer 0.4.0\n????")
+                .err()
+                .unwrap()
+                .to_string(),
+        );
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut model = create_test_model();
+        assert_eq!(ModelCapabilities::empty(), model.capabilities());
+        model.set_calibration(Some(PlattScaling::new(-0.5, 0.25)));
+        model.set_pipeline(vec!["wsconst:D".into()]);
+        assert_eq!(
+            ModelCapabilities::CALIBRATION | ModelCapabilities::PIPELINE,
+            model.capabilities(),
+        );
+        let data = model.to_vec().unwrap();
+        let config = bincode::config::standard();
+        let (header, _): (ModelHeader, _) =
+            bincode::decode_from_slice(&data[MODEL_MAGIC.len()..], config).unwrap();
+        assert_eq!(model.capabilities().bits(), header.capabilities);
+        assert_eq!(env!("CARGO_PKG_VERSION"), header.required_version);
+    }
+
+    #[test]
+    fn test_read_unknown_capabilities() {
+        let model = create_test_model();
+        let mut data = MODEL_MAGIC.to_vec();
+        let config = bincode::config::standard();
+        let header = ModelHeader {
+            capabilities: 1 << 63,
+            required_version: "0.8.1".into(),
+        };
+        data.extend(bincode::encode_to_vec(&header, config).unwrap());
+        data.extend(bincode::encode_to_vec(&model.0, config).unwrap());
+        data.extend(bincode::encode_to_vec(&model.1, config).unwrap());
+        match Model::read_slice(&data) {
+            Err(VaporettoError::ModelVersion(e)) => {
+                assert_eq!(MODEL_FORMAT_VERSION, e.format_version());
+                assert_eq!(Some("0.8.1"), e.required_version());
+            }
+            _ => panic!("must be ModelVersionError"),
+        }
+    }
 }
@@ -0,0 +1,261 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use crate::errors::{Result, VaporettoError};
+
+/// Mapping from character positions in a transformed string to those in the original string.
+///
+/// Each character of the transformed string has a range of characters in the original string
+/// from which it is produced. Characters produced from the same run of original characters share
+/// the same range.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::OffsetMap;
+///
+/// // "ｶﾞｲﾄﾞ" is transformed into "ガイド".
+/// let offsets = OffsetMap::from_ranges(vec![0..2, 2..3, 3..5], 5).unwrap();
+/// assert_eq!(0..3, offsets.to_original(0..2));
+/// assert_eq!(3..3, offsets.to_original(2..2));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetMap {
+    // Range in the original string for each character in the transformed string.
+    starts: Vec<usize>,
+    ends: Vec<usize>,
+    original_len: usize,
+}
+
+impl OffsetMap {
+    /// Creates a mapping that does not move any character.
+    pub fn identity(len: usize) -> Self {
+        Self {
+            starts: (0..len).collect(),
+            ends: (1..=len).collect(),
+            original_len: len,
+        }
+    }
+
+    /// Creates a mapping for a string of `len` characters produced from the empty string.
+    ///
+    /// Every character corresponds to the empty range at the beginning of the original string.
+    pub fn from_empty(len: usize) -> Self {
+        Self {
+            starts: vec![0; len],
+            ends: vec![0; len],
+            original_len: 0,
+        }
+    }
+
+    /// Creates a mapping from the ranges of the original characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `ranges` - A range in the original string for each character in the transformed string.
+    /// * `original_len` - The number of characters in the original string.
+    ///
+    /// # Errors
+    ///
+    /// If the ranges are empty, the starts or ends are decreasing, or the ranges exceed
+    /// `original_len`, an error variant will be returned.
+    pub fn from_ranges<I>(ranges: I, original_len: usize) -> Result<Self>
+    where
+        I: IntoIterator<Item = Range<usize>>,
+    {
+        let mut starts = vec![];
+        let mut ends = vec![];
+        for range in ranges {
+            if range.is_empty() {
+                return Err(VaporettoError::invalid_argument(
+                    "ranges",
+                    "must not contain empty ranges",
+                ));
+            }
+            if starts.last().is_some_and(|&start| range.start < start)
+                || ends.last().is_some_and(|&end| range.end < end)
+            {
+                return Err(VaporettoError::invalid_argument(
+                    "ranges",
+                    "must be in the order of the original string",
+                ));
+            }
+            starts.push(range.start);
+            ends.push(range.end);
+        }
+        if ends.last().is_some_and(|&end| end > original_len) {
+            return Err(VaporettoError::invalid_argument(
+                "original_len",
+                "must be larger than or equal to the ends of ranges",
+            ));
+        }
+        Ok(Self {
+            starts,
+            ends,
+            original_len,
+        })
+    }
+
+    /// Returns the number of characters in the transformed string.
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns `true` if the transformed string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Returns `true` if every character corresponds to the character at the same position in
+    /// the original string.
+    pub fn is_identity(&self) -> bool {
+        self.original_len == self.len()
+            && self
+                .starts
+                .iter()
+                .zip(&self.ends)
+                .enumerate()
+                .all(|(i, (&start, &end))| start == i && end == i + 1)
+    }
+
+    /// Returns the number of characters in the original string.
+    pub const fn original_len(&self) -> usize {
+        self.original_len
+    }
+
+    /// Converts a range of characters in the transformed string into that in the original string.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - A range of character positions in the transformed string.
+    ///
+    /// # Returns
+    ///
+    /// The smallest range in the original string covering all the characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of the transformed string.
+    pub fn to_original(&self, range: Range<usize>) -> Range<usize> {
+        assert!(range.end <= self.len(), "range out of bounds");
+        if range.start >= range.end {
+            let pos = self
+                .starts
+                .get(range.start)
+                .copied()
+                .unwrap_or(self.original_len);
+            return pos..pos;
+        }
+        self.starts[range.start]..self.ends[range.end - 1]
+    }
+
+    /// Returns the position in the original string corresponding to the boundary before the
+    /// `i`-th character of the transformed string, or `None` if the boundary is inside a run of
+    /// characters produced together.
+    pub fn boundary_to_original(&self, i: usize) -> Option<usize> {
+        match (i.checked_sub(1), self.starts.get(i)) {
+            (None, _) => Some(0),
+            (Some(_), None) => (i == self.len()).then_some(self.original_len),
+            (Some(prev), Some(&start)) => (self.ends[prev] <= start).then_some(start),
+        }
+    }
+
+    /// Composes two mappings applied in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `next` - A mapping from the string transformed by `next` to the string transformed by
+    ///   `self`.
+    ///
+    /// # Returns
+    ///
+    /// A mapping from the string transformed by `next` to the original string of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `next.original_len()` is not equal to `self.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::OffsetMap;
+    ///
+    /// let first = OffsetMap::from_ranges(vec![0..2, 2..3], 3).unwrap();
+    /// let second = OffsetMap::from_ranges(vec![0..1, 1..2, 1..2], 2).unwrap();
+    /// let offsets = first.compose(&second);
+    /// assert_eq!(2..3, offsets.to_original(1..2));
+    /// assert_eq!(0..3, offsets.to_original(0..3));
+    /// ```
+    pub fn compose(&self, next: &Self) -> Self {
+        assert_eq!(self.len(), next.original_len, "lengths mismatch");
+        let (starts, ends) = next
+            .starts
+            .iter()
+            .zip(&next.ends)
+            .map(|(&start, &end)| {
+                let range = self.to_original(start..end);
+                (range.start, range.end)
+            })
+            .unzip();
+        Self {
+            starts,
+            ends,
+            original_len: self.original_len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let offsets = OffsetMap::identity(3);
+        assert!(offsets.is_identity());
+        assert_eq!(3, offsets.len());
+        assert_eq!(1..3, offsets.to_original(1..3));
+        assert_eq!(3..3, offsets.to_original(3..3));
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2), Some(3), None],
+            (0..5)
+                .map(|i| offsets.boundary_to_original(i))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_boundary_to_original() {
+        // "㍿だ" is transformed into "株式会社だ".
+        let offsets = OffsetMap::from_ranges(vec![0..1, 0..1, 0..1, 0..1, 1..2], 2).unwrap();
+        assert!(!offsets.is_identity());
+        assert_eq!(
+            vec![Some(0), None, None, None, Some(1), Some(2)],
+            (0..6)
+                .map(|i| offsets.boundary_to_original(i))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_from_empty() {
+        let offsets = OffsetMap::from_empty(2);
+        assert_eq!(2, offsets.len());
+        assert_eq!(0, offsets.original_len());
+        assert_eq!(0..0, offsets.to_original(0..2));
+        assert_eq!(0..0, offsets.to_original(2..2));
+        assert_eq!(
+            OffsetMap::from_empty(2),
+            OffsetMap::identity(0).compose(&offsets)
+        );
+    }
+
+    #[test]
+    fn test_from_ranges_invalid() {
+        assert!(OffsetMap::from_ranges(vec![0..1, 1..1], 2).is_err());
+        assert!(OffsetMap::from_ranges(vec![1..2, 0..1], 2).is_err());
+        assert!(OffsetMap::from_ranges(vec![0..2, 1..1], 2).is_err());
+        assert!(OffsetMap::from_ranges(vec![0..1, 1..3], 2).is_err());
+    }
+}
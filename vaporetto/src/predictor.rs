@@ -1,14 +1,18 @@
+use core::cmp::Reverse;
 use core::ops::AddAssign;
 
 #[cfg(all(feature = "fix-weight-length", feature = "portable-simd"))]
 use core::simd::Simd;
 
+use alloc::borrow::Cow;
+use alloc::collections::BinaryHeap;
 use alloc::vec::Vec;
 
 #[cfg(feature = "tag-prediction")]
-use alloc::borrow::Cow;
-#[cfg(feature = "tag-prediction")]
+use alloc::collections::BTreeSet;
 use alloc::string::String;
+#[cfg(feature = "tag-prediction")]
+use alloc::sync::Arc;
 
 use bincode::{
     de::{BorrowDecoder, Decoder},
@@ -17,17 +21,29 @@ use bincode::{
     BorrowDecode, Decode, Encode,
 };
 
-#[cfg(feature = "tag-prediction")]
 use hashbrown::HashMap;
 
-use crate::char_scorer::CharScorer;
-use crate::errors::Result;
+use crate::attribution::{Attribution, AttributionModel};
+use crate::calibration::PlattScaling;
+use crate::char_scorer::{CharScorer, CharScorerBoundary};
+use crate::dict_model::{DictModel, WordWeightRecord};
+#[cfg(feature = "std")]
+use crate::disk_dict::DiskDictionary;
+use crate::errors::{Result, VaporettoError};
 use crate::model::Model;
-use crate::sentence::{CharacterBoundary, Sentence};
+#[cfg(feature = "tag-prediction")]
+use crate::model::TagModelReport;
+use crate::ngram_model::NgramModel;
+use crate::sentence::{CharTypeRanges, CharacterBoundary, Sentence};
+#[cfg(feature = "std")]
+use crate::timings::StageTimings;
 use crate::type_scorer::TypeScorer;
 
+use crate::utils::HeapBytes;
 #[cfg(feature = "tag-prediction")]
 use crate::utils::SerializableHashMap;
+#[cfg(feature = "std")]
+use crate::utils::SplitMix64Rng;
 
 pub const WEIGHT_FIXED_LEN: usize = 8;
 
@@ -115,6 +131,17 @@ impl WeightVector {
     }
 }
 
+impl HeapBytes for WeightVector {
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Variable(w) => w.heap_bytes(),
+
+            #[cfg(feature = "fix-weight-length")]
+            Self::Fixed(_) => 0,
+        }
+    }
+}
+
 impl From<Vec<i32>> for WeightVector {
     fn from(src: Vec<i32>) -> Self {
         match src.len() {
@@ -140,6 +167,15 @@ pub struct PositionalWeight<W> {
     weight: W,
 }
 
+impl<W> HeapBytes for PositionalWeight<W>
+where
+    W: HeapBytes,
+{
+    fn heap_bytes(&self) -> usize {
+        self.weight.heap_bytes()
+    }
+}
+
 impl PositionalWeight<Vec<i32>> {
     pub const fn new(offset: i16, weight: Vec<i32>) -> Self {
         Self { offset, weight }
@@ -261,16 +297,31 @@ impl AddAssign<&Self> for PositionalWeightWithTag {
     }
 }
 
+/// Stages of [`Predictor::predict()`].
+enum Stage {
+    Char,
+    Type,
+    Dict,
+}
+
 #[cfg(feature = "tag-prediction")]
 #[derive(Decode, Encode)]
 struct TagPredictor {
-    tags: Vec<Vec<String>>,
+    // IDs of tag candidates in the tag name table.
+    tags: Vec<Vec<u32>>,
     bias: WeightVector,
 }
 
+#[cfg(feature = "tag-prediction")]
+impl HeapBytes for TagPredictor {
+    fn heap_bytes(&self) -> usize {
+        self.tags.heap_bytes() + self.bias.heap_bytes()
+    }
+}
+
 #[cfg(feature = "tag-prediction")]
 impl TagPredictor {
-    pub fn new(tags: Vec<Vec<String>>, bias: Vec<i32>) -> Self {
+    pub fn new(tags: Vec<Vec<u32>>, bias: Vec<i32>) -> Self {
         Self {
             tags,
             bias: bias.into(),
@@ -282,28 +333,137 @@ impl TagPredictor {
         &self.bias
     }
 
+    /// Returns a mask of candidates allowed by the lexicon, or `None` if nothing is restricted.
+    ///
+    /// The mask has the same layout as the scores. If no candidate of a tag is allowed, the tag
+    /// is not restricted.
+    fn build_lexicon_mask(&self, names: &[String], allowed: &[Vec<String>]) -> Option<Vec<bool>> {
+        let mut mask = vec![];
+        let mut restricted = false;
+        for (i, tag_cands) in self.tags.iter().enumerate() {
+            if tag_cands.len() < 2 {
+                continue;
+            }
+            let allowed = allowed.get(i).map_or(&[][..], Vec::as_slice);
+            let level_mask: Vec<bool> = tag_cands
+                .iter()
+                .map(|&c| allowed.contains(&names[c as usize]))
+                .collect();
+            if level_mask.contains(&true) {
+                restricted |= level_mask.contains(&false);
+                mask.extend(level_mask);
+            } else {
+                mask.resize(mask.len() + tag_cands.len(), true);
+            }
+        }
+        restricted.then_some(mask)
+    }
+
     #[inline]
-    pub fn predict<'a>(&'a self, scores: &[i32], tags: &mut [Option<Cow<'a, str>>]) {
+    pub fn predict<'a>(
+        &self,
+        names: &'a [String],
+        scores: &[i32],
+        mask: Option<&[bool]>,
+        tags: &mut [Option<Cow<'a, str>>],
+    ) {
         let mut offset = 0;
         for (tag_cands, tag) in self.tags.iter().zip(tags) {
             if tag_cands.len() >= 2 {
                 let mut idx = 0;
                 let mut max_score = i32::MIN;
                 for (i, &s) in scores[offset..offset + tag_cands.len()].iter().enumerate() {
-                    if s > max_score {
+                    if mask.map_or(true, |mask| mask[offset + i]) && s > max_score {
                         idx = i;
                         max_score = s;
                     }
                 }
-                tag.replace(Cow::Borrowed(&tag_cands[idx]));
+                tag.replace(Cow::Borrowed(&names[tag_cands[idx] as usize]));
                 offset += tag_cands.len();
             } else {
-                *tag = tag_cands.first().map(|t| Cow::Borrowed(t.as_str()));
+                *tag = tag_cands
+                    .first()
+                    .map(|&t| Cow::Borrowed(names[t as usize].as_str()));
             }
         }
     }
 }
 
+/// Tags restricted by [`Predictor::set_tag_lexicon()`].
+#[cfg(feature = "tag-prediction")]
+#[derive(Default)]
+struct TagLexicon {
+    /// Masks of candidates of words having tag models.
+    masks: HashMap<u32, Vec<bool>>,
+
+    /// Tags of words not having tag models, assigned when only one tag is allowed.
+    fixed_tags: HashMap<String, Vec<Option<String>>>,
+}
+
+#[cfg(feature = "tag-prediction")]
+impl HeapBytes for TagLexicon {
+    fn heap_bytes(&self) -> usize {
+        self.masks.heap_bytes() + self.fixed_tags.heap_bytes()
+    }
+}
+
+/// Boundary scores broken down into each feature family, returned by
+/// [`Predictor::score_components()`].
+///
+/// Each vector has one element per character boundary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScoreComponents {
+    /// Bias added to all boundaries.
+    pub bias: i32,
+
+    /// Scores of character n-grams.
+    pub char_ngram: Vec<i32>,
+
+    /// Scores of character type n-grams.
+    pub type_ngram: Vec<i32>,
+
+    /// Scores of the dictionary of the model, the user dictionary, and disk dictionaries.
+    pub dict: Vec<i32>,
+}
+
+/// Heap memory used by a predictor in bytes, returned by [`Predictor::memory_usage()`].
+///
+/// The values are computed from the capacities of the internal buffers and do not include the
+/// overhead of the allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Automaton of character n-grams and dictionary words merged into them.
+    pub char_automaton: usize,
+
+    /// Automaton of character type n-grams.
+    pub type_automaton: usize,
+
+    /// Automata of dictionary words scored separately and the user dictionary.
+    pub dict_automaton: usize,
+
+    /// Boundary weights of all automata.
+    pub weights: usize,
+
+    /// Tag weights, tag predictors, tag transitions, tag names, and the tag lexicon.
+    pub tag_models: usize,
+
+    /// Other data, e.g., character type definitions, user dictionary entries, and n-grams kept for
+    /// attribution.
+    pub others: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the total number of bytes.
+    pub const fn total(&self) -> usize {
+        self.char_automaton
+            + self.type_automaton
+            + self.dict_automaton
+            + self.weights
+            + self.tag_models
+            + self.others
+    }
+}
+
 pub struct PredictorData {
     char_scorer: Option<CharScorer>,
     type_scorer: Option<TypeScorer>,
@@ -313,8 +473,27 @@ pub struct PredictorData {
     tag_predictor: Option<SerializableHashMap<String, (u32, TagPredictor)>>,
     #[cfg(feature = "tag-prediction")]
     n_tags: usize,
+
+    // Sorted names of all tag candidates shared by tag predictors, indexed by tag IDs. The table
+    // can also be shared by other predictors with the same tag set.
+    #[cfg(feature = "tag-prediction")]
+    tag_names: Arc<[String]>,
+
+    char_type_ranges: CharTypeRanges,
+    calibration: Option<PlattScaling>,
+
+    // Scorer of the dictionary words separated from the character n-grams so that their weights
+    // can be scaled.
+    dict_scorer: Option<CharScorerBoundary>,
+
+    // Transition weights of each tag level, mapping the preceding tag to the following tags.
+    #[cfg(feature = "tag-prediction")]
+    tag_transitions: Vec<TagTransitionMap>,
 }
 
+#[cfg(feature = "tag-prediction")]
+type TagTransitionMap = SerializableHashMap<String, SerializableHashMap<String, i32>>;
+
 impl<'de> BorrowDecode<'de> for PredictorData {
     /// WARNING: This function is inherently unsafe. Do not publish this function outside this
     /// crate.
@@ -337,6 +516,13 @@ impl<'de> BorrowDecode<'de> for PredictorData {
         let tag_predictor = Decode::decode(decoder)?;
         #[cfg(feature = "tag-prediction")]
         let n_tags = Decode::decode(decoder)?;
+        #[cfg(feature = "tag-prediction")]
+        let tag_names = Vec::<String>::decode(decoder)?.into();
+        let char_type_ranges = Decode::decode(decoder)?;
+        let calibration = Decode::decode(decoder)?;
+        let dict_scorer = BorrowDecode::borrow_decode(decoder)?;
+        #[cfg(feature = "tag-prediction")]
+        let tag_transitions = Decode::decode(decoder)?;
         Ok(Self {
             char_scorer,
             type_scorer,
@@ -345,6 +531,13 @@ impl<'de> BorrowDecode<'de> for PredictorData {
             tag_predictor,
             #[cfg(feature = "tag-prediction")]
             n_tags,
+            #[cfg(feature = "tag-prediction")]
+            tag_names,
+            char_type_ranges,
+            calibration,
+            dict_scorer,
+            #[cfg(feature = "tag-prediction")]
+            tag_transitions,
         })
     }
 }
@@ -369,6 +562,13 @@ impl Encode for PredictorData {
         Encode::encode(&self.tag_predictor, encoder)?;
         #[cfg(feature = "tag-prediction")]
         Encode::encode(&self.n_tags, encoder)?;
+        #[cfg(feature = "tag-prediction")]
+        Encode::encode(&*self.tag_names, encoder)?;
+        Encode::encode(&self.char_type_ranges, encoder)?;
+        Encode::encode(&self.calibration, encoder)?;
+        Encode::encode(&self.dict_scorer, encoder)?;
+        #[cfg(feature = "tag-prediction")]
+        Encode::encode(&self.tag_transitions, encoder)?;
         Ok(())
     }
 }
@@ -432,10 +632,32 @@ assert_eq!(
 )]
 pub struct Predictor {
     data: PredictorData,
+
+    #[cfg(feature = "tag-prediction")]
     tag_scores: bool,
+
+    unknown_span_len: Option<usize>,
+    threshold: i32,
+    margin_band: Option<u32>,
+    user_dict: Vec<WordWeightRecord>,
+    user_dict_index: HashMap<String, usize>,
+    user_dict_scorer: Option<CharScorerBoundary>,
+
+    #[cfg(feature = "std")]
+    disk_dicts: Vec<DiskDictionary>,
+
+    #[cfg(feature = "tag-prediction")]
+    tag_lexicon: Option<TagLexicon>,
+    #[cfg(feature = "tag-prediction")]
+    tag_model_report: TagModelReport,
+
+    attribution: Option<AttributionModel>,
 }
 
 impl Predictor {
+    /// A tag assigned to spans kept by [`Predictor::keep_unknown_spans()`].
+    pub const UNKNOWN_SPAN_TAG: &'static str = "UNSEG";
+
     /// Creates a new predictor from the model.
     ///
     /// # Arguments
@@ -445,8 +667,74 @@ impl Predictor {
     ///
     /// # Errors
     ///
-    /// Returns an error variant when the model is invalid.
+    /// Returns an error variant when the model is invalid. If `predict_tags` is true, invalid
+    /// entries of tag models are also removed instead of being treated as errors (see
+    /// [`Predictor::tag_model_report()`]).
     pub fn new(model: Model, predict_tags: bool) -> Result<Self> {
+        Self::build(model, predict_tags, false, false)
+    }
+
+    /// Creates a new predictor from the model like [`Predictor::new()`], building the scorers
+    /// of the character n-grams, the character type n-grams, and the dictionary in parallel
+    /// threads.
+    ///
+    /// This reduces the startup time for large models. The resulting predictor is the same as
+    /// the one created by [`Predictor::new()`] if `separate_dict` is false, and by
+    /// [`Predictor::with_scalable_dictionary()`] otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant when the model is invalid.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn new_parallel(model: Model, predict_tags: bool, separate_dict: bool) -> Result<Self> {
+        Self::build(model, predict_tags, separate_dict, true)
+    }
+
+    /// Creates a new predictor from the model, where the dictionary of the model is scored
+    /// separately from the character n-grams.
+    ///
+    /// The prediction results are the same as [`Predictor::new()`], but the weights of the
+    /// dictionary can be scaled by [`Predictor::predict_with_dict_scale()`]. Prediction is
+    /// slightly slower because one more automaton is scanned.
+    ///
+    /// # Errors
+    ///
+    /// If the construction fails, an error variant will be returned.
+    pub fn with_scalable_dictionary(model: Model, predict_tags: bool) -> Result<Self> {
+        Self::build(model, predict_tags, true, false)
+    }
+
+    /// Creates a new predictor from the model, keeping n-grams and words of the model for
+    /// [`Predictor::predict_with_attribution()`].
+    ///
+    /// The prediction results are the same as [`Predictor::new()`], but the predictor uses about
+    /// twice as much memory.
+    ///
+    /// # Errors
+    ///
+    /// If the construction fails, an error variant will be returned.
+    pub fn with_attribution(model: Model, predict_tags: bool) -> Result<Self> {
+        let attribution = AttributionModel::new(&model);
+        let mut predictor = Self::build(model, predict_tags, false, false)?;
+        predictor.attribution = Some(attribution);
+        Ok(predictor)
+    }
+
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn build(
+        mut model: Model,
+        predict_tags: bool,
+        separate_dict: bool,
+        parallel: bool,
+    ) -> Result<Self> {
+        let separate_dict_model = if separate_dict {
+            let dict_model = core::mem::replace(&mut model.0.dict_model, DictModel(vec![]));
+            (!dict_model.0.is_empty()).then_some(dict_model)
+        } else {
+            None
+        };
+
         #[cfg(feature = "tag-prediction")]
         let mut tag_char_ngram_model = vec![];
         #[cfg(feature = "tag-prediction")]
@@ -459,37 +747,124 @@ impl Predictor {
             panic!("tag prediction is unsupported");
         }
         #[cfg(feature = "tag-prediction")]
-        let tag_predictor = predict_tags.then(|| {
+        let tag_model_report = if predict_tags {
+            model.fix_tag_models(false)
+        } else {
+            TagModelReport::default()
+        };
+        #[cfg(feature = "tag-prediction")]
+        let mut tag_names = vec![];
+        #[cfg(feature = "tag-prediction")]
+        let tag_predictor = if predict_tags {
+            // Tag names are shared by tag predictors of all tokens.
+            let names: BTreeSet<&String> = model
+                .0
+                .tag_models
+                .iter()
+                .flat_map(|tag_model| tag_model.tags.iter().flatten())
+                .collect();
+            tag_names = names.into_iter().cloned().collect();
+            let mut tag_ids = HashMap::new();
+            for (i, name) in tag_names.iter().enumerate() {
+                let tag_id = u32::try_from(i)
+                    .map_err(|_| VaporettoError::invalid_argument("model", "too many tags"))?;
+                tag_ids.insert(name.as_str(), tag_id);
+            }
             let mut tag_predictor = HashMap::new();
-            for (i, tag_model) in model.0.tag_models.into_iter().enumerate() {
+            for tag_model in model.0.tag_models {
                 n_tags = n_tags.max(tag_model.tags.len());
+                let token_id = u32::try_from(tag_char_ngram_model.len()).map_err(|_| {
+                    VaporettoError::invalid_argument("model", "too many tag models")
+                })?;
+                let tags = tag_model
+                    .tags
+                    .iter()
+                    .map(|cands| cands.iter().map(|tag| tag_ids[tag.as_str()]).collect())
+                    .collect();
                 // token does not duplicate in the model.
                 tag_predictor.insert(
                     tag_model.token,
-                    (
-                        u32::try_from(i).unwrap(),
-                        TagPredictor::new(tag_model.tags, tag_model.bias),
-                    ),
+                    (token_id, TagPredictor::new(tags, tag_model.bias)),
                 );
                 tag_char_ngram_model.push(tag_model.char_ngram_model);
                 tag_type_ngram_model.push(tag_model.type_ngram_model);
             }
-            SerializableHashMap(tag_predictor)
-        });
+            Some(SerializableHashMap(tag_predictor))
+        } else {
+            None
+        };
+        #[cfg(feature = "tag-prediction")]
+        let tag_transitions = model
+            .1
+            .tag_transitions
+            .into_iter()
+            .map(|transitions| {
+                let mut map = TagTransitionMap::default();
+                for (prev, next, weight) in transitions {
+                    map.entry(prev).or_default().insert(next, weight);
+                }
+                map
+            })
+            .collect();
+
+        let char_ngram_model = model.0.char_ngram_model;
+        let dict_model = model.0.dict_model;
+        let char_window_size = model.0.char_window_size;
+        let build_char_scorer = move || {
+            CharScorer::new(
+                char_ngram_model,
+                dict_model,
+                char_window_size,
+                #[cfg(feature = "tag-prediction")]
+                tag_char_ngram_model,
+            )
+        };
+        let type_ngram_model = model.0.type_ngram_model;
+        let type_window_size = model.0.type_window_size;
+        // The cache only supports built-in character types.
+        #[cfg(feature = "cache-type-score")]
+        let cache_type_score = !model.1.char_type_ranges.has_extra_types();
+        let build_type_scorer = move || {
+            TypeScorer::new(
+                type_ngram_model,
+                type_window_size,
+                #[cfg(feature = "tag-prediction")]
+                tag_type_ngram_model,
+                #[cfg(feature = "cache-type-score")]
+                cache_type_score,
+            )
+        };
+        let build_dict_scorer = move || {
+            separate_dict_model
+                .map(|dict_model| CharScorerBoundary::new(NgramModel(vec![]), dict_model, 0))
+                .transpose()
+        };
 
-        let char_scorer = CharScorer::new(
-            model.0.char_ngram_model,
-            model.0.dict_model,
-            model.0.char_window_size,
-            #[cfg(feature = "tag-prediction")]
-            tag_char_ngram_model,
-        )?;
-        let type_scorer = TypeScorer::new(
-            model.0.type_ngram_model,
-            model.0.type_window_size,
-            #[cfg(feature = "tag-prediction")]
-            tag_type_ngram_model,
-        )?;
+        #[cfg(feature = "std")]
+        let (char_scorer, type_scorer, dict_scorer) = if parallel {
+            std::thread::scope(|scope| {
+                let char_scorer = scope.spawn(build_char_scorer);
+                let type_scorer = scope.spawn(build_type_scorer);
+                let dict_scorer = build_dict_scorer();
+                fn join<T>(h: std::thread::ScopedJoinHandle<'_, T>) -> T {
+                    h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))
+                }
+                (join(char_scorer), join(type_scorer), dict_scorer)
+            })
+        } else {
+            (
+                build_char_scorer(),
+                build_type_scorer(),
+                build_dict_scorer(),
+            )
+        };
+        #[cfg(not(feature = "std"))]
+        let (char_scorer, type_scorer, dict_scorer) = (
+            build_char_scorer(),
+            build_type_scorer(),
+            build_dict_scorer(),
+        );
+        let (char_scorer, type_scorer, dict_scorer) = (char_scorer?, type_scorer?, dict_scorer?);
         Ok(Self {
             data: PredictorData {
                 char_scorer,
@@ -500,121 +875,1390 @@ impl Predictor {
                 tag_predictor,
                 #[cfg(feature = "tag-prediction")]
                 n_tags,
+                #[cfg(feature = "tag-prediction")]
+                tag_names: tag_names.into(),
+
+                char_type_ranges: model.1.char_type_ranges,
+                calibration: model.1.calibration,
+                dict_scorer,
+
+                #[cfg(feature = "tag-prediction")]
+                tag_transitions,
             },
+
+            #[cfg(feature = "tag-prediction")]
             tag_scores: false,
+
+            unknown_span_len: None,
+            threshold: 0,
+            margin_band: None,
+            user_dict: vec![],
+            user_dict_index: HashMap::new(),
+            user_dict_scorer: None,
+
+            #[cfg(feature = "std")]
+            disk_dicts: vec![],
+
+            #[cfg(feature = "tag-prediction")]
+            tag_lexicon: None,
+            #[cfg(feature = "tag-prediction")]
+            tag_model_report,
+
+            attribution: None,
         })
     }
 
+    /// Returns inconsistencies of the tag models found when the predictor was created.
+    ///
+    /// Invalid weights are removed and invalid biases are fixed as [`Model::prune_tag_models()`]
+    /// does, so the remaining entries of each tag model are still used. Dangling character n-grams
+    /// are only counted. The report is not serialized, so it is empty for deserialized predictors.
+    #[cfg(feature = "tag-prediction")]
+    pub const fn tag_model_report(&self) -> TagModelReport {
+        self.tag_model_report
+    }
+
+    /// Returns the calibration of boundary scores stored in the model.
+    pub const fn calibration(&self) -> Option<&PlattScaling> {
+        self.data.calibration.as_ref()
+    }
+
     /// Stores tag scores if the given `flag` is `true`.
     #[cfg(feature = "tag-prediction")]
     pub fn store_tag_scores(&mut self, flag: bool) {
         self.tag_scores = flag;
     }
 
-    /// Predicts word boundaries of the given sentence.
-    /// If necessary, this function also prepares for predicting tags.
-    pub fn predict<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
-        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
-        sentence.boundary_scores.clear();
-        sentence.boundary_scores.resize(
-            sentence.score_padding * 2 + sentence.len() - 1,
-            self.data.bias,
-        );
-        if let Some(scorer) = self.data.char_scorer.as_ref() {
-            scorer.add_scores(sentence);
-        }
-        if let Some(scorer) = self.data.type_scorer.as_ref() {
-            scorer.add_scores(sentence);
+    /// Restricts tags of known words to the candidates allowed by the given lexicon.
+    ///
+    /// `lexicon` maps each word to lists of allowed tags of each level (e.g., parts-of-speech and
+    /// pronunciations). For a word having a tag model, each tag is chosen among the allowed
+    /// candidates; a level is not restricted if its list is empty or contains no candidate of
+    /// the model. For a word not having a tag model, a tag is assigned if exactly one tag is
+    /// allowed for the level. Words not contained in the lexicon are predicted as usual.
+    ///
+    /// The lexicon replaces the previous one and is not stored by
+    /// [`Predictor::serialize_to_vec()`]. An empty lexicon removes the restriction.
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn set_tag_lexicon(&mut self, lexicon: HashMap<String, Vec<Vec<String>>>) {
+        if lexicon.is_empty() {
+            self.tag_lexicon = None;
+            return;
         }
-        for (b, s) in sentence
-            .boundaries
-            .iter_mut()
-            .zip(&sentence.boundary_scores[sentence.score_padding..])
-        {
-            if *s > 0 {
-                *b = CharacterBoundary::WordBoundary;
+        let mut result = TagLexicon::default();
+        for (word, allowed) in lexicon {
+            let tag_model = self
+                .data
+                .tag_predictor
+                .as_ref()
+                .and_then(|tag_predictor| tag_predictor.get(&word));
+            if let Some((token_id, tag_predictor)) = tag_model {
+                if let Some(mask) = tag_predictor.build_lexicon_mask(&self.data.tag_names, &allowed)
+                {
+                    result.masks.insert(*token_id, mask);
+                }
             } else {
-                *b = CharacterBoundary::NotWordBoundary;
+                let fixed_tags: Vec<Option<String>> = allowed
+                    .into_iter()
+                    .take(self.data.n_tags)
+                    .map(|mut tags| (tags.len() == 1).then(|| tags.pop().unwrap()))
+                    .collect();
+                if fixed_tags.iter().any(Option::is_some) {
+                    result.fixed_tags.insert(word, fixed_tags);
+                }
             }
         }
-        sentence.set_predictor(self);
+        self.tag_lexicon = Some(result);
     }
 
+    /// Returns the table of tag names of all levels, sorted in lexicographic order.
+    ///
+    /// Tag predictors of all tokens share this table, and each tag is identified by its index in
+    /// the table, which can be obtained by [`Predictor::tag_id()`]. Bindings can exchange the
+    /// IDs instead of strings. The table is empty if the predictor is created with
+    /// `predict_tags = false`.
     #[cfg(feature = "tag-prediction")]
-    pub(crate) fn predict_tags<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
-        let tag_predictor = self
-            .data
-            .tag_predictor
-            .as_ref()
-            .expect("this predictor is created with predict_tags = false");
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn tag_names(&self) -> &[String] {
+        &self.data.tag_names
+    }
 
-        if self.data.n_tags == 0 {
-            return;
+    /// Returns the shared handle of the table returned by [`Predictor::tag_names()`].
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub const fn tag_name_table(&self) -> &Arc<[String]> {
+        &self.data.tag_names
+    }
+
+    /// Makes this predictor use the given table of tag names if it is equal to its own table.
+    ///
+    /// Predictors created from the same model have separate tables. Sharing one of them, e.g.,
+    /// the one returned by [`Predictor::tag_name_table()`] of another predictor, avoids the
+    /// duplication when many predictors use the same tag set. Returns `false` if the tables
+    /// differ, in which case nothing is changed.
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn share_tag_names(&mut self, table: &Arc<[String]>) -> bool {
+        if self.data.tag_names != *table {
+            return false;
         }
-        let mut scores = vec![];
-        let mut range_start = Some(0);
-        sentence.n_tags = self.data.n_tags;
-        sentence.tags.clear();
-        sentence
-            .tags
-            .resize(sentence.len() * self.data.n_tags, None);
-        sentence.tag_scores.clear();
-        if self.tag_scores {
-            sentence.tag_scores.resize(sentence.len(), None);
+        self.data.tag_names = Arc::clone(table);
+        true
+    }
+
+    /// Returns the ID of the given tag in [`Predictor::tag_names()`], or `None` if no tag model
+    /// has the tag.
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn tag_id(&self, tag: &str) -> Option<u32> {
+        self.data
+            .tag_names
+            .binary_search_by(|name| name.as_str().cmp(tag))
+            .ok()
+            .and_then(|id| u32::try_from(id).ok())
+    }
+
+    /// Returns the heap memory used by the predictor.
+    ///
+    /// Disk dictionaries added by [`Predictor::add_disk_dictionary()`] are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{Model, Predictor};
+    ///
+    /// let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin")).unwrap();
+    /// let predictor = Predictor::new(model, false).unwrap();
+    ///
+    /// let usage = predictor.memory_usage();
+    /// assert!(usage.char_automaton > 0);
+    /// assert!(usage.total() > usage.weights);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        if let Some(scorer) = &self.data.char_scorer {
+            let bytes = scorer.heap_bytes();
+            usage.char_automaton += bytes.automaton;
+            usage.weights += bytes.weights;
+            usage.tag_models += bytes.tag_weights;
         }
-        for (i, &b) in sentence.boundaries.iter().enumerate() {
-            if b == CharacterBoundary::Unknown {
-                range_start.take();
-            } else if b == CharacterBoundary::WordBoundary {
-                if let Some(&range_start) = range_start.as_ref() {
-                    let token = sentence.text_substring(range_start, i + 1);
-                    if let Some((token_id, tag_predictor)) = tag_predictor.get(token) {
-                        scores.clear();
-                        scores.resize(tag_predictor.bias().len(), 0);
-                        tag_predictor.bias().add_scores(&mut scores);
-                        if let Some(scorer) = self.data.char_scorer.as_ref() {
-                            debug_assert!(i < sentence.char_pma_states.len());
-                            // token_id is always smaller than tag_weight.len() because
-                            // tag_predictor is created to contain such values in the new()
-                            // function.
-                            unsafe {
-                                scorer.add_tag_scores(*token_id, i, sentence, &mut scores);
-                            }
-                        }
-                        if let Some(scorer) = self.data.type_scorer.as_ref() {
-                            debug_assert!(i < sentence.type_pma_states.len());
-                            // token_id is always smaller than tag_weight.len() because
-                            // tag_predictor is created to contain such values in the new()
-                            // function.
-                            unsafe {
-                                scorer.add_tag_scores(*token_id, i, sentence, &mut scores);
-                            }
-                        }
-                        tag_predictor.predict(
-                            &scores,
-                            &mut sentence.tags[i * self.data.n_tags..(i + 1) * self.data.n_tags],
-                        );
-                        if !sentence.tag_scores.is_empty() {
-                            sentence.tag_scores[i].replace((&tag_predictor.tags, scores));
-                            scores = vec![];
-                        }
-                    }
-                }
-                range_start.replace(i + 1);
-            }
+        if let Some(scorer) = &self.data.type_scorer {
+            let bytes = scorer.heap_bytes();
+            usage.type_automaton += bytes.automaton;
+            usage.weights += bytes.weights;
+            usage.tag_models += bytes.tag_weights;
         }
-        if let Some(&range_start) = range_start.as_ref() {
-            let token = sentence.text_substring(range_start, sentence.len());
-            if let Some((token_id, tag_predictor)) = tag_predictor.get(token) {
-                scores.clear();
-                scores.resize(tag_predictor.bias().len(), 0);
-                tag_predictor.bias().add_scores(&mut scores);
-                if let Some(scorer) = self.data.char_scorer.as_ref() {
-                    debug_assert!(sentence.len() <= sentence.char_pma_states.len());
-                    // token_id is always smaller than tag_weight.len() because tag_predictor is
-                    // created to contain such values in the new() function.
-                    unsafe {
-                        scorer.add_tag_scores(*token_id, sentence.len() - 1, sentence, &mut scores);
+        for scorer in [&self.data.dict_scorer, &self.user_dict_scorer]
+            .into_iter()
+            .flatten()
+        {
+            let bytes = scorer.heap_bytes();
+            usage.dict_automaton += bytes.automaton;
+            usage.weights += bytes.weights;
+        }
+        #[cfg(feature = "tag-prediction")]
+        {
+            usage.tag_models += self.data.tag_predictor.heap_bytes()
+                + self
+                    .data
+                    .tag_names
+                    .iter()
+                    .map(|name| core::mem::size_of::<String>() + name.heap_bytes())
+                    .sum::<usize>()
+                + self.data.tag_transitions.heap_bytes()
+                + self.tag_lexicon.heap_bytes();
+        }
+        usage.others += self.data.char_type_ranges.heap_bytes()
+            + self.user_dict.heap_bytes()
+            + self.user_dict_index.heap_bytes()
+            + self.attribution.heap_bytes();
+        usage
+    }
+
+    /// Sets the decision threshold of boundary scores.
+    ///
+    /// Boundaries with scores larger than the threshold are word boundaries. The default is 0.
+    /// A positive threshold splits texts only when the model is confident, improving the
+    /// precision of word boundaries at the cost of the recall.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let mut predictor = Predictor::new(model, false)?;
+predictor.set_threshold(i32::MAX);
+
+let mut s = Sentence::from_raw(\"まぁ社長は火星猫だ\")?;
+predictor.predict(&mut s);
+assert_eq!(1, s.iter_tokens().count());
+# Ok(())
+# }
+```
+"
+    )]
+    pub fn set_threshold(&mut self, threshold: i32) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the margin band around the threshold.
+    ///
+    /// Boundaries whose scores differ from the threshold by at most `margin` are set to
+    /// [`CharacterBoundary::Unknown`] instead of being decided by the model, so that downstream
+    /// rules can resolve them. Note that tokens adjacent to unknown boundaries are skipped by
+    /// [`Sentence::iter_tokens()`]. Set to `None` to disable it (default).
+    pub fn set_margin_band(&mut self, margin: Option<u32>) {
+        self.margin_band = margin;
+    }
+
+    /// Keeps runs of characters that no n-gram or word in the model covers as single tokens
+    /// if the runs are longer than or equal to `min_len` characters.
+    ///
+    /// Such a run consists of characters of the same type and is tagged
+    /// [`Predictor::UNKNOWN_SPAN_TAG`] instead of being split by the bias. This is useful for
+    /// scripts that the model has not been trained on. Set to `None` to disable it (default).
+    pub fn keep_unknown_spans(&mut self, min_len: Option<usize>) {
+        self.unknown_span_len = min_len;
+    }
+
+    /// Adds a word to the user dictionary of this predictor.
+    ///
+    /// Unlike [`Model::replace_dictionary()`], this function does not require reconstructing the
+    /// predictor. Only the automaton of the user dictionary is rebuilt. The weights of the record
+    /// are added to the scores computed by the model, so a word contained in the dictionary of
+    /// the model is not replaced. If the user dictionary already contains the word, the record is
+    /// replaced. Tags in the comment of the record are not used.
+    ///
+    /// The user dictionary is not stored by [`Predictor::serialize_to_vec()`]. Since each call
+    /// rebuilds the automaton, use [`Predictor::extend_dict_words()`] to add many words.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if the word is empty or too long.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence, WordWeightRecord};
+
+let model = Model::read(File::open(\"../resources/model.bin\").unwrap()).unwrap();
+let mut predictor = Predictor::new(model, false).unwrap();
+
+let mut buf = String::new();
+
+let record = WordWeightRecord::new(\"火星猫\".into(), vec![0, -50000, -50000, 0], \"\".into());
+predictor.add_dict_word(record.unwrap()).unwrap();
+let mut s = Sentence::from_raw(\"火星猫\").unwrap();
+predictor.predict(&mut s);
+s.write_tokenized_text(&mut buf);
+assert_eq!(\"火星猫\", buf);
+
+predictor.remove_dict_word(\"火星猫\").unwrap();
+let mut s = Sentence::from_raw(\"火星猫\").unwrap();
+predictor.predict(&mut s);
+s.write_tokenized_text(&mut buf);
+assert_eq!(\"火星 猫\", buf);
+```
+"
+    )]
+    pub fn add_dict_word(&mut self, record: WordWeightRecord) -> Result<()> {
+        self.extend_dict_words([record])
+    }
+
+    /// Adds words to the user dictionary of this predictor.
+    ///
+    /// This is equivalent to calling [`Predictor::add_dict_word()`] for each record, but the
+    /// automaton of the user dictionary is rebuilt only once.
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if a word is empty or too long. In that case, the user
+    /// dictionary is left unchanged.
+    pub fn extend_dict_words<I>(&mut self, records: I) -> Result<()>
+    where
+        I: IntoIterator<Item = WordWeightRecord>,
+    {
+        let n_words = self.user_dict.len();
+        let mut replaced = vec![];
+        for record in records {
+            if record.word.is_empty() {
+                self.rollback_user_dict(n_words, replaced);
+                return Err(VaporettoError::invalid_argument(
+                    "record",
+                    "word must not be empty",
+                ));
+            }
+            if let Some(&i) = self.user_dict_index.get(&record.word) {
+                replaced.push((i, core::mem::replace(&mut self.user_dict[i], record)));
+            } else {
+                self.user_dict_index
+                    .insert(record.word.clone(), self.user_dict.len());
+                self.user_dict.push(record);
+            }
+        }
+        match Self::build_user_dict_scorer(&self.user_dict) {
+            Ok(scorer) => {
+                self.user_dict_scorer = scorer;
+                Ok(())
+            }
+            Err(e) => {
+                self.rollback_user_dict(n_words, replaced);
+                Err(e)
+            }
+        }
+    }
+
+    /// Restores the user dictionary to the state before [`Predictor::extend_dict_words()`].
+    fn rollback_user_dict(&mut self, n_words: usize, replaced: Vec<(usize, WordWeightRecord)>) {
+        for (i, record) in replaced.into_iter().rev() {
+            self.user_dict[i] = record;
+        }
+        for record in self.user_dict.drain(n_words..) {
+            self.user_dict_index.remove(&record.word);
+        }
+    }
+
+    /// Removes a word from the user dictionary of this predictor and returns the removed record.
+    ///
+    /// Words in the dictionary of the model cannot be removed by this function. The last word of
+    /// the user dictionary is moved to the position of the removed word.
+    pub fn remove_dict_word(&mut self, word: &str) -> Option<WordWeightRecord> {
+        let i = self.user_dict_index.remove(word)?;
+        let record = self.user_dict.swap_remove(i);
+        if let Some(moved) = self.user_dict.get(i) {
+            self.user_dict_index.insert(moved.word.clone(), i);
+        }
+        // The remaining words have already been built successfully.
+        self.user_dict_scorer = Self::build_user_dict_scorer(&self.user_dict).unwrap();
+        Some(record)
+    }
+
+    /// Returns words added by [`Predictor::add_dict_word()`].
+    pub fn user_dictionary(&self) -> &[WordWeightRecord] {
+        &self.user_dict
+    }
+
+    /// Adds a dictionary stored on disk to this predictor.
+    ///
+    /// Words of the dictionary are applied in the same way as the user dictionary, but they are
+    /// looked up in the file during prediction instead of being loaded into memory. Blocks
+    /// missing from the cache are read one at a time, so prediction in multiple threads may be
+    /// slow until frequently used blocks are cached.
+    ///
+    /// If reading the file fails during prediction, words of the dictionary are ignored in the
+    /// sentence. The error can be obtained by [`DiskDictionary::take_error()`] through
+    /// [`Predictor::disk_dictionaries()`].
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn add_disk_dictionary(&mut self, dict: DiskDictionary) {
+        self.disk_dicts.push(dict);
+    }
+
+    /// Returns dictionaries added by [`Predictor::add_disk_dictionary()`].
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn disk_dictionaries(&self) -> &[DiskDictionary] {
+        &self.disk_dicts
+    }
+
+    fn build_user_dict_scorer(dict: &[WordWeightRecord]) -> Result<Option<CharScorerBoundary>> {
+        if dict.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(CharScorerBoundary::new(
+            NgramModel(vec![]),
+            DictModel::new(dict.to_vec()),
+            0,
+        )?))
+    }
+
+    /// Predicts word boundaries of the given sentence.
+    /// If necessary, this function also prepares for predicting tags.
+    ///
+    /// Character types of the sentence are overwritten if the model defines custom character
+    /// types.
+    pub fn predict<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
+        self.predict_inner(sentence, None, |_| ());
+    }
+
+    /// Predicts word boundaries of the given sentence like [`Predictor::predict()`] and adds the
+    /// elapsed time of each stage to `timings`.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn predict_timed<'a>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+        timings: &mut StageTimings,
+    ) {
+        let mut last = std::time::Instant::now();
+        self.predict_inner(sentence, None, |stage| {
+            let now = std::time::Instant::now();
+            let elapsed = now - last;
+            match stage {
+                Stage::Char => timings.char_scorer += elapsed,
+                Stage::Type => timings.type_scorer += elapsed,
+                Stage::Dict => timings.dict_scorer += elapsed,
+            }
+            last = now;
+        });
+    }
+
+    /// Predicts word boundaries of the given sentence with the weights of dictionaries
+    /// multiplied by `scale`.
+    ///
+    /// For example, a scale larger than 1 boosts dictionary influence for product catalogs,
+    /// and a scale smaller than 1 reduces it for prose. The scale is applied to the user
+    /// dictionary and, if the predictor is created by [`Predictor::with_scalable_dictionary()`],
+    /// the dictionary of the model. Otherwise, the dictionary of the model is merged with the
+    /// character n-grams and is not scaled.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::with_scalable_dictionary(model, false)?;
+
+let mut s = Sentence::from_raw(\"まぁ社長は火星猫だ\")?;
+predictor.predict_with_dict_scale(&mut s, 1.0);
+let mut buf = String::new();
+s.write_tokenized_text(&mut buf);
+assert_eq!(\"まぁ 社長 は 火星 猫 だ\", buf);
+# Ok(())
+# }
+```
+"
+    )]
+    pub fn predict_with_dict_scale<'a>(&'a self, sentence: &mut Sentence<'_, 'a>, scale: f64) {
+        self.predict_inner(sentence, Some(scale), |_| ());
+    }
+
+    /// Predicts word boundaries of the given sentence like [`Predictor::predict()`] and returns
+    /// the boundary scores broken down into each feature family.
+    ///
+    /// For each boundary, the score stored in `sentence` is the sum of the bias and the
+    /// components. Unless the predictor is created by [`Predictor::with_scalable_dictionary()`],
+    /// the dictionary of the model is merged with the character n-grams, so its weights are
+    /// included in [`ScoreComponents::char_ngram`].
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::with_scalable_dictionary(model, false)?;
+
+let mut s = Sentence::from_raw(\"まぁ社長は火星猫だ\")?;
+let components = predictor.score_components(&mut s);
+for (i, &score) in s.boundary_scores().iter().enumerate() {
+    assert_eq!(
+        score,
+        components.bias
+            + components.char_ngram[i]
+            + components.type_ngram[i]
+            + components.dict[i],
+    );
+}
+# Ok(())
+# }
+```
+"
+    )]
+    pub fn score_components<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) -> ScoreComponents {
+        self.predict(sentence);
+        let scores = core::mem::take(&mut sentence.boundary_scores);
+        let range = sentence.score_padding..sentence.score_padding + sentence.boundaries.len();
+
+        let mut component = |add_scores: &dyn Fn(&mut Sentence)| {
+            sentence.boundary_scores.clear();
+            sentence.boundary_scores.resize(scores.len(), 0);
+            add_scores(sentence);
+            sentence.boundary_scores[range.clone()].to_vec()
+        };
+        let char_ngram = component(&|sentence| {
+            if let Some(scorer) = self.data.char_scorer.as_ref() {
+                scorer.add_scores(sentence);
+            }
+        });
+        let type_ngram = component(&|sentence| {
+            if let Some(scorer) = self.data.type_scorer.as_ref() {
+                scorer.add_scores(sentence);
+            }
+        });
+        let dict = component(&|sentence| {
+            if let Some(scorer) = self.data.dict_scorer.as_ref() {
+                scorer.add_scores(sentence);
+            }
+            if let Some(scorer) = self.user_dict_scorer.as_ref() {
+                scorer.add_scores(sentence);
+            }
+            #[cfg(feature = "std")]
+            for dict in &self.disk_dicts {
+                dict.add_scores(sentence);
+            }
+        });
+
+        sentence.boundary_scores = scores;
+        ScoreComponents {
+            bias: self.data.bias,
+            char_ngram,
+            type_ngram,
+            dict,
+        }
+    }
+
+    /// Predicts word boundaries of the given sentence like [`Predictor::predict()`] and returns
+    /// the features contributing to each boundary score.
+    ///
+    /// For each boundary, the character n-grams, character type n-grams, and words of the
+    /// dictionary of the model and the user dictionary that add non-zero weights to the
+    /// boundary are returned in descending order of the absolute weights. The score stored in
+    /// `sentence` is the sum of the bias and the weights, except for weights of disk
+    /// dictionaries, which are not attributed.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::with_attribution(model, false)?;
+
+let mut s = Sentence::from_raw(\"まぁ社長は火星猫だ\")?;
+let attributions = predictor.predict_with_attribution(&mut s)?;
+for (i, features) in attributions.iter().enumerate() {
+    let sum: i32 = features.iter().map(|a| a.weight).sum();
+    assert_eq!(s.boundary_scores()[i], predictor.bias() + sum);
+}
+# Ok(())
+# }
+```
+"
+    )]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error variant if the predictor is not created by
+    /// [`Predictor::with_attribution()`].
+    pub fn predict_with_attribution<'a>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+    ) -> Result<Vec<Vec<Attribution>>> {
+        let attribution = self.attribution.as_ref().ok_or_else(|| {
+            VaporettoError::invalid_argument(
+                "self",
+                "attribution requires a predictor created by Predictor::with_attribution()",
+            )
+        })?;
+        self.predict(sentence);
+        Ok(attribution.attribute(sentence, &self.user_dict))
+    }
+
+    /// Returns the bias added to all boundary scores.
+    pub const fn bias(&self) -> i32 {
+        self.data.bias
+    }
+
+    /// `on_stage` is called at the end of each stage.
+    #[allow(clippy::cast_possible_truncation)]
+    #[inline(always)]
+    fn predict_inner<'a, F>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+        dict_scale: Option<f64>,
+        mut on_stage: F,
+    ) where
+        F: FnMut(Stage),
+    {
+        sentence.apply_char_type_ranges(&self.data.char_type_ranges);
+        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
+        sentence.boundary_scores.clear();
+        sentence.boundary_scores.resize(
+            sentence.score_padding * 2 + sentence.len() - 1,
+            self.data.bias,
+        );
+        if let Some(scorer) = self.data.char_scorer.as_ref() {
+            scorer.add_scores(sentence);
+        }
+        on_stage(Stage::Char);
+        if let Some(scorer) = self.data.type_scorer.as_ref() {
+            scorer.add_scores(sentence);
+        }
+        on_stage(Stage::Type);
+        let base_scores = dict_scale.map(|_| sentence.boundary_scores.clone());
+        if let Some(scorer) = self.data.dict_scorer.as_ref() {
+            scorer.add_scores(sentence);
+        }
+        if let Some(scorer) = self.user_dict_scorer.as_ref() {
+            scorer.add_scores(sentence);
+        }
+        #[cfg(feature = "std")]
+        for dict in &self.disk_dicts {
+            dict.add_scores(sentence);
+        }
+        if let (Some(scale), Some(base_scores)) = (dict_scale, base_scores) {
+            for (y, &base) in sentence.boundary_scores.iter_mut().zip(&base_scores) {
+                let diff = f64::from(*y - base) * scale;
+                // Rounds half away from zero.
+                *y = base + if diff >= 0.0 { diff + 0.5 } else { diff - 0.5 } as i32;
+            }
+        }
+        self.set_boundaries_by_scores(sentence);
+        sentence.unknown_spans.clear();
+        if let Some(min_len) = self.unknown_span_len {
+            self.keep_unknown_spans_of(sentence, min_len);
+        }
+        on_stage(Stage::Dict);
+        sentence.set_predictor(self);
+    }
+
+    fn set_boundaries_by_scores(&self, sentence: &mut Sentence) {
+        for (b, s) in sentence
+            .boundaries
+            .iter_mut()
+            .zip(&sentence.boundary_scores[sentence.score_padding..])
+        {
+            *b = if self.margin_band.is_some_and(|margin| {
+                (i64::from(*s) - i64::from(self.threshold)).unsigned_abs() <= u64::from(margin)
+            }) {
+                CharacterBoundary::Unknown
+            } else if *s > self.threshold {
+                CharacterBoundary::WordBoundary
+            } else {
+                CharacterBoundary::NotWordBoundary
+            };
+        }
+    }
+
+    /// Predicts word boundaries of the given sentence with the preceding text as context.
+    ///
+    /// The context is prepended to the sentence only for computing boundary scores, so n-grams
+    /// and dictionary words spanning both texts affect the boundaries at the beginning of the
+    /// sentence. This improves segmentation of sentence-initial fragments in chat logs. Only the
+    /// last few characters of the context affect the result, so passing the last characters of
+    /// the previous sentence is enough. Tags are predicted without the context.
+    ///
+    /// If `context` is empty or contains NULL, this function is equivalent to
+    /// [`Predictor::predict()`].
+    pub fn predict_with_context<'a>(&'a self, sentence: &mut Sentence<'_, 'a>, context: &str) {
+        self.predict(sentence);
+        let Ok(mut s) = Sentence::from_raw(format!("{context}{}", sentence.as_raw_text())) else {
+            return;
+        };
+        let n_context = s.len() - sentence.len();
+        if n_context == 0 {
+            return;
+        }
+        s.char_types[n_context..].copy_from_slice(&sentence.char_types);
+        self.predict_inner(&mut s, None, |_| ());
+        sentence.boundary_scores.clear();
+        sentence
+            .boundary_scores
+            .extend_from_slice(&s.boundary_scores[n_context..]);
+        self.set_boundaries_by_scores(sentence);
+        sentence.unknown_spans.clear();
+        if let Some(min_len) = self.unknown_span_len {
+            self.keep_unknown_spans_of(sentence, min_len);
+        }
+    }
+
+    /// Predicts word boundaries of the given sentence and returns the `n` best hypotheses.
+    ///
+    /// Since each boundary is predicted independently, a hypothesis other than the best one is
+    /// obtained by flipping some boundaries of the best one. The cost of a hypothesis is the sum
+    /// of the absolute differences between the scores of the flipped boundaries and the
+    /// threshold, i.e., the loss of the total margin.
+    /// Hypotheses are sorted in ascending order of their costs, so the first element always
+    /// holds the boundaries stored in `sentence` with a cost of 0.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{CharacterBoundary, Model, Predictor, Sentence};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::new(model, false)?;
+
+let mut s = Sentence::from_raw(\"火星猫\")?;
+let hyps = predictor.predict_nbest(&mut s, 3);
+
+assert_eq!(3, hyps.len());
+assert_eq!((s.boundaries().to_vec(), 0), hyps[0]);
+assert!(hyps[1].1 <= hyps[2].1);
+# Ok(())
+# }
+```
+"
+    )]
+    pub fn predict_nbest<'a>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+        n: usize,
+    ) -> Vec<(Vec<CharacterBoundary>, u64)> {
+        self.predict(sentence);
+        let mut results = vec![];
+        if n == 0 {
+            return results;
+        }
+        results.push((sentence.boundaries().to_vec(), 0));
+
+        // Enumerates subsets of boundaries in ascending order of the sum of absolute scores.
+        let mut costs: Vec<(u64, usize)> = sentence
+            .boundary_scores()
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let margin = i64::from(s) - i64::from(self.threshold);
+                (margin.unsigned_abs(), i)
+            })
+            .collect();
+        costs.sort_unstable();
+        let mut heap = BinaryHeap::new();
+        if !costs.is_empty() {
+            heap.push(Reverse((costs[0].0, vec![0])));
+        }
+        while results.len() < n {
+            let Some(Reverse((cost, flips))) = heap.pop() else {
+                break;
+            };
+            let last = *flips.last().unwrap();
+            if let Some(&(next_cost, _)) = costs.get(last + 1) {
+                let mut added = flips.clone();
+                added.push(last + 1);
+                heap.push(Reverse((cost + next_cost, added)));
+                let mut replaced = flips.clone();
+                *replaced.last_mut().unwrap() = last + 1;
+                heap.push(Reverse((cost - costs[last].0 + next_cost, replaced)));
+            }
+            let mut boundaries = sentence.boundaries().to_vec();
+            for &i in &flips {
+                let b = &mut boundaries[costs[i].1];
+                *b = match b {
+                    CharacterBoundary::WordBoundary => CharacterBoundary::NotWordBoundary,
+                    _ => CharacterBoundary::WordBoundary,
+                };
+            }
+            results.push((boundaries, cost));
+        }
+        results
+    }
+
+    /// Predicts word boundaries of the given sentence and samples each boundary randomly instead
+    /// of thresholding the scores.
+    ///
+    /// A word boundary is placed with probability `1 / (1 + exp(-(score - threshold) /
+    /// temperature))`, so calling this function repeatedly produces diverse segmentations for
+    /// data augmentation such as subword regularization. Boundaries set to
+    /// [`CharacterBoundary::Unknown`] by the margin band and those around unknown spans are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentence` - A sentence.
+    /// * `temperature` - Scale of boundary scores. Larger values produce more diverse
+    ///   segmentations. If it is not positive, the result is the same as
+    ///   [`Predictor::predict()`].
+    /// * `seed` - A seed of the random number generator. The same seed always produces the same
+    ///   segmentation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, Predictor, Sentence};
+    ///
+    /// let model = Model::read(File::open("../resources/model.bin")?)?;
+    /// let predictor = Predictor::new(model, false)?;
+    ///
+    /// let mut s = Sentence::from_raw("まぁ社長は火星猫だ")?;
+    /// let mut segmentations = std::collections::HashSet::new();
+    /// for seed in 0..10 {
+    ///     predictor.predict_sampled(&mut s, 10000.0, seed);
+    ///     segmentations.insert(s.boundaries().to_vec());
+    /// }
+    /// assert!(segmentations.len() > 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn predict_sampled<'a>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+        temperature: f64,
+        seed: u64,
+    ) {
+        self.predict(sentence);
+        if temperature.is_nan() || temperature <= 0.0 {
+            return;
+        }
+        let mut fixed = vec![false; sentence.boundaries.len()];
+        for &(start, end) in &sentence.unknown_spans {
+            fixed[start.saturating_sub(1)..end.min(sentence.boundaries.len())].fill(true);
+        }
+        let mut rng = SplitMix64Rng::new(seed);
+        let scores = &sentence.boundary_scores[sentence.score_padding..];
+        for ((b, &s), fixed) in sentence.boundaries.iter_mut().zip(scores).zip(fixed) {
+            if fixed || *b == CharacterBoundary::Unknown {
+                continue;
+            }
+            let x = (f64::from(s) - f64::from(self.threshold)) / temperature;
+            // Avoids overflow of exp().
+            let p = if x >= 0.0 {
+                1.0 / (1.0 + (-x).exp())
+            } else {
+                let e = x.exp();
+                e / (1.0 + e)
+            };
+            *b = if rng.next_f64() < p {
+                CharacterBoundary::WordBoundary
+            } else {
+                CharacterBoundary::NotWordBoundary
+            };
+        }
+    }
+
+    /// Predicts word boundaries of the given sentences in parallel using the global thread pool
+    /// of [`rayon`].
+    ///
+    /// The results are the same as calling [`Predictor::predict()`] for each sentence, and the
+    /// order of the sentences is retained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, Predictor, Sentence};
+    ///
+    /// let model = Model::read(File::open("../resources/model.bin")?)?;
+    /// let predictor = Predictor::new(model, false)?;
+    ///
+    /// let sentences = vec![
+    ///     Sentence::from_raw("まぁ社長は火星猫だ")?,
+    ///     Sentence::from_raw("まぁ良いだろう")?,
+    /// ];
+    /// let sentences = predictor.predict_batch(sentences);
+    ///
+    /// let mut buf = String::new();
+    /// sentences[0].write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+    /// sentences[1].write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 良い だろう", buf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn predict_batch<'a, 'b>(
+        &'b self,
+        mut sentences: Vec<Sentence<'a, 'b>>,
+    ) -> Vec<Sentence<'a, 'b>> {
+        use rayon::prelude::*;
+
+        sentences
+            .par_iter_mut()
+            .for_each(|sentence| self.predict(sentence));
+        sentences
+    }
+
+    /// Splits the given document into lines and predicts word boundaries of them using all
+    /// available cores.
+    ///
+    /// Empty lines are skipped. Unlike [`Predictor::predict_batch()`], this function spawns
+    /// scoped threads internally, so a thread pool is not required.
+    ///
+    /// # Errors
+    ///
+    /// If a line contains NULL, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, Predictor};
+    ///
+    /// let model = Model::read(File::open("../resources/model.bin")?)?;
+    /// let predictor = Predictor::new(model, false)?;
+    ///
+    /// let sentences = predictor.predict_document("まぁ社長は火星猫だ\n\nまぁ良いだろう\n")?;
+    ///
+    /// assert_eq!(2, sentences.len());
+    /// let mut buf = String::new();
+    /// sentences[0].write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+    /// sentences[1].write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ 良い だろう", buf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn predict_document<'a, 'b>(&'b self, text: &'a str) -> Result<Vec<Sentence<'a, 'b>>> {
+        let mut sentences = vec![];
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            sentences.push(Sentence::from_raw(line)?);
+        }
+        let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if n_threads <= 1 || sentences.len() <= 1 {
+            for sentence in &mut sentences {
+                self.predict(sentence);
+            }
+            return Ok(sentences);
+        }
+        let chunk_size = sentences.len().div_ceil(n_threads);
+        std::thread::scope(|scope| {
+            for chunk in sentences.chunks_mut(chunk_size) {
+                scope.spawn(|| {
+                    for sentence in chunk {
+                        self.predict(sentence);
+                    }
+                });
+            }
+        });
+        Ok(sentences)
+    }
+
+    /// Returns an iterator that lazily reads lines from `reader` and predicts their word
+    /// boundaries. See [`StreamTokenizer`](crate::StreamTokenizer) for details.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub const fn predict_streaming<R>(&self, reader: R) -> crate::StreamTokenizer<'_, R>
+    where
+        R: std::io::BufRead,
+    {
+        crate::StreamTokenizer::new(self, reader)
+    }
+
+    fn keep_unknown_spans_of(&self, sentence: &mut Sentence, min_len: usize) {
+        let mut covered = vec![false; sentence.len()];
+        if let Some(scorer) = self.data.char_scorer.as_ref() {
+            scorer.mark_covered_chars(sentence, &mut covered);
+        }
+        if let Some(scorer) = self.data.dict_scorer.as_ref() {
+            scorer.mark_covered_chars(sentence, &mut covered);
+        }
+        if let Some(scorer) = self.user_dict_scorer.as_ref() {
+            scorer.mark_covered_chars(sentence, &mut covered);
+        }
+        #[cfg(feature = "std")]
+        for dict in &self.disk_dicts {
+            dict.mark_covered_chars(sentence, &mut covered);
+        }
+        let mut start = 0;
+        for i in 1..=sentence.len() {
+            if i == sentence.len()
+                || covered[i]
+                || covered[i - 1]
+                || sentence.char_types[i] != sentence.char_types[i - 1]
+            {
+                if !covered[i - 1] && i - start >= min_len {
+                    sentence.unknown_spans.push((start, i));
+                }
+                start = i;
+            }
+        }
+        if sentence.unknown_spans.is_empty() {
+            return;
+        }
+        for &(start, end) in &sentence.unknown_spans {
+            if start != 0 {
+                sentence.boundaries[start - 1] = CharacterBoundary::WordBoundary;
+            }
+            sentence.boundaries[start..end - 1].fill(CharacterBoundary::NotWordBoundary);
+            if end != sentence.len() {
+                sentence.boundaries[end - 1] = CharacterBoundary::WordBoundary;
+            }
+        }
+        if sentence.n_tags == 0 || sentence.tags.len() != sentence.n_tags * sentence.len() {
+            sentence.reset_tags(sentence.n_tags.max(1));
+        }
+        Self::fill_unknown_span_tags(sentence);
+    }
+
+    fn fill_unknown_span_tags(sentence: &mut Sentence) {
+        let n_tags = sentence.n_tags;
+        for &(_, end) in &sentence.unknown_spans {
+            let tags = &mut sentence.tags[(end - 1) * n_tags..end * n_tags];
+            tags.fill(None);
+            tags[0].replace(Cow::Borrowed(Self::UNKNOWN_SPAN_TAG));
+            if let Some(scores) = sentence.tag_scores.get_mut(end - 1) {
+                scores.take();
+            }
+        }
+    }
+
+    /// Predicts tags of a single token in the given context.
+    ///
+    /// Word boundaries are placed at both ends of `word` and removed inside it, so the token is
+    /// classified without constructing sentences and boundaries by hand. The contexts are
+    /// segmented by the model, but only characters within the window affect the result.
+    ///
+    /// # Errors
+    ///
+    /// If `word` is empty or any argument contains NULL, an error variant will be returned.
+    ///
+    /// # Panics
+    ///
+    /// If the predictor is created with `predict_tags = false`, this function panics.
+    ///
+    #[cfg_attr(
+        feature = "std",
+        doc = "
+# Examples
+
+```
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::fs::File;
+
+use vaporetto::{Model, Predictor};
+
+let model = Model::read(File::open(\"../resources/model.bin\")?)?;
+let predictor = Predictor::new(model, true)?;
+
+let tags = predictor.predict_tag(\"良い\", \"まぁ\", \"だろう\")?;
+assert_eq!(Some(\"形容詞\"), tags[0].as_deref());
+assert_eq!(Some(\"ヨイ\"), tags[1].as_deref());
+# Ok(())
+# }
+```
+"
+    )]
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn predict_tag(
+        &self,
+        word: &str,
+        left_context: &str,
+        right_context: &str,
+    ) -> Result<Vec<Option<Cow<'_, str>>>> {
+        if word.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "word",
+                "must contain at least one character",
+            ));
+        }
+        let mut text = String::with_capacity(left_context.len() + word.len() + right_context.len());
+        text.push_str(left_context);
+        text.push_str(word);
+        text.push_str(right_context);
+        let mut sentence = Sentence::from_raw(text)?;
+        self.predict(&mut sentence);
+        let start = left_context.chars().count();
+        let end = start + word.chars().count();
+        let boundaries = sentence.boundaries_mut();
+        if start != 0 {
+            boundaries[start - 1] = CharacterBoundary::WordBoundary;
+        }
+        boundaries[start..end - 1].fill(CharacterBoundary::NotWordBoundary);
+        if let Some(b) = boundaries.get_mut(end - 1) {
+            *b = CharacterBoundary::WordBoundary;
+        }
+        self.predict_tags(&mut sentence);
+        let n_tags = sentence.n_tags();
+        Ok(sentence
+            .tags_mut()
+            .get_mut((end - 1) * n_tags..end * n_tags)
+            .map_or_else(Vec::new, |tags| tags.iter_mut().map(Option::take).collect()))
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    pub(crate) fn predict_tags<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
+        self.predict_tags_inner(sentence, self.tag_scores);
+    }
+
+    /// Predicts tags like [`Predictor::predict_tags()`], then re-decodes levels having transition
+    /// weights by the Viterbi algorithm so that the sum of tag scores and transition weights is
+    /// maximized over each run of tokens having tag models.
+    #[cfg(feature = "tag-prediction")]
+    pub(crate) fn predict_tags_joint<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
+        self.predict_tags_inner(sentence, true);
+        if self.data.n_tags != 0 && !self.data.tag_transitions.is_empty() {
+            let mut chain = vec![];
+            let mut start = 0;
+            for i in 0..sentence.len() {
+                if i + 1 != sentence.len()
+                    && sentence.boundaries[i] != CharacterBoundary::WordBoundary
+                {
+                    continue;
+                }
+                if sentence.tag_scores[i].is_some() {
+                    let token = sentence.text_substring(start, i + 1);
+                    let mask = self
+                        .tag_predictor_id(token)
+                        .and_then(|id| self.lexicon_mask(id));
+                    chain.push((i, mask));
+                } else {
+                    self.decode_tag_chain(sentence, &chain);
+                    chain.clear();
+                }
+                start = i + 1;
+            }
+            self.decode_tag_chain(sentence, &chain);
+        }
+        if !self.tag_scores {
+            sentence.tag_scores.clear();
+        }
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    fn tag_predictor_id(&self, token: &str) -> Option<u32> {
+        self.data
+            .tag_predictor
+            .as_ref()
+            .and_then(|tag_predictor| tag_predictor.get(token))
+            .map(|(token_id, _)| *token_id)
+    }
+
+    /// Decodes tags of the given tokens, which are pairs of the last character position and the
+    /// lexicon mask, for each level having transition weights.
+    #[cfg(feature = "tag-prediction")]
+    fn decode_tag_chain<'a>(
+        &'a self,
+        sentence: &mut Sentence<'_, 'a>,
+        chain: &[(usize, Option<&[bool]>)],
+    ) {
+        if chain.len() < 2 {
+            return;
+        }
+        let n_tags = self.data.n_tags;
+        for (level, transitions) in self.data.tag_transitions.iter().enumerate() {
+            if transitions.is_empty() || level >= n_tags {
+                continue;
+            }
+            // Candidates and emission scores of each token. Disallowed candidates have no score.
+            let mut lattice: Vec<(&'a [u32], Vec<Option<i64>>)> = vec![];
+            for &(i, mask) in chain {
+                let (tags, scores) = sentence.tag_scores[i].as_ref().unwrap();
+                let tags: &'a [Vec<u32>] = tags;
+                let offset: usize = tags[..level]
+                    .iter()
+                    .map(Vec::len)
+                    .filter(|&len| len >= 2)
+                    .sum();
+                let cands = &tags[level];
+                let emissions = if cands.len() >= 2 {
+                    (0..cands.len())
+                        .map(|j| {
+                            mask.map_or(true, |mask| mask[offset + j])
+                                .then(|| i64::from(scores[offset + j]))
+                        })
+                        .collect()
+                } else {
+                    vec![Some(0); cands.len()]
+                };
+                lattice.push((cands.as_slice(), emissions));
+            }
+            for (start, end) in Self::non_empty_runs(&lattice) {
+                let path = self.viterbi(&lattice[start..end], transitions);
+                for (&(i, _), (&(cands, _), j)) in chain[start..end]
+                    .iter()
+                    .zip(lattice[start..end].iter().zip(path))
+                {
+                    sentence.tags[i * n_tags + level]
+                        .replace(Cow::Borrowed(&self.data.tag_names[cands[j] as usize]));
+                }
+            }
+        }
+    }
+
+    /// Returns ranges of the lattice in which every token has at least one candidate.
+    #[cfg(feature = "tag-prediction")]
+    fn non_empty_runs(lattice: &[(&[u32], Vec<Option<i64>>)]) -> Vec<(usize, usize)> {
+        let mut runs = vec![];
+        let mut start = 0;
+        for (i, (_, emissions)) in lattice.iter().enumerate() {
+            if emissions.iter().all(Option::is_none) {
+                if i - start >= 2 {
+                    runs.push((start, i));
+                }
+                start = i + 1;
+            }
+        }
+        if lattice.len() - start >= 2 {
+            runs.push((start, lattice.len()));
+        }
+        runs
+    }
+
+    /// Returns indices of candidates on the best path. Missing transitions weigh zero.
+    #[cfg(feature = "tag-prediction")]
+    fn viterbi(
+        &self,
+        lattice: &[(&[u32], Vec<Option<i64>>)],
+        transitions: &TagTransitionMap,
+    ) -> Vec<usize> {
+        let mut backpointers: Vec<Vec<usize>> = vec![];
+        let mut prev_scores = lattice[0].1.clone();
+        for w in lattice.windows(2) {
+            let (prev_cands, _) = &w[0];
+            let (cands, emissions) = &w[1];
+            let mut scores = vec![None; cands.len()];
+            let mut pointers = vec![0; cands.len()];
+            for (j, emission) in emissions.iter().enumerate() {
+                let Some(emission) = emission else {
+                    continue;
+                };
+                for (k, prev_score) in prev_scores.iter().enumerate() {
+                    let Some(prev_score) = prev_score else {
+                        continue;
+                    };
+                    let names = &self.data.tag_names;
+                    let weight = transitions
+                        .get(&names[prev_cands[k] as usize])
+                        .and_then(|next| next.get(&names[cands[j] as usize]))
+                        .copied()
+                        .unwrap_or(0);
+                    let score = prev_score + emission + i64::from(weight);
+                    if scores[j].map_or(true, |s| score > s) {
+                        scores[j] = Some(score);
+                        pointers[j] = k;
+                    }
+                }
+            }
+            backpointers.push(pointers);
+            prev_scores = scores;
+        }
+        let mut best = 0;
+        for (j, score) in prev_scores.iter().enumerate() {
+            if score.is_some() && (prev_scores[best].is_none() || score > &prev_scores[best]) {
+                best = j;
+            }
+        }
+        let mut path = vec![best];
+        for pointers in backpointers.iter().rev() {
+            best = pointers[best];
+            path.push(best);
+        }
+        path.reverse();
+        path
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    fn predict_tags_inner<'a>(&'a self, sentence: &mut Sentence<'_, 'a>, store_scores: bool) {
+        let tag_predictor = self
+            .data
+            .tag_predictor
+            .as_ref()
+            .expect("this predictor is created with predict_tags = false");
+
+        if self.data.n_tags == 0 {
+            return;
+        }
+        let mut scores = vec![];
+        let mut range_start = Some(0);
+        sentence.n_tags = self.data.n_tags;
+        sentence.tags.clear();
+        sentence
+            .tags
+            .resize(sentence.len() * self.data.n_tags, None);
+        sentence.tag_scores.clear();
+        if store_scores {
+            sentence.tag_scores.resize(sentence.len(), None);
+        }
+        for (i, &b) in sentence.boundaries.iter().enumerate() {
+            if b == CharacterBoundary::Unknown {
+                range_start.take();
+            } else if b == CharacterBoundary::WordBoundary {
+                if let Some(&range_start) = range_start.as_ref() {
+                    let token = sentence.text_substring(range_start, i + 1);
+                    if let Some((token_id, tag_predictor)) = tag_predictor.get(token) {
+                        scores.clear();
+                        scores.resize(tag_predictor.bias().len(), 0);
+                        tag_predictor.bias().add_scores(&mut scores);
+                        if let Some(scorer) = self.data.char_scorer.as_ref() {
+                            debug_assert!(i < sentence.char_pma_states.len());
+                            // token_id is always smaller than tag_weight.len() because
+                            // tag_predictor is created to contain such values in the new()
+                            // function.
+                            unsafe {
+                                scorer.add_tag_scores(*token_id, i, sentence, &mut scores);
+                            }
+                        }
+                        if let Some(scorer) = self.data.type_scorer.as_ref() {
+                            debug_assert!(i < sentence.type_pma_states.len());
+                            // token_id is always smaller than tag_weight.len() because
+                            // tag_predictor is created to contain such values in the new()
+                            // function.
+                            unsafe {
+                                scorer.add_tag_scores(*token_id, i, sentence, &mut scores);
+                            }
+                        }
+                        tag_predictor.predict(
+                            &self.data.tag_names,
+                            &scores,
+                            self.lexicon_mask(*token_id),
+                            &mut sentence.tags[i * self.data.n_tags..(i + 1) * self.data.n_tags],
+                        );
+                        if !sentence.tag_scores.is_empty() {
+                            sentence.tag_scores[i].replace((&tag_predictor.tags, scores));
+                            scores = vec![];
+                        }
+                    } else if let Some(fixed_tags) = self.lexicon_fixed_tags(token) {
+                        Self::assign_fixed_tags(
+                            fixed_tags,
+                            &mut sentence.tags[i * self.data.n_tags..(i + 1) * self.data.n_tags],
+                        );
+                    }
+                }
+                range_start.replace(i + 1);
+            }
+        }
+        if let Some(&range_start) = range_start.as_ref() {
+            let token = sentence.text_substring(range_start, sentence.len());
+            if let Some((token_id, tag_predictor)) = tag_predictor.get(token) {
+                scores.clear();
+                scores.resize(tag_predictor.bias().len(), 0);
+                tag_predictor.bias().add_scores(&mut scores);
+                if let Some(scorer) = self.data.char_scorer.as_ref() {
+                    debug_assert!(sentence.len() <= sentence.char_pma_states.len());
+                    // token_id is always smaller than tag_weight.len() because tag_predictor is
+                    // created to contain such values in the new() function.
+                    unsafe {
+                        scorer.add_tag_scores(*token_id, sentence.len() - 1, sentence, &mut scores);
                     }
                 }
                 if let Some(scorer) = self.data.type_scorer.as_ref() {
@@ -626,10 +2270,46 @@ impl Predictor {
                     }
                 }
                 let i = sentence.len() - 1;
-                tag_predictor.predict(&scores, &mut sentence.tags[i * self.data.n_tags..]);
+                tag_predictor.predict(
+                    &self.data.tag_names,
+                    &scores,
+                    self.lexicon_mask(*token_id),
+                    &mut sentence.tags[i * self.data.n_tags..],
+                );
                 if !sentence.tag_scores.is_empty() {
                     sentence.tag_scores[i].replace((&tag_predictor.tags, scores));
                 }
+            } else if let Some(fixed_tags) = self.lexicon_fixed_tags(token) {
+                let i = sentence.len() - 1;
+                Self::assign_fixed_tags(fixed_tags, &mut sentence.tags[i * self.data.n_tags..]);
+            }
+        }
+        Self::fill_unknown_span_tags(sentence);
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[inline]
+    fn lexicon_mask(&self, token_id: u32) -> Option<&[bool]> {
+        self.tag_lexicon
+            .as_ref()
+            .and_then(|lexicon| lexicon.masks.get(&token_id))
+            .map(Vec::as_slice)
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[inline]
+    fn lexicon_fixed_tags(&self, token: &str) -> Option<&[Option<String>]> {
+        self.tag_lexicon
+            .as_ref()
+            .and_then(|lexicon| lexicon.fixed_tags.get(token))
+            .map(Vec::as_slice)
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    fn assign_fixed_tags<'a>(fixed_tags: &'a [Option<String>], tags: &mut [Option<Cow<'a, str>>]) {
+        for (tag, fixed_tag) in tags.iter_mut().zip(fixed_tags) {
+            if let Some(fixed_tag) = fixed_tag {
+                tag.replace(Cow::Borrowed(fixed_tag));
             }
         }
     }
@@ -654,7 +2334,26 @@ impl Predictor {
         Ok((
             Self {
                 data: predictor_data,
+
+                #[cfg(feature = "tag-prediction")]
                 tag_scores: false,
+
+                unknown_span_len: None,
+                threshold: 0,
+                margin_band: None,
+                user_dict: vec![],
+                user_dict_index: HashMap::new(),
+                user_dict_scorer: None,
+
+                #[cfg(feature = "std")]
+                disk_dicts: vec![],
+
+                #[cfg(feature = "tag-prediction")]
+                tag_lexicon: None,
+                #[cfg(feature = "tag-prediction")]
+                tag_model_report: TagModelReport::default(),
+
+                attribution: None,
             },
             &data[size..],
         ))
@@ -665,7 +2364,8 @@ impl Predictor {
 mod tests {
     use super::*;
 
-    use crate::dict_model::{DictModel, WordWeightRecord};
+    use alloc::string::String;
+
     use crate::model::TagModel;
     use crate::ngram_model::{NgramData, NgramModel, TagNgramData, TagNgramModel, TagWeight};
     use crate::CharacterBoundary::*;
@@ -835,24 +2535,278 @@ mod tests {
     }
 
     #[test]
-    fn test_predict_boundaries() {
+    fn test_predict_boundaries() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores(),);
+        assert_eq!(
+            &[
+                NotWordBoundary,
+                WordBoundary,
+                WordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                WordBoundary
+            ],
+            sentence.boundaries(),
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_predict_timed() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        let mut timings = StageTimings::default();
+        predictor.predict_timed(&mut sentence, &mut timings);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+        assert_eq!(core::time::Duration::ZERO, timings.tag_fill);
+        assert_eq!(core::time::Duration::ZERO, timings.filters);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_new_parallel() {
+        let predictor = Predictor::new_parallel(create_test_model(), false, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+
+        let predictor = Predictor::new_parallel(create_test_model(), false, true).unwrap();
+        predictor.predict_with_dict_scale(&mut sentence, 0.0);
+        assert_eq!(&[-22, 35, 38, 22, -32, 26, 28], sentence.boundary_scores());
+    }
+
+    #[test]
+    fn test_predict_reuses_buffers() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        let boundaries_ptr = sentence.boundaries.as_ptr();
+        let scores_ptr = sentence.boundary_scores.as_ptr();
+
+        sentence.update_raw("地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        let mut fresh = Sentence::from_raw("地球人だ").unwrap();
+        predictor.predict(&mut fresh);
+        assert_eq!(fresh.boundary_scores(), sentence.boundary_scores());
+        assert_eq!(boundaries_ptr, sentence.boundaries.as_ptr());
+        assert_eq!(scores_ptr, sentence.boundary_scores.as_ptr());
+    }
+
+    #[test]
+    fn test_predict_combining_and_control_chars() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        for text in [
+            "カ\u{3099}ス",
+            "\u{3099}人",
+            "人\u{200D}\u{200C}",
+            "\u{202E}地球\u{202C}人\u{200F}",
+            "\u{200D}",
+        ] {
+            let mut sentence = Sentence::from_raw(text).unwrap();
+            predictor.predict(&mut sentence);
+            let n_chars = text.chars().count();
+            assert_eq!(n_chars - 1, sentence.boundaries().len());
+            assert_eq!(n_chars - 1, sentence.boundary_scores().len());
+            let mut buf = String::new();
+            sentence.write_tokenized_text(&mut buf);
+            assert_eq!(text, buf.replace(' ', ""));
+        }
+    }
+
+    #[test]
+    fn test_predict_with_dict_scale() {
+        let model = create_test_model();
+        let predictor = Predictor::with_scalable_dictionary(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+        predictor.predict_with_dict_scale(&mut sentence, 1.0);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+        predictor.predict_with_dict_scale(&mut sentence, 0.0);
+        assert_eq!(&[-22, 35, 38, 22, -32, 26, 28], sentence.boundary_scores());
+        predictor.predict_with_dict_scale(&mut sentence, 2.0);
+        assert_eq!(&[-22, 73, 78, 64, -76, 110, 68], sentence.boundary_scores());
+        predictor.predict_with_dict_scale(&mut sentence, 0.5);
+        assert_eq!(&[-22, 45, 48, 33, -43, 47, 38], sentence.boundary_scores());
+    }
+
+    #[test]
+    fn test_score_components() {
+        let model = create_test_model();
+        let predictor = Predictor::with_scalable_dictionary(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        let components = predictor.score_components(&mut sentence);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+        assert_eq!(WordBoundary, sentence.boundaries()[1]);
+        for (i, &score) in sentence.boundary_scores().iter().enumerate() {
+            assert_eq!(
+                score,
+                components.bias
+                    + components.char_ngram[i]
+                    + components.type_ngram[i]
+                    + components.dict[i],
+            );
+        }
+        // Same as the differences of the scores from predict_with_dict_scale() with a scale of 0.
+        assert_eq!(&[0, 19, 20, 21, -22, 42, 20], &components.dict[..]);
+
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let merged = predictor.score_components(&mut sentence);
+        assert_eq!(vec![0; 7], merged.dict);
+        assert_eq!(components.type_ngram, merged.type_ngram);
+    }
+
+    #[test]
+    fn test_predict_with_attribution() {
         let model = create_test_model();
-        let predictor = Predictor::new(model, false).unwrap();
+        let mut predictor = Predictor::with_attribution(model, false).unwrap();
+        predictor
+            .add_dict_word(WordWeightRecord::new("地球".into(), vec![1, 2, 3], "".into()).unwrap())
+            .unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        let attributions = predictor.predict_with_attribution(&mut sentence).unwrap();
+        assert_eq!(7, attributions.len());
+        for (i, features) in attributions.iter().enumerate() {
+            let sum: i32 = features.iter().map(|a| a.weight).sum();
+            assert_eq!(sentence.boundary_scores()[i], predictor.bias() + sum);
+            assert!(features
+                .windows(2)
+                .all(|w| w[0].weight.unsigned_abs() >= w[1].weight.unsigned_abs()));
+        }
+        // The weight between 地 and 球 of the user dictionary.
+        assert!(attributions[4].contains(&Attribution {
+            kind: crate::FeatureKind::Dictionary,
+            range: 4..6,
+            weight: 2,
+        }));
+
+        let predictor = Predictor::new(create_test_model(), false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        assert!(predictor.predict_with_attribution(&mut sentence).is_err());
+    }
+
+    #[test]
+    fn test_predict_threshold() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        predictor.set_threshold(50);
         let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
         predictor.predict(&mut sentence);
-        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores(),);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
         assert_eq!(
             &[
                 NotWordBoundary,
                 WordBoundary,
                 WordBoundary,
+                NotWordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+            ],
+            sentence.boundaries(),
+        );
+
+        predictor.set_margin_band(Some(5));
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(
+            &[
+                NotWordBoundary,
+                Unknown,
                 WordBoundary,
                 NotWordBoundary,
+                NotWordBoundary,
                 WordBoundary,
-                WordBoundary
+                Unknown,
             ],
             sentence.boundaries(),
         );
+
+        // The costs of n-best hypotheses are differences from the threshold.
+        let hyps = predictor.predict_nbest(&mut sentence, 2);
+        assert_eq!(2, hyps[1].1);
+    }
+
+    #[test]
+    fn test_predict_with_context() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("地球人だ").unwrap();
+        predictor.predict_with_context(&mut sentence, "この人は");
+        assert_eq!(&[-54, 68, 48], sentence.boundary_scores());
+        assert_eq!(
+            &[NotWordBoundary, WordBoundary, WordBoundary],
+            sentence.boundaries(),
+        );
+
+        predictor.predict_with_context(&mut sentence, "");
+        assert_eq!(&[-36, 68, 48], sentence.boundary_scores());
+    }
+
+    #[test]
+    fn test_predict_with_dict_scale_merged() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict_with_dict_scale(&mut sentence, 0.0);
+        assert_eq!(&[-22, 54, 58, 43, -54, 68, 48], sentence.boundary_scores());
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tag() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, true).unwrap();
+        assert_eq!(
+            vec![Some(Cow::Borrowed("名詞")), Some(Cow::Borrowed("ヒト"))],
+            predictor.predict_tag("人", "この", "は地球人だ").unwrap(),
+        );
+        assert_eq!(
+            vec![Some(Cow::Borrowed("接尾辞")), Some(Cow::Borrowed("ジン"))],
+            predictor.predict_tag("人", "この人は地球", "だ").unwrap(),
+        );
+        assert_eq!(
+            vec![Some(Cow::Borrowed("名詞")), Some(Cow::Borrowed("チキュー"))],
+            predictor.predict_tag("地球", "", "").unwrap(),
+        );
+        assert!(predictor.predict_tag("", "この", "だ").is_err());
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_tag_names() {
+        let predictor = Predictor::new(create_test_model(), true).unwrap();
+        // 名詞 is shared by both tag models.
+        assert_eq!(
+            &["ジン", "チキュー", "ヒト", "マンホーム", "名詞", "接尾辞"],
+            predictor.tag_names(),
+        );
+        assert_eq!(Some(4), predictor.tag_id("名詞"));
+        assert_eq!(None, predictor.tag_id("動詞"));
+
+        let mut other = Predictor::new(create_test_model(), true).unwrap();
+        assert!(!Arc::ptr_eq(
+            predictor.tag_name_table(),
+            other.tag_name_table()
+        ));
+        assert!(other.share_tag_names(predictor.tag_name_table()));
+        assert!(Arc::ptr_eq(
+            predictor.tag_name_table(),
+            other.tag_name_table()
+        ));
+
+        let mut predictor = Predictor::new(create_test_model(), false).unwrap();
+        assert!(predictor.tag_names().is_empty());
+        assert!(!predictor.share_tag_names(other.tag_name_table()));
     }
 
     #[cfg(feature = "tag-prediction")]
@@ -899,6 +2853,460 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_with_lexicon() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, true).unwrap();
+        let mut lexicon = HashMap::new();
+        lexicon.insert("人".into(), vec![vec!["名詞".into()], vec![]]);
+        lexicon.insert("地球".into(), vec![vec![], vec!["マンホーム".into()]]);
+        lexicon.insert("は".into(), vec![vec!["助詞".into()], vec!["ワ".into()]]);
+        lexicon.insert(
+            "だ".into(),
+            vec![vec!["助動詞".into()], vec!["ダ".into(), "デス".into()]],
+        );
+        predictor.set_tag_lexicon(lexicon);
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        assert_eq!(
+            &[
+                None,
+                None,
+                None,
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("ヒト")),
+                Some(Cow::Borrowed("助詞")),
+                Some(Cow::Borrowed("ワ")),
+                None,
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("マンホーム")),
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("ジン")),
+                Some(Cow::Borrowed("助動詞")),
+                None,
+            ],
+            sentence.tags()
+        );
+
+        predictor.set_tag_lexicon(HashMap::new());
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        assert_eq!(Some(Cow::Borrowed("接尾辞")), sentence.tags()[12]);
+        assert_eq!(None, sentence.tags()[14]);
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_joint() {
+        let mut model = create_test_model();
+        model.set_tag_transitions(0, vec![("名詞".into(), "接尾辞".into(), -1000)]);
+        model.set_tag_transitions(1, vec![("チキュー".into(), "ジン".into(), -1000)]);
+        let predictor = Predictor::new(model, true).unwrap();
+        let data = predictor.serialize_to_vec().unwrap();
+        let (predictor, _) = unsafe { Predictor::deserialize_from_slice_unchecked(&data).unwrap() };
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        assert_eq!(Some(Cow::Borrowed("接尾辞")), sentence.tags()[12]);
+        assert_eq!(Some(Cow::Borrowed("ジン")), sentence.tags()[13]);
+
+        sentence.fill_tags_joint();
+        assert_eq!(
+            &[
+                None,
+                None,
+                None,
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("ヒト")),
+                None,
+                None,
+                None,
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("チキュー")),
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("ヒト")),
+                None,
+                None,
+            ],
+            sentence.tags()
+        );
+        assert!(sentence.tag_scores.is_empty());
+    }
+
+    #[test]
+    fn test_wrap_points() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(vec![6, 3, 2, 7, 4], sentence.wrap_points());
+        assert_eq!(Some(3), sentence.best_wrap_point(0, 4));
+        assert_eq!(Some(6), sentence.best_wrap_point(3, 4));
+        assert_eq!(None, sentence.best_wrap_point(6, 4));
+    }
+
+    #[test]
+    fn test_predict_char_type_ranges() {
+        let mut model = create_test_model();
+        let mut ranges = CharTypeRanges::new();
+        ranges.add('球', '球', Hiragana as u8).unwrap();
+        ranges.add('だ', 'だ', 7).unwrap();
+        model.set_char_type_ranges(ranges);
+        let predictor = Predictor::new(model, false).unwrap();
+        let data = predictor.serialize_to_vec().unwrap();
+        let (predictor, _) = unsafe { Predictor::deserialize_from_slice_unchecked(&data).unwrap() };
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(
+            &[
+                Hiragana as u8,
+                Hiragana as u8,
+                Kanji as u8,
+                Hiragana as u8,
+                Kanji as u8,
+                Hiragana as u8,
+                Kanji as u8,
+                7,
+            ],
+            sentence.char_types(),
+        );
+        assert_eq!(&[-22, 54, 44, 58, -24, 70, 14], sentence.boundary_scores());
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut predictor = Predictor::new(create_test_model(), false).unwrap();
+        let usage = predictor.memory_usage();
+        assert!(usage.char_automaton > 0);
+        assert_eq!(0, usage.dict_automaton);
+        assert!(usage.weights > 0);
+        assert_eq!(0, usage.tag_models);
+        assert_eq!(
+            usage.char_automaton + usage.type_automaton + usage.weights + usage.others,
+            usage.total(),
+        );
+
+        predictor
+            .add_dict_word(WordWeightRecord::new("星人".into(), vec![1, 2, 3], "".into()).unwrap())
+            .unwrap();
+        let usage_dict = predictor.memory_usage();
+        assert!(usage_dict.dict_automaton > 0);
+        assert!(usage_dict.weights > usage.weights);
+        assert!(usage_dict.others > usage.others);
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_memory_usage_tags() {
+        let predictor = Predictor::new(create_test_model(), true).unwrap();
+        assert!(predictor.memory_usage().tag_models > 0);
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict(&mut sentence);
+        let orig_scores = sentence.boundary_scores().to_vec();
+
+        predictor
+            .add_dict_word(WordWeightRecord::new("星人".into(), vec![1, 2, 3], "".into()).unwrap())
+            .unwrap();
+        predictor
+            .add_dict_word(WordWeightRecord::new("星人".into(), vec![4, 5, 6], "".into()).unwrap())
+            .unwrap();
+        assert_eq!(1, predictor.user_dictionary().len());
+        assert!(predictor
+            .add_dict_word(WordWeightRecord::new("".into(), vec![1], "".into()).unwrap())
+            .is_err());
+
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(
+            &[orig_scores[0] + 4, orig_scores[1] + 5],
+            sentence.boundary_scores(),
+        );
+
+        assert!(predictor.remove_dict_word("火星").is_none());
+        assert_eq!(
+            vec![4, 5, 6],
+            predictor.remove_dict_word("星人").unwrap().weights
+        );
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(orig_scores, sentence.boundary_scores());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_disk_dict() {
+        let records = vec![
+            WordWeightRecord::new("火星人".into(), vec![1, 2, 3, 4], "".into()).unwrap(),
+            WordWeightRecord::new("星人".into(), vec![5, 6, 7], "".into()).unwrap(),
+            WordWeightRecord::new("は".into(), vec![8, 9], "".into()).unwrap(),
+            WordWeightRecord::new("この".into(), vec![10, 11, 12], "".into()).unwrap(),
+        ];
+        let mut predictor_mem = Predictor::new(create_test_model(), false).unwrap();
+        for record in records.clone() {
+            predictor_mem.add_dict_word(record).unwrap();
+        }
+        let mut data = vec![];
+        DiskDictionary::write(records, 2, &mut data).unwrap();
+        let mut predictor_disk = Predictor::new(create_test_model(), false).unwrap();
+        predictor_disk
+            .add_disk_dictionary(DiskDictionary::new(std::io::Cursor::new(data)).unwrap());
+
+        for text in ["この火星人は火星人", "火星人", "は", "星人この"] {
+            let mut s_mem = Sentence::from_raw(text).unwrap();
+            predictor_mem.predict(&mut s_mem);
+            let mut s_disk = Sentence::from_raw(text).unwrap();
+            predictor_disk.predict(&mut s_disk);
+            assert_eq!(s_mem.boundary_scores(), s_disk.boundary_scores());
+        }
+
+        predictor_mem.keep_unknown_spans(Some(2));
+        predictor_disk.keep_unknown_spans(Some(2));
+        let mut s_mem = Sentence::from_raw("火星人ابこの").unwrap();
+        predictor_mem.predict(&mut s_mem);
+        let mut s_disk = Sentence::from_raw("火星人ابこの").unwrap();
+        predictor_disk.predict(&mut s_disk);
+        assert_eq!(&[(3, 5)], s_disk.unknown_spans());
+        assert_eq!(s_mem.unknown_spans(), s_disk.unknown_spans());
+    }
+
+    #[test]
+    fn test_extend_dict_words() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict(&mut sentence);
+        let orig_scores = sentence.boundary_scores().to_vec();
+
+        predictor
+            .extend_dict_words([
+                WordWeightRecord::new("火星".into(), vec![1, 2, 3], "".into()).unwrap(),
+                WordWeightRecord::new("星人".into(), vec![4, 5, 6], "".into()).unwrap(),
+                WordWeightRecord::new("火星".into(), vec![7, 8, 9], "".into()).unwrap(),
+            ])
+            .unwrap();
+        assert_eq!(2, predictor.user_dictionary().len());
+
+        // A failed batch leaves the dictionary unchanged.
+        assert!(predictor
+            .extend_dict_words([
+                WordWeightRecord::new("星人".into(), vec![1, 1, 1], "".into()).unwrap(),
+                WordWeightRecord::new("人".into(), vec![1, 1], "".into()).unwrap(),
+                WordWeightRecord::new("".into(), vec![1], "".into()).unwrap(),
+            ])
+            .is_err());
+        assert_eq!(2, predictor.user_dictionary().len());
+        assert!(predictor.remove_dict_word("人").is_none());
+
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(
+            &[orig_scores[0] + 8 + 4, orig_scores[1] + 9 + 5],
+            sentence.boundary_scores(),
+        );
+
+        assert_eq!(
+            vec![7, 8, 9],
+            predictor.remove_dict_word("火星").unwrap().weights
+        );
+        assert_eq!(
+            vec![4, 5, 6],
+            predictor.remove_dict_word("星人").unwrap().weights
+        );
+        assert!(predictor.user_dictionary().is_empty());
+    }
+
+    #[test]
+    fn test_predict_nbest() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        let hyps = predictor.predict_nbest(&mut sentence, 10);
+
+        // 2 boundaries yield 4 hypotheses.
+        assert_eq!(4, hyps.len());
+        assert_eq!((sentence.boundaries().to_vec(), 0), hyps[0]);
+        let scores: Vec<u64> = sentence
+            .boundary_scores()
+            .iter()
+            .map(|s| u64::from(s.unsigned_abs()))
+            .collect();
+        let mut expected_costs = vec![0, scores[0], scores[1], scores[0] + scores[1]];
+        expected_costs.sort_unstable();
+        assert_eq!(
+            expected_costs,
+            hyps.iter().map(|(_, cost)| *cost).collect::<Vec<_>>(),
+        );
+        for (boundaries, cost) in &hyps {
+            let flipped_cost: u64 = boundaries
+                .iter()
+                .zip(sentence.boundaries())
+                .zip(&scores)
+                .filter(|((b1, b2), _)| b1 != b2)
+                .map(|(_, s)| s)
+                .sum();
+            assert_eq!(*cost, flipped_cost);
+        }
+    }
+
+    #[test]
+    fn test_predict_nbest_zero() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        assert!(predictor.predict_nbest(&mut sentence, 0).is_empty());
+        let mut sentence = Sentence::from_raw("人").unwrap();
+        assert_eq!(1, predictor.predict_nbest(&mut sentence, 3).len());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_predict_sampled() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut expected = Sentence::from_raw("火星人の火星猫").unwrap();
+        predictor.predict(&mut expected);
+
+        let mut sentence = Sentence::from_raw("火星人の火星猫").unwrap();
+        predictor.predict_sampled(&mut sentence, 0.0, 0);
+        assert_eq!(expected.boundaries(), sentence.boundaries());
+
+        // Very low temperature approaches thresholding.
+        predictor.predict_sampled(&mut sentence, 1e-9, 0);
+        assert_eq!(expected.boundaries(), sentence.boundaries());
+
+        // The same seed produces the same segmentation.
+        let mut samples = vec![];
+        for seed in 0..20 {
+            predictor.predict_sampled(&mut sentence, 1e9, seed);
+            let boundaries = sentence.boundaries().to_vec();
+            predictor.predict_sampled(&mut sentence, 1e9, seed);
+            assert_eq!(boundaries, sentence.boundaries());
+            samples.push(boundaries);
+        }
+        assert!(samples.iter().any(|b| b != expected.boundaries()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_predict_sampled_margin() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        predictor.set_margin_band(Some(u32::MAX));
+        let mut sentence = Sentence::from_raw("火星人").unwrap();
+        predictor.predict_sampled(&mut sentence, 1e9, 0);
+        assert_eq!(
+            &[CharacterBoundary::Unknown, CharacterBoundary::Unknown],
+            sentence.boundaries(),
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_predict_batch() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let texts = ["この人は地球人だ", "火星人です", "人", "この人はمرحباだ"];
+        let sentences = predictor.predict_batch(
+            texts
+                .iter()
+                .map(|&t| Sentence::from_raw(t).unwrap())
+                .collect(),
+        );
+        assert_eq!(texts.len(), sentences.len());
+        for (text, s_batch) in texts.iter().zip(&sentences) {
+            let mut s = Sentence::from_raw(*text).unwrap();
+            predictor.predict(&mut s);
+            assert_eq!(s.boundaries(), s_batch.boundaries());
+            assert_eq!(s.boundary_scores(), s_batch.boundary_scores());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_predict_document() {
+        let model = create_test_model();
+        let predictor = Predictor::new(model, false).unwrap();
+        let texts = ["この人は地球人だ", "火星人です", "人", "この人はمرحباだ"];
+        let document = texts.join("\n\n").repeat(100);
+        let sentences = predictor.predict_document(&document).unwrap();
+        assert_eq!(
+            document.lines().filter(|l| !l.is_empty()).count(),
+            sentences.len()
+        );
+        for (line, s_doc) in document.lines().filter(|l| !l.is_empty()).zip(&sentences) {
+            let mut s = Sentence::from_raw(line).unwrap();
+            predictor.predict(&mut s);
+            assert_eq!(s.boundaries(), s_doc.boundaries());
+            assert_eq!(s.boundary_scores(), s_doc.boundary_scores());
+        }
+
+        assert!(predictor.predict_document("人\n\0").is_err());
+    }
+
+    #[test]
+    fn test_predict_unknown_spans() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        predictor.keep_unknown_spans(Some(3));
+        let mut sentence = Sentence::from_raw("この人はمرحباだ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(&[(4, 9)], sentence.unknown_spans());
+        assert_eq!(
+            &[
+                WordBoundary,
+                NotWordBoundary,
+                NotWordBoundary,
+                NotWordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+            ],
+            &sentence.boundaries()[3..],
+        );
+        assert_eq!(1, sentence.n_tags());
+        assert_eq!(Some(Cow::Borrowed("UNSEG")), sentence.tags()[8]);
+    }
+
+    #[test]
+    fn test_predict_unknown_spans_short() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, false).unwrap();
+        predictor.keep_unknown_spans(Some(6));
+        let mut sentence = Sentence::from_raw("この人はمرحباだ").unwrap();
+        predictor.predict(&mut sentence);
+        assert!(sentence.unknown_spans().is_empty());
+        assert_eq!(0, sentence.n_tags());
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_unknown_spans() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, true).unwrap();
+        predictor.keep_unknown_spans(Some(3));
+        let mut sentence = Sentence::from_raw("この人はمرحباだ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        assert_eq!(&[(4, 9)], sentence.unknown_spans());
+        assert_eq!(2, sentence.n_tags());
+        assert_eq!(
+            &[Some(Cow::Borrowed("UNSEG")), None],
+            &sentence.tags()[16..18],
+        );
+    }
+
     #[test]
     fn test_serialization() {
         let model = create_test_model();
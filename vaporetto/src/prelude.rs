@@ -0,0 +1,13 @@
+//! Commonly used items.
+//!
+//! ```
+//! use vaporetto::prelude::*;
+//!
+//! let s = Sentence::from_tokenized("火星 猫").unwrap();
+//! assert_eq!(CharacterBoundary::WordBoundary, s.boundaries()[1]);
+//! ```
+
+pub use crate::errors::VaporettoError;
+pub use crate::model::Model;
+pub use crate::predictor::Predictor;
+pub use crate::sentence::{CharacterBoundary, CharacterType, Sentence, Token};
@@ -1,9 +1,20 @@
+use core::fmt::{self, Write};
+use core::iter::FusedIterator;
+use core::ops::Range;
+use core::str::FromStr;
+
 use alloc::borrow::Cow;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use bincode::{Decode, Encode};
+use hashbrown::HashSet;
+
+use crate::char_class::TypeScheme;
 use crate::errors::{Result, VaporettoError};
+use crate::offset_map::OffsetMap;
 use crate::predictor::Predictor;
+use crate::utils::HeapBytes;
 
 /// Character type.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -67,8 +78,264 @@ impl CharacterType {
     }
 }
 
+/// Pushes a string into a CoNLL-U column, percent-encoding tabs and line breaks that separate
+/// columns and lines.
+///
+/// If `features` is `true`, `%`, `|`, and `=` that separate features or their names and values
+/// are also encoded.
+fn push_conllu_escaped(buf: &mut String, s: &str, features: bool) {
+    for c in s.chars() {
+        match c {
+            '%' if features => buf.push_str("%25"),
+            '|' if features => buf.push_str("%7C"),
+            '=' if features => buf.push_str("%3D"),
+            '\t' => buf.push_str("%09"),
+            '\n' => buf.push_str("%0A"),
+            '\r' => buf.push_str("%0D"),
+            _ => buf.push(c),
+        }
+    }
+}
+
+/// Returns `true` if the given character is combined with the preceding character.
+fn is_combining_char(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x0300..=0x036F         // Combining Diacritical Marks
+            | 0x1AB0..=0x1AFF   // Combining Diacritical Marks Extended
+            | 0x1DC0..=0x1DFF   // Combining Diacritical Marks Supplement
+            | 0x200C..=0x200D   // ZWNJ and ZWJ
+            | 0x20D0..=0x20FF   // Combining Diacritical Marks for Symbols
+            | 0x3099..=0x309A   // Combining Katakana-Hiragana (Semi-)Voiced Sound Marks
+            | 0xFE00..=0xFE0F   // Variation Selectors
+            | 0xFE20..=0xFE2F   // Combining Half Marks
+            | 0xE0100..=0xE01EF // Variation Selectors Supplement
+    )
+}
+
+/// User-defined character types assigned to ranges of characters.
+///
+/// The ranges override types returned by [`CharacterType::get_type()`]. Type IDs from 1 to 6
+/// correspond to [`CharacterType`], and other non-zero IDs can be used for new classes.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{CharTypeRanges, CharacterType, Sentence};
+///
+/// let mut ranges = CharTypeRanges::new();
+/// // Box-drawing characters
+/// ranges.add('\u{2500}', '\u{257F}', 7).unwrap();
+///
+/// let mut s = Sentence::from_raw("┌猫┐").unwrap();
+/// s.apply_char_type_ranges(&ranges);
+/// assert_eq!(&[7, CharacterType::Kanji as u8, 7], s.char_types());
+/// ```
+///
+/// Ranges can also be parsed from a definition text. Each line contains a type ID and a range of
+/// code points in hexadecimal with an optional `U+` prefix. The end of the range can be omitted
+/// for a single character. Built-in types can also be specified by `D`, `R`, `H`, `T`, `K`, and
+/// `O`, and texts following `#` are ignored. [`ToString::to_string()`](alloc::string::ToString)
+/// writes ranges in the same format.
+///
+/// ```
+/// use vaporetto::{CharTypeRanges, CharacterType};
+///
+/// let ranges: CharTypeRanges = "
+///     7 U+1F300 U+1FAFF  # Emoji
+///     8 AC00 D7A3        # Hangul syllables
+///     K 3005
+/// "
+/// .parse()
+/// .unwrap();
+/// assert_eq!(Some(7), ranges.get_type('🐈'));
+/// assert_eq!(Some(8), ranges.get_type('고'));
+/// assert_eq!(Some(CharacterType::Kanji as u8), ranges.get_type('々'));
+/// assert_eq!(
+///     "5 3005 3005\n8 AC00 D7A3\n7 1F300 1FAFF\n",
+///     ranges.to_string(),
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Decode, Encode)]
+pub struct CharTypeRanges {
+    // Sorted list of non-overlapping (start, end, type_id), where end is inclusive.
+    ranges: Vec<(u32, u32, u8)>,
+}
+
+impl HeapBytes for CharTypeRanges {
+    fn heap_bytes(&self) -> usize {
+        self.ranges.capacity() * core::mem::size_of::<(u32, u32, u8)>()
+    }
+}
+
+impl CharTypeRanges {
+    /// Creates a new empty set of ranges.
+    pub const fn new() -> Self {
+        Self { ranges: vec![] }
+    }
+
+    /// Creates ranges that assign type IDs to characters by their scripts and general categories.
+    ///
+    /// Characters not in the built-in table of
+    /// [`ScriptCategory::get_type()`](crate::ScriptCategory::get_type) keep types of
+    /// [`CharacterType`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{
+    ///     CharCategory, CharTypeRanges, Script, ScriptCategory, Sentence, TypeScheme,
+    /// };
+    ///
+    /// let ranges = CharTypeRanges::from_scheme(TypeScheme::Category);
+    ///
+    /// let mut s = Sentence::from_raw("x+1").unwrap();
+    /// s.apply_char_type_ranges(&ranges);
+    /// let id = |category| {
+    ///     TypeScheme::Category.type_id(ScriptCategory {
+    ///         script: Script::Common,
+    ///         category,
+    ///     })
+    /// };
+    /// assert_eq!(
+    ///     &[
+    ///         id(CharCategory::Letter),
+    ///         id(CharCategory::Symbol),
+    ///         id(CharCategory::Number),
+    ///     ],
+    ///     s.char_types(),
+    /// );
+    /// ```
+    pub fn from_scheme(scheme: TypeScheme) -> Self {
+        Self {
+            ranges: scheme.ranges().collect(),
+        }
+    }
+
+    /// Assigns a type ID to characters from `start` to `end` (inclusive).
+    ///
+    /// # Errors
+    ///
+    /// An error variant will be returned if `start` is larger than `end`, `type_id` is 0, or the
+    /// range overlaps with an existing range.
+    pub fn add(&mut self, start: char, end: char, type_id: u8) -> Result<()> {
+        if start > end {
+            return Err(VaporettoError::invalid_argument(
+                "start",
+                "must be smaller than or equal to `end`",
+            ));
+        }
+        if type_id == 0 {
+            return Err(VaporettoError::invalid_argument("type_id", "must not be 0"));
+        }
+        let (start, end) = (u32::from(start), u32::from(end));
+        let idx = self.ranges.partition_point(|&(s, _, _)| s < start);
+        if idx != 0 && self.ranges[idx - 1].1 >= start
+            || idx != self.ranges.len() && self.ranges[idx].0 <= end
+        {
+            return Err(VaporettoError::invalid_argument(
+                "start",
+                "the range overlaps with an existing range",
+            ));
+        }
+        self.ranges.insert(idx, (start, end, type_id));
+        Ok(())
+    }
+
+    /// Returns the type ID of the given character if it is contained in any range.
+    pub fn get_type(&self, c: char) -> Option<u8> {
+        let c = u32::from(c);
+        let idx = self.ranges.partition_point(|&(start, _, _)| start <= c);
+        if idx == 0 {
+            return None;
+        }
+        let (_, end, type_id) = self.ranges[idx - 1];
+        (c <= end).then_some(type_id)
+    }
+
+    /// Returns `true` if no range is defined.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns an iterator of defined ranges as tuples of the start, end (inclusive), and type ID.
+    pub fn iter(&self) -> impl Iterator<Item = (char, char, u8)> + '_ {
+        self.ranges.iter().map(|&(start, end, type_id)| {
+            // Ranges are created from valid characters in add().
+            (
+                char::from_u32(start).unwrap(),
+                char::from_u32(end).unwrap(),
+                type_id,
+            )
+        })
+    }
+
+    /// Returns `true` if any range uses a type ID other than built-in types.
+    #[cfg(feature = "cache-type-score")]
+    pub(crate) fn has_extra_types(&self) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(_, _, type_id)| type_id > CharacterType::Other as u8)
+    }
+}
+
+fn parse_code_point(s: &str) -> Result<char> {
+    let s = s.strip_prefix("U+").unwrap_or(s);
+    u32::from_str_radix(s, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| VaporettoError::invalid_argument("s", format!("invalid code point: {s}")))
+}
+
+impl FromStr for CharTypeRanges {
+    type Err = VaporettoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ranges = Self::new();
+        for line in s.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let cols: Vec<_> = line.split_ascii_whitespace().collect();
+            let (type_id, start, end) = match cols[..] {
+                [type_id, start] => (type_id, start, start),
+                [type_id, start, end] => (type_id, start, end),
+                _ => {
+                    return Err(VaporettoError::invalid_argument(
+                        "s",
+                        format!("invalid character type definition: {line}"),
+                    ))
+                }
+            };
+            let type_id = match type_id {
+                "D" => CharacterType::Digit as u8,
+                "R" => CharacterType::Roman as u8,
+                "H" => CharacterType::Hiragana as u8,
+                "T" => CharacterType::Katakana as u8,
+                "K" => CharacterType::Kanji as u8,
+                "O" => CharacterType::Other as u8,
+                _ => type_id.parse().map_err(|_| {
+                    VaporettoError::invalid_argument("s", format!("invalid type ID: {type_id}"))
+                })?,
+            };
+            ranges.add(parse_code_point(start)?, parse_code_point(end)?, type_id)?;
+        }
+        Ok(ranges)
+    }
+}
+
+impl fmt::Display for CharTypeRanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &(start, end, type_id) in &self.ranges {
+            writeln!(f, "{type_id} {start:04X} {end:04X}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Boundary type.
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 #[repr(u8)]
 pub enum CharacterBoundary {
     /// Inner of a word.
@@ -81,6 +348,153 @@ pub enum CharacterBoundary {
     Unknown = 2,
 }
 
+/// Characters that must not start a line.
+const NO_LINE_START_CHARS: &[char] = &[
+    '、', '。', '，', '．', ',', '.', '」', '』', '）', ')', '】', '〕', '！', '？', '!', '?',
+    'ー', '・', '：', '；', 'ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ', 'っ', 'ゃ', 'ゅ', 'ょ', 'ァ', 'ィ', 'ゥ',
+    'ェ', 'ォ', 'ッ', 'ャ', 'ュ', 'ョ', '々', 'ゝ', 'ゞ', 'ヽ', 'ヾ',
+];
+
+/// Characters that must not end a line.
+const NO_LINE_END_CHARS: &[char] = &['「', '『', '（', '(', '【', '〔'];
+
+/// Handling of invisible characters in [`Sentence::from_raw_with_invisible_chars()`].
+///
+/// Invisible characters are control characters (e.g., U+0007 BELL and U+000A LINE FEED), zero-width
+/// characters (U+200B ZERO WIDTH SPACE, U+200C ZERO WIDTH NON-JOINER, U+200D ZERO WIDTH JOINER,
+/// and U+2060 WORD JOINER), and U+FEFF BYTE ORDER MARK.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvisibleChars {
+    /// Keeps invisible characters as ordinary characters.
+    #[default]
+    Keep,
+
+    /// Removes invisible characters before prediction. Removed characters are attached to the
+    /// preceding character, or the following one at the beginning of the text.
+    Strip,
+
+    /// Returns an error if the text contains invisible characters.
+    Error,
+}
+
+impl InvisibleChars {
+    /// Returns `true` if the character is an invisible character.
+    pub fn is_invisible(c: char) -> bool {
+        c.is_control() || matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+    }
+}
+
+/// Format of tokenized texts used by [`Sentence::from_tokenized_with_format()`] and
+/// [`Sentence::write_tokenized_text_with_format()`].
+///
+/// A tokenized text follows the grammar below, where `SEP`, `TAG`, and `ESC` are the token
+/// separator, the tag separator, and the escape character, respectively:
+///
+/// ```text
+/// text  ::= token (SEP token)*
+/// token ::= char+ (TAG char*)*
+/// char  ::= ESC any | any - (SEP | TAG | ESC | NULL)
+/// ```
+///
+/// The `i`-th string following `TAG` in a token is the `i`-th tag of the token, and an empty one
+/// is a missing tag. Any character, including the separators, the escape character, and
+/// whitespaces, can appear in surfaces and tags if it is escaped. The default format uses `' '`,
+/// `'/'`, and `'\\'` as in [`Sentence::from_tokenized()`].
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{Sentence, TokenizedFormat};
+///
+/// // Dates and URLs can be written without escaping.
+/// let format = TokenizedFormat::with_separators(' ', '\t', '\\').unwrap();
+/// let s = Sentence::from_tokenized_with_format("1/15\t名詞\t\tイチガツ に\t助詞", &format)
+///     .unwrap();
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("1\\/15/名詞//イチガツ に/助詞", buf);
+///
+/// assert!(TokenizedFormat::with_separators('/', '/', '\\').is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenizedFormat {
+    token_separator: char,
+    tag_separator: char,
+    escape: char,
+}
+
+impl Default for TokenizedFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenizedFormat {
+    /// Creates the default format using `' '`, `'/'`, and `'\\'`.
+    pub const fn new() -> Self {
+        Self {
+            token_separator: ' ',
+            tag_separator: '/',
+            escape: '\\',
+        }
+    }
+
+    /// Creates a format with the given characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `token_separator` - A character inserted to each token boundary.
+    /// * `tag_separator` - A character preceding each tag.
+    /// * `escape` - A character escaping the following character.
+    ///
+    /// # Errors
+    ///
+    /// If the characters are not distinct or contain NULL, an error variant will be returned.
+    pub fn with_separators(
+        token_separator: char,
+        tag_separator: char,
+        escape: char,
+    ) -> Result<Self> {
+        if token_separator == tag_separator || token_separator == escape || tag_separator == escape
+        {
+            return Err(VaporettoError::invalid_argument(
+                "format",
+                "separators and the escape character must be distinct",
+            ));
+        }
+        if [token_separator, tag_separator, escape].contains(&'\0') {
+            return Err(VaporettoError::invalid_argument(
+                "format",
+                "must not contain NULL",
+            ));
+        }
+        Ok(Self {
+            token_separator,
+            tag_separator,
+            escape,
+        })
+    }
+
+    /// Returns the token separator.
+    pub const fn token_separator(&self) -> char {
+        self.token_separator
+    }
+
+    /// Returns the tag separator.
+    pub const fn tag_separator(&self) -> char {
+        self.tag_separator
+    }
+
+    /// Returns the escape character.
+    pub const fn escape(&self) -> char {
+        self.escape
+    }
+
+    const fn is_special(&self, c: char) -> bool {
+        c == self.token_separator || c == self.tag_separator || c == self.escape
+    }
+}
+
 /// Sentence data containing boundary and tag annotations.
 pub struct Sentence<'a, 'b> {
     pub(crate) text: Cow<'a, str>,
@@ -92,8 +506,9 @@ pub struct Sentence<'a, 'b> {
     pub(crate) type_pma_states: Vec<u32>,
     pub(crate) tags: Vec<Option<Cow<'b, str>>>,
     #[allow(clippy::type_complexity)]
-    pub(crate) tag_scores: Vec<Option<(&'b [Vec<String>], Vec<i32>)>>,
+    pub(crate) tag_scores: Vec<Option<(&'b [Vec<u32>], Vec<i32>)>>,
     pub(crate) n_tags: usize,
+    pub(crate) unknown_spans: Vec<(usize, usize)>,
     predictor: Option<&'b Predictor>,
     str_to_char_pos: Vec<usize>,
     char_to_str_pos: Vec<usize>,
@@ -124,6 +539,7 @@ impl Default for Sentence<'_, '_> {
             tags: vec![],
             tag_scores: vec![],
             n_tags: 0,
+            unknown_spans: vec![],
             predictor: None,
             str_to_char_pos: vec![],
             char_to_str_pos: vec![],
@@ -146,6 +562,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
         self.type_pma_states.clear();
         self.tags.clear();
         self.n_tags = 0;
+        self.unknown_spans.clear();
         self.predictor.take();
         self.str_to_char_pos.clear();
         self.str_to_char_pos.push(0);
@@ -237,6 +654,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
             tags: vec![],
             tag_scores: vec![],
             n_tags: 0,
+            unknown_spans: vec![],
             str_to_char_pos,
             char_to_str_pos,
         })
@@ -274,13 +692,91 @@ impl<'a, 'b> Sentence<'a, 'b> {
         self.score_padding = 0;
         self.char_pma_states.clear();
         self.type_pma_states.clear();
+        self.unknown_spans.clear();
         self.predictor.take();
         self.tags.clear();
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    /// Creates a new [`Sentence`] from a given text with the given handling of invisible
+    /// characters.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of a new [`Sentence`] and the mapping from its characters to those of `text`.
+    /// If invisible characters are stripped, pass the mapping to
+    /// [`Sentence::project_boundaries()`] after prediction to obtain tokens of `text` with the
+    /// stripped characters reattached.
+    ///
+    /// # Errors
+    ///
+    /// If the given `text` is empty or consists of stripped characters only, or `policy` is
+    /// [`InvisibleChars::Error`] and the text contains invisible characters, an error variant will
+    /// be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{CharacterBoundary, InvisibleChars, Sentence};
+    ///
+    /// let text = "\u{feff}火星\u{200b}猫";
+    /// let (mut s, map) = Sentence::from_raw_with_invisible_chars(text, InvisibleChars::Strip)
+    ///     .unwrap();
+    /// assert_eq!("火星猫", s.as_raw_text());
+    ///
+    /// s.boundaries_mut().copy_from_slice(&[
+    ///     CharacterBoundary::NotWordBoundary,
+    ///     CharacterBoundary::WordBoundary,
+    /// ]);
+    /// let s_orig = s.project_boundaries(text, &map).unwrap();
+    /// let mut buf = String::new();
+    /// s_orig.write_tokenized_text(&mut buf);
+    /// assert_eq!("\u{feff}火星\u{200b} 猫", buf);
+    ///
+    /// assert!(Sentence::from_raw_with_invisible_chars(text, InvisibleChars::Error).is_err());
+    /// ```
+    pub fn from_raw_with_invisible_chars(
+        text: impl Into<Cow<'a, str>>,
+        policy: InvisibleChars,
+    ) -> Result<(Self, OffsetMap)> {
+        let text = text.into();
+        let len = text.chars().count();
+        let has_invisible = text.chars().any(InvisibleChars::is_invisible);
+        match policy {
+            InvisibleChars::Strip if has_invisible => {
+                let mut stripped = String::new();
+                let mut ranges: Vec<Range<usize>> = vec![];
+                for (i, c) in text.chars().enumerate() {
+                    if !InvisibleChars::is_invisible(c) {
+                        stripped.push(c);
+                        ranges.push(i..i + 1);
+                    } else if let Some(range) = ranges.last_mut() {
+                        range.end = i + 1;
+                    }
+                }
+                if let Some(range) = ranges.first_mut() {
+                    range.start = 0;
+                }
+                let s = Self::from_raw(stripped)?;
+                let map = OffsetMap::from_ranges(ranges, len)?;
+                return Ok((s, map));
+            }
+            InvisibleChars::Error if has_invisible => {
+                return Err(VaporettoError::invalid_argument(
+                    "text",
+                    "must not contain invisible characters",
+                ));
+            }
+            _ => (),
+        }
+        Ok((Self::from_raw(text)?, OffsetMap::identity(len)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn parse_tokenized(
         tokenized_text: &str,
+        format: &TokenizedFormat,
         text: &mut String,
         char_types: &mut Vec<u8>,
         boundaries: &mut Vec<CharacterBoundary>,
@@ -308,11 +804,11 @@ impl<'a, 'b> Sentence<'a, 'b> {
         for c in tokenized_text.chars() {
             match (escape, c) {
                 // escape a following character
-                (false, '\\') => {
+                (false, c) if c == format.escape => {
                     escape = true;
                 }
                 // token boundary
-                (false, ' ') => {
+                (false, c) if c == format.token_separator => {
                     if text.is_empty() {
                         return Err(VaporettoError::invalid_argument(
                             "tokenized_text",
@@ -331,11 +827,11 @@ impl<'a, 'b> Sentence<'a, 'b> {
                     prev_boundary = true;
                 }
                 // tag
-                (false, '/') => {
+                (false, c) if c == format.tag_separator => {
                     if text.is_empty() || prev_boundary {
                         return Err(VaporettoError::invalid_argument(
                             "tokenized_text",
-                            "a slash must follow a character",
+                            "a tag separator must follow a character",
                         ));
                     }
                     if let Some(tag) = tag_str.replace(String::new()) {
@@ -409,6 +905,8 @@ impl<'a, 'b> Sentence<'a, 'b> {
     ///   - If necessary, multiple tags following each slash (`'/'`) can be added to each token.
     ///   - Each character following a back slash (`'\\'`) is escaped.
     ///
+    /// See [`TokenizedFormat`] for the formal grammar and other separators.
+    ///
     /// # Errors
     ///
     /// This function will return an error variant when the given text is empty, starts/ends with a
@@ -429,6 +927,30 @@ impl<'a, 'b> Sentence<'a, 'b> {
     /// assert!(s.is_err());
     /// ```
     pub fn from_tokenized(tokenized_text: &str) -> Result<Self> {
+        Self::from_tokenized_with_format(tokenized_text, &TokenizedFormat::new())
+    }
+
+    /// Creates a new [`Sentence`] from a tokenized text in the given format.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error variant when the given text is empty, starts/ends with a
+    /// token separator, or contains consecutive token separators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{Sentence, TokenizedFormat};
+    ///
+    /// let format = TokenizedFormat::with_separators(' ', '_', '\\').unwrap();
+    /// let s = Sentence::from_tokenized_with_format("2024/01/15_名詞 に_助詞", &format).unwrap();
+    /// assert_eq!("2024/01/15に", s.as_raw_text());
+    /// assert_eq!(1, s.n_tags());
+    /// ```
+    pub fn from_tokenized_with_format(
+        tokenized_text: &str,
+        format: &TokenizedFormat,
+    ) -> Result<Self> {
         let mut text = String::new();
         let mut char_types = vec![];
         let mut boundaries = vec![];
@@ -437,6 +959,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
         let mut tags = vec![];
         Self::parse_tokenized(
             tokenized_text,
+            format,
             &mut text,
             &mut char_types,
             &mut boundaries,
@@ -457,6 +980,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
             tags,
             tag_scores: vec![],
             n_tags,
+            unknown_spans: vec![],
             str_to_char_pos,
             char_to_str_pos,
         })
@@ -469,6 +993,8 @@ impl<'a, 'b> Sentence<'a, 'b> {
     ///   - If necessary, multiple tags following each slash (`'/'`) can be added to each token.
     ///   - Each character following a back slash (`'\\'`) is escaped.
     ///
+    /// See [`TokenizedFormat`] for the formal grammar and other separators.
+    ///
     /// # Errors
     ///
     /// This function will return an error variant when the given text is empty, starts/ends with a
@@ -488,8 +1014,23 @@ impl<'a, 'b> Sentence<'a, 'b> {
     /// assert_eq!("まぁ良いだろう", s.as_raw_text());
     /// ```
     pub fn update_tokenized(&mut self, tokenized_text: &str) -> Result<()> {
+        self.update_tokenized_with_format(tokenized_text, &TokenizedFormat::new())
+    }
+
+    /// Updates the [`Sentence`] using a tokenized text in the given format.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error variant when the given text is empty, starts/ends with a
+    /// token separator, or contains consecutive token separators.
+    pub fn update_tokenized_with_format(
+        &mut self,
+        tokenized_text: &str,
+        format: &TokenizedFormat,
+    ) -> Result<()> {
         if let Err(e) = Self::parse_tokenized(
             tokenized_text,
+            format,
             self.text.to_mut(),
             &mut self.char_types,
             &mut self.boundaries,
@@ -504,6 +1045,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
         self.score_padding = 0;
         self.char_pma_states.clear();
         self.type_pma_states.clear();
+        self.unknown_spans.clear();
         self.predictor.take();
         self.n_tags = self.tags.len() / self.char_types.len();
         Ok(())
@@ -695,6 +1237,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
             tags,
             tag_scores: vec![],
             n_tags,
+            unknown_spans: vec![],
             str_to_char_pos,
             char_to_str_pos,
         })
@@ -758,6 +1301,7 @@ impl<'a, 'b> Sentence<'a, 'b> {
         self.score_padding = 0;
         self.char_pma_states.clear();
         self.type_pma_states.clear();
+        self.unknown_spans.clear();
         self.predictor.take();
         self.n_tags = self.tags.len() / self.char_types.len();
         Ok(())
@@ -880,6 +1424,48 @@ impl<'a, 'b> Sentence<'a, 'b> {
         }
     }
 
+    /// Writes a tokenized text in the given format. Tokens adjacent to
+    /// [`CharacterBoundary::Unknown`] will be skipped.
+    ///
+    /// Separators and escape characters in surfaces and tags are escaped, so the result can be
+    /// parsed by [`Sentence::from_tokenized_with_format()`] with the same format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{Sentence, TokenizedFormat};
+    ///
+    /// let format = TokenizedFormat::with_separators('\t', '|', '^').unwrap();
+    /// let s = Sentence::from_tokenized("https:\\/\\/example.com/名詞 へ/助詞").unwrap();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text_with_format(&mut buf, &format);
+    /// assert_eq!("https://example.com|名詞\tへ|助詞", buf);
+    /// ```
+    pub fn write_tokenized_text_with_format(&self, buf: &mut String, format: &TokenizedFormat) {
+        let push_escaped = |buf: &mut String, text: &str| {
+            for c in text.chars() {
+                if format.is_special(c) {
+                    buf.push(format.escape);
+                }
+                buf.push(c);
+            }
+        };
+        buf.clear();
+        for token in self.iter_tokens() {
+            if !buf.is_empty() {
+                buf.push(format.token_separator);
+            }
+            push_escaped(buf, token.surface());
+            let ts = token.tags();
+            for tag in &ts[..ts.iter().rposition(|x| x.is_some()).map_or(0, |x| x + 1)] {
+                buf.push(format.tag_separator);
+                if let Some(tag) = tag {
+                    push_escaped(buf, tag);
+                }
+            }
+        }
+    }
+
     /// Writes a text with partial annotations.
     ///
     /// # Examples
@@ -938,6 +1524,77 @@ impl<'a, 'b> Sentence<'a, 'b> {
         }
     }
 
+    /// Writes the sentence in the CoNLL-U format.
+    ///
+    /// Each token is written as a line, where FORM is the surface, XPOS is the first tag, and
+    /// UPOS is obtained by applying `upos_map` to XPOS. Unknown columns are filled with `_`.
+    /// MISC contains the character range of the token (`TokenRange=start:end`), the score of the
+    /// boundary after the token if the sentence has been predicted (`BoundaryScore=score`), and
+    /// the remaining tags (`Tag2=...`, `Tag3=...`, ...). Tabs and line breaks in the text, the
+    /// surfaces, and the tags are percent-encoded (e.g., a tab is written as `%09`) so that they
+    /// do not break the columns. In tags, `%`, `|`, and `=` are also percent-encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("火星/名詞/カセー に/助詞 行く").unwrap();
+    /// let mut buf = String::new();
+    /// s.write_conllu(&mut buf, |pos| match pos {
+    ///     "名詞" => Some("NOUN"),
+    ///     "助詞" => Some("ADP"),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(
+    ///     "# text = 火星に行く\n\
+    ///      1\t火星\t_\tNOUN\t名詞\t_\t_\t_\t_\tTokenRange=0:2|Tag2=カセー\n\
+    ///      2\tに\t_\tADP\t助詞\t_\t_\t_\t_\tTokenRange=2:3\n\
+    ///      3\t行く\t_\t_\t_\t_\t_\t_\t_\tTokenRange=3:5\n\n",
+    ///     buf,
+    /// );
+    /// ```
+    pub fn write_conllu<'c, F>(&self, buf: &mut String, upos_map: F)
+    where
+        F: Fn(&str) -> Option<&'c str>,
+    {
+        buf.clear();
+        buf.push_str("# text = ");
+        push_conllu_escaped(buf, &self.text, false);
+        buf.push('\n');
+        let scores = self.boundary_scores();
+        for (i, token) in self.iter_tokens().enumerate() {
+            let tags = token.tags();
+            let xpos = tags.first().and_then(|t| t.as_deref());
+            let upos = xpos.and_then(&upos_map);
+            // Writing to a String never fails.
+            write!(buf, "{}\t", i + 1).unwrap();
+            push_conllu_escaped(buf, token.surface(), false);
+            buf.push_str("\t_\t");
+            push_conllu_escaped(buf, upos.unwrap_or("_"), true);
+            buf.push('\t');
+            push_conllu_escaped(buf, xpos.unwrap_or("_"), true);
+            write!(
+                buf,
+                "\t_\t_\t_\t_\tTokenRange={}:{}",
+                token.start(),
+                token.end(),
+            )
+            .unwrap();
+            if let Some(score) = scores.get(token.end() - 1) {
+                write!(buf, "|BoundaryScore={score}").unwrap();
+            }
+            for (j, tag) in tags.iter().enumerate().skip(1) {
+                if let Some(tag) = tag {
+                    write!(buf, "|Tag{}=", j + 1).unwrap();
+                    push_conllu_escaped(buf, tag, true);
+                }
+            }
+            buf.push('\n');
+        }
+        buf.push('\n');
+    }
+
     /// Removes tag information and updates the number of tags.
     ///
     /// # Examples
@@ -989,6 +1646,21 @@ impl<'a, 'b> Sentence<'a, 'b> {
         &self.char_types
     }
 
+    /// Overrides character types with the given ranges.
+    ///
+    /// [`Predictor::predict()`] calls this function with the ranges stored in the model, so
+    /// training data should also be processed by the same ranges.
+    pub fn apply_char_type_ranges(&mut self, ranges: &CharTypeRanges) {
+        if ranges.is_empty() {
+            return;
+        }
+        for (c, char_type) in self.text.chars().zip(&mut self.char_types) {
+            if let Some(type_id) = ranges.get_type(c) {
+                *char_type = type_id;
+            }
+        }
+    }
+
     /// Returns a slice of boundary types.
     ///
     /// # Examples
@@ -1030,7 +1702,42 @@ impl<'a, 'b> Sentence<'a, 'b> {
         &mut self.boundaries
     }
 
+    /// Removes boundaries inside combining character sequences.
+    ///
+    /// Boundaries are removed before combining marks (e.g., U+3099 COMBINING KATAKANA-HIRAGANA
+    /// VOICED SOUND MARK), variation selectors, ZWJ, and ZWNJ, and after ZWJ. This function
+    /// should be called after prediction. Note that character windows of the model are still
+    /// counted in code points, so a combining mark itself is one character for feature
+    /// extraction. Bidirectional control characters are treated as ordinary characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{CharacterBoundary, Sentence};
+    ///
+    /// let mut s = Sentence::from_raw("カ\u{3099}ス").unwrap();
+    /// s.boundaries_mut().fill(CharacterBoundary::WordBoundary);
+    /// s.concat_combining_sequences();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("カ\u{3099} ス", buf);
+    /// ```
+    pub fn concat_combining_sequences(&mut self) {
+        let mut prev_is_zwj = false;
+        for (i, c) in self.text.chars().enumerate() {
+            if i != 0 && (prev_is_zwj || is_combining_char(c)) {
+                self.boundaries[i - 1] = CharacterBoundary::NotWordBoundary;
+            }
+            prev_is_zwj = c == '\u{200D}';
+        }
+    }
+
     /// Returns a slice of boundary scores.
+    ///
+    /// The `i`-th score corresponds to the boundary between the `i`-th and `(i+1)`-th characters.
+    /// The scores are set by [`Predictor::predict()`](crate::Predictor::predict) and are not
+    /// changed by modifying boundaries. If the sentence has not been predicted or the text has
+    /// been updated after prediction, an empty slice is returned.
     #[inline]
     pub fn boundary_scores(&self) -> &[i32] {
         if self.boundary_scores.is_empty() {
@@ -1040,6 +1747,107 @@ impl<'a, 'b> Sentence<'a, 'b> {
         }
     }
 
+    /// Returns probabilities of word boundaries calibrated from boundary scores.
+    ///
+    /// The `i`-th probability corresponds to the `i`-th score of [`Sentence::boundary_scores()`].
+    /// If the sentence has not been predicted or the model has no calibration (see
+    /// [`Model::set_calibration()`](crate::Model::set_calibration)), `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, PlattScaling, Predictor, Sentence};
+    ///
+    /// let mut model = Model::read(File::open("../resources/model.bin").unwrap()).unwrap();
+    /// model.set_calibration(Some(PlattScaling::new(-0.0001, 0.0)));
+    /// let predictor = Predictor::new(model, false).unwrap();
+    ///
+    /// let mut s = Sentence::from_raw("火星猫").unwrap();
+    /// assert!(s.boundary_probabilities().is_none());
+    /// predictor.predict(&mut s);
+    /// let probs = s.boundary_probabilities().unwrap();
+    /// assert_eq!(2, probs.len());
+    /// assert!(probs[1] > 0.5);
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn boundary_probabilities(&self) -> Option<Vec<f64>> {
+        let calibration = self.predictor?.calibration()?;
+        let scores = self.boundary_scores();
+        if scores.is_empty() {
+            return None;
+        }
+        Some(scores.iter().map(|&s| calibration.probability(s)).collect())
+    }
+
+    /// Returns positions where lines can be wrapped, ranked from the most preferred.
+    ///
+    /// Each position is the index of the character starting the next line. Only word boundaries
+    /// are suggested, and positions violating basic Japanese line breaking rules (kinsoku shori)
+    /// are excluded, e.g., before `、` or `ー` and after `「`. Positions are sorted in descending
+    /// order of [`Sentence::boundary_scores()`], so breaking at earlier positions seldom splits
+    /// words undetected by the predictor. Ties, including all positions of sentences that have
+    /// not been predicted, are ordered by position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("「 まぁ 、 社長 は 火星 猫 だ 」").unwrap();
+    /// assert_eq!(vec![4, 6, 7, 9, 10], s.wrap_points());
+    /// ```
+    pub fn wrap_points(&self) -> Vec<usize> {
+        let scores = self.boundary_scores();
+        let mut points: Vec<_> = self
+            .text
+            .chars()
+            .zip(self.text.chars().skip(1))
+            .zip(&self.boundaries)
+            .enumerate()
+            .filter(|&(_, ((prev, next), &b))| {
+                b == CharacterBoundary::WordBoundary
+                    && !NO_LINE_END_CHARS.contains(&prev)
+                    && !NO_LINE_START_CHARS.contains(&next)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        points.sort_by_key(|&i| core::cmp::Reverse(scores.get(i).copied().unwrap_or(0)));
+        points.iter_mut().for_each(|i| *i += 1);
+        points
+    }
+
+    /// Returns the most preferred wrap point of a line starting at `start` and containing at
+    /// most `max_width` characters, or `None` if the rest of the text fits in the line or no
+    /// position is allowed.
+    ///
+    /// Calling this function repeatedly with the returned position as the next `start` fills
+    /// lines with words. See [`Sentence::wrap_points()`] for allowed positions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("まぁ 社長 は 火星 猫 だ").unwrap();
+    /// assert_eq!(Some(5), s.best_wrap_point(0, 6));
+    /// assert_eq!(None, s.best_wrap_point(5, 6));
+    /// ```
+    pub fn best_wrap_point(&self, start: usize, max_width: usize) -> Option<usize> {
+        let end = start.saturating_add(max_width);
+        if end >= self.len() {
+            return None;
+        }
+        let scores = self.boundary_scores();
+        // Among positions with the best score, the last one fills the line the most.
+        self.wrap_points()
+            .into_iter()
+            .filter(|&i| start < i && i <= end)
+            .max_by_key(|&i| (scores.get(i - 1).copied().unwrap_or(0), i))
+    }
+
     /// Returns a reference to the internal representation of tags.
     ///
     /// In the representation, tags are stored in an array, and
@@ -1095,6 +1903,367 @@ impl<'a, 'b> Sentence<'a, 'b> {
         &mut self.tags
     }
 
+    /// Sets the boundary between the `i`-th and `(i+1)`-th characters.
+    ///
+    /// Unlike modifying [`Sentence::boundaries_mut()`] directly, tags of the token ending at the
+    /// `i`-th character are removed if the token is split or concatenated, so that tags are not
+    /// left at positions other than the ends of tokens. Boundary scores are kept as the output of
+    /// the predictor.
+    ///
+    /// # Errors
+    ///
+    /// If `i` is out of range, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{CharacterBoundary, Sentence};
+    ///
+    /// let mut s = Sentence::from_tokenized("火星/名詞 猫/名詞").unwrap();
+    /// s.set_boundary(1, CharacterBoundary::NotWordBoundary).unwrap();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("火星猫/名詞", buf);
+    ///
+    /// assert!(s.set_boundary(2, CharacterBoundary::WordBoundary).is_err());
+    /// ```
+    pub fn set_boundary(&mut self, i: usize, boundary: CharacterBoundary) -> Result<()> {
+        let b = self
+            .boundaries
+            .get_mut(i)
+            .ok_or_else(|| VaporettoError::invalid_argument("i", "out of range"))?;
+        if *b == boundary {
+            return Ok(());
+        }
+        *b = boundary;
+        if let Some(tags) = self.tags.get_mut(i * self.n_tags..(i + 1) * self.n_tags) {
+            tags.fill(None);
+        }
+        if let Some(scores) = self.tag_scores.get_mut(i) {
+            scores.take();
+        }
+        Ok(())
+    }
+
+    /// Sets a tag of the `index`-th token in the order of [`Sentence::iter_tokens()`].
+    ///
+    /// The tag is stored at the last character of the token. If `level` is larger than or equal
+    /// to [`Sentence::n_tags()`], the number of tags is extended while keeping existing tags.
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of range, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let mut s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+    /// s.set_token_tag(1, 0, "名詞").unwrap();
+    /// s.set_token_tag(1, 1, "ネコ").unwrap();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("火星/名詞 猫/名詞/ネコ", buf);
+    ///
+    /// assert!(s.set_token_tag(2, 0, "名詞").is_err());
+    /// ```
+    pub fn set_token_tag<T>(&mut self, index: usize, level: usize, tag: T) -> Result<()>
+    where
+        T: Into<Cow<'b, str>>,
+    {
+        let sentence: &Sentence<'_, 'b> = self;
+        let end = sentence
+            .iter_tokens()
+            .nth(index)
+            .map(|token| token.end())
+            .ok_or_else(|| VaporettoError::invalid_argument("index", "out of range"))?;
+        if level >= self.n_tags {
+            let n_tags = level + 1;
+            let mut tags = vec![None; n_tags * self.len()];
+            for (dst, src) in tags
+                .chunks_mut(n_tags)
+                .zip(self.tags.chunks(self.n_tags.max(1)))
+            {
+                dst[..src.len()].clone_from_slice(src);
+            }
+            self.tags = tags;
+            self.n_tags = n_tags;
+        }
+        self.tags[(end - 1) * self.n_tags + level].replace(tag.into());
+        Ok(())
+    }
+
+    /// Transfers boundaries and tags of this sentence onto the text before transformation.
+    ///
+    /// This is used to obtain tokens of the original text after predicting the text transformed
+    /// by string filters that change the number of characters. Boundaries inside a run of
+    /// characters produced from the same original characters are dropped, and tags of the tokens
+    /// concatenated by them are overwritten by those of the last tagged ones. Boundary scores are not transferred.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The original text.
+    /// * `map` - A mapping from character positions of this sentence to those of `text`.
+    ///
+    /// # Errors
+    ///
+    /// If the lengths of the sentence and `text` do not match the mapping, an error variant will
+    /// be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{OffsetMap, Sentence};
+    ///
+    /// // "ｶﾞｲﾄﾞ㍿" is transformed into "ガイド株式会社".
+    /// let s = Sentence::from_tokenized("ガイド/名詞 株式/名詞 会社/名詞").unwrap();
+    /// let map = OffsetMap::from_ranges(
+    ///     vec![0..2, 2..3, 3..5, 5..6, 5..6, 5..6, 5..6],
+    ///     6,
+    /// )
+    /// .unwrap();
+    /// let s_orig = s.project_boundaries("ｶﾞｲﾄﾞ\u{337f}", &map).unwrap();
+    /// let mut buf = String::new();
+    /// s_orig.write_tokenized_text(&mut buf);
+    /// assert_eq!("ｶﾞｲﾄﾞ/名詞 \u{337f}/名詞", buf);
+    /// ```
+    pub fn project_boundaries<'c>(
+        &self,
+        text: impl Into<Cow<'c, str>>,
+        map: &OffsetMap,
+    ) -> Result<Sentence<'c, 'b>> {
+        let mut s = Sentence::from_raw(text)?;
+        if (self.len(), s.len()) != (map.len(), map.original_len()) {
+            return Err(VaporettoError::invalid_argument("map", "lengths mismatch"));
+        }
+        s.boundaries.fill(CharacterBoundary::NotWordBoundary);
+        for (i, &b) in self.boundaries.iter().enumerate() {
+            if b == CharacterBoundary::NotWordBoundary {
+                continue;
+            }
+            if let Some(pos) = map.boundary_to_original(i + 1) {
+                s.boundaries[pos - 1] = b;
+            }
+        }
+        if self.n_tags != 0 {
+            s.reset_tags(self.n_tags);
+            for (i, tags) in self.tags.chunks(self.n_tags).enumerate() {
+                if tags.iter().any(Option::is_some) {
+                    let pos = map.to_original(i..i + 1).end - 1;
+                    s.tags[pos * self.n_tags..(pos + 1) * self.n_tags].clone_from_slice(tags);
+                }
+            }
+        }
+        Ok(s)
+    }
+
+    /// Shortens the sentence to the first `n` characters.
+    ///
+    /// Boundaries, boundary scores, tags, and tag scores of the remaining characters are kept,
+    /// so the result can be used without predicting it again. If the last token is cut in the
+    /// middle, the remaining part becomes a token without tags. If `n` is larger than or equal
+    /// to the number of characters, nothing is changed.
+    ///
+    /// # Errors
+    ///
+    /// If `n` is 0, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let mut s = Sentence::from_tokenized("まぁ/副詞 社長/名詞 は/助詞 火星/名詞").unwrap();
+    /// s.truncate_chars(5).unwrap();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ/副詞 社長/名詞 は/助詞", buf);
+    ///
+    /// s.truncate_chars(3).unwrap();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ/副詞 社", buf);
+    /// ```
+    pub fn truncate_chars(&mut self, n: usize) -> Result<()> {
+        if n == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "n",
+                "must be larger than 0",
+            ));
+        }
+        if n >= self.len() {
+            return Ok(());
+        }
+        let byte_len = self.char_to_str_pos[n];
+        match &mut self.text {
+            Cow::Borrowed(text) => *text = &text[..byte_len],
+            Cow::Owned(text) => text.truncate(byte_len),
+        }
+        self.str_to_char_pos.truncate(byte_len + 1);
+        self.char_to_str_pos.truncate(n + 1);
+        self.char_types.truncate(n);
+        self.boundaries.truncate(n - 1);
+        if !self.boundary_scores.is_empty() {
+            self.boundary_scores
+                .truncate(self.score_padding * 2 + n - 1);
+        }
+        self.char_pma_states.truncate(n);
+        self.type_pma_states.truncate(n);
+        self.tags.truncate(n * self.n_tags);
+        self.tag_scores.truncate(n);
+        self.unknown_spans.retain(|&(start, _)| start < n);
+        if let Some((_, end)) = self.unknown_spans.last_mut() {
+            *end = (*end).min(n);
+        }
+        Ok(())
+    }
+
+    /// Shortens the sentence to the first `n` tokens delimited by word boundaries.
+    ///
+    /// Boundaries, boundary scores, tags, and tag scores of the remaining characters are kept as
+    /// in [`Sentence::truncate_chars()`]. If the sentence has `n` or fewer tokens, nothing is
+    /// changed.
+    ///
+    /// # Errors
+    ///
+    /// If `n` is 0, an error variant will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let mut s = Sentence::from_tokenized("まぁ/副詞 社長/名詞 は/助詞 火星/名詞").unwrap();
+    /// s.take_tokens(2).unwrap();
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ/副詞 社長/名詞", buf);
+    /// ```
+    pub fn take_tokens(&mut self, n: usize) -> Result<()> {
+        if n == 0 {
+            return Err(VaporettoError::invalid_argument(
+                "n",
+                "must be larger than 0",
+            ));
+        }
+        let end = self
+            .boundaries
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == CharacterBoundary::WordBoundary)
+            .nth(n - 1)
+            .map_or(self.len(), |(i, _)| i + 1);
+        self.truncate_chars(end)
+    }
+
+    /// Appends another sentence to the end of this sentence.
+    ///
+    /// A word boundary is inserted between the two texts, and boundaries, boundary scores, tags,
+    /// tag scores, and unknown spans of `other` are moved to the corresponding positions, so
+    /// chunks of a document predicted separately can be reassembled without predicting them
+    /// again. The inserted boundary has the score `i32::MAX`. If only one of the sentences has
+    /// boundary scores, the scores are cleared. If the numbers of tags differ, tags are filled
+    /// with `None` up to the larger number.
+    ///
+    /// Tags can be filled again by [`Sentence::fill_tags()`] only if both sentences are predicted
+    /// by the same predictor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let mut s = Sentence::from_tokenized("まぁ/副詞 社長/名詞").unwrap();
+    /// let t = Sentence::from_tokenized("は/助詞 火星 猫/名詞/ネコ").unwrap();
+    /// s.append(&t);
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("まぁ/副詞 社長/名詞 は/助詞 火星 猫/名詞/ネコ", buf);
+    /// ```
+    pub fn append(&mut self, other: &Sentence<'_, 'b>) {
+        let len = self.len();
+        let byte_len = self.text.len();
+
+        self.text.to_mut().push_str(&other.text);
+        self.char_types.extend_from_slice(&other.char_types);
+        self.boundaries.push(CharacterBoundary::WordBoundary);
+        self.boundaries.extend_from_slice(&other.boundaries);
+        self.str_to_char_pos.resize(self.text.len() + 1, 0);
+        for (i, &pos) in other.char_to_str_pos.iter().enumerate() {
+            self.str_to_char_pos[byte_len + pos] = len + i;
+        }
+        self.char_to_str_pos
+            .extend(other.char_to_str_pos[1..].iter().map(|&pos| byte_len + pos));
+
+        if self.boundary_scores.is_empty() || other.boundary_scores.is_empty() {
+            self.boundary_scores.clear();
+        } else {
+            self.boundary_scores.truncate(self.score_padding + len - 1);
+            self.boundary_scores.push(i32::MAX);
+            self.boundary_scores
+                .extend_from_slice(other.boundary_scores());
+            self.boundary_scores
+                .resize(self.score_padding * 2 + self.boundaries.len(), 0);
+        }
+
+        // States of automata are only valid for the predictor that produced them.
+        let same_predictor = match (self.predictor, other.predictor) {
+            (Some(p), Some(q)) => core::ptr::eq(p, q),
+            _ => false,
+        };
+        if same_predictor
+            && self.char_pma_states.len() == len
+            && other.char_pma_states.len() == other.len()
+            && self.type_pma_states.len() == len
+            && other.type_pma_states.len() == other.len()
+        {
+            self.char_pma_states
+                .extend_from_slice(&other.char_pma_states);
+            self.type_pma_states
+                .extend_from_slice(&other.type_pma_states);
+        } else {
+            self.char_pma_states.clear();
+            self.type_pma_states.clear();
+            self.predictor.take();
+        }
+
+        if !self.tags.is_empty() || !other.tags.is_empty() {
+            let n_tags = self.n_tags.max(other.n_tags);
+            let mut tags = Vec::with_capacity(self.len() * n_tags);
+            for (s, s_len) in [(&*self, len), (other, other.len())] {
+                for i in 0..s_len {
+                    let chunk = s.tags.get(i * s.n_tags..(i + 1) * s.n_tags).unwrap_or(&[]);
+                    tags.extend_from_slice(chunk);
+                    tags.resize(tags.len() + n_tags - chunk.len(), None);
+                }
+            }
+            self.tags = tags;
+            self.n_tags = n_tags;
+        }
+
+        if !self.tag_scores.is_empty() || !other.tag_scores.is_empty() {
+            self.tag_scores.resize(len, None);
+            self.tag_scores.extend_from_slice(&other.tag_scores);
+            self.tag_scores.resize(self.len(), None);
+        }
+
+        self.unknown_spans.extend(
+            other
+                .unknown_spans
+                .iter()
+                .map(|&(start, end)| (len + start, len + end)),
+        );
+    }
+
+    /// Returns character ranges kept as single tokens by
+    /// [`Predictor::keep_unknown_spans()`](crate::Predictor::keep_unknown_spans).
+    ///
+    /// Each range is represented as a tuple of the start and end positions.
+    #[inline]
+    pub fn unknown_spans(&self) -> &[(usize, usize)] {
+        &self.unknown_spans
+    }
+
     /// Update the tag information.
     /// If you want to predict tags, call this function after calling [`Predictor::predict()`] and
     /// word boundaries are fixed.
@@ -1136,25 +2305,115 @@ assert_eq!(
     #[cfg(feature = "tag-prediction")]
     #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
     #[inline]
-    pub fn fill_tags(&mut self) {
-        if let Some(p) = self.predictor.as_ref() {
-            p.predict_tags(self);
+    pub fn fill_tags(&mut self) {
+        if let Some(p) = self.predictor.as_ref() {
+            p.predict_tags(self);
+        }
+    }
+
+    /// Updates the tag information like [`Sentence::fill_tags()`], jointly decoding the tags of
+    /// adjacent tokens.
+    ///
+    /// For each tag level having transition weights set by [`Model::set_tag_transitions()`], tags
+    /// are chosen to maximize the sum of their scores and the weights of transitions between
+    /// adjacent tokens. A token without a tag model breaks the sequence. Other levels are the
+    /// same as [`Sentence::fill_tags()`]. This function stores tag scores temporarily even if
+    /// [`Predictor::store_tag_scores()`] is set to false.
+    ///
+    /// [`Model::set_tag_transitions()`]: crate::Model::set_tag_transitions
+    ///
+    /// # Panics
+    ///
+    /// The predictor must be created with `predict_tags = true`.
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn fill_tags_joint(&mut self) {
+        if let Some(p) = self.predictor.as_ref() {
+            p.predict_tags_joint(self);
+        }
+    }
+
+    /// Updates the tag information like [`Sentence::fill_tags()`] and adds the elapsed time to
+    /// [`StageTimings::tag_fill`](crate::StageTimings::tag_fill).
+    ///
+    /// # Panics
+    ///
+    /// The predictor must be created with `predict_tags = true`.
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "tag-prediction"))))]
+    pub fn fill_tags_timed(&mut self, timings: &mut crate::StageTimings) {
+        let start = std::time::Instant::now();
+        self.fill_tags();
+        timings.tag_fill += start.elapsed();
+    }
+
+    /// Returns the maximum number of tags.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("火星/名詞/カセー に 行き/動詞 まし/助動詞/マシ た").unwrap();
+    /// assert_eq!(2, s.n_tags());
+    /// ```
+    #[inline]
+    pub const fn n_tags(&self) -> usize {
+        self.n_tags
+    }
+
+    /// Returns a key consisting of the text and boundaries of this sentence.
+    ///
+    /// Two sentences have the same key if and only if they have the same segmentation result.
+    /// Tags and scores are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s1 = Sentence::from_tokenized("火星 に 行き まし た").unwrap();
+    /// let s2 = Sentence::from_tokenized("火星/名詞 に 行き まし た").unwrap();
+    /// let s3 = Sentence::from_tokenized("火星 に 行き ました").unwrap();
+    /// assert!(s1.key() == s2.key());
+    /// assert!(s1.key() != s3.key());
+    /// ```
+    #[inline]
+    pub fn key(&self) -> SentenceKey<'_> {
+        SentenceKey {
+            text: &self.text,
+            boundaries: &self.boundaries,
         }
     }
 
-    /// Returns the maximum number of tags.
+    /// Removes sentences having the same segmentation result as preceding sentences.
+    ///
+    /// The order of remaining sentences is preserved. See [`Sentence::key()`] for the
+    /// equivalence.
     ///
     /// # Examples
     ///
     /// ```
     /// use vaporetto::Sentence;
     ///
-    /// let s = Sentence::from_tokenized("火星/名詞/カセー に 行き/動詞 まし/助動詞/マシ た").unwrap();
-    /// assert_eq!(2, s.n_tags());
+    /// let mut sentences = vec![
+    ///     Sentence::from_tokenized("火星 猫").unwrap(),
+    ///     Sentence::from_tokenized("火星猫").unwrap(),
+    ///     Sentence::from_tokenized("火星 猫").unwrap(),
+    /// ];
+    /// Sentence::dedup(&mut sentences);
+    /// assert_eq!(2, sentences.len());
+    ///
+    /// let mut buf = String::new();
+    /// sentences[1].write_tokenized_text(&mut buf);
+    /// assert_eq!("火星猫", buf);
     /// ```
-    #[inline]
-    pub const fn n_tags(&self) -> usize {
-        self.n_tags
+    pub fn dedup(sentences: &mut Vec<Self>) {
+        let mut keys = HashSet::new();
+        let keep: Vec<bool> = sentences.iter().map(|s| keys.insert(s.key())).collect();
+        drop(keys);
+        let mut keep = keep.into_iter();
+        sentences.retain(|_| keep.next().unwrap());
     }
 
     #[inline]
@@ -1186,6 +2445,29 @@ assert_eq!(
     }
 }
 
+/// Key of a sentence for comparing and hashing segmentation results.
+///
+/// This is returned by [`Sentence::key()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SentenceKey<'a> {
+    text: &'a str,
+    boundaries: &'a [CharacterBoundary],
+}
+
+impl<'a> SentenceKey<'a> {
+    /// Returns the text.
+    #[inline]
+    pub const fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns the boundaries.
+    #[inline]
+    pub const fn boundaries(&self) -> &'a [CharacterBoundary] {
+        self.boundaries
+    }
+}
+
 /// A Token information.
 #[derive(Clone, Copy)]
 pub struct Token<'a, 'b> {
@@ -1202,6 +2484,10 @@ impl<'a, 'b> Token<'a, 'b> {
     }
 
     /// Returns tags of this token.
+    ///
+    /// The returned slice contains [`Sentence::n_tags()`] elements, one for each tag level such
+    /// as a part-of-speech and a pronunciation. Tags that are not given or not predicted are
+    /// `None`.
     #[inline]
     pub fn tags(&self) -> &'a [Option<Cow<'b, str>>] {
         let start = (self.end - 1) * self.sentence.n_tags();
@@ -1209,6 +2495,25 @@ impl<'a, 'b> Token<'a, 'b> {
         &self.sentence.tags[start..end]
     }
 
+    /// Returns the tag of the given level, or `None` if the tag is not given or the level is out
+    /// of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("火星/名詞/カセー 猫/名詞").unwrap();
+    /// let tokens: Vec<_> = s.iter_tokens().collect();
+    /// assert_eq!(Some("カセー"), tokens[0].tag(1));
+    /// assert_eq!(None, tokens[1].tag(1));
+    /// assert_eq!(None, tokens[1].tag(2));
+    /// ```
+    #[inline]
+    pub fn tag(&self, level: usize) -> Option<&'a str> {
+        self.tags().get(level)?.as_deref()
+    }
+
     /// Returns tag candidates with scores.
     ///
     /// The return value is a two-dimensional array. The outer array index corresponding to the
@@ -1227,14 +2532,16 @@ impl<'a, 'b> Token<'a, 'b> {
             "Predictor::store_tag_scores() must be set to true to use this function.",
         );
         if let Some((tags, scores)) = self.sentence.tag_scores[self.end - 1].as_ref() {
+            // Tag scores are stored only by the predictor.
+            let names = self.sentence.predictor.unwrap().tag_names();
             let mut i = 0;
             for cands in *tags {
                 let mut inner = vec![];
                 if cands.len() == 1 {
-                    inner.push((cands[0].as_str(), 0));
+                    inner.push((names[cands[0] as usize].as_str(), 0));
                 } else {
-                    for cand in cands {
-                        inner.push((cand.as_str(), scores[i]));
+                    for &cand in cands {
+                        inner.push((names[cand as usize].as_str(), scores[i]));
                         i += 1;
                     }
                 }
@@ -1255,6 +2562,49 @@ impl<'a, 'b> Token<'a, 'b> {
     pub const fn end(&self) -> usize {
         self.end
     }
+
+    /// Returns the range of this token in characters.
+    #[inline]
+    pub const fn char_range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the start position of this token in bytes.
+    #[inline]
+    pub fn byte_start(&self) -> usize {
+        self.sentence.char_to_str_pos[self.start]
+    }
+
+    /// Returns the end position of this token in bytes.
+    #[inline]
+    pub fn byte_end(&self) -> usize {
+        self.sentence.char_to_str_pos[self.end]
+    }
+
+    /// Returns the range of this token in bytes.
+    ///
+    /// The range points to the text of the sentence, so
+    /// `&s.as_raw_text()[token.byte_range()]` is equal to the surface.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("火星 に Mars 探査 機").unwrap();
+    /// let ranges: Vec<_> = s
+    ///     .iter_tokens()
+    ///     .map(|token| (token.char_range(), token.byte_range()))
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec![(0..2, 0..6), (2..3, 6..9), (3..7, 9..13), (7..9, 13..19), (9..10, 19..22)],
+    ///     ranges,
+    /// );
+    /// ```
+    #[inline]
+    pub fn byte_range(&self) -> Range<usize> {
+        self.byte_start()..self.byte_end()
+    }
 }
 
 /// Iterator returned by [`Sentence::iter_tokens()`].
@@ -1294,6 +2644,18 @@ impl<'a, 'b> Iterator for TokenIterator<'a, 'b> {
     }
 }
 
+impl FusedIterator for TokenIterator<'_, '_> {}
+
+impl<'a, 'b> IntoIterator for &'a Sentence<'a, 'b> {
+    type Item = Token<'a, 'b>;
+    type IntoIter = TokenIterator<'a, 'b>;
+
+    /// Returns an iterator of tokens. See [`Sentence::iter_tokens()`].
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_tokens()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2738,6 +4100,21 @@ mod tests {
         assert!(it.next().is_none());
     }
 
+    #[test]
+    fn test_sentence_into_iter() {
+        let s = Sentence::from_tokenized("火星 猫 の 生態").unwrap();
+        let mut surfaces = vec![];
+        for token in &s {
+            surfaces.push(token.surface());
+        }
+        assert_eq!(vec!["火星", "猫", "の", "生態"], surfaces);
+
+        let mut it = s.iter_tokens();
+        assert_eq!(4, it.by_ref().count());
+        assert!(it.next().is_none());
+        assert!(it.next().is_none());
+    }
+
     #[test]
     fn test_sentence_to_tokenized_vec_with_tags() {
         let s =
@@ -2935,4 +4312,468 @@ mod tests {
         );
         assert!(s.boundary_scores().is_empty());
     }
+
+    #[test]
+    fn test_write_conllu_with_scores() {
+        let mut s = Sentence::from_tokenized("火星/名詞 猫/名詞/ネコ だ").unwrap();
+        s.score_padding = 1;
+        s.boundary_scores = vec![0, -10, 20, 30, 0];
+        let mut buf = String::new();
+        s.write_conllu(&mut buf, |_| Some("NOUN"));
+        assert_eq!(
+            "# text = 火星猫だ\n\
+             1\t火星\t_\tNOUN\t名詞\t_\t_\t_\t_\tTokenRange=0:2|BoundaryScore=20\n\
+             2\t猫\t_\tNOUN\t名詞\t_\t_\t_\t_\tTokenRange=2:3|BoundaryScore=30|Tag2=ネコ\n\
+             3\tだ\t_\t_\t_\t_\t_\t_\t_\tTokenRange=3:4\n\n",
+            buf,
+        );
+    }
+
+    #[test]
+    fn test_write_conllu_escape_surfaces() {
+        let s = Sentence::from_tokenized("火星\t猫 100%").unwrap();
+        let mut buf = String::new();
+        s.write_conllu(&mut buf, |_| None);
+        assert_eq!(
+            "# text = 火星%09猫100%\n\
+             1\t火星%09猫\t_\t_\t_\t_\t_\t_\t_\tTokenRange=0:4\n\
+             2\t100%\t_\t_\t_\t_\t_\t_\t_\tTokenRange=4:8\n\n",
+            buf,
+        );
+    }
+
+    #[test]
+    fn test_write_conllu_escape_tags() {
+        let mut s = Sentence::from_tokenized("火星/名詞/カセー に/助詞").unwrap();
+        s.tags_mut()[2] = Some("名詞\t固有".into());
+        s.tags_mut()[3] = Some("a|b=c\n100%".into());
+        let mut buf = String::new();
+        s.write_conllu(&mut buf, |_| None);
+        assert_eq!(
+            "# text = 火星に\n\
+             1\t火星\t_\t_\t名詞%09固有\t_\t_\t_\t_\tTokenRange=0:2|Tag2=a%7Cb%3Dc%0A100%25\n\
+             2\tに\t_\t_\t助詞\t_\t_\t_\t_\tTokenRange=2:3\n\n",
+            buf,
+        );
+    }
+
+    #[test]
+    fn test_set_boundary() {
+        let mut s = Sentence::from_tokenized("火星/名詞 猫/名詞/ネコ だ").unwrap();
+        s.set_boundary(2, CharacterBoundary::NotWordBoundary)
+            .unwrap();
+        s.set_boundary(0, CharacterBoundary::WordBoundary).unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火 星/名詞 猫だ", buf);
+        assert!(s.tags()[4..].iter().all(Option::is_none));
+
+        assert!(s.set_boundary(3, CharacterBoundary::WordBoundary).is_err());
+    }
+
+    #[test]
+    fn test_set_token_tag() {
+        let mut s = Sentence::from_tokenized("火星/名詞 猫 だ").unwrap();
+        s.set_token_tag(2, 0, "助動詞").unwrap();
+        s.set_token_tag(0, 2, "カセイ").unwrap();
+        assert_eq!(3, s.n_tags());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星/名詞//カセイ 猫 だ/助動詞", buf);
+
+        let mut s = Sentence::from_tokenized("火星猫").unwrap();
+        s.set_token_tag(0, 0, String::from("名詞")).unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星猫/名詞", buf);
+
+        assert!(s.set_token_tag(1, 0, "名詞").is_err());
+    }
+
+    #[test]
+    fn test_from_raw_with_invisible_chars() {
+        let text = "\u{200d}a\u{7}\u{2060}b\u{feff}";
+        let (s, map) = Sentence::from_raw_with_invisible_chars(text, InvisibleChars::Keep).unwrap();
+        assert_eq!(text, s.as_raw_text());
+        assert!(map.is_identity());
+
+        let (s, map) =
+            Sentence::from_raw_with_invisible_chars(text, InvisibleChars::Strip).unwrap();
+        assert_eq!("ab", s.as_raw_text());
+        assert_eq!(0..4, map.to_original(0..1));
+        assert_eq!(4..6, map.to_original(1..2));
+
+        let (s, map) =
+            Sentence::from_raw_with_invisible_chars("ab", InvisibleChars::Strip).unwrap();
+        assert_eq!("ab", s.as_raw_text());
+        assert!(map.is_identity());
+
+        assert!(Sentence::from_raw_with_invisible_chars(text, InvisibleChars::Error).is_err());
+        assert!(Sentence::from_raw_with_invisible_chars("ab", InvisibleChars::Error).is_ok());
+        assert!(
+            Sentence::from_raw_with_invisible_chars("\u{200b}\n", InvisibleChars::Strip).is_err()
+        );
+    }
+
+    #[test]
+    fn test_tokenized_format_roundtrip() {
+        let format = TokenizedFormat::with_separators('\t', '|', '%').unwrap();
+        let text = "https://a.jp/b|URL|%%\t100%%|名詞\t火 星|名詞||カセイ";
+        let s = Sentence::from_tokenized_with_format(text, &format).unwrap();
+        assert_eq!("https://a.jp/b100%火 星", s.as_raw_text());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!(
+            "https:\\/\\/a.jp\\/b/URL/% 100%/名詞 火\\ 星/名詞//カセイ",
+            buf,
+        );
+        s.write_tokenized_text_with_format(&mut buf, &format);
+        assert_eq!(text, buf);
+    }
+
+    #[test]
+    fn test_tokenized_format_errors() {
+        let format = TokenizedFormat::with_separators('|', '#', '\\').unwrap();
+        assert!(Sentence::from_tokenized_with_format("|a", &format).is_err());
+        assert!(Sentence::from_tokenized_with_format("a||b", &format).is_err());
+        assert!(Sentence::from_tokenized_with_format("a|#b", &format).is_err());
+        let mut s = Sentence::from_tokenized_with_format("a b|c#x", &format).unwrap();
+        assert_eq!("a bc", s.as_raw_text());
+        s.update_tokenized_with_format("d|e", &format).unwrap();
+        assert_eq!("de", s.as_raw_text());
+
+        assert!(TokenizedFormat::with_separators(' ', '/', '/').is_err());
+        assert!(TokenizedFormat::with_separators(' ', '\0', '\\').is_err());
+    }
+
+    #[test]
+    fn test_append() {
+        let mut s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+        s.unknown_spans.push((0, 2));
+        s.score_padding = 1;
+        s.boundary_scores = vec![0, -10, 20, 0];
+        let mut t = Sentence::from_raw("だ。").unwrap();
+        t.boundaries_mut()[0] = CharacterBoundary::WordBoundary;
+        t.tag_scores.resize(t.len(), None);
+        t.unknown_spans.push((1, 2));
+        t.score_padding = 1;
+        t.boundary_scores = vec![0, 30, 0];
+        s.append(&t);
+
+        assert_eq!("火星猫だ。", s.as_raw_text());
+        assert_eq!(5, s.len());
+        assert_eq!(
+            &[
+                Kanji as u8,
+                Kanji as u8,
+                Kanji as u8,
+                Hiragana as u8,
+                Other as u8
+            ],
+            s.char_types(),
+        );
+        assert_eq!(
+            &[
+                CharacterBoundary::NotWordBoundary,
+                CharacterBoundary::WordBoundary,
+                CharacterBoundary::WordBoundary,
+                CharacterBoundary::WordBoundary,
+            ],
+            s.boundaries(),
+        );
+        assert_eq!(&[-10, 20, i32::MAX, 30], s.boundary_scores());
+        assert_eq!(s.score_padding * 2 + 4, s.boundary_scores.len());
+        assert_eq!(
+            &[None, Some(Cow::Borrowed("名詞")), None, None, None],
+            s.tags(),
+        );
+        assert_eq!(5, s.tag_scores.len());
+        assert_eq!(&[(0, 2), (4, 5)], s.unknown_spans());
+        assert_eq!(
+            &[0, 0, 0, 1, 0, 0, 2, 0, 0, 3, 0, 0, 4, 0, 0, 5],
+            s.str_to_char_pos.as_slice()
+        );
+        assert_eq!(&[0, 3, 6, 9, 12, 15], s.char_to_str_pos.as_slice());
+        assert_eq!("猫", s.text_substring(2, 3));
+        assert_eq!("だ。", s.text_substring(3, 5));
+    }
+
+    #[test]
+    fn test_append_without_scores() {
+        let mut s = Sentence::from_tokenized("火星/名詞/カセイ").unwrap();
+        s.score_padding = 1;
+        s.boundary_scores = vec![0, 10, 0];
+        let t = Sentence::from_tokenized("猫/名詞").unwrap();
+        s.append(&t);
+        assert!(s.boundary_scores().is_empty());
+        assert_eq!(2, s.n_tags());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星/名詞/カセイ 猫/名詞", buf);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        let mut s = Sentence::from_tokenized("火星/名詞 猫/名詞 だ").unwrap();
+        s.tag_scores.resize(s.len(), None);
+        s.unknown_spans.push((1, 4));
+        s.score_padding = 1;
+        s.boundary_scores = vec![0, 10, -20, 30, 0];
+        s.truncate_chars(10).unwrap();
+        assert_eq!("火星猫だ", s.as_raw_text());
+
+        s.truncate_chars(3).unwrap();
+        assert_eq!("火星猫", s.as_raw_text());
+        assert_eq!(3, s.len());
+        assert_eq!(&[Kanji as u8; 3], s.char_types());
+        assert_eq!(
+            &[
+                CharacterBoundary::NotWordBoundary,
+                CharacterBoundary::WordBoundary,
+            ],
+            s.boundaries(),
+        );
+        assert_eq!(
+            &[
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("名詞"))
+            ],
+            s.tags(),
+        );
+        assert_eq!(&[10, -20], s.boundary_scores());
+        assert_eq!(3, s.tag_scores.len());
+        assert_eq!(&[(1, 3)], s.unknown_spans());
+        assert_eq!(
+            &[0, 0, 0, 1, 0, 0, 2, 0, 0, 3],
+            s.str_to_char_pos.as_slice()
+        );
+        assert_eq!(&[0, 3, 6, 9], s.char_to_str_pos.as_slice());
+        assert!(s.truncate_chars(0).is_err());
+
+        let mut s = Sentence::from_raw(String::from("火星猫")).unwrap();
+        s.truncate_chars(1).unwrap();
+        assert_eq!("火", s.as_raw_text());
+        assert!(s.boundaries().is_empty());
+    }
+
+    #[test]
+    fn test_take_tokens() {
+        let mut s = Sentence::from_partial_annotation("火-星|猫 だ").unwrap();
+        s.take_tokens(2).unwrap();
+        assert_eq!("火星猫だ", s.as_raw_text());
+        s.take_tokens(1).unwrap();
+        assert_eq!("火星", s.as_raw_text());
+        assert!(s.take_tokens(0).is_err());
+    }
+
+    #[test]
+    fn test_project_boundaries() {
+        // "㍿ｶﾞ猫" is transformed into "株式会社ガ猫".
+        let map = OffsetMap::from_ranges(vec![0..1, 0..1, 0..1, 0..1, 1..3, 3..4], 4).unwrap();
+        let s = Sentence::from_partial_annotation("株-式|会 社|ガ|猫").unwrap();
+        let s_orig = s.project_boundaries("㍿ｶﾞ猫", &map).unwrap();
+        assert_eq!(
+            [
+                CharacterBoundary::WordBoundary,
+                CharacterBoundary::NotWordBoundary,
+                CharacterBoundary::WordBoundary,
+            ],
+            s_orig.boundaries(),
+        );
+        assert!(s_orig.boundary_scores().is_empty());
+
+        let s = Sentence::from_tokenized("株式/名詞/カブシキ 会社 ガ/助詞 猫/名詞").unwrap();
+        let s_orig = s.project_boundaries("㍿ｶﾞ猫", &map).unwrap();
+        let mut buf = String::new();
+        s_orig.write_tokenized_text(&mut buf);
+        assert_eq!("㍿/名詞/カブシキ ｶﾞ/助詞 猫/名詞", buf);
+
+        assert!(s.project_boundaries("㍿ｶﾞ", &map).is_err());
+        assert!(Sentence::from_raw("猫")
+            .unwrap()
+            .project_boundaries("㍿ｶﾞ猫", &map)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sentence_key() {
+        let s1 = Sentence::from_tokenized("火星 猫").unwrap();
+        let s2 = Sentence::from_raw("火星猫").unwrap();
+        let s3 = Sentence::from_partial_annotation("火-星|猫").unwrap();
+        assert_eq!(s1.key(), s3.key());
+        assert_ne!(s1.key(), s2.key());
+        assert_eq!("火星猫", s2.key().text());
+        assert_eq!(&[Unknown, Unknown], s2.key().boundaries());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut sentences = vec![
+            Sentence::from_tokenized("火星 猫").unwrap(),
+            Sentence::from_tokenized("地球 猫").unwrap(),
+            Sentence::from_tokenized("火星/名詞 猫").unwrap(),
+            Sentence::from_tokenized("火星猫").unwrap(),
+            Sentence::from_tokenized("地球 猫").unwrap(),
+        ];
+        Sentence::dedup(&mut sentences);
+        let mut buf = String::new();
+        let mut results = vec![];
+        for s in &sentences {
+            s.write_tokenized_text(&mut buf);
+            results.push(buf.clone());
+        }
+        assert_eq!(vec!["火星 猫", "地球 猫", "火星猫"], results);
+    }
+
+    #[test]
+    fn test_char_type_ranges() {
+        let mut ranges = CharTypeRanges::new();
+        ranges.add('\u{2500}', '\u{257F}', 7).unwrap();
+        ranges.add('々', '々', Kanji as u8).unwrap();
+        ranges.add('A', 'Z', 8).unwrap();
+
+        assert_eq!(Some(7), ranges.get_type('\u{2500}'));
+        assert_eq!(Some(7), ranges.get_type('\u{257F}'));
+        assert_eq!(None, ranges.get_type('\u{2580}'));
+        assert_eq!(Some(Kanji as u8), ranges.get_type('々'));
+        assert_eq!(Some(8), ranges.get_type('M'));
+        assert_eq!(None, ranges.get_type('a'));
+        assert_eq!(
+            vec![
+                ('A', 'Z', 8),
+                ('\u{2500}', '\u{257F}', 7),
+                ('々', '々', Kanji as u8)
+            ],
+            ranges.iter().collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_parse_char_type_ranges() {
+        let ranges: CharTypeRanges = "7 2500 257F # box-drawing\n\nK U+3005\n8 41 5A"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            vec![
+                ('A', 'Z', 8),
+                ('\u{2500}', '\u{257F}', 7),
+                ('々', '々', Kanji as u8)
+            ],
+            ranges.iter().collect::<Vec<_>>(),
+        );
+        assert_eq!(ranges, ranges.to_string().parse().unwrap());
+
+        assert!("7 2500".parse::<CharTypeRanges>().is_ok());
+        assert!("7".parse::<CharTypeRanges>().is_err());
+        assert!("7 2500 257F 2600".parse::<CharTypeRanges>().is_err());
+        assert!("X 2500".parse::<CharTypeRanges>().is_err());
+        assert!("0 2500".parse::<CharTypeRanges>().is_err());
+        assert!("7 D800".parse::<CharTypeRanges>().is_err());
+        assert!("7 41 5A\n8 5A 61".parse::<CharTypeRanges>().is_err());
+    }
+
+    #[test]
+    fn test_char_type_ranges_invalid() {
+        let mut ranges = CharTypeRanges::new();
+        ranges.add('b', 'd', 7).unwrap();
+        assert!(ranges.add('d', 'f', 7).is_err());
+        assert!(ranges.add('a', 'b', 7).is_err());
+        assert!(ranges.add('a', 'z', 7).is_err());
+        assert!(ranges.add('z', 'x', 7).is_err());
+        assert!(ranges.add('x', 'z', 0).is_err());
+        ranges.add('e', 'e', 7).unwrap();
+        ranges.add('a', 'a', 7).unwrap();
+    }
+
+    #[test]
+    fn test_apply_char_type_ranges() {
+        let mut ranges = CharTypeRanges::new();
+        ranges.add('\u{2500}', '\u{257F}', 7).unwrap();
+        let mut s = Sentence::from_raw("│猫│ですA").unwrap();
+        s.apply_char_type_ranges(&ranges);
+        assert_eq!(
+            &[
+                7,
+                Kanji as u8,
+                7,
+                Hiragana as u8,
+                Hiragana as u8,
+                Roman as u8
+            ],
+            s.char_types()
+        );
+    }
+
+    #[test]
+    fn test_sentence_from_raw_combining_mark() {
+        let s = Sentence::from_raw("カ\u{3099}ス").unwrap();
+
+        assert_eq!(
+            &[0, 0, 0, 1, 0, 0, 2, 0, 0, 3],
+            s.str_to_char_pos.as_slice()
+        );
+        assert_eq!([0, 3, 6, 9], s.char_to_str_pos());
+        assert_eq!(
+            [Katakana as u8, Other as u8, Katakana as u8],
+            s.char_types()
+        );
+        assert_eq!(2, s.boundaries().len());
+    }
+
+    #[test]
+    fn test_sentence_tokenized_combining_mark_roundtrip() {
+        let s = Sentence::from_tokenized("カ\u{3099} ス").unwrap();
+
+        assert_eq!("カ\u{3099}ス", s.as_raw_text());
+        assert_eq!(&[NotWordBoundary, WordBoundary], s.boundaries());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("カ\u{3099} ス", buf);
+    }
+
+    #[test]
+    fn test_sentence_tokenized_control_chars_roundtrip() {
+        let text = "a\u{200D}b \u{200C}c \u{200F}\u{202E}d\u{202C}";
+        let s = Sentence::from_tokenized(text).unwrap();
+
+        assert_eq!(
+            "a\u{200D}b\u{200C}c\u{200F}\u{202E}d\u{202C}",
+            s.as_raw_text()
+        );
+        assert_eq!(9, s.char_types().len());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!(text, buf);
+    }
+
+    #[test]
+    fn test_concat_combining_sequences() {
+        let mut s =
+            Sentence::from_raw("カ\u{3099}e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200F}x").unwrap();
+        s.boundaries_mut().fill(WordBoundary);
+        s.concat_combining_sequences();
+
+        assert_eq!(
+            &[
+                NotWordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                WordBoundary,
+            ],
+            s.boundaries()
+        );
+    }
+
+    #[test]
+    fn test_wrap_points_kinsoku() {
+        let s = Sentence::from_tokenized("（ 注 ） ちょっと ー 待っ て 。").unwrap();
+        assert_eq!(vec![3, 8, 10], s.wrap_points());
+        assert_eq!(Some(8), s.best_wrap_point(0, 9));
+        assert!(Sentence::from_raw("あ").unwrap().wrap_points().is_empty());
+    }
 }
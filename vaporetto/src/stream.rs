@@ -0,0 +1,144 @@
+use std::io::BufRead;
+
+use crate::errors::Result;
+use crate::predictor::Predictor;
+use crate::sentence::Sentence;
+
+/// Tokenizer that lazily reads lines from a reader and predicts word boundaries.
+///
+/// Each line is treated as a sentence. Empty lines are skipped.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+///
+/// use vaporetto::{Model, Predictor};
+///
+/// let f = File::open("../resources/model.bin")?;
+/// let model = Model::read(f)?;
+/// let predictor = Predictor::new(model, false)?;
+///
+/// let input = "まぁ社長は火星猫だ\n\nまぁ良いだろう\n".as_bytes();
+/// let mut buf = String::new();
+/// let mut outputs = vec![];
+/// for s in predictor.predict_streaming(input) {
+///     s?.write_tokenized_text(&mut buf);
+///     outputs.push(buf.clone());
+/// }
+/// assert_eq!(vec!["まぁ 社長 は 火星 猫 だ", "まぁ 良い だろう"], outputs);
+/// # Ok(())
+/// # }
+/// ```
+pub struct StreamTokenizer<'p, R> {
+    predictor: &'p Predictor,
+    reader: R,
+    line: String,
+}
+
+impl<'p, R> StreamTokenizer<'p, R>
+where
+    R: BufRead,
+{
+    /// Creates a new stream tokenizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `predictor` - A predictor.
+    /// * `reader` - A reader of the input text.
+    pub const fn new(predictor: &'p Predictor, reader: R) -> Self {
+        Self {
+            predictor,
+            reader,
+            line: String::new(),
+        }
+    }
+
+    /// Reads the next sentence into `sentence` and predicts its word boundaries.
+    ///
+    /// Unlike the [`Iterator`] implementation, this function reuses the buffers of the given
+    /// sentence.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the reader reaches the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// [`VaporettoError::IOError`](crate::errors::VaporettoError::IOError) will be returned if
+    /// reading fails.
+    pub fn read_sentence(&mut self, sentence: &mut Sentence<'static, 'p>) -> Result<bool> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(false);
+            }
+            let text = self.line.trim_end_matches(['\n', '\r']);
+            if text.is_empty() {
+                continue;
+            }
+            sentence.update_raw(text.to_string())?;
+            self.predictor.predict(sentence);
+            return Ok(true);
+        }
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<'p, R> Iterator for StreamTokenizer<'p, R>
+where
+    R: BufRead,
+{
+    type Item = Result<Sentence<'static, 'p>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut sentence = Sentence::default();
+        match self.read_sentence(&mut sentence) {
+            Ok(true) => Some(Ok(sentence)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Model;
+
+    fn create_predictor() -> Predictor {
+        let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin")).unwrap();
+        Predictor::new(model, false).unwrap()
+    }
+
+    #[test]
+    fn test_read_sentence_reuse() {
+        let predictor = create_predictor();
+        let mut tokenizer = StreamTokenizer::new(&predictor, "火星猫\r\n\n\nまぁ社長".as_bytes());
+        let mut s = Sentence::default();
+        let mut buf = String::new();
+
+        assert!(tokenizer.read_sentence(&mut s).unwrap());
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星 猫", buf);
+
+        assert!(tokenizer.read_sentence(&mut s).unwrap());
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長", buf);
+
+        assert!(!tokenizer.read_sentence(&mut s).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let predictor = create_predictor();
+        let mut tokenizer = StreamTokenizer::new(&predictor, &b"\xff\xfe\n"[..]);
+        assert!(tokenizer.next().unwrap().is_err());
+    }
+}
@@ -9,7 +9,7 @@ use crate::errors::{Result, VaporettoError};
 use crate::model::TagModel;
 use crate::ngram_model::{TagNgramData, TagNgramModel, TagWeight};
 use crate::sentence::Sentence;
-use crate::trainer::{NgramFeature, SolverType};
+use crate::trainer::{check_problem_size, NgramFeature, SolverType};
 
 use crate::trainer::QUANTIZE_BIT_DEPTH;
 
@@ -113,11 +113,11 @@ impl<'a> TagTrainer<'a> {
         examples: &'b [TagExample<'a>],
         idx: usize,
         tag_ids: &HashMap<&'a str, usize>,
-    ) -> (
+    ) -> Result<(
         HashMap<&'b TagFeature<'a>, u32>,
         Vec<Vec<(u32, f64)>>,
         Vec<f64>,
-    ) {
+    )> {
         let mut feature_ids = HashMap::new();
         let mut xs = vec![];
         let mut ys = vec![];
@@ -129,13 +129,14 @@ impl<'a> TagTrainer<'a> {
             }
             let mut feature_vec = vec![];
             for feature in &example.features {
-                let new_id = u32::try_from(feature_ids.len() + 1).unwrap();
+                let new_id = u32::try_from(feature_ids.len() + 1).unwrap_or(u32::MAX);
                 let feature_id = *feature_ids.entry(feature).or_insert(new_id);
                 feature_vec.push((feature_id, 1f64));
             }
             xs.push(feature_vec);
         }
-        (feature_ids, xs, ys)
+        check_problem_size(xs.len(), feature_ids.len())?;
+        Ok((feature_ids, xs, ys))
     }
 
     fn train_tag(
@@ -177,7 +178,7 @@ impl<'a> TagTrainer<'a> {
             }
 
             // train
-            let (feature_ids, xs, ys) = Self::gen_feature_vecs(examples, i, tag_ids);
+            let (feature_ids, xs, ys) = Self::gen_feature_vecs(examples, i, tag_ids)?;
 
             let mut builder = liblinear::Builder::new();
             let training_input = liblinear::util::TrainingInput::from_sparse_features(ys, xs)
@@ -194,7 +195,9 @@ impl<'a> TagTrainer<'a> {
 
             // Calculates the quantize multiplier
             let mut weight_max = 1e-6f64;
-            for i in 0..i32::try_from(tag_ids.len()).unwrap() {
+            let n_labels = i32::try_from(tag_ids.len())
+                .map_err(|_| VaporettoError::invalid_argument("sentence", "too many tags"))?;
+            for i in 0..n_labels {
                 let bias = model.label_bias(i).abs();
                 weight_max = weight_max.max(bias);
                 for fid in 0..model.num_features() {
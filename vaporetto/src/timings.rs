@@ -0,0 +1,102 @@
+use core::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+/// Elapsed time of each stage of the tokenization pipeline.
+///
+/// The stages of the predictor are measured by [`Predictor::predict_timed()`] and
+/// [`Sentence::fill_tags_timed()`]. Each call adds the elapsed time to the corresponding fields,
+/// so one instance can accumulate times over many sentences. Stages outside this crate, such as
+/// filters of `vaporetto_rules`, can be measured by [`StageTimings::measure_filters()`].
+///
+/// [`Predictor::predict_timed()`]: crate::Predictor::predict_timed
+/// [`Sentence::fill_tags_timed()`]: crate::Sentence::fill_tags_timed
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+///
+/// use vaporetto::{Model, Predictor, Sentence, StageTimings};
+///
+/// let model = Model::read(File::open("../resources/model.bin")?)?;
+/// let predictor = Predictor::new(model, false)?;
+///
+/// let mut timings = StageTimings::default();
+/// let mut s = Sentence::from_raw("まぁ社長は火星猫だ")?;
+/// predictor.predict_timed(&mut s, &mut timings);
+/// timings.measure_filters(|| s.concat_combining_sequences());
+///
+/// assert!(timings.total() >= timings.char_scorer);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    /// Time spent on preparing scores and adding scores of character n-grams.
+    pub char_scorer: Duration,
+
+    /// Time spent on adding scores of character type n-grams.
+    pub type_scorer: Duration,
+
+    /// Time spent on adding scores of dictionaries and deciding boundaries.
+    pub dict_scorer: Duration,
+
+    /// Time spent on predicting tags.
+    pub tag_fill: Duration,
+
+    /// Time spent on filters.
+    pub filters: Duration,
+}
+
+impl StageTimings {
+    /// Returns the sum of all stages.
+    pub fn total(&self) -> Duration {
+        self.char_scorer + self.type_scorer + self.dict_scorer + self.tag_fill + self.filters
+    }
+
+    /// Runs the given function and adds its elapsed time to [`StageTimings::filters`].
+    pub fn measure_filters<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.filters += start.elapsed();
+        result
+    }
+}
+
+impl AddAssign for StageTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.char_scorer += other.char_scorer;
+        self.type_scorer += other.type_scorer;
+        self.dict_scorer += other.dict_scorer;
+        self.tag_fill += other.tag_fill;
+        self.filters += other.filters;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_and_add_assign() {
+        let mut timings = StageTimings {
+            char_scorer: Duration::from_millis(1),
+            type_scorer: Duration::from_millis(2),
+            dict_scorer: Duration::from_millis(3),
+            tag_fill: Duration::from_millis(4),
+            filters: Duration::from_millis(5),
+        };
+        assert_eq!(Duration::from_millis(15), timings.total());
+        timings += timings;
+        assert_eq!(Duration::from_millis(30), timings.total());
+        assert_eq!(Duration::from_millis(2), timings.char_scorer);
+    }
+
+    #[test]
+    fn test_measure_filters() {
+        let mut timings = StageTimings::default();
+        assert_eq!(3, timings.measure_filters(|| 1 + 2));
+        assert_eq!(timings.filters, timings.total());
+    }
+}
@@ -11,12 +11,29 @@ use crate::dict_model::{DictModel, WordWeightRecord};
 use crate::errors::{Result, VaporettoError};
 use crate::model::Model;
 use crate::ngram_model::{NgramData, NgramModel};
-use crate::sentence::{CharacterBoundary, Sentence};
+use crate::sentence::{CharTypeRanges, CharacterBoundary, Sentence};
 use crate::tag_trainer::TagTrainer;
 
 // Bit depth for weight quantization.
 pub const QUANTIZE_BIT_DEPTH: u8 = 16;
 
+/// Checks that a problem can be passed to liblinear, which uses `i32` for the numbers of
+/// examples and feature indices.
+pub fn check_problem_size(n_examples: usize, n_features: usize) -> Result<()> {
+    // liblinear adds one more feature for the bias.
+    if i32::try_from(n_examples).is_err() || i32::try_from(n_features + 1).is_err() {
+        return Err(VaporettoError::invalid_argument(
+            "sentence",
+            format!(
+                "the training data has too many examples ({n_examples}) or features \
+                 ({n_features}), the maximum is {}",
+                i32::MAX - 1,
+            ),
+        ));
+    }
+    Ok(())
+}
+
 /// Solver type.
 #[cfg_attr(docsrs, doc(cfg(feature = "train")))]
 #[derive(Clone, Copy, Debug)]
@@ -153,6 +170,10 @@ impl From<usize> for DummyValue {
 
 /// Trainer.
 ///
+/// This trainer learns weights of character n-grams, character type n-grams, and dictionary
+/// words, as well as tag models, from fully or partially annotated corpora, and produces a
+/// [`Model`] directly. KyTea is not required for training.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -257,11 +278,14 @@ impl<'a> Trainer<'a> {
         })
     }
 
-    fn gen_features<'b>(
+    fn gen_features<'b, F>(
         &self,
         sentence: &'a Sentence<'a, 'b>,
         examples: &mut Vec<(Vec<BoundaryFeature<'a>>, CharacterBoundary)>,
-    ) {
+        is_excluded: F,
+    ) where
+        F: Fn(&str) -> bool,
+    {
         for (i, &b) in sentence.boundaries().iter().enumerate() {
             let mut features = vec![];
             // adds character n-gram features
@@ -295,6 +319,9 @@ impl<'a> Trainer<'a> {
         // adds dictionary features
         if let Some(pma) = self.dict_pma.as_ref() {
             for m in pma.find_overlapping_iter(sentence.text.as_ref()) {
+                if is_excluded(&sentence.text[m.start()..m.end()]) {
+                    continue;
+                }
                 debug_assert!(sentence.text.is_char_boundary(m.start()));
                 let start = unsafe { sentence.str_to_char_pos(m.start()) };
                 debug_assert!(sentence.text.is_char_boundary(m.end()));
@@ -319,16 +346,31 @@ impl<'a> Trainer<'a> {
 
     /// Adds a sentence to the trainer.
     pub fn add_example<'b>(&mut self, sentence: &'a Sentence<'a, 'b>) {
+        self.add_example_excluding_words(sentence, |_| false);
+    }
+
+    /// Adds a sentence to the trainer, where dictionary words for which `is_excluded` returns
+    /// `true` are not used as features of the sentence.
+    ///
+    /// This is used for jackknifing: when the dictionary contains words of the training corpus,
+    /// excluding words seen only in the document of the sentence prevents the model from
+    /// relying on the dictionary too much, because words in texts to be predicted are often not
+    /// in the dictionary.
+    pub fn add_example_excluding_words<'b, F>(
+        &mut self,
+        sentence: &'a Sentence<'a, 'b>,
+        is_excluded: F,
+    ) where
+        F: Fn(&str) -> bool,
+    {
         let mut examples = vec![];
-        self.gen_features(sentence, &mut examples);
+        self.gen_features(sentence, &mut examples, is_excluded);
         for (features, b) in examples {
             let mut feature_vector = HashMap::new();
             for feature in features {
-                let new_id = self.feature_ids.len() + 1;
-                let feature_id = *self
-                    .feature_ids
-                    .entry(feature)
-                    .or_insert(new_id.try_into().unwrap());
+                // IDs are saturated here, and too many features are reported by train().
+                let new_id = u32::try_from(self.feature_ids.len() + 1).unwrap_or(u32::MAX);
+                let feature_id = *self.feature_ids.entry(feature).or_insert(new_id);
                 *feature_vector.entry(feature_id).or_insert(0f64) += 1f64;
             }
             self.xs.push(feature_vector.into_iter().collect());
@@ -348,8 +390,11 @@ impl<'a> Trainer<'a> {
     ///
     /// # Errors
     ///
-    /// If the solver returns an error, that will be propagated.
+    /// If the solver returns an error, that will be propagated. An error variant will also be
+    /// returned if the number of character boundaries or features exceeds the limit of
+    /// liblinear, `i32::MAX - 1`.
     pub fn train(self, epsilon: f64, cost: f64, solver: SolverType) -> Result<Model> {
+        check_problem_size(self.xs.len(), self.feature_ids.len())?;
         let mut builder = liblinear::Builder::new();
         let training_input = liblinear::util::TrainingInput::from_sparse_features(self.ys, self.xs)
             .map_err(|e| VaporettoError::invalid_model(format!("liblinear error: {e:?}")))?;
@@ -424,13 +469,13 @@ impl<'a> Trainer<'a> {
                 }) => {
                     let len = ngram.len();
                     let pos = usize::try_from(
-                        isize::from(self.char_window_size) - isize::try_from(len)? - rel_position,
+                        isize::from(self.type_window_size) - isize::try_from(len)? - rel_position,
                     )
                     .unwrap();
                     if let Some(weights) = type_ngram_weights.get_mut(ngram) {
                         weights[pos] = weight;
                     } else {
-                        let mut weights = vec![0; usize::from(self.char_window_size) * 2 - len + 1];
+                        let mut weights = vec![0; usize::from(self.type_window_size) * 2 - len + 1];
                         weights[pos] = weight;
                         type_ngram_weights.insert(ngram.to_vec(), weights);
                     }
@@ -490,6 +535,270 @@ impl<'a> Trainer<'a> {
     pub fn n_features(&self) -> usize {
         self.feature_ids.len()
     }
+
+    /// Adapts the word boundary weights of an existing model to the given sentences by online
+    /// learning, without retraining from scratch.
+    ///
+    /// Weights are updated by the passive-aggressive algorithm: for each annotated boundary
+    /// whose score does not have the correct sign with at least `margin`, the weights of its
+    /// character n-grams, character type n-grams, and dictionary words are changed by the
+    /// minimum amount that fixes it. Character (type) n-grams not in the model are added.
+    /// The sentences are visited `n_epochs` times in the given order. The bias, tag models,
+    /// and the other data of the model are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A model to be updated.
+    /// * `sentences` - Fully or partially annotated sentences. Unknown boundaries are ignored.
+    /// * `n_epochs` - The number of passes over the sentences.
+    /// * `margin` - The required margin, in the same unit as
+    ///   [`Sentence::boundary_scores()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    ///
+    /// use vaporetto::{Model, Predictor, Sentence, Trainer};
+    ///
+    /// let model = Model::read(File::open("../resources/model.bin")?)?;
+    /// let corrected = [Sentence::from_tokenized("火星猫 の 生態")?];
+    /// let model = Trainer::update(model, &corrected, 3, 1000)?;
+    ///
+    /// let predictor = Predictor::new(model, false)?;
+    /// let mut s = Sentence::from_raw("火星猫の生態")?;
+    /// predictor.predict(&mut s);
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("火星猫 の 生態", buf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the model is invalid, an error variant will be returned.
+    pub fn update(
+        mut model: Model,
+        sentences: &[Sentence],
+        n_epochs: usize,
+        margin: i32,
+    ) -> Result<Model> {
+        let mut weights = OnlineWeights::new(&mut model)?;
+        let examples: Vec<_> = sentences
+            .iter()
+            .map(|s| weights.gen_features(s, &model.1.char_type_ranges))
+            .collect();
+        let margin = f64::from(margin);
+        for _ in 0..n_epochs {
+            for (features, b) in examples.iter().flatten() {
+                let y = match b {
+                    CharacterBoundary::WordBoundary => 1.,
+                    CharacterBoundary::NotWordBoundary => -1.,
+                    CharacterBoundary::Unknown => continue,
+                };
+                if features.is_empty() {
+                    continue;
+                }
+                let score = weights.score(features);
+                let loss = margin - y * score;
+                if loss > 0. {
+                    #[allow(clippy::cast_precision_loss)]
+                    let tau = loss / features.len() as f64;
+                    for &(id, pos) in features {
+                        weights.weights[id][pos] += tau * y;
+                    }
+                }
+            }
+        }
+        weights.write_back(&mut model);
+        Ok(model)
+    }
+}
+
+/// Boundary weights of a model held in floating point numbers for online learning.
+struct OnlineWeights {
+    char_window_size: usize,
+    char_ngram_size: usize,
+    type_window_size: usize,
+    type_ngram_size: usize,
+    dict_word_max_len: usize,
+    bias: f64,
+    weights: Vec<Vec<f64>>,
+    char_ngram_ids: BTreeMap<String, usize>,
+    type_ngram_ids: BTreeMap<Vec<u8>, usize>,
+    dict_ids: HashMap<String, usize>,
+    dict_record_ids: Vec<usize>,
+}
+
+impl OnlineWeights {
+    /// Moves the boundary weights out of the model.
+    fn new(model: &mut Model) -> Result<Self> {
+        let data = &mut model.0;
+        let mut weights = vec![];
+        let mut char_ngram_ids = BTreeMap::new();
+        let mut char_ngram_size = 0;
+        for d in core::mem::take(&mut data.char_ngram_model.0) {
+            let len = d.ngram.chars().count();
+            if d.weights.len() + len != usize::from(data.char_window_size) * 2 + 1 {
+                return Err(VaporettoError::invalid_model(
+                    "invalid size of weight vector of character n-gram",
+                ));
+            }
+            char_ngram_size = char_ngram_size.max(len);
+            char_ngram_ids.insert(d.ngram, weights.len());
+            weights.push(d.weights.into_iter().map(f64::from).collect());
+        }
+        let mut type_ngram_ids = BTreeMap::new();
+        let mut type_ngram_size = 0;
+        for d in core::mem::take(&mut data.type_ngram_model.0) {
+            if d.weights.len() + d.ngram.len() != usize::from(data.type_window_size) * 2 + 1 {
+                return Err(VaporettoError::invalid_model(
+                    "invalid size of weight vector of character type n-gram",
+                ));
+            }
+            type_ngram_size = type_ngram_size.max(d.ngram.len());
+            type_ngram_ids.insert(d.ngram, weights.len());
+            weights.push(d.weights.into_iter().map(f64::from).collect());
+        }
+        let mut dict_ids = HashMap::new();
+        let mut dict_record_ids = vec![];
+        let mut dict_word_max_len = 0;
+        for d in &data.dict_model.0 {
+            dict_word_max_len = dict_word_max_len.max(d.word.chars().count());
+            // Only the first one of duplicate words is updated.
+            dict_ids.entry(d.word.clone()).or_insert(weights.len());
+            dict_record_ids.push(weights.len());
+            weights.push(d.weights.iter().copied().map(f64::from).collect());
+        }
+        Ok(Self {
+            char_window_size: usize::from(data.char_window_size),
+            char_ngram_size,
+            type_window_size: usize::from(data.type_window_size),
+            type_ngram_size,
+            dict_word_max_len,
+            bias: f64::from(data.bias),
+            weights,
+            char_ngram_ids,
+            type_ngram_ids,
+            dict_ids,
+            dict_record_ids,
+        })
+    }
+
+    /// Returns pairs of a weight vector ID and a position in the vector for each boundary,
+    /// adding n-grams not in the model.
+    fn gen_features(
+        &mut self,
+        sentence: &Sentence,
+        char_type_ranges: &CharTypeRanges,
+    ) -> Vec<(Vec<(usize, usize)>, CharacterBoundary)> {
+        let mut examples: Vec<_> = sentence.boundaries().iter().map(|&b| (vec![], b)).collect();
+        // The `pos`-th weight of an n-gram ending at `end` is added to the boundary
+        // `end + pos - offset - 1`.
+        let add_weight = |examples: &mut Vec<(Vec<_>, _)>, id, len, end: usize, offset: usize| {
+            for pos in 0..len {
+                if let Some(i) = (end + pos).checked_sub(offset + 1) {
+                    if let Some((features, _)) = examples.get_mut(i) {
+                        features.push((id, pos));
+                    }
+                }
+            }
+        };
+        for n in 1..=self.char_ngram_size.min(self.char_window_size * 2) {
+            for start in 0..(sentence.len() + 1).saturating_sub(n) {
+                let ngram = sentence.text_substring(start, start + n);
+                let id = if let Some(&id) = self.char_ngram_ids.get(ngram) {
+                    id
+                } else {
+                    self.char_ngram_ids
+                        .insert(ngram.to_string(), self.weights.len());
+                    self.weights
+                        .push(vec![0.; self.char_window_size * 2 + 1 - n]);
+                    self.weights.len() - 1
+                };
+                let len = self.weights[id].len();
+                add_weight(&mut examples, id, len, start + n, self.char_window_size);
+            }
+        }
+        let mut char_types = sentence.char_types().to_vec();
+        for (c, char_type) in sentence.as_raw_text().chars().zip(&mut char_types) {
+            if let Some(type_id) = char_type_ranges.get_type(c) {
+                *char_type = type_id;
+            }
+        }
+        for n in 1..=self.type_ngram_size.min(self.type_window_size * 2) {
+            for start in 0..(sentence.len() + 1).saturating_sub(n) {
+                let ngram = &char_types[start..start + n];
+                let id = if let Some(&id) = self.type_ngram_ids.get(ngram) {
+                    id
+                } else {
+                    self.type_ngram_ids
+                        .insert(ngram.to_vec(), self.weights.len());
+                    self.weights
+                        .push(vec![0.; self.type_window_size * 2 + 1 - n]);
+                    self.weights.len() - 1
+                };
+                let len = self.weights[id].len();
+                add_weight(&mut examples, id, len, start + n, self.type_window_size);
+            }
+        }
+        for n in 1..=self.dict_word_max_len {
+            for start in 0..(sentence.len() + 1).saturating_sub(n) {
+                if let Some(&id) = self.dict_ids.get(sentence.text_substring(start, start + n)) {
+                    let len = self.weights[id].len();
+                    add_weight(&mut examples, id, len, start + n, n);
+                }
+            }
+        }
+        examples
+    }
+
+    fn score(&self, features: &[(usize, usize)]) -> f64 {
+        self.bias
+            + features
+                .iter()
+                .map(|&(id, pos)| self.weights[id][pos])
+                .sum::<f64>()
+    }
+
+    /// Moves the updated weights back into the model. N-grams whose weights are all zero are
+    /// removed.
+    #[allow(clippy::cast_possible_truncation)]
+    fn write_back(self, model: &mut Model) {
+        let mut weights: Vec<_> = self
+            .weights
+            .into_iter()
+            .map(|w| Some(w.into_iter().map(|x| x.round() as i32).collect::<Vec<_>>()))
+            .collect();
+        let data = &mut model.0;
+        data.char_ngram_model.0 = self
+            .char_ngram_ids
+            .into_iter()
+            .filter_map(|(ngram, id)| {
+                let weights = weights[id].take().unwrap();
+                weights
+                    .iter()
+                    .any(|&w| w != 0)
+                    .then_some(NgramData { ngram, weights })
+            })
+            .collect();
+        data.type_ngram_model.0 = self
+            .type_ngram_ids
+            .into_iter()
+            .filter_map(|(ngram, id)| {
+                let weights = weights[id].take().unwrap();
+                weights
+                    .iter()
+                    .any(|&w| w != 0)
+                    .then_some(NgramData { ngram, weights })
+            })
+            .collect();
+        for (d, id) in data.dict_model.0.iter_mut().zip(self.dict_record_ids) {
+            d.weights = weights[id].take().unwrap();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -503,7 +812,7 @@ mod tests {
         let s = Sentence::from_tokenized("これ は テスト です").unwrap();
         let trainer = Trainer::new(3, 3, 2, 2, vec![], 4, &[]).unwrap();
         let mut examples = vec![];
-        trainer.gen_features(&s, &mut examples);
+        trainer.gen_features(&s, &mut examples, |_| false);
 
         // こ-れ
         assert_eq!(
@@ -708,7 +1017,7 @@ mod tests {
         )
         .unwrap();
         let mut examples = vec![];
-        trainer.gen_features(&s, &mut examples);
+        trainer.gen_features(&s, &mut examples, |_| false);
 
         // こ-れ
         assert_eq!(
@@ -865,4 +1174,97 @@ mod tests {
         );
         assert_eq!(NotWordBoundary, examples[6].1);
     }
+
+    #[test]
+    fn check_features_excluding_words() {
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let trainer = Trainer::new(
+            2,
+            2,
+            2,
+            2,
+            vec!["これ".into(), "これは".into(), "テスト".into()],
+            4,
+            &[],
+        )
+        .unwrap();
+        let mut examples = vec![];
+        trainer.gen_features(&s, &mut examples, |w| w == "テスト");
+
+        let dict_features: Vec<_> = examples
+            .iter()
+            .map(|(features, _)| {
+                features
+                    .iter()
+                    .filter(|f| matches!(f, BoundaryFeature::DictionaryWord(_)))
+                    .count()
+            })
+            .collect();
+        // Only これ and これは are matched.
+        assert_eq!(vec![2, 2, 1, 0, 0, 0, 0], dict_features);
+    }
+
+    #[test]
+    fn test_check_problem_size() {
+        let max = usize::try_from(i32::MAX).unwrap();
+        assert!(check_problem_size(max, max - 1).is_ok());
+        assert!(check_problem_size(max + 1, 0).is_err());
+        assert!(check_problem_size(0, max).is_err());
+    }
+
+    #[test]
+    fn test_train_different_window_sizes() {
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let mut trainer = Trainer::new(3, 3, 1, 2, vec![], 4, &[]).unwrap();
+        trainer.add_example(&s);
+        let model = trainer
+            .train(0.01, 1.0, SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+
+        for d in &model.0.type_ngram_model.0 {
+            assert_eq!(2 - d.ngram.len() + 1, d.weights.len());
+        }
+        crate::Predictor::new(model, false).unwrap();
+    }
+
+    #[test]
+    fn test_update() {
+        let sents = [
+            Sentence::from_tokenized("これ は テスト です").unwrap(),
+            Sentence::from_tokenized("あれ も テスト だ").unwrap(),
+        ];
+        let train = || {
+            let mut trainer = Trainer::new(3, 3, 2, 2, vec!["テスト".into()], 4, &[]).unwrap();
+            for s in &sents {
+                trainer.add_example(s);
+            }
+            trainer
+                .train(0.01, 1.0, SolverType::L1RegularizedL2LossSVC)
+                .unwrap()
+        };
+        let mut model = train();
+
+        // Scores of the online weights are the same as the predictor.
+        let mut s = Sentence::from_raw("これはテストかな").unwrap();
+        let mut weights = OnlineWeights::new(&mut model).unwrap();
+        let examples = weights.gen_features(&s, &CharTypeRanges::new());
+        let scores: Vec<_> = examples
+            .iter()
+            .map(|(features, _)| weights.score(features))
+            .collect();
+        weights.write_back(&mut model);
+        let predictor = crate::Predictor::new(model, false).unwrap();
+        predictor.predict(&mut s);
+        let expected: Vec<_> = s.boundary_scores().iter().map(|&x| f64::from(x)).collect();
+        assert_eq!(expected, scores);
+
+        let corrected = Sentence::from_tokenized("これ はテ ストかな").unwrap();
+        let model = Trainer::update(train(), &[corrected], 5, 100).unwrap();
+        let predictor = crate::Predictor::new(model, false).unwrap();
+        let mut s = Sentence::from_raw("これはテストかな").unwrap();
+        predictor.predict(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("これ はテ ストかな", buf);
+    }
 }
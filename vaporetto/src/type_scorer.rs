@@ -17,6 +17,7 @@ use bincode::{BorrowDecode, Encode};
 use crate::errors::Result;
 use crate::ngram_model::NgramModel;
 use crate::sentence::Sentence;
+use crate::utils::ScorerHeapBytes;
 
 #[cfg(feature = "tag-prediction")]
 use crate::ngram_model::TagNgramModel;
@@ -105,6 +106,7 @@ impl TypeScorer {
         ngram_model: NgramModel<Vec<u8>>,
         window_size: u8,
         #[cfg(feature = "tag-prediction")] tag_ngram_model: Vec<TagNgramModel<Vec<u8>>>,
+        #[cfg(feature = "cache-type-score")] allow_cache: bool,
     ) -> Result<Option<Self>> {
         if ngram_model.0.is_empty() || window_size == 0 {
             return Ok(None);
@@ -114,7 +116,7 @@ impl TypeScorer {
         if tag_ngram_model.is_empty() {
             match window_size {
                 #[cfg(feature = "cache-type-score")]
-                0..=CACHE_MAX_WINDOW_SIZE => Ok(Some(Self::BoundaryCache(
+                0..=CACHE_MAX_WINDOW_SIZE if allow_cache => Ok(Some(Self::BoundaryCache(
                     TypeScorerBoundaryCache::new(ngram_model, window_size)?,
                 ))),
                 _ => Ok(Some(Self::Boundary(TypeScorerBoundary::new(
@@ -133,7 +135,7 @@ impl TypeScorer {
         #[cfg(not(feature = "tag-prediction"))]
         match window_size {
             #[cfg(feature = "cache-type-score")]
-            0..=CACHE_MAX_WINDOW_SIZE => Ok(Some(Self::BoundaryCache(
+            0..=CACHE_MAX_WINDOW_SIZE if allow_cache => Ok(Some(Self::BoundaryCache(
                 TypeScorerBoundaryCache::new(ngram_model, window_size)?,
             ))),
             _ => Ok(Some(Self::Boundary(TypeScorerBoundary::new(
@@ -143,6 +145,18 @@ impl TypeScorer {
         }
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        match self {
+            Self::Boundary(scorer) => scorer.heap_bytes(),
+
+            #[cfg(feature = "cache-type-score")]
+            Self::BoundaryCache(scorer) => scorer.heap_bytes(),
+
+            #[cfg(feature = "tag-prediction")]
+            Self::BoundaryTag(scorer) => scorer.heap_bytes(),
+        }
+    }
+
     #[inline]
     pub fn add_scores(&self, sentence: &mut Sentence) {
         match self {
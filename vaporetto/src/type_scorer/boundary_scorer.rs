@@ -13,6 +13,7 @@ use crate::ngram_model::NgramModel;
 use crate::predictor::{PositionalWeight, WeightVector};
 use crate::sentence::Sentence;
 use crate::type_scorer::TypeWeightMerger;
+use crate::utils::{HeapBytes, ScorerHeapBytes};
 
 pub struct TypeScorerBoundary {
     pma: DoubleArrayAhoCorasick<u32>,
@@ -59,6 +60,14 @@ impl TypeScorerBoundary {
         Ok(Self { pma, weights })
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: self.pma.heap_bytes(),
+            weights: self.weights.heap_bytes(),
+            tag_weights: 0,
+        }
+    }
+
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
     pub fn add_scores(&self, sentence: &mut Sentence) {
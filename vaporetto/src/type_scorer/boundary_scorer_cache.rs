@@ -6,6 +6,7 @@ use daachorse::DoubleArrayAhoCorasick;
 use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::NgramModel;
 use crate::sentence::Sentence;
+use crate::utils::{HeapBytes, ScorerHeapBytes};
 
 const ALPHABET_SIZE: usize = 8;
 const ALPHABET_MASK: usize = ALPHABET_SIZE - 1;
@@ -55,6 +56,14 @@ impl TypeScorerBoundaryCache {
         })
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: 0,
+            weights: self.scores.heap_bytes(),
+            tag_weights: 0,
+        }
+    }
+
     #[inline(always)]
     pub fn add_scores(&self, sentence: &mut Sentence) {
         sentence.type_pma_states.clear();
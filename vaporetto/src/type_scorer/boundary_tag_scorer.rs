@@ -13,7 +13,7 @@ use crate::ngram_model::{NgramModel, TagNgramModel};
 use crate::predictor::{PositionalWeight, PositionalWeightWithTag, WeightVector};
 use crate::sentence::Sentence;
 use crate::type_scorer::TypeWeightMerger;
-use crate::utils::{SerializableHashMap, SplitMix64Builder};
+use crate::utils::{HeapBytes, ScorerHeapBytes, SerializableHashMap, SplitMix64Builder};
 
 pub struct TypeScorerBoundaryTag {
     pma: DoubleArrayAhoCorasick<u32>,
@@ -73,11 +73,12 @@ impl TypeScorerBoundaryTag {
         let mut ngrams = vec![];
         let mut weights = vec![];
         for (i, (ngram, weight)) in merger.merge().into_iter().enumerate() {
+            let i = u32::try_from(i)
+                .map_err(|_| VaporettoError::invalid_argument("model", "too many n-grams"))?;
             ngrams.push(ngram);
             weights.push(weight.weight.map(|w| w.into()));
             for ((token_id, rel_position), weight) in weight.tag_info {
-                tag_weight[token_id][usize::from(rel_position)]
-                    .insert(u32::try_from(i).unwrap(), weight.into());
+                tag_weight[token_id][usize::from(rel_position)].insert(i, weight.into());
             }
         }
         let pma = DoubleArrayAhoCorasick::new(ngrams)
@@ -89,6 +90,14 @@ impl TypeScorerBoundaryTag {
         })
     }
 
+    pub fn heap_bytes(&self) -> ScorerHeapBytes {
+        ScorerHeapBytes {
+            automaton: self.pma.heap_bytes(),
+            weights: self.weights.heap_bytes(),
+            tag_weights: self.tag_weight.heap_bytes(),
+        }
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
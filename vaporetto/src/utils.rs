@@ -1,18 +1,21 @@
-use core::hash::{BuildHasher, Hash, Hasher};
+#[cfg(feature = "tag-prediction")]
+use core::hash::Hasher;
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "tag-prediction")]
 use core::ops::{Deref, DerefMut};
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 #[cfg(feature = "kytea")]
 use std::io::{self, Read};
 
-use bincode::{
-    de::Decoder,
-    enc::{write::Writer, Encoder},
-    error::{DecodeError, EncodeError},
-    Decode, Encode,
-};
-use hashbrown::{DefaultHashBuilder, HashMap};
+#[cfg(feature = "tag-prediction")]
+use bincode::{de::Decoder, enc::Encoder, error::DecodeError, Decode, Encode};
+use bincode::{enc::write::Writer, error::EncodeError};
+#[cfg(feature = "tag-prediction")]
+use hashbrown::DefaultHashBuilder;
+use hashbrown::HashMap;
 
 #[cfg(feature = "fix-weight-length")]
 #[inline(always)]
@@ -26,6 +29,86 @@ pub const fn trim_end_zeros(mut w: &[i32]) -> &[i32] {
     w
 }
 
+/// Number of bytes allocated on the heap, used for [`Predictor::memory_usage()`].
+///
+/// [`Predictor::memory_usage()`]: crate::Predictor::memory_usage
+pub trait HeapBytes {
+    fn heap_bytes(&self) -> usize;
+}
+
+macro_rules! impl_heap_bytes_for_primitives {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl HeapBytes for $t {
+                #[inline(always)]
+                fn heap_bytes(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_bytes_for_primitives!(bool, u8, u32, i32, usize);
+
+impl HeapBytes for String {
+    fn heap_bytes(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T> HeapBytes for Vec<T>
+where
+    T: HeapBytes,
+{
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * core::mem::size_of::<T>()
+            + self.iter().map(HeapBytes::heap_bytes).sum::<usize>()
+    }
+}
+
+impl<T> HeapBytes for Option<T>
+where
+    T: HeapBytes,
+{
+    fn heap_bytes(&self) -> usize {
+        self.as_ref().map_or(0, HeapBytes::heap_bytes)
+    }
+}
+
+impl<A, B> HeapBytes for (A, B)
+where
+    A: HeapBytes,
+    B: HeapBytes,
+{
+    fn heap_bytes(&self) -> usize {
+        self.0.heap_bytes() + self.1.heap_bytes()
+    }
+}
+
+impl<K, V, S> HeapBytes for HashMap<K, V, S>
+where
+    K: HeapBytes + Eq + Hash,
+    V: HeapBytes,
+    S: BuildHasher,
+{
+    fn heap_bytes(&self) -> usize {
+        self.allocation_size()
+            + self
+                .iter()
+                .map(|(k, v)| k.heap_bytes() + v.heap_bytes())
+                .sum::<usize>()
+    }
+}
+
+/// Heap bytes of a scorer broken down into its components.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScorerHeapBytes {
+    pub automaton: usize,
+    pub weights: usize,
+    pub tag_weights: usize,
+}
+
 pub struct VecWriter(pub Vec<u8>);
 
 impl Writer for VecWriter {
@@ -35,9 +118,11 @@ impl Writer for VecWriter {
     }
 }
 
+#[cfg(feature = "tag-prediction")]
 #[derive(Clone, Debug, Default)]
 pub struct SerializableHashMap<K, V, S = DefaultHashBuilder>(pub HashMap<K, V, S>);
 
+#[cfg(feature = "tag-prediction")]
 impl<K, V, S> Deref for SerializableHashMap<K, V, S> {
     type Target = HashMap<K, V, S>;
 
@@ -46,12 +131,26 @@ impl<K, V, S> Deref for SerializableHashMap<K, V, S> {
     }
 }
 
+#[cfg(feature = "tag-prediction")]
 impl<K, V, S> DerefMut for SerializableHashMap<K, V, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+#[cfg(feature = "tag-prediction")]
+impl<K, V, S> HeapBytes for SerializableHashMap<K, V, S>
+where
+    K: HeapBytes + Eq + Hash,
+    V: HeapBytes,
+    S: BuildHasher,
+{
+    fn heap_bytes(&self) -> usize {
+        self.0.heap_bytes()
+    }
+}
+
+#[cfg(feature = "tag-prediction")]
 impl<K, V, S> Decode for SerializableHashMap<K, V, S>
 where
     K: Decode + Eq + Hash,
@@ -70,6 +169,7 @@ where
     }
 }
 
+#[cfg(feature = "tag-prediction")]
 impl<K, V, S> Encode for SerializableHashMap<K, V, S>
 where
     K: Encode,
@@ -86,11 +186,44 @@ where
     }
 }
 
+/// SplitMix64 pseudo random number generator, so that the same seed always produces the same
+/// results.
+pub struct SplitMix64Rng(u64);
+
+impl SplitMix64Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+
+    /// Returns a random number in `[0, 1)`.
+    #[cfg(feature = "std")]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(feature = "tag-prediction")]
 // Copied from https://prng.di.unimi.it/splitmix64.c
 pub struct SplitMix64 {
     x: u64,
 }
 
+#[cfg(feature = "tag-prediction")]
 impl SplitMix64 {
     fn add(&mut self, i: u64) {
         self.x ^= i;
@@ -101,6 +234,7 @@ impl SplitMix64 {
     }
 }
 
+#[cfg(feature = "tag-prediction")]
 impl Hasher for SplitMix64 {
     #[inline(always)]
     fn finish(&self) -> u64 {
@@ -155,9 +289,11 @@ impl Hasher for SplitMix64 {
     }
 }
 
+#[cfg(feature = "tag-prediction")]
 #[derive(Clone, Copy, Default)]
 pub struct SplitMix64Builder;
 
+#[cfg(feature = "tag-prediction")]
 impl BuildHasher for SplitMix64Builder {
     type Hasher = SplitMix64;
 
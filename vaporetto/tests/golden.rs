@@ -0,0 +1,100 @@
+//! Regression tests comparing outputs of the predictor with golden files.
+//!
+//! The corpus and the expected outputs are stored in `resources/golden/`. If a change of the
+//! predictor intentionally modifies the outputs, regenerate the golden files with:
+//!
+//! ```sh
+//! VAPORETTO_UPDATE_GOLDEN=1 cargo test --test golden
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use vaporetto::{Model, Predictor, Sentence};
+
+const UPDATE_ENV: &str = "VAPORETTO_UPDATE_GOLDEN";
+
+fn resource_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../resources")
+        .join(name)
+}
+
+fn load_predictor(predict_tags: bool) -> Predictor {
+    let data = fs::read(resource_path("model.bin")).unwrap();
+    let (model, _) = Model::read_slice(&data).unwrap();
+    Predictor::new(model, predict_tags).unwrap()
+}
+
+/// Tokenizes each line of the corpus and writes the tokenized text followed by the boundary
+/// scores.
+#[cfg_attr(not(feature = "tag-prediction"), allow(unused_variables))]
+fn run_corpus(predictor: &Predictor, corpus: &str, predict_tags: bool) -> String {
+    let mut output = String::new();
+    let mut buf = String::new();
+    let mut s = Sentence::default();
+    for line in corpus.lines() {
+        s.update_raw(line).unwrap();
+        predictor.predict(&mut s);
+        #[cfg(feature = "tag-prediction")]
+        if predict_tags {
+            s.fill_tags();
+        }
+        s.write_tokenized_text(&mut buf);
+        output.push_str(&buf);
+        output.push('\n');
+        for (i, score) in s.boundary_scores().iter().enumerate() {
+            if i != 0 {
+                output.push(' ');
+            }
+            write!(output, "{score}").unwrap();
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Compares the actual output with the golden file and panics with a line-by-line diff.
+fn assert_golden(name: &str, actual: &str) {
+    let path = resource_path("golden").join(name);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap();
+    if expected == actual {
+        return;
+    }
+    let mut diff = String::new();
+    let expected_lines: Vec<_> = expected.lines().collect();
+    let actual_lines: Vec<_> = actual.lines().collect();
+    for i in 0..expected_lines.len().max(actual_lines.len()) {
+        let e = expected_lines.get(i);
+        let a = actual_lines.get(i);
+        if e != a {
+            writeln!(diff, "line {}:", i + 1).unwrap();
+            writeln!(diff, "  expected: {}", e.unwrap_or(&"<none>")).unwrap();
+            writeln!(diff, "  actual:   {}", a.unwrap_or(&"<none>")).unwrap();
+        }
+    }
+    panic!(
+        "the output differs from {}\n{diff}set {UPDATE_ENV}=1 to update the golden file",
+        path.display(),
+    );
+}
+
+#[test]
+fn test_golden_tokenization() {
+    let corpus = fs::read_to_string(resource_path("golden/input.txt")).unwrap();
+    let predictor = load_predictor(false);
+    assert_golden("tokenized.txt", &run_corpus(&predictor, &corpus, false));
+}
+
+#[cfg(feature = "tag-prediction")]
+#[test]
+fn test_golden_tagging() {
+    let corpus = fs::read_to_string(resource_path("golden/input.txt")).unwrap();
+    let predictor = load_predictor(true);
+    assert_golden("tagged.txt", &run_corpus(&predictor, &corpus, true));
+}
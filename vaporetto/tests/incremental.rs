@@ -0,0 +1,68 @@
+//! Tests simulating an input method that re-tokenizes the text after every keystroke.
+//!
+//! The same [`Sentence`] buffer is reused for every prefix, as in `examples/ime.rs`, and the
+//! results must be the same as tokenizing each prefix from scratch.
+
+use std::fs;
+use std::path::Path;
+
+use vaporetto::{Model, Predictor, Sentence};
+
+fn load_predictor(predict_tags: bool) -> Predictor {
+    let data =
+        fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/model.bin")).unwrap();
+    let (model, _) = Model::read_slice(&data).unwrap();
+    Predictor::new(model, predict_tags).unwrap()
+}
+
+fn prefixes(text: &str) -> impl Iterator<Item = &str> {
+    text.char_indices().map(|(i, c)| &text[..i + c.len_utf8()])
+}
+
+#[test]
+fn test_typing_matches_fresh_prediction() {
+    let predictor = load_predictor(false);
+    let mut s = Sentence::default();
+    for text in ["まぁ社長は火星猫だ", "まぁ良いだろう"] {
+        for prefix in prefixes(text) {
+            s.update_raw(prefix).unwrap();
+            predictor.predict(&mut s);
+
+            let mut expected = Sentence::from_raw(prefix).unwrap();
+            predictor.predict(&mut expected);
+            assert_eq!(expected.boundaries(), s.boundaries(), "prefix: {prefix}");
+            assert_eq!(expected.boundary_scores(), s.boundary_scores());
+        }
+    }
+    let mut buf = String::new();
+    s.write_tokenized_text(&mut buf);
+    assert_eq!("まぁ 良い だろう", buf);
+}
+
+#[cfg(feature = "tag-prediction")]
+#[test]
+fn test_typing_with_tags() {
+    let mut predictor = load_predictor(true);
+    predictor.store_tag_scores(true);
+    let mut s = Sentence::default();
+    let mut buf = String::new();
+    let mut expected_buf = String::new();
+    for prefix in prefixes("まぁ良いだろう") {
+        s.update_raw(prefix).unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+
+        let mut expected = Sentence::from_raw(prefix).unwrap();
+        predictor.predict(&mut expected);
+        expected.fill_tags();
+        s.write_tokenized_text(&mut buf);
+        expected.write_tokenized_text(&mut expected_buf);
+        assert_eq!(expected_buf, buf);
+
+        assert_eq!(
+            expected.iter_tokens().last().unwrap().tag_candidates(),
+            s.iter_tokens().last().unwrap().tag_candidates(),
+        );
+    }
+    assert_eq!("まぁ/副詞/マー 良い/形容詞/ヨイ だろう/助動詞/ダロー", buf);
+}
@@ -0,0 +1,297 @@
+//! Annotators labeling spans of tokens.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+use vaporetto::{Sentence, Token};
+
+/// Condition of a single token.
+#[derive(Clone, Debug)]
+pub enum TokenMatcher {
+    /// Matches any token.
+    Any,
+
+    /// Matches a token having the surface.
+    Surface(String),
+
+    /// Matches a token whose surface is contained in the set, e.g., a gazetteer.
+    SurfaceIn(HashSet<String>),
+
+    /// Matches a token having the tag at the level.
+    Tag(usize, String),
+
+    /// Matches a token whose surface matches the regular expression.
+    ///
+    /// The expression is searched in the surface, so anchor it with `^` and `$` to match the whole
+    /// surface.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+
+    /// Matches a token satisfying all the conditions.
+    All(Vec<Self>),
+}
+
+impl TokenMatcher {
+    fn is_match(&self, token: &Token) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Surface(surface) => token.surface() == surface,
+            Self::SurfaceIn(surfaces) => surfaces.contains(token.surface()),
+            Self::Tag(level, tag) => token.tag(*level) == Some(tag.as_str()),
+            #[cfg(feature = "regex")]
+            Self::Regex(re) => re.is_match(token.surface()),
+            Self::All(matchers) => matchers.iter().all(|m| m.is_match(token)),
+        }
+    }
+}
+
+/// Element of a pattern matching a repetition of tokens.
+#[derive(Clone, Debug)]
+pub struct PatternElement {
+    matcher: TokenMatcher,
+    min: usize,
+    max: usize,
+}
+
+impl PatternElement {
+    /// Creates a new element matching exactly one token.
+    pub const fn new(matcher: TokenMatcher) -> Self {
+        Self {
+            matcher,
+            min: 1,
+            max: 1,
+        }
+    }
+
+    /// Allows this element to match no token.
+    #[must_use]
+    pub const fn optional(mut self) -> Self {
+        self.min = 0;
+        self
+    }
+
+    /// Allows this element to match one or more consecutive tokens.
+    #[must_use]
+    pub const fn one_or_more(mut self) -> Self {
+        self.max = usize::MAX;
+        self
+    }
+}
+
+impl From<TokenMatcher> for PatternElement {
+    fn from(matcher: TokenMatcher) -> Self {
+        Self::new(matcher)
+    }
+}
+
+/// Span of tokens labeled by [`PatternAnnotator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Annotation<'p, 's> {
+    label: &'p str,
+    surface: &'s str,
+    start: usize,
+    end: usize,
+}
+
+impl<'p, 's> Annotation<'p, 's> {
+    /// Returns the label of the matched pattern.
+    #[inline]
+    pub const fn label(&self) -> &'p str {
+        self.label
+    }
+
+    /// Returns the surface of the span.
+    #[inline]
+    pub const fn surface(&self) -> &'s str {
+        self.surface
+    }
+
+    /// Returns the start position of the span in characters.
+    #[inline]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the end position of the span in characters.
+    #[inline]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+}
+
+/// Annotator labeling token sequences matching patterns.
+///
+/// Patterns are searched from the beginning of the sentence, and the longest match is taken at
+/// each token. If patterns match the same length, the one added first is taken. Annotations do
+/// not overlap each other.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::annotator::{PatternAnnotator, PatternElement, TokenMatcher};
+///
+/// let mut annotator = PatternAnnotator::new();
+/// annotator.add_pattern(
+///     "PLANET",
+///     vec![
+///         TokenMatcher::SurfaceIn(["火星".into(), "地球".into()].into_iter().collect()).into(),
+///         PatternElement::new(TokenMatcher::Tag(0, "接尾辞".into())).optional(),
+///     ],
+/// );
+///
+/// let s = Sentence::from_tokenized("火星/名詞 猫/名詞 と 地球/名詞 人/接尾辞").unwrap();
+/// let spans: Vec<_> = annotator
+///     .annotate(&s)
+///     .iter()
+///     .map(|a| (a.label(), a.surface(), a.start(), a.end()))
+///     .collect();
+/// assert_eq!(vec![("PLANET", "火星", 0, 2), ("PLANET", "地球人", 4, 7)], spans);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PatternAnnotator {
+    patterns: Vec<(String, Vec<PatternElement>)>,
+}
+
+impl PatternAnnotator {
+    /// Creates a new annotator without patterns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern with the label.
+    ///
+    /// Matches of no tokens are ignored, so a pattern consisting only of optional elements
+    /// matches only non-empty sequences.
+    pub fn add_pattern<S>(&mut self, label: S, pattern: Vec<PatternElement>)
+    where
+        S: Into<String>,
+    {
+        self.patterns.push((label.into(), pattern));
+    }
+
+    /// Returns annotations of the sentence in order of appearance.
+    pub fn annotate<'p, 's>(&'p self, sentence: &'s Sentence) -> Vec<Annotation<'p, 's>> {
+        let text = sentence.as_raw_text();
+        let tokens: Vec<_> = sentence.iter_tokens().collect();
+        let mut byte_offsets = Vec::with_capacity(tokens.len() + 1);
+        byte_offsets.push(0);
+        for token in &tokens {
+            byte_offsets.push(byte_offsets.last().unwrap() + token.surface().len());
+        }
+
+        let mut annotations = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            let mut best: Option<(usize, &str)> = None;
+            for (label, pattern) in &self.patterns {
+                if let Some(len) = Self::longest_match(pattern, &tokens[i..]) {
+                    if len != 0 && best.map_or(true, |(best_len, _)| len > best_len) {
+                        best = Some((len, label));
+                    }
+                }
+            }
+            if let Some((len, label)) = best {
+                annotations.push(Annotation {
+                    label,
+                    surface: &text[byte_offsets[i]..byte_offsets[i + len]],
+                    start: tokens[i].start(),
+                    end: tokens[i + len - 1].end(),
+                });
+                i += len;
+            } else {
+                i += 1;
+            }
+        }
+        annotations
+    }
+
+    /// Returns the number of tokens of the longest match at the beginning of `tokens`.
+    fn longest_match(pattern: &[PatternElement], tokens: &[Token]) -> Option<usize> {
+        let Some((elem, rest)) = pattern.split_first() else {
+            return Some(0);
+        };
+        let mut n_repeats = 0;
+        while n_repeats < elem.max
+            && tokens
+                .get(n_repeats)
+                .is_some_and(|t| elem.matcher.is_match(t))
+        {
+            n_repeats += 1;
+        }
+        (elem.min..=n_repeats)
+            .rev()
+            .filter_map(|n| Self::longest_match(rest, &tokens[n..]).map(|len| n + len))
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_longest_match() {
+        let mut annotator = PatternAnnotator::new();
+        annotator.add_pattern("NOUN", vec![TokenMatcher::Tag(0, "名詞".into()).into()]);
+        annotator.add_pattern(
+            "NOUNS",
+            vec![PatternElement::new(TokenMatcher::Tag(0, "名詞".into())).one_or_more()],
+        );
+        let s = Sentence::from_tokenized("火星/名詞 猫/名詞 は 人/名詞 だ").unwrap();
+        let spans: Vec<_> = annotator
+            .annotate(&s)
+            .iter()
+            .map(|a| (a.label(), a.surface(), a.start(), a.end()))
+            .collect();
+        assert_eq!(vec![("NOUNS", "火星猫", 0, 3), ("NOUN", "人", 4, 5)], spans);
+    }
+
+    #[test]
+    fn test_annotate_backtrack() {
+        let mut annotator = PatternAnnotator::new();
+        annotator.add_pattern(
+            "DATE",
+            vec![
+                PatternElement::new(TokenMatcher::Any).one_or_more(),
+                TokenMatcher::Surface("日".into()).into(),
+            ],
+        );
+        let s = Sentence::from_tokenized("3 月 1 日 と 2 日").unwrap();
+        let spans: Vec<_> = annotator.annotate(&s).iter().map(|a| a.surface()).collect();
+        assert_eq!(vec!["3月1日と2日"], spans);
+    }
+
+    #[test]
+    fn test_annotate_all_and_optional() {
+        let mut annotator = PatternAnnotator::new();
+        annotator.add_pattern(
+            "OPT",
+            vec![PatternElement::new(TokenMatcher::All(vec![
+                TokenMatcher::Tag(0, "名詞".into()),
+                TokenMatcher::Surface("猫".into()),
+            ]))
+            .optional()],
+        );
+        let s = Sentence::from_tokenized("火星/名詞 猫/名詞 猫").unwrap();
+        let spans: Vec<_> = annotator.annotate(&s).iter().map(|a| a.start()).collect();
+        assert_eq!(vec![2], spans);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_annotate_regex() {
+        let mut annotator = PatternAnnotator::new();
+        annotator.add_pattern(
+            "NUMBER",
+            vec![
+                TokenMatcher::Regex(regex::Regex::new("^[0-9]+$").unwrap()).into(),
+                PatternElement::new(TokenMatcher::Surface("円".into())).optional(),
+            ],
+        );
+        let s = Sentence::from_tokenized("値段 は 100 円 です 2 つ").unwrap();
+        let spans: Vec<_> = annotator.annotate(&s).iter().map(|a| a.surface()).collect();
+        assert_eq!(vec!["100円", "2"], spans);
+    }
+}
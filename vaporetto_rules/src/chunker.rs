@@ -0,0 +1,250 @@
+//! Chunkers grouping tokens into phrases.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+use vaporetto::Sentence;
+
+const DEFAULT_HEAD_TAGS: &[&str] = &["名詞"];
+const DEFAULT_PREFIX_TAGS: &[&str] = &["接頭辞", "接頭詞"];
+const DEFAULT_SUFFIX_TAGS: &[&str] = &["接尾辞"];
+
+/// Phrase extracted by [`NounPhraseChunker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Phrase<'a> {
+    surface: &'a str,
+    start: usize,
+    end: usize,
+    n_tokens: usize,
+    score: i32,
+}
+
+impl<'a> Phrase<'a> {
+    /// Returns the surface of this phrase.
+    #[inline]
+    pub const fn surface(&self) -> &'a str {
+        self.surface
+    }
+
+    /// Returns the start position of this phrase in characters.
+    #[inline]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the end position of this phrase in characters.
+    #[inline]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the number of tokens in this phrase.
+    #[inline]
+    pub const fn n_tokens(&self) -> usize {
+        self.n_tokens
+    }
+
+    /// Returns the score of this phrase.
+    ///
+    /// The score is the smaller boundary score of both ends of the phrase, so a larger score means
+    /// the phrase is more clearly separated from its context. Ends of the sentence are not
+    /// counted. If the sentence has not been predicted or the phrase covers the whole sentence,
+    /// the score is [`i32::MAX`].
+    #[inline]
+    pub const fn score(&self) -> i32 {
+        self.score
+    }
+}
+
+/// Chunker grouping consecutive tokens into noun phrases by their tags.
+///
+/// A phrase consists of zero or more prefixes, a head, and following heads and suffixes. Tags of
+/// the given level are compared with the sets of head, prefix, and suffix tags. Prefixes not
+/// followed by heads and suffixes not preceded by heads are not included in phrases.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::chunker::NounPhraseChunker;
+///
+/// let s = Sentence::from_tokenized(
+///     "新/接頭辞 火星/名詞 猫/名詞 は/助詞 地球/名詞 人/接尾辞 だ/助動詞",
+/// )
+/// .unwrap();
+/// let chunker = NounPhraseChunker::new(0);
+/// let phrases: Vec<_> = chunker.chunk(&s).iter().map(|p| p.surface()).collect();
+/// assert_eq!(vec!["新火星猫", "地球人"], phrases);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NounPhraseChunker {
+    level: usize,
+    head_tags: HashSet<String>,
+    prefix_tags: HashSet<String>,
+    suffix_tags: HashSet<String>,
+}
+
+impl NounPhraseChunker {
+    /// Creates a new chunker comparing tags of the given level.
+    ///
+    /// By default, heads are `名詞`, prefixes are `接頭辞` and `接頭詞`, and suffixes are `接尾辞`.
+    pub fn new(level: usize) -> Self {
+        let to_set = |tags: &[&str]| tags.iter().map(|&t| t.into()).collect();
+        Self {
+            level,
+            head_tags: to_set(DEFAULT_HEAD_TAGS),
+            prefix_tags: to_set(DEFAULT_PREFIX_TAGS),
+            suffix_tags: to_set(DEFAULT_SUFFIX_TAGS),
+        }
+    }
+
+    /// Sets tags of heads.
+    pub fn set_head_tags<I, S>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.head_tags = tags.into_iter().map(Into::into).collect();
+    }
+
+    /// Sets tags of prefixes attached to the following head.
+    pub fn set_prefix_tags<I, S>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.prefix_tags = tags.into_iter().map(Into::into).collect();
+    }
+
+    /// Sets tags of suffixes attached to the preceding head.
+    pub fn set_suffix_tags<I, S>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.suffix_tags = tags.into_iter().map(Into::into).collect();
+    }
+
+    /// Extracts phrases from the given sentence in order of appearance.
+    pub fn chunk<'a>(&self, sentence: &'a Sentence) -> Vec<Phrase<'a>> {
+        let text = sentence.as_raw_text();
+        let scores = sentence.boundary_scores();
+        let edge_score = |pos: usize| {
+            pos.checked_sub(1)
+                .and_then(|i| scores.get(i))
+                .copied()
+                .unwrap_or(i32::MAX)
+        };
+
+        let mut phrases = vec![];
+        let mut byte_pos = 0;
+        // The start of the pending phrase in characters and bytes, and the number of tokens.
+        let mut pending: Option<(usize, usize, usize)> = None;
+        let mut has_head = false;
+        let mut last_end = (0, 0);
+        let mut flush = |pending: &mut Option<(usize, usize, usize)>,
+                         has_head: bool,
+                         (end, byte_end): (usize, usize)| {
+            if let Some((start, byte_start, n_tokens)) = pending.take() {
+                if has_head {
+                    phrases.push(Phrase {
+                        surface: &text[byte_start..byte_end],
+                        start,
+                        end,
+                        n_tokens,
+                        score: edge_score(start).min(edge_score(end)),
+                    });
+                }
+            }
+        };
+        for token in sentence.iter_tokens() {
+            let byte_start = byte_pos;
+            byte_pos += token.surface().len();
+            let tag = token.tag(self.level).unwrap_or_default();
+            let is_head = self.head_tags.contains(tag);
+            let is_prefix = !is_head && self.prefix_tags.contains(tag);
+            let is_suffix = !is_head && has_head && self.suffix_tags.contains(tag);
+            let in_phrase = is_head || is_prefix || is_suffix;
+            // A prefix after a head starts a new phrase.
+            if !in_phrase || (is_prefix && has_head) {
+                flush(&mut pending, has_head, last_end);
+                has_head = false;
+            }
+            if in_phrase {
+                pending
+                    .get_or_insert_with(|| (token.start(), byte_start, 0))
+                    .2 += 1;
+                has_head |= is_head;
+            }
+            last_end = (token.end(), byte_pos);
+        }
+        flush(&mut pending, has_head, last_end);
+        phrases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_default() {
+        let s = Sentence::from_tokenized(
+            "この/連体詞 新/接頭辞 型/接尾辞 火星/名詞 猫/名詞 は/助詞 御/接頭辞 地球/名詞 人/接尾辞 だ",
+        )
+        .unwrap();
+        let chunker = NounPhraseChunker::new(0);
+        let phrases = chunker.chunk(&s);
+        assert_eq!(2, phrases.len());
+        assert_eq!("火星猫", phrases[0].surface());
+        assert_eq!(
+            (4, 7, 2),
+            (phrases[0].start(), phrases[0].end(), phrases[0].n_tokens())
+        );
+        assert_eq!("御地球人", phrases[1].surface());
+        assert_eq!(
+            (8, 12, 3),
+            (phrases[1].start(), phrases[1].end(), phrases[1].n_tokens())
+        );
+        assert_eq!(i32::MAX, phrases[1].score());
+    }
+
+    #[test]
+    fn test_chunk_prefix_after_head() {
+        let s = Sentence::from_tokenized("火星/名詞 新/接頭辞 猫/名詞 新/接頭辞").unwrap();
+        let chunker = NounPhraseChunker::new(0);
+        let phrases: Vec<_> = chunker.chunk(&s).iter().map(|p| p.surface()).collect();
+        assert_eq!(vec!["火星", "新猫"], phrases);
+    }
+
+    #[test]
+    fn test_chunk_custom_tags() {
+        let s = Sentence::from_tokenized("Rust/NOUN compiler/NOUN is/VERB fast/ADJ").unwrap();
+        let mut chunker = NounPhraseChunker::new(0);
+        chunker.set_head_tags(["NOUN"]);
+        chunker.set_prefix_tags(["ADJ"]);
+        chunker.set_suffix_tags(Vec::<String>::new());
+        let phrases: Vec<_> = chunker.chunk(&s).iter().map(|p| p.surface()).collect();
+        assert_eq!(vec!["Rustcompiler"], phrases);
+    }
+
+    #[test]
+    fn test_chunk_scores() {
+        let data = include_bytes!("../../resources/model.bin");
+        let (model, _) = vaporetto::Model::read_slice(data).unwrap();
+        let predictor = vaporetto::Predictor::new(model, true).unwrap();
+        let mut s = Sentence::from_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        let phrases = NounPhraseChunker::new(0).chunk(&s);
+        assert_eq!(2, phrases.len());
+        assert_eq!("まぁ社長", phrases[0].surface());
+        assert_eq!(s.boundary_scores()[3], phrases[0].score());
+        assert_eq!("火星猫", phrases[1].surface());
+        assert_eq!(
+            s.boundary_scores()[4].min(s.boundary_scores()[7]),
+            phrases[1].score()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Detokenizer reconstructing natural texts from tokens.
+
+use alloc::string::String;
+
+use vaporetto::{Script, ScriptCategory, Sentence};
+
+/// Characters that are not preceded by a space.
+const NO_SPACE_BEFORE: &str = ".,!?;:%)]}";
+
+/// Characters that are not followed by a space.
+const NO_SPACE_AFTER: &str = "([{";
+
+/// Returns `true` if the character is written without spaces, e.g., Japanese and Chinese
+/// characters and fullwidth forms.
+fn is_unspaced(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x3000..=0x30FF     // CJK Symbols and Punctuation, Hiragana, and Katakana
+            | 0xFF00..=0xFFEF // Halfwidth and Fullwidth Forms
+    ) || ScriptCategory::get_type(c)
+        .is_some_and(|t| matches!(t.script, Script::Hiragana | Script::Katakana | Script::Han))
+}
+
+/// Detokenizer joining tokens with spaces where the writing systems need them.
+///
+/// Tokens are concatenated without spaces if either side is a Japanese character, and otherwise
+/// joined with a space. No space is inserted before closing punctuation such as `.` and `)`, after
+/// opening brackets, and inside pairs of `"` tokens.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto_rules::detokenizer::Detokenizer;
+///
+/// let detokenizer = Detokenizer::new();
+/// assert_eq!(
+///     "Vaporettoは高速(fast)だ。",
+///     detokenizer.detokenize(["Vaporetto", "は", "高速", "(", "fast", ")", "だ", "。"]),
+/// );
+/// assert_eq!(
+///     "I like Vaporetto, a tokenizer.",
+///     detokenizer.detokenize(["I", "like", "Vaporetto", ",", "a", "tokenizer", "."]),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Detokenizer {
+    space_between_scripts: bool,
+}
+
+impl Detokenizer {
+    /// Creates a new Detokenizer.
+    pub const fn new() -> Self {
+        Self {
+            space_between_scripts: false,
+        }
+    }
+
+    /// Inserts spaces between Japanese tokens and other tokens, e.g., `Rust で 書く`.
+    /// The default is `false`.
+    pub const fn space_between_scripts(mut self, yes: bool) -> Self {
+        self.space_between_scripts = yes;
+        self
+    }
+
+    fn needs_space(&self, prev: char, next: char) -> bool {
+        if prev.is_whitespace()
+            || next.is_whitespace()
+            || NO_SPACE_BEFORE.contains(next)
+            || NO_SPACE_AFTER.contains(prev)
+        {
+            return false;
+        }
+        match (is_unspaced(prev), is_unspaced(next)) {
+            (false, false) => true,
+            (true, true) => false,
+            _ => self.space_between_scripts,
+        }
+    }
+
+    /// Joins tokens into a text.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - Surfaces of tokens. Empty tokens are ignored.
+    ///
+    /// # Returns
+    ///
+    /// The text.
+    pub fn detokenize<I, S>(&self, tokens: I) -> String
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut result = String::new();
+        let mut prev = None;
+        let mut in_quote = false;
+        let mut after_open_quote = false;
+        for token in tokens {
+            let token = token.as_ref();
+            let (Some(first), Some(last)) = (token.chars().next(), token.chars().next_back())
+            else {
+                continue;
+            };
+            let is_quote = token == "\"";
+            // Quotes are attached to the quoted tokens.
+            let attached = after_open_quote || (is_quote && in_quote);
+            if let Some(prev) = prev {
+                if !attached && self.needs_space(prev, first) {
+                    result.push(' ');
+                }
+            }
+            if is_quote {
+                in_quote = !in_quote;
+            }
+            after_open_quote = is_quote && in_quote;
+            result.push_str(token);
+            prev = Some(last);
+        }
+        result
+    }
+
+    /// Joins tokens of a sentence into a text.
+    ///
+    /// Tokens adjacent to [`CharacterBoundary::Unknown`](vaporetto::CharacterBoundary::Unknown)
+    /// are skipped as in [`Sentence::iter_tokens()`].
+    pub fn detokenize_sentence(&self, sentence: &Sentence) -> String {
+        self.detokenize(sentence.iter_tokens().map(|token| token.surface()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detokenize_japanese() {
+        let detokenizer = Detokenizer::new();
+        let s = Sentence::from_tokenized("Rust で 書か れ た tokenizer です 。").unwrap();
+        assert_eq!(
+            "Rustで書かれたtokenizerです。",
+            detokenizer.detokenize_sentence(&s),
+        );
+        assert_eq!(
+            "Rust で書かれた tokenizer です。",
+            detokenizer
+                .space_between_scripts(true)
+                .detokenize_sentence(&s),
+        );
+    }
+
+    #[test]
+    fn test_detokenize_latin() {
+        let detokenizer = Detokenizer::new();
+        assert_eq!(
+            "He said \"hi (again)\" and left: 100%!",
+            detokenizer.detokenize([
+                "He", "said", "\"", "hi", "(", "again", ")", "\"", "and", "left", ":", "100", "%",
+                "!",
+            ]),
+        );
+        assert_eq!("", detokenizer.detokenize(["", ""]));
+        assert_eq!("a b", detokenizer.detokenize(["a", "", "b"]));
+    }
+
+    #[test]
+    fn test_detokenize_fullwidth() {
+        let detokenizer = Detokenizer::new().space_between_scripts(true);
+        assert_eq!(
+            "Ｖａｐｏｒｅｔｔｏは Rust 製",
+            detokenizer.detokenize(["Ｖａｐｏｒｅｔｔｏ", "は", "Rust", "製"]),
+        );
+    }
+}
@@ -4,6 +4,9 @@
 //!
 //! ## Examples
 //!
+//! Filters are applied to the input and the prediction result as follows.
+//! [`pipeline::PipelineBuilder`] wires them in the same order.
+//!
 //! ```no_run
 //! use std::fs::File;
 //! use std::io::BufReader;
@@ -51,22 +54,98 @@
 #[macro_use]
 extern crate alloc;
 
+pub mod annotator;
+pub mod chunker;
+pub mod detokenizer;
+pub mod markup;
+pub mod pipeline;
+pub mod prelude;
 pub mod sentence_filters;
 pub mod string_filters;
 
+use alloc::boxed::Box;
 use alloc::string::String;
 
-use vaporetto::Sentence;
+use vaporetto::{OffsetMap, Sentence};
 
+/// Filter for [`Sentence`]s.
+///
+/// Sentence filters are applied after [`Predictor::predict()`](vaporetto::Predictor::predict).
+/// Filters can read [`Sentence::boundary_scores()`], which always hold the scores computed by the
+/// predictor even if preceding filters have modified boundaries. The scores are empty if the
+/// sentence has not been predicted.
 pub trait SentenceFilter: Send + Sync {
     /// Filter a specified sentence using rules.
     fn filter(&self, sentence: &mut Sentence);
 }
 
+impl<F> SentenceFilter for Box<F>
+where
+    F: SentenceFilter + ?Sized,
+{
+    fn filter(&self, sentence: &mut Sentence) {
+        (**self).filter(sentence);
+    }
+}
+
+/// Filter for strings.
+///
+/// String filters are applied before prediction. [`StringFilter::filter_with_offsets()`] also
+/// returns the correspondence to the input, with which
+/// [`Sentence::project_boundaries()`] transfers the prediction result onto the input.
 pub trait StringFilter<S>: Send + Sync
 where
     S: AsRef<str>,
 {
     /// Filter a specified string using rules.
     fn filter(&self, string: S) -> String;
+
+    /// Filter a specified string and returns the mapping of character positions from the result
+    /// to the input.
+    ///
+    /// The default implementation returns the identity mapping if the number of characters is
+    /// not changed, and otherwise maps every character to the entire input. If the input is
+    /// empty, every character is mapped to the empty range. Filters changing the number of
+    /// characters should override this method.
+    fn filter_with_offsets(&self, string: S) -> (String, OffsetMap) {
+        let len = string.as_ref().chars().count();
+        let result = self.filter(string);
+        let n_chars = result.chars().count();
+        let offsets = if n_chars == len {
+            OffsetMap::identity(len)
+        } else if len == 0 {
+            OffsetMap::from_empty(n_chars)
+        } else {
+            // The ranges are non-empty and within the input.
+            OffsetMap::from_ranges(vec![0..len; n_chars], len).unwrap()
+        };
+        (result, offsets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct QuoteFilter;
+
+    impl<S> StringFilter<S> for QuoteFilter
+    where
+        S: AsRef<str>,
+    {
+        fn filter(&self, string: S) -> String {
+            format!("「{}」", string.as_ref())
+        }
+    }
+
+    #[test]
+    fn test_default_filter_with_offsets() {
+        let (result, offsets) = QuoteFilter.filter_with_offsets("火星");
+        assert_eq!("「火星」", result);
+        assert_eq!(OffsetMap::from_ranges(vec![0..2; 4], 2).unwrap(), offsets);
+
+        let (result, offsets) = QuoteFilter.filter_with_offsets("");
+        assert_eq!("「」", result);
+        assert_eq!(OffsetMap::from_empty(2), offsets);
+    }
 }
@@ -0,0 +1,278 @@
+//! Tokenization of XML and HTML documents.
+//!
+//! [`tokenize_markup()`] strips tags, decodes character references, tokenizes each text node,
+//! and returns tokens with their byte ranges in the original document, i.e., standoff
+//! annotations. [`annotate_markup()`] inserts the tokens back into the document.
+//!
+//! Text nodes are split at whitespace, and contents of comments, `<script>`, and `<style>` are
+//! skipped.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use vaporetto::{Model, Predictor};
+//! use vaporetto_rules::{markup, pipeline::PipelineBuilder};
+//!
+//! let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin"))?;
+//! let tokenizer = PipelineBuilder::new(Predictor::new(model, false)?).build();
+//!
+//! let html = "<p>まぁ社長は<b>火星猫</b>だ</p>";
+//! let tokens = markup::tokenize_markup(&tokenizer, html);
+//! assert_eq!(
+//!     "<p><w>まぁ</w><w>社長</w><w>は</w><b><w>火星</w><w>猫</w></b><w>だ</w></p>",
+//!     markup::annotate_markup(html, &tokens, "<w>", "</w>"),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use core::ops::Range;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::pipeline::Tokenizer;
+
+/// Elements whose contents are not text nodes.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Token in a markup document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkupToken {
+    /// Surface of the token with decoded character references.
+    pub surface: String,
+
+    /// Byte range of the token in the document.
+    pub range: Range<usize>,
+
+    /// Tags of the token.
+    pub tags: Vec<Option<String>>,
+}
+
+/// Run of non-whitespace characters in a text node.
+#[derive(Default)]
+struct TextRun {
+    text: String,
+
+    // Byte positions of characters in the document.
+    starts: Vec<usize>,
+    end: usize,
+}
+
+impl TextRun {
+    fn take(&mut self, runs: &mut Vec<Self>) {
+        if !self.text.is_empty() {
+            runs.push(core::mem::take(self));
+        }
+    }
+}
+
+/// Returns the length of the tag at the beginning of `s`.
+fn tag_len(s: &str) -> Option<usize> {
+    if s.starts_with("<!--") {
+        return s.find("-->").map(|i| i + 3);
+    }
+    let c = s[1..].chars().next()?;
+    if !c.is_ascii_alphabetic() && !"/!?".contains(c) {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i + 1),
+            None => (),
+        }
+    }
+    None
+}
+
+/// Returns the name of the raw text element if the tag opens it.
+fn raw_text_element(tag: &str) -> Option<&'static str> {
+    let name_end = tag[1..]
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .map_or(tag.len(), |i| i + 1);
+    if tag.ends_with("/>") {
+        return None;
+    }
+    let name = &tag[1..name_end];
+    RAW_TEXT_ELEMENTS
+        .iter()
+        .copied()
+        .find(|e| e.eq_ignore_ascii_case(name))
+}
+
+/// Decodes the character reference at the beginning of `s` and returns the character and the
+/// length of the reference.
+fn decode_reference(s: &str) -> Option<(char, usize)> {
+    let end = s.get(..32).unwrap_or(s).find(';')?;
+    let name = &s[1..end];
+    let c = if let Some(num) = name.strip_prefix('#') {
+        let code = if let Some(hex) = num.strip_prefix(['x', 'X']) {
+            u32::from_str_radix(hex, 16).ok()?
+        } else {
+            num.parse().ok()?
+        };
+        char::from_u32(code)?
+    } else {
+        match name {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            "nbsp" => '\u{a0}',
+            _ => return None,
+        }
+    };
+    Some((c, end + 1))
+}
+
+/// Extracts runs of non-whitespace characters in text nodes.
+fn text_runs(markup: &str) -> Vec<TextRun> {
+    let mut runs = vec![];
+    let mut run = TextRun::default();
+    let mut pos = 0;
+    while let Some(rest) = markup.get(pos..).filter(|rest| !rest.is_empty()) {
+        if rest.starts_with('<') {
+            if let Some(len) = tag_len(rest) {
+                run.take(&mut runs);
+                pos += len;
+                if let Some(name) = raw_text_element(&rest[..len]) {
+                    // Skips to the closing tag.
+                    let close = format!("</{name}");
+                    pos = markup[pos..]
+                        .to_ascii_lowercase()
+                        .find(&close)
+                        .map_or(markup.len(), |i| pos + i);
+                }
+                continue;
+            }
+        }
+        let (c, len) = if rest.starts_with('&') {
+            decode_reference(rest)
+        } else {
+            None
+        }
+        .unwrap_or_else(|| {
+            let c = rest.chars().next().unwrap();
+            (c, c.len_utf8())
+        });
+        if c.is_whitespace() {
+            run.take(&mut runs);
+        } else {
+            run.text.push(c);
+            run.starts.push(pos);
+            run.end = pos + len;
+        }
+        pos += len;
+    }
+    run.take(&mut runs);
+    runs
+}
+
+/// Tokenizes text nodes of an XML or HTML document.
+///
+/// Tokens are aligned to the document through [`Tokenizer::tokenize_original()`], so string
+/// filters of the tokenizer may change the number of characters.
+///
+/// # Arguments
+///
+/// * `tokenizer` - A tokenizer.
+/// * `markup` - A document.
+///
+/// # Returns
+///
+/// Tokens in the order of appearance.
+pub fn tokenize_markup(tokenizer: &Tokenizer, markup: &str) -> Vec<MarkupToken> {
+    let mut tokens = vec![];
+    for run in text_runs(markup) {
+        let Ok(s) = tokenizer.tokenize_original(&run.text) else {
+            continue;
+        };
+        let byte_pos = |i: usize| run.starts.get(i).copied().unwrap_or(run.end);
+        for token in s.iter_tokens() {
+            tokens.push(MarkupToken {
+                surface: token.surface().to_string(),
+                range: byte_pos(token.start())..byte_pos(token.end()),
+                tags: token
+                    .tags()
+                    .iter()
+                    .map(|tag| tag.as_ref().map(|tag| tag.to_string()))
+                    .collect(),
+            });
+        }
+    }
+    tokens
+}
+
+/// Inserts tokens into the document.
+///
+/// # Arguments
+///
+/// * `markup` - A document.
+/// * `tokens` - Tokens returned by [`tokenize_markup()`] for the document.
+/// * `open` - A string inserted before each token.
+/// * `close` - A string inserted after each token.
+///
+/// # Returns
+///
+/// The annotated document.
+pub fn annotate_markup(markup: &str, tokens: &[MarkupToken], open: &str, close: &str) -> String {
+    let mut result =
+        String::with_capacity(markup.len() + tokens.len() * (open.len() + close.len()));
+    let mut pos = 0;
+    for token in tokens {
+        result.push_str(&markup[pos..token.range.start]);
+        result.push_str(open);
+        result.push_str(&markup[token.range.clone()]);
+        result.push_str(close);
+        pos = token.range.end;
+    }
+    result.push_str(&markup[pos..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vaporetto::{Model, Predictor};
+
+    use crate::pipeline::PipelineBuilder;
+
+    fn create_tokenizer() -> Tokenizer {
+        let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin")).unwrap();
+        PipelineBuilder::new(Predictor::new(model, false).unwrap()).build()
+    }
+
+    #[test]
+    fn test_text_runs() {
+        let runs = text_runs(
+            "<p title=\"a>b\">火星 &amp;&#x732B;&#29483;&unknown;</p>\n<script>if (a<b) {}</script><!-- <b> --><STYLE>p {}</style>1 < 2",
+        );
+        let texts: Vec<_> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(vec!["火星", "&猫猫&unknown;", "1", "<", "2"], texts);
+        assert_eq!(
+            vec![22, 27, 35, 43, 44, 45, 46, 47, 48, 49, 50, 51],
+            runs[1].starts
+        );
+        assert_eq!(52, runs[1].end);
+    }
+
+    #[test]
+    fn test_tokenize_markup() {
+        let tokenizer = create_tokenizer();
+        let html = "<p>まぁ社長は&#x706B;星猫だ</p>";
+        let tokens = tokenize_markup(&tokenizer, html);
+        let surfaces: Vec<_> = tokens.iter().map(|t| t.surface.as_str()).collect();
+        assert_eq!(vec!["まぁ", "社長", "は", "火星", "猫", "だ"], surfaces);
+        assert_eq!(18..29, tokens[3].range);
+        assert_eq!("&#x706B;星", &html[tokens[3].range.clone()]);
+        assert_eq!(
+            "<p>[まぁ][社長][は][&#x706B;星][猫][だ]</p>",
+            annotate_markup(html, &tokens, "[", "]"),
+        );
+    }
+}
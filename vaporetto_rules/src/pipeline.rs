@@ -0,0 +1,400 @@
+//! Pipelines chaining filters and a predictor.
+//!
+//! Filters must be applied in a fixed order: string filters before prediction, sentence filters
+//! after prediction, and tag filters after filling tags. [`PipelineBuilder`] wires them into a
+//! [`Tokenizer`] in this order.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use vaporetto::{CharacterType, Model, Predictor};
+//! use vaporetto_rules::{
+//!     pipeline::PipelineBuilder,
+//!     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
+//!     string_filters::KyteaFullwidthFilter,
+//! };
+//!
+//! let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin"))?;
+//! let predictor = Predictor::new(model, false)?;
+//!
+//! let tokenizer = PipelineBuilder::new(predictor)
+//!     .string_filter(KyteaFullwidthFilter)
+//!     .sentence_filter(ConcatGraphemeClustersFilter)
+//!     .sentence_filter(KyteaWsConstFilter::new(CharacterType::Digit))
+//!     .build();
+//!
+//! let s = tokenizer.tokenize("まぁ社長は火星猫だ")?;
+//! let mut buf = String::new();
+//! s.write_tokenized_text(&mut buf);
+//! assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Filters can also be specified by names, e.g., those recorded in a model by
+//! [`Model::set_pipeline()`](vaporetto::Model::set_pipeline). See [`parse_filter_name()`] for the
+//! available names.
+
+use core::fmt;
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use vaporetto::errors::Result;
+use vaporetto::{CharacterType, OffsetMap, Predictor, Sentence};
+
+use crate::sentence_filters::{
+    ConcatGraphemeClustersFilter, KyteaWsConstFilter, NumberConcatenator, PatternConcatenator,
+    SplitLinebreaksFilter,
+};
+use crate::string_filters::KyteaFullwidthFilter;
+#[cfg(feature = "unicode-normalization")]
+use crate::string_filters::{NfcFilter, NfdFilter, NfkcFilter, NfkdFilter};
+use crate::{SentenceFilter, StringFilter};
+
+/// Error used when a filter name is not recognized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownFilterError {
+    name: String,
+}
+
+impl UnknownFilterError {
+    /// Returns the unrecognized name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for UnknownFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UnknownFilterError: {}", self.name)
+    }
+}
+
+/// Filter specified by a name.
+pub enum PipelineFilter {
+    /// String filter applied before prediction.
+    String(Box<dyn StringFilter<String>>),
+
+    /// Sentence filter applied after prediction.
+    Sentence(Box<dyn SentenceFilter>),
+}
+
+/// Creates a filter from its name.
+///
+/// The following names are available:
+///
+/// * `kytea-fullwidth` - [`KyteaFullwidthFilter`]
+/// * `nfc`, `nfd`, `nfkc`, `nfkd` - Unicode normalization filters. Requires the
+///   `unicode-normalization` feature.
+/// * `wsconst:D`, `wsconst:R`, `wsconst:H`, `wsconst:T`, `wsconst:K`, `wsconst:O` -
+///   [`KyteaWsConstFilter`] for digits, roman, hiragana, katakana, kanji, and other characters.
+/// * `wsconst:G` - [`ConcatGraphemeClustersFilter`]
+/// * `concat-patterns` - [`PatternConcatenator`]
+/// * `concat-numbers` - [`NumberConcatenator`]
+/// * `split-linebreaks` - [`SplitLinebreaksFilter`]
+///
+/// # Errors
+///
+/// If the name is not recognized, an error variant will be returned.
+pub fn parse_filter_name(name: &str) -> core::result::Result<PipelineFilter, UnknownFilterError> {
+    let filter = match name {
+        KyteaFullwidthFilter::NAME => PipelineFilter::String(Box::new(KyteaFullwidthFilter)),
+        #[cfg(feature = "unicode-normalization")]
+        NfcFilter::NAME => PipelineFilter::String(Box::new(NfcFilter)),
+        #[cfg(feature = "unicode-normalization")]
+        NfdFilter::NAME => PipelineFilter::String(Box::new(NfdFilter)),
+        #[cfg(feature = "unicode-normalization")]
+        NfkcFilter::NAME => PipelineFilter::String(Box::new(NfkcFilter)),
+        #[cfg(feature = "unicode-normalization")]
+        NfkdFilter::NAME => PipelineFilter::String(Box::new(NfkdFilter)),
+        "wsconst:G" => PipelineFilter::Sentence(Box::new(ConcatGraphemeClustersFilter)),
+        "concat-patterns" => PipelineFilter::Sentence(Box::new(PatternConcatenator::new())),
+        "concat-numbers" => PipelineFilter::Sentence(Box::new(NumberConcatenator::new())),
+        "split-linebreaks" => PipelineFilter::Sentence(Box::new(SplitLinebreaksFilter)),
+        _ => {
+            let char_type = match name.strip_prefix("wsconst:") {
+                Some("D") => CharacterType::Digit,
+                Some("R") => CharacterType::Roman,
+                Some("H") => CharacterType::Hiragana,
+                Some("T") => CharacterType::Katakana,
+                Some("K") => CharacterType::Kanji,
+                Some("O") => CharacterType::Other,
+                _ => {
+                    return Err(UnknownFilterError {
+                        name: name.to_string(),
+                    })
+                }
+            };
+            PipelineFilter::Sentence(Box::new(KyteaWsConstFilter::new(char_type)))
+        }
+    };
+    Ok(filter)
+}
+
+/// Builder of a [`Tokenizer`].
+pub struct PipelineBuilder {
+    predictor: Predictor,
+    string_filters: Vec<Box<dyn StringFilter<String>>>,
+    sentence_filters: Vec<Box<dyn SentenceFilter>>,
+    tag_filters: Vec<Box<dyn SentenceFilter>>,
+    fill_tags: bool,
+}
+
+impl PipelineBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `predictor` - A predictor.
+    ///
+    /// # Returns
+    ///
+    /// A new PipelineBuilder.
+    pub fn new(predictor: Predictor) -> Self {
+        Self {
+            predictor,
+            string_filters: vec![],
+            sentence_filters: vec![],
+            tag_filters: vec![],
+            fill_tags: false,
+        }
+    }
+
+    /// Adds a string filter applied to the input before prediction.
+    ///
+    /// Filters are applied in the order of addition.
+    pub fn string_filter<F>(mut self, filter: F) -> Self
+    where
+        F: StringFilter<String> + 'static,
+    {
+        self.string_filters.push(Box::new(filter));
+        self
+    }
+
+    /// Adds a sentence filter modifying boundaries after prediction.
+    ///
+    /// Filters are applied in the order of addition.
+    pub fn sentence_filter<F>(mut self, filter: F) -> Self
+    where
+        F: SentenceFilter + 'static,
+    {
+        self.sentence_filters.push(Box::new(filter));
+        self
+    }
+
+    /// Adds filters specified by names, e.g., [`Model::pipeline()`](vaporetto::Model::pipeline).
+    ///
+    /// String filters and sentence filters are added in the order of the names. See
+    /// [`parse_filter_name()`] for the available names.
+    ///
+    /// # Errors
+    ///
+    /// If a name is not recognized, an error variant will be returned.
+    pub fn spec<I, S>(mut self, names: I) -> core::result::Result<Self, UnknownFilterError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for name in names {
+            match parse_filter_name(name.as_ref())? {
+                PipelineFilter::String(filter) => self.string_filters.push(filter),
+                PipelineFilter::Sentence(filter) => self.sentence_filters.push(filter),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds a sentence filter applied after filling tags, e.g., a
+    /// [`PatternMatchTagger`](crate::sentence_filters::PatternMatchTagger).
+    ///
+    /// Filters are applied in the order of addition. This also enables
+    /// [`fill_tags()`](Self::fill_tags).
+    pub fn tag_filter<F>(mut self, filter: F) -> Self
+    where
+        F: SentenceFilter + 'static,
+    {
+        self.tag_filters.push(Box::new(filter));
+        self.fill_tags = true;
+        self
+    }
+
+    /// Fills tags after the sentence filters are applied.
+    ///
+    /// The predictor must be created with `predict_tags = true` to predict tags.
+    pub const fn fill_tags(mut self, fill_tags: bool) -> Self {
+        self.fill_tags = fill_tags;
+        self
+    }
+
+    /// Builds the tokenizer.
+    pub fn build(self) -> Tokenizer {
+        Tokenizer {
+            predictor: self.predictor,
+            string_filters: self.string_filters,
+            sentence_filters: self.sentence_filters,
+            tag_filters: self.tag_filters,
+            fill_tags: self.fill_tags,
+        }
+    }
+}
+
+/// Tokenizer applying filters and prediction in order, created by [`PipelineBuilder`].
+pub struct Tokenizer {
+    predictor: Predictor,
+    string_filters: Vec<Box<dyn StringFilter<String>>>,
+    sentence_filters: Vec<Box<dyn SentenceFilter>>,
+    tag_filters: Vec<Box<dyn SentenceFilter>>,
+    fill_tags: bool,
+}
+
+impl Tokenizer {
+    /// Returns the predictor.
+    pub const fn predictor(&self) -> &Predictor {
+        &self.predictor
+    }
+
+    /// Tokenizes a text.
+    ///
+    /// If the string filters keep the positions of characters, e.g.,
+    /// [`KyteaFullwidthFilter`](crate::string_filters::KyteaFullwidthFilter), the returned
+    /// sentence holds the original text. Otherwise, it holds the filtered text. Boundary scores are
+    /// available only in the latter case.
+    ///
+    /// # Errors
+    ///
+    /// If the text is empty, an error variant will be returned.
+    pub fn tokenize(&self, text: &str) -> Result<Sentence<'static, '_>> {
+        let (filtered, offsets) = self.filter_with_offsets(text);
+        let keep_original = filtered != text && offsets.is_identity();
+        let s = self.predict_filtered(filtered)?;
+        if !keep_original {
+            return Ok(s);
+        }
+        let mut s_orig = Sentence::from_raw(text.to_string())?;
+        s_orig.boundaries_mut().copy_from_slice(s.boundaries());
+        s_orig.reset_tags(s.n_tags());
+        s_orig.tags_mut().clone_from_slice(s.tags());
+        Ok(s_orig)
+    }
+
+    /// Tokenizes a text and returns the sentence holding the original text.
+    ///
+    /// Unlike [`Tokenizer::tokenize()`], the result is transferred onto the original text by
+    /// [`Sentence::project_boundaries()`] even if the string filters change the number of
+    /// characters. Boundary scores are not available.
+    ///
+    /// # Errors
+    ///
+    /// If the text is empty, an error variant will be returned.
+    pub fn tokenize_original(&self, text: &str) -> Result<Sentence<'static, '_>> {
+        let (filtered, offsets) = self.filter_with_offsets(text);
+        let s = self.predict_filtered(filtered)?;
+        s.project_boundaries(text.to_string(), &offsets)
+    }
+
+    fn filter_with_offsets(&self, text: &str) -> (String, OffsetMap) {
+        let init = (text.to_string(), OffsetMap::identity(text.chars().count()));
+        self.string_filters
+            .iter()
+            .fold(init, |(s, offsets), filter| {
+                let (s, next) = filter.filter_with_offsets(s);
+                (s, offsets.compose(&next))
+            })
+    }
+
+    fn predict_filtered(&self, filtered: String) -> Result<Sentence<'static, '_>> {
+        let mut s = Sentence::from_raw(filtered)?;
+        self.predictor.predict(&mut s);
+        self.sentence_filters
+            .iter()
+            .for_each(|filter| filter.filter(&mut s));
+        if self.fill_tags {
+            s.fill_tags();
+            self.tag_filters
+                .iter()
+                .for_each(|filter| filter.filter(&mut s));
+        }
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vaporetto::{CharacterType, Model};
+
+    use crate::sentence_filters::KyteaWsConstFilter;
+    use crate::string_filters::KyteaFullwidthFilter;
+
+    fn create_predictor(predict_tags: bool) -> Predictor {
+        let (model, _) = Model::read_slice(include_bytes!("../../resources/model.bin")).unwrap();
+        Predictor::new(model, predict_tags).unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_original_text() {
+        let tokenizer = PipelineBuilder::new(create_predictor(false))
+            .string_filter(KyteaFullwidthFilter)
+            .sentence_filter(KyteaWsConstFilter::new(CharacterType::Kanji))
+            .build();
+        let s = tokenizer.tokenize("まぁ社長は火星猫だ!").unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長 は 火星猫 だ !", buf);
+
+        assert!(tokenizer.tokenize("").is_err());
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_tokenize_original() {
+        let tokenizer = PipelineBuilder::new(create_predictor(false))
+            .string_filter(NfkcFilter)
+            .string_filter(KyteaFullwidthFilter)
+            .build();
+        let s = tokenizer
+            .tokenize_original("まぁ社長は火星猫だ\u{337f}")
+            .unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長 は 火星 猫 だ \u{337f}", buf);
+        assert!(s.boundary_scores().is_empty());
+
+        assert!(tokenizer.tokenize_original("").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_tags() {
+        let tokenizer = PipelineBuilder::new(create_predictor(true))
+            .fill_tags(true)
+            .build();
+        let s = tokenizer.tokenize("まぁ社長は火星猫だ").unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!(
+            "まぁ/名詞/マー 社長/名詞/シャチョー は/助詞/ワ 火星/名詞/カセー 猫/名詞/ネコ だ/助動詞/ダ",
+            buf,
+        );
+        assert!(!s.boundary_scores().is_empty());
+    }
+
+    #[test]
+    fn test_spec() {
+        let tokenizer = PipelineBuilder::new(create_predictor(false))
+            .spec(["kytea-fullwidth", "wsconst:K"])
+            .unwrap()
+            .build();
+        let s = tokenizer.tokenize("まぁ社長は火星猫だ!").unwrap();
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長 は 火星猫 だ !", buf);
+
+        let err = PipelineBuilder::new(create_predictor(false))
+            .spec(["wsconst:K", "wsconst:X"])
+            .err()
+            .unwrap();
+        assert_eq!("wsconst:X", err.name());
+    }
+}
@@ -0,0 +1,16 @@
+//! Commonly used items.
+//!
+//! ```
+//! use vaporetto_rules::prelude::*;
+//!
+//! let mut s = vaporetto::Sentence::from_raw("火星猫").unwrap();
+//! ConcatGraphemeClustersFilter.filter(&mut s);
+//! ```
+
+pub use crate::chunker::NounPhraseChunker;
+pub use crate::pipeline::PipelineBuilder;
+pub use crate::sentence_filters::{
+    ConcatGraphemeClustersFilter, KyteaWsConstFilter, SentenceSplitter,
+};
+pub use crate::string_filters::KyteaFullwidthFilter;
+pub use crate::{SentenceFilter, StringFilter};
@@ -2,10 +2,24 @@
 
 mod concat_grapheme_clusters;
 mod kytea_wsconst;
+mod number_concatenator;
+mod pattern_concatenator;
 mod pattern_match_tagger;
+#[cfg(feature = "regex")]
+mod regex_boundaries;
+mod resolve_unknown_boundaries;
+mod sentence_splitter;
 mod split_linebreaks;
+mod uncertain_boundaries;
 
 pub use concat_grapheme_clusters::ConcatGraphemeClustersFilter;
 pub use kytea_wsconst::KyteaWsConstFilter;
+pub use number_concatenator::NumberConcatenator;
+pub use pattern_concatenator::PatternConcatenator;
 pub use pattern_match_tagger::PatternMatchTagger;
+#[cfg(feature = "regex")]
+pub use regex_boundaries::{RegexJoinFilter, RegexSplitFilter};
+pub use resolve_unknown_boundaries::ResolveUnknownBoundariesFilter;
+pub use sentence_splitter::{SentenceSplitter, SentenceSplitterBuilder, SplitSentences};
 pub use split_linebreaks::SplitLinebreaksFilter;
+pub use uncertain_boundaries::UncertainBoundariesFilter;
@@ -4,6 +4,24 @@ use vaporetto::{CharacterBoundary, Sentence};
 use crate::SentenceFilter;
 
 /// Grapheme cluster concatenator.
+///
+/// This filter removes boundaries inside extended grapheme clusters, e.g., emoji ZWJ sequences,
+/// emoji with skin tone modifiers or variation selectors, flags, and characters followed by
+/// combining marks, so that no token contains a part of a cluster. Boundaries between clusters
+/// are kept.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::{sentence_filters::ConcatGraphemeClustersFilter, SentenceFilter};
+///
+/// let mut s = Sentence::from_tokenized("猫 \u{1f408} \u{200d} \u{2b1b} だ").unwrap();
+/// ConcatGraphemeClustersFilter.filter(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("猫 \u{1f408}\u{200d}\u{2b1b} だ", buf);
+/// ```
 #[derive(Clone, Default)]
 pub struct ConcatGraphemeClustersFilter;
 
@@ -80,4 +98,54 @@ mod tests {
         s.write_tokenized_text(&mut buf);
         assert_eq!("これ は 手 \u{1f44f}\u{1f3fd} で す", buf);
     }
+
+    #[test]
+    fn test_concat_grapheme_clusters_variation_selector() {
+        let mut s = Sentence::from_tokenized("\u{2764} \u{fe0f} \u{845b} \u{e0100} 城").unwrap();
+        let filter = ConcatGraphemeClustersFilter;
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("\u{2764}\u{fe0f} \u{845b}\u{e0100} 城", buf);
+    }
+
+    #[test]
+    fn test_concat_grapheme_clusters_flags() {
+        let mut s = Sentence::from_tokenized("\u{1f1ef} \u{1f1f5} \u{1f1fa} \u{1f1f8}").unwrap();
+        let filter = ConcatGraphemeClustersFilter;
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("\u{1f1ef}\u{1f1f5} \u{1f1fa}\u{1f1f8}", buf);
+    }
+
+    #[test]
+    fn test_concat_grapheme_clusters_dakuten() {
+        let mut s = Sentence::from_tokenized("カ \u{3099} ス ト").unwrap();
+        let filter = ConcatGraphemeClustersFilter;
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("カ\u{3099} ス ト", buf);
+    }
+
+    #[test]
+    fn test_concat_grapheme_clusters_zwnj() {
+        let mut s = Sentence::from_tokenized("a \u{200C} b").unwrap();
+        let filter = ConcatGraphemeClustersFilter;
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("a\u{200C} b", buf);
+    }
+
+    #[test]
+    fn test_concat_grapheme_clusters_bidi() {
+        let mut s = Sentence::from_tokenized("\u{202E} a b \u{202C}").unwrap();
+        let filter = ConcatGraphemeClustersFilter;
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("\u{202E} a b \u{202C}", buf);
+    }
 }
@@ -0,0 +1,289 @@
+use core::ops::Range;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use vaporetto::{CharacterBoundary, Sentence};
+
+use super::pattern_concatenator::to_halfwidth;
+use crate::SentenceFilter;
+
+const DEFAULT_COUNTERS: &[&str] = &["円", "%", "個", "人", "回", "件", "歳"];
+
+const DATE_UNITS: [char; 3] = ['年', '月', '日'];
+
+/// Returns the end of the run of ASCII digits from `start`.
+fn scan_digits(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .map_or(chars.len(), |i| start + i)
+}
+
+/// Filter that concatenates numbers, counters following numbers, and dates into single tokens.
+///
+/// The following patterns are concatenated:
+///
+/// * Numbers with thousands separators and decimal points, e.g., `1,234.5`.
+/// * Numbers followed by counters, e.g., `1234円`.
+/// * Dates, e.g., `2024年1月15日`, `1月15日`, `2024/01/15`, and `2024-01-15`.
+///
+/// Fullwidth ASCII characters are treated as halfwidth ones, and `。` is treated as `.`.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::{sentence_filters::NumberConcatenator, SentenceFilter};
+///
+/// let mut s = Sentence::from_tokenized(
+///     "2024 年 1 月 15 日 に 1 , 234 . 5 円 を 払っ た",
+/// )
+/// .unwrap();
+/// NumberConcatenator::new().filter(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("2024年1月15日 に 1,234.5円 を 払っ た", buf);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NumberConcatenator {
+    numbers: bool,
+    dates: bool,
+    counters: Vec<String>,
+}
+
+impl Default for NumberConcatenator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NumberConcatenator {
+    /// Creates a new NumberConcatenator concatenating numbers, dates, and the default counters
+    /// (`円`, `%`, `個`, `人`, `回`, `件`, and `歳`).
+    pub fn new() -> Self {
+        Self {
+            numbers: true,
+            dates: true,
+            counters: DEFAULT_COUNTERS.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// Concatenates digits, thousands separators, and decimal points.
+    pub const fn numbers(mut self, yes: bool) -> Self {
+        self.numbers = yes;
+        self
+    }
+
+    /// Concatenates dates.
+    pub const fn dates(mut self, yes: bool) -> Self {
+        self.dates = yes;
+        self
+    }
+
+    /// Replaces counters concatenated with preceding numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `counters` - Counters, e.g., `円` and `ドル`. Fullwidth ASCII characters are treated as
+    ///   halfwidth ones.
+    ///
+    /// # Returns
+    ///
+    /// The filter with the given counters.
+    pub fn counters<I, S>(mut self, counters: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.counters = counters
+            .into_iter()
+            .map(|c| c.as_ref().chars().map(to_halfwidth).collect())
+            .filter(|c: &String| !c.is_empty())
+            .collect();
+        self
+    }
+
+    fn match_number(chars: &[char], start: usize) -> Option<usize> {
+        let mut end = scan_digits(chars, start);
+        if end == start {
+            return None;
+        }
+        // Thousands separators are followed by exactly three digits.
+        while chars.get(end) == Some(&',') && scan_digits(chars, end + 1) == end + 4 {
+            end += 4;
+        }
+        if chars.get(end) == Some(&'.') {
+            let frac_end = scan_digits(chars, end + 1);
+            if frac_end > end + 1 {
+                end = frac_end;
+            }
+        }
+        Some(end)
+    }
+
+    fn match_date(chars: &[char], start: usize) -> Option<usize> {
+        // Numeric dates, e.g., 2024/01/15
+        let end1 = scan_digits(chars, start);
+        if end1 != start {
+            if let Some(&sep) = chars.get(end1).filter(|&&c| c == '/' || c == '-') {
+                let end2 = scan_digits(chars, end1 + 1);
+                if end2 > end1 + 1 && chars.get(end2) == Some(&sep) {
+                    let end3 = scan_digits(chars, end2 + 1);
+                    if end3 > end2 + 1 {
+                        return Some(end3);
+                    }
+                }
+            }
+        }
+        // Dates with units, e.g., 2024年1月15日
+        let mut pos = start;
+        let mut n_units = 0;
+        let mut next_unit = 0;
+        loop {
+            let end = scan_digits(chars, pos);
+            let Some(unit) = chars
+                .get(end)
+                .filter(|_| end != pos)
+                .and_then(|c| DATE_UNITS.iter().position(|u| u == c))
+            else {
+                break;
+            };
+            // Units are consecutive in the order of year, month, and day.
+            if unit < next_unit || (n_units != 0 && unit != next_unit) {
+                break;
+            }
+            pos = end + 1;
+            n_units += 1;
+            next_unit = unit + 1;
+        }
+        (n_units >= 2).then_some(pos)
+    }
+
+    fn match_counter(&self, chars: &[char], start: usize) -> Option<usize> {
+        self.counters
+            .iter()
+            .filter(|counter| {
+                let mut it = chars[start..].iter();
+                counter.chars().all(|c| it.next() == Some(&c))
+            })
+            .map(|counter| start + counter.chars().count())
+            .max()
+    }
+
+    fn find_spans(&self, chars: &[char]) -> Vec<Range<usize>> {
+        let mut spans = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let mut end = if self.dates {
+                Self::match_date(chars, i)
+            } else {
+                None
+            };
+            if end.is_none() {
+                end = Self::match_number(chars, i).and_then(|end| {
+                    self.match_counter(chars, end)
+                        .or_else(|| self.numbers.then_some(end))
+                });
+            }
+            if let Some(end) = end {
+                spans.push(i..end);
+                i = end;
+            } else {
+                // Numbers do not start in the middle of digits.
+                i = scan_digits(chars, i).max(i + 1);
+            }
+        }
+        spans
+    }
+}
+
+impl SentenceFilter for NumberConcatenator {
+    fn filter(&self, sentence: &mut Sentence) {
+        let chars: Vec<_> = sentence.as_raw_text().chars().map(to_halfwidth).collect();
+        let spans = self.find_spans(&chars);
+        let boundaries = sentence.boundaries_mut();
+        for span in spans {
+            boundaries[span.start..span.end - 1].fill(CharacterBoundary::NotWordBoundary);
+            if let Some(b) = span
+                .start
+                .checked_sub(1)
+                .and_then(|i| boundaries.get_mut(i))
+            {
+                *b = CharacterBoundary::WordBoundary;
+            }
+            if let Some(b) = boundaries.get_mut(span.end - 1) {
+                *b = CharacterBoundary::WordBoundary;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn concat(filter: &NumberConcatenator, text: &str) -> String {
+        // Splits all characters.
+        let mut s = Sentence::from_raw(text).unwrap();
+        s.boundaries_mut().fill(CharacterBoundary::WordBoundary);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_concat_numbers() {
+        let filter = NumberConcatenator::new();
+        assert_eq!(
+            "1,234,567.89 と 1 , 2 , 3 と 3 . と 12.5%",
+            concat(&filter, "1,234,567.89と1,2,3と3.と12.5%"),
+        );
+        assert_eq!(
+            "1 2 3 4 と 1234円",
+            concat(&NumberConcatenator::new().numbers(false), "1234と1234円"),
+        );
+    }
+
+    #[test]
+    fn test_concat_counters() {
+        let filter = NumberConcatenator::new().counters(["ドル", "ドル札", "％"]);
+        assert_eq!(
+            "100ドル札 と 5ドル と 3 円",
+            concat(&filter, "100ドル札と5ドルと3円"),
+        );
+        assert_eq!(
+            "3 円",
+            concat(&NumberConcatenator::new().counters([""; 0]), "3円"),
+        );
+    }
+
+    #[test]
+    fn test_concat_dates() {
+        let filter = NumberConcatenator::new();
+        assert_eq!(
+            "2024年1月15日 、 1月15日 、 2024\\/01\\/15 、 2024-01-15 、 2024 年 、 1 日 2 月",
+            concat(
+                &filter,
+                "2024年1月15日、1月15日、2024/01/15、2024-01-15、2024年、1日2月"
+            ),
+        );
+        assert_eq!(
+            "1 月 1 5 日",
+            concat(
+                &NumberConcatenator::new().dates(false).numbers(false),
+                "1月15日"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_concat_fullwidth() {
+        let filter = NumberConcatenator::new();
+        assert_eq!(
+            "１，２３４円 と ３。５ 。",
+            concat(&filter, "１，２３４円と３。５。"),
+        );
+    }
+}
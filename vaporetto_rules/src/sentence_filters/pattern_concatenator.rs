@@ -0,0 +1,303 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use vaporetto::{CharacterBoundary, Sentence};
+
+use crate::SentenceFilter;
+
+const URL_PREFIXES: &[&str] = &["http://", "https://", "ftp://", "www."];
+
+/// Converts fullwidth ASCII characters into halfwidth ones, so that patterns also match texts
+/// normalized by [`KyteaFullwidthFilter`](crate::string_filters::KyteaFullwidthFilter).
+pub fn to_halfwidth(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(u32::from(c) - 0xFEE0).unwrap(),
+        '。' => '.',
+        '−' => '-',
+        '”' => '"',
+        '’' => '\'',
+        _ => c,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+const fn is_ascii_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_url_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c)
+}
+
+fn is_email_local_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._%+-".contains(c)
+}
+
+const fn is_domain_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '-'
+}
+
+/// Returns the end of the run of characters satisfying `f` from `start`.
+fn scan(chars: &[char], start: usize, f: impl Fn(char) -> bool) -> usize {
+    chars[start..]
+        .iter()
+        .position(|&c| !f(c))
+        .map_or(chars.len(), |i| start + i)
+}
+
+/// Filter that concatenates URLs, email addresses, hashtags, and mentions into single tokens.
+///
+/// Fullwidth ASCII characters are treated as halfwidth ones, and `。` is treated as `.`.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::{sentence_filters::PatternConcatenator, SentenceFilter};
+///
+/// let mut s = Sentence::from_tokenized(
+///     "@ vaporetto_ja さん から info @ example . com に 連絡 # 形態素 解析 。",
+/// )
+/// .unwrap();
+/// PatternConcatenator::new().filter(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!(
+///     "@vaporetto_ja さん から info@example.com に 連絡 #形態素解析 。",
+///     buf,
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct PatternConcatenator {
+    urls: bool,
+    emails: bool,
+    hashtags: bool,
+    mentions: bool,
+}
+
+impl Default for PatternConcatenator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PatternConcatenator {
+    /// Creates a new PatternConcatenator concatenating all kinds of patterns.
+    pub const fn new() -> Self {
+        Self {
+            urls: true,
+            emails: true,
+            hashtags: true,
+            mentions: true,
+        }
+    }
+
+    /// Concatenates URLs starting with `http://`, `https://`, `ftp://`, or `www.`.
+    pub const fn urls(mut self, yes: bool) -> Self {
+        self.urls = yes;
+        self
+    }
+
+    /// Concatenates email addresses.
+    pub const fn emails(mut self, yes: bool) -> Self {
+        self.emails = yes;
+        self
+    }
+
+    /// Concatenates hashtags, e.g., `#火星猫`.
+    pub const fn hashtags(mut self, yes: bool) -> Self {
+        self.hashtags = yes;
+        self
+    }
+
+    /// Concatenates mentions, e.g., `@vaporetto`.
+    pub const fn mentions(mut self, yes: bool) -> Self {
+        self.mentions = yes;
+        self
+    }
+
+    fn match_url(chars: &[char], start: usize) -> Option<usize> {
+        let prefix = URL_PREFIXES.iter().find(|prefix| {
+            let mut it = chars[start..].iter();
+            prefix
+                .chars()
+                .all(|p| it.next().is_some_and(|c| c.eq_ignore_ascii_case(&p)))
+        })?;
+        let body_start = start + prefix.len();
+        let mut end = scan(chars, body_start, is_url_char);
+        // Trailing punctuation and unbalanced parentheses are not parts of the URL.
+        while end > body_start {
+            let c = chars[end - 1];
+            let n_open = chars[start..end].iter().filter(|&&c| c == '(').count();
+            let n_close = chars[start..end].iter().filter(|&&c| c == ')').count();
+            if ".,!?:;'\"".contains(c) || (c == ')' && n_close > n_open) {
+                end -= 1;
+            } else {
+                break;
+            }
+        }
+        (end > body_start).then_some(end)
+    }
+
+    fn match_email(chars: &[char], start: usize) -> Option<usize> {
+        let at = scan(chars, start, is_email_local_char);
+        if at == start || chars.get(at) != Some(&'@') {
+            return None;
+        }
+        let mut end = scan(chars, at + 1, is_domain_char);
+        while end > at + 1 && matches!(chars[end - 1], '.' | '-') {
+            end -= 1;
+        }
+        let domain = &chars[at + 1..end];
+        let tld_start = domain.iter().rposition(|&c| c == '.')? + 1;
+        let tld = &domain[tld_start..];
+        (tld_start > 1 && tld.len() >= 2 && tld.iter().all(char::is_ascii_alphabetic))
+            .then_some(end)
+    }
+
+    fn match_hashtag(chars: &[char], start: usize) -> Option<usize> {
+        if chars[start] != '#' {
+            return None;
+        }
+        let end = scan(chars, start + 1, is_word_char);
+        // Numbers such as `#1` are not hashtags.
+        chars[start + 1..end]
+            .iter()
+            .any(|c| !c.is_ascii_digit())
+            .then_some(end)
+    }
+
+    fn match_mention(chars: &[char], start: usize) -> Option<usize> {
+        if chars[start] != '@' {
+            return None;
+        }
+        let end = scan(chars, start + 1, is_ascii_word_char);
+        (end > start + 1).then_some(end)
+    }
+
+    fn find_spans(&self, chars: &[char]) -> Vec<Range<usize>> {
+        let mut spans = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            // Patterns do not start in the middle of ASCII words.
+            let prev = i.checked_sub(1).map(|j| chars[j]);
+            let after_word = prev.is_some_and(is_ascii_word_char);
+            let mut end = if self.urls && !after_word {
+                Self::match_url(chars, i)
+            } else {
+                None
+            };
+            if end.is_none() && self.emails && !prev.is_some_and(is_email_local_char) {
+                end = Self::match_email(chars, i);
+            }
+            if end.is_none() && self.mentions && !after_word {
+                end = Self::match_mention(chars, i);
+            }
+            if end.is_none() && self.hashtags && !after_word {
+                end = Self::match_hashtag(chars, i);
+            }
+            if let Some(end) = end {
+                spans.push(i..end);
+                i = end;
+            } else {
+                i += 1;
+            }
+        }
+        spans
+    }
+}
+
+impl SentenceFilter for PatternConcatenator {
+    fn filter(&self, sentence: &mut Sentence) {
+        let chars: Vec<_> = sentence.as_raw_text().chars().map(to_halfwidth).collect();
+        let spans = self.find_spans(&chars);
+        let boundaries = sentence.boundaries_mut();
+        for span in spans {
+            boundaries[span.start..span.end - 1].fill(CharacterBoundary::NotWordBoundary);
+            if let Some(b) = span
+                .start
+                .checked_sub(1)
+                .and_then(|i| boundaries.get_mut(i))
+            {
+                *b = CharacterBoundary::WordBoundary;
+            }
+            if let Some(b) = boundaries.get_mut(span.end - 1) {
+                *b = CharacterBoundary::WordBoundary;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    fn concat(filter: &PatternConcatenator, text: &str) -> String {
+        // Splits all characters.
+        let mut s = Sentence::from_raw(text).unwrap();
+        s.boundaries_mut().fill(CharacterBoundary::WordBoundary);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_concat_urls() {
+        let filter = PatternConcatenator::new();
+        assert_eq!(
+            "( HTTPS:\\/\\/example.com\\/a_(b) ) を 見 る 。",
+            concat(&filter, "(HTTPS://example.com/a_(b))を見る。"),
+        );
+        assert_eq!(
+            "詳 細 は www.example.com .",
+            concat(&filter, "詳細はwww.example.com."),
+        );
+        assert_eq!("x w w w . y", concat(&filter, "xwww.y"));
+    }
+
+    #[test]
+    fn test_concat_emails() {
+        let filter = PatternConcatenator::new();
+        assert_eq!(
+            "連 絡 は info+ja@mail.example.com へ",
+            concat(&filter, "連絡はinfo+ja@mail.example.comへ"),
+        );
+        assert_eq!("a @ b . c", concat(&filter, "a@b.c"));
+    }
+
+    #[test]
+    fn test_concat_hashtags_and_mentions() {
+        let filter = PatternConcatenator::new();
+        assert_eq!(
+            "@user_1 、 #火星猫 、 # 1 、 C # 、 ( @ )",
+            concat(&filter, "@user_1、#火星猫、#1、C#、(@)"),
+        );
+        assert_eq!(
+            "@ u s e r 、 #火星猫",
+            concat(
+                &PatternConcatenator::new().mentions(false),
+                "@user、#火星猫"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_concat_fullwidth() {
+        let filter = PatternConcatenator::new();
+        assert_eq!(
+            "＠ｕｓｅｒ さ ん ｈｔｔｐ：／／ｅｘ．ｊｐ",
+            concat(&filter, "＠ｕｓｅｒさんｈｔｔｐ：／／ｅｘ．ｊｐ"),
+        );
+        assert_eq!(
+            "ｉｎｆｏ＠ｅｘ。ｊｐ 。",
+            concat(&filter, "ｉｎｆｏ＠ｅｘ。ｊｐ。"),
+        );
+    }
+}
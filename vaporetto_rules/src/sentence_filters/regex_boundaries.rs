@@ -0,0 +1,178 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use regex::Regex;
+use vaporetto::{CharacterBoundary, Sentence};
+
+use crate::SentenceFilter;
+
+/// Returns character ranges of matches of the pattern in the sentence.
+fn match_ranges(pattern: &Regex, sentence: &Sentence) -> Vec<Range<usize>> {
+    let text = sentence.as_raw_text();
+    let mut str_to_char_pos = vec![0; text.len() + 1];
+    for (i, (pos, _)) in text.char_indices().enumerate() {
+        str_to_char_pos[pos] = i;
+    }
+    str_to_char_pos[text.len()] = text.chars().count();
+    pattern
+        .find_iter(text)
+        .map(|m| str_to_char_pos[m.start()]..str_to_char_pos[m.end()])
+        .collect()
+}
+
+/// Places word boundaries at both ends of the range unless they are the ends of the sentence.
+fn split_range(boundaries: &mut [CharacterBoundary], range: &Range<usize>) {
+    for pos in [range.start, range.end] {
+        if let Some(b) = pos.checked_sub(1).and_then(|i| boundaries.get_mut(i)) {
+            *b = CharacterBoundary::WordBoundary;
+        }
+    }
+}
+
+/// Filter that forces each match of a regular expression to be a single token.
+///
+/// This is useful for URLs, email addresses, and product codes that the model tends to split.
+///
+/// # Examples
+///
+/// ```
+/// use regex::Regex;
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::{sentence_filters::RegexJoinFilter, SentenceFilter};
+///
+/// let mut s = Sentence::from_tokenized("連絡 は info @ example . com へ").unwrap();
+/// let filter = RegexJoinFilter::new(Regex::new(r"[0-9A-Za-z.]+@[0-9A-Za-z.]+").unwrap());
+/// filter.filter(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("連絡 は info@example.com へ", buf);
+/// ```
+#[derive(Clone)]
+pub struct RegexJoinFilter {
+    pattern: Regex,
+}
+
+impl RegexJoinFilter {
+    /// Creates a new RegexJoinFilter.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - A regular expression. Empty matches are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A new RegexJoinFilter.
+    pub const fn new(pattern: Regex) -> Self {
+        Self { pattern }
+    }
+}
+
+impl SentenceFilter for RegexJoinFilter {
+    fn filter(&self, sentence: &mut Sentence) {
+        let ranges = match_ranges(&self.pattern, sentence);
+        let boundaries = sentence.boundaries_mut();
+        for range in ranges {
+            if range.is_empty() {
+                continue;
+            }
+            boundaries[range.start..range.end - 1].fill(CharacterBoundary::NotWordBoundary);
+            split_range(boundaries, &range);
+        }
+    }
+}
+
+/// Filter that places word boundaries at both ends of each match of a regular expression.
+///
+/// Boundaries inside matches are kept. An empty match places a word boundary at its position.
+///
+/// # Examples
+///
+/// ```
+/// use regex::Regex;
+/// use vaporetto::Sentence;
+/// use vaporetto_rules::{sentence_filters::RegexSplitFilter, SentenceFilter};
+///
+/// let mut s = Sentence::from_tokenized("型番ABC-123です").unwrap();
+/// let filter = RegexSplitFilter::new(Regex::new(r"[A-Z]+-[0-9]+").unwrap());
+/// filter.filter(&mut s);
+/// let mut buf = String::new();
+/// s.write_tokenized_text(&mut buf);
+/// assert_eq!("型番 ABC-123 です", buf);
+/// ```
+#[derive(Clone)]
+pub struct RegexSplitFilter {
+    pattern: Regex,
+}
+
+impl RegexSplitFilter {
+    /// Creates a new RegexSplitFilter.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - A regular expression.
+    ///
+    /// # Returns
+    ///
+    /// A new RegexSplitFilter.
+    pub const fn new(pattern: Regex) -> Self {
+        Self { pattern }
+    }
+}
+
+impl SentenceFilter for RegexSplitFilter {
+    fn filter(&self, sentence: &mut Sentence) {
+        let ranges = match_ranges(&self.pattern, sentence);
+        let boundaries = sentence.boundaries_mut();
+        for range in ranges {
+            split_range(boundaries, &range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    #[test]
+    fn test_regex_join() {
+        let mut s = Sentence::from_tokenized("型番 ABC - 123 を 送る").unwrap();
+        let filter = RegexJoinFilter::new(Regex::new(r"[A-Z]+-[0-9]+|x*").unwrap());
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("型番 ABC-123 を 送る", buf);
+    }
+
+    #[test]
+    fn test_regex_join_middle() {
+        let mut s = Sentence::from_tokenized("型番A BC1 23です").unwrap();
+        let filter = RegexJoinFilter::new(Regex::new(r"[A-Z]+[0-9]+").unwrap());
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("型番 ABC123 です", buf);
+    }
+
+    #[test]
+    fn test_regex_split() {
+        let mut s = Sentence::from_tokenized("第3章の2節").unwrap();
+        let filter = RegexSplitFilter::new(Regex::new(r"[0-9]|の").unwrap());
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("第 3 章 の 2 節", buf);
+    }
+
+    #[test]
+    fn test_regex_split_empty_match() {
+        let mut s = Sentence::from_tokenized("AB+cd").unwrap();
+        let filter = RegexSplitFilter::new(Regex::new(r"[a-z]*\b").unwrap());
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("AB + cd", buf);
+    }
+}
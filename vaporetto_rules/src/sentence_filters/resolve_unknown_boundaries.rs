@@ -0,0 +1,91 @@
+use alloc::vec::Vec;
+
+use vaporetto::{CharacterBoundary, Sentence};
+
+use crate::SentenceFilter;
+
+/// Filter that resolves unknown boundaries, e.g., boundaries in the margin band of
+/// [`Predictor::set_margin_band()`](vaporetto::Predictor::set_margin_band).
+///
+/// The inner filter is applied to the sentence, and then known boundaries are restored to the
+/// values before applying it. Boundaries that are still unknown are set to the default value.
+#[derive(Clone)]
+pub struct ResolveUnknownBoundariesFilter<F> {
+    filter: F,
+    default: CharacterBoundary,
+}
+
+impl<F> ResolveUnknownBoundariesFilter<F> {
+    /// Creates a new ResolveUnknownBoundariesFilter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - A filter applied to unknown boundaries.
+    /// * `default` - A value set to boundaries not resolved by the inner filter.
+    ///
+    /// # Returns
+    ///
+    /// A new ResolveUnknownBoundariesFilter.
+    pub const fn new(filter: F, default: CharacterBoundary) -> Self {
+        Self { filter, default }
+    }
+}
+
+impl<F> SentenceFilter for ResolveUnknownBoundariesFilter<F>
+where
+    F: SentenceFilter,
+{
+    fn filter(&self, sentence: &mut Sentence) {
+        let known_boundaries: Vec<_> = sentence
+            .boundaries()
+            .iter()
+            .map(|&b| (b != CharacterBoundary::Unknown).then_some(b))
+            .collect();
+        self.filter.filter(sentence);
+        for (b, orig_b) in sentence.boundaries_mut().iter_mut().zip(known_boundaries) {
+            if let Some(orig_b) = orig_b {
+                *b = orig_b;
+            } else if *b == CharacterBoundary::Unknown {
+                *b = self.default;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    use vaporetto::CharacterType;
+
+    use crate::sentence_filters::KyteaWsConstFilter;
+
+    #[test]
+    fn test_resolve_unknown_boundaries() {
+        // 火|星|猫|だ
+        let mut s = Sentence::from_raw("火星猫だ").unwrap();
+        s.boundaries_mut().copy_from_slice(&[
+            CharacterBoundary::Unknown,
+            CharacterBoundary::WordBoundary,
+            CharacterBoundary::Unknown,
+        ]);
+        let filter = ResolveUnknownBoundariesFilter::new(
+            KyteaWsConstFilter::new(CharacterType::Kanji),
+            CharacterBoundary::WordBoundary,
+        );
+        filter.filter(&mut s);
+        assert_eq!(
+            &[
+                CharacterBoundary::NotWordBoundary,
+                CharacterBoundary::WordBoundary,
+                CharacterBoundary::WordBoundary,
+            ],
+            s.boundaries(),
+        );
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星 猫 だ", buf);
+    }
+}
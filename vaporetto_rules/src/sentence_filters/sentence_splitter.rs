@@ -0,0 +1,351 @@
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+use vaporetto::{CharacterBoundary, Sentence};
+
+use crate::SentenceFilter;
+
+const DEFAULT_TERMINATORS: &[char] = &['。', '！', '？', '!', '?'];
+const DEFAULT_QUOTE_PAIRS: &[(char, char)] = &[
+    ('「', '」'),
+    ('『', '』'),
+    ('（', '）'),
+    ('(', ')'),
+    ('【', '】'),
+];
+
+const fn is_line_break(c: char) -> bool {
+    c == '\n' || c == '\r'
+}
+
+/// Builder of [`SentenceSplitter`]s.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto_rules::sentence_filters::SentenceSplitterBuilder;
+///
+/// let mut builder = SentenceSplitterBuilder::new();
+/// builder.set_terminators(['。', '．']);
+/// builder.set_quote_pairs([('「', '」')]);
+/// builder.set_keep_delimiters(false);
+/// let splitter = builder.build();
+///
+/// let sentences: Vec<_> = splitter.split("「はい。」と答えた．次の文。").collect();
+/// assert_eq!(vec!["「はい。」と答えた", "次の文"], sentences);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SentenceSplitterBuilder {
+    terminators: Vec<char>,
+    quote_pairs: Vec<(char, char)>,
+    keep_delimiters: bool,
+    split_lines: bool,
+}
+
+impl Default for SentenceSplitterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SentenceSplitterBuilder {
+    /// Creates a new builder with the default settings.
+    ///
+    /// By default, sentences are terminated by `。！？!?`, terminators inside `「」『』（）()【】`
+    /// are ignored, delimiters are kept with the previous sentence, and line breaks are treated
+    /// as normal characters.
+    pub fn new() -> Self {
+        Self {
+            terminators: DEFAULT_TERMINATORS.to_vec(),
+            quote_pairs: DEFAULT_QUOTE_PAIRS.to_vec(),
+            keep_delimiters: true,
+            split_lines: false,
+        }
+    }
+
+    /// Sets characters that terminate sentences.
+    ///
+    /// A run of consecutive terminators, such as `！？`, is treated as one delimiter.
+    pub fn set_terminators<I>(&mut self, terminators: I)
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.terminators = terminators.into_iter().collect();
+    }
+
+    /// Sets pairs of opening and closing quotes.
+    ///
+    /// Terminators between an opening quote and the corresponding closing quote do not split
+    /// sentences. The opening and closing characters may be the same, such as `('"', '"')`.
+    pub fn set_quote_pairs<I>(&mut self, quote_pairs: I)
+    where
+        I: IntoIterator<Item = (char, char)>,
+    {
+        self.quote_pairs = quote_pairs.into_iter().collect();
+    }
+
+    /// Sets whether delimiters are kept at the end of the previous sentence.
+    ///
+    /// If `false`, delimiters are removed from the split sentences.
+    pub fn set_keep_delimiters(&mut self, keep_delimiters: bool) {
+        self.keep_delimiters = keep_delimiters;
+    }
+
+    /// Sets whether line breaks split sentences.
+    ///
+    /// If `true`, a run of `\n` and `\r` always terminates a sentence, even inside quotes, and is
+    /// removed from the split sentences regardless of [`Self::set_keep_delimiters()`]. Quotes
+    /// left open are closed at the line break. This is useful for raw documents whose sentences
+    /// may lack terminators at the ends of paragraphs.
+    pub fn set_split_lines(&mut self, split_lines: bool) {
+        self.split_lines = split_lines;
+    }
+
+    /// Builds a new [`SentenceSplitter`].
+    pub fn build(&self) -> SentenceSplitter {
+        SentenceSplitter {
+            terminators: self.terminators.iter().copied().collect(),
+            quote_pairs: self.quote_pairs.iter().copied().collect(),
+            keep_delimiters: self.keep_delimiters,
+            split_lines: self.split_lines,
+        }
+    }
+}
+
+/// Sentence splitter with quote awareness.
+///
+/// [`SentenceSplitter::split()`] splits a raw document into sentences. As a [`SentenceFilter`],
+/// this splitter inserts word boundaries around sentence delimiters so that no token crosses
+/// sentences.
+#[derive(Clone, Debug)]
+pub struct SentenceSplitter {
+    terminators: HashSet<char>,
+    quote_pairs: HashMap<char, char>,
+    keep_delimiters: bool,
+    split_lines: bool,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        SentenceSplitterBuilder::new().build()
+    }
+}
+
+impl SentenceSplitter {
+    /// Creates a new builder. See [`SentenceSplitterBuilder`].
+    pub fn builder() -> SentenceSplitterBuilder {
+        SentenceSplitterBuilder::new()
+    }
+
+    /// Splits the given text into sentences.
+    ///
+    /// Empty sentences are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto_rules::sentence_filters::SentenceSplitter;
+    ///
+    /// let splitter = SentenceSplitter::default();
+    /// let sentences: Vec<_> = splitter.split("「行くぞ！」と彼は言った。本当？はい").collect();
+    /// assert_eq!(vec!["「行くぞ！」と彼は言った。", "本当？", "はい"], sentences);
+    /// ```
+    pub const fn split<'a, 'b>(&'b self, text: &'a str) -> SplitSentences<'a, 'b> {
+        SplitSentences {
+            splitter: self,
+            text,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Finds the next delimiter and returns its byte range and whether it is kept.
+    fn find_delimiter(&self, text: &str, stack: &mut Vec<char>) -> Option<(usize, usize, bool)> {
+        let mut it = text.char_indices().peekable();
+        while let Some((i, c)) = it.next() {
+            if self.split_lines && is_line_break(c) {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = it.peek() {
+                    if !is_line_break(c) {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    it.next();
+                }
+                stack.clear();
+                return Some((i, end, false));
+            } else if stack.last() == Some(&c) {
+                stack.pop();
+            } else if let Some(&closing) = self.quote_pairs.get(&c) {
+                stack.push(closing);
+            } else if stack.is_empty() && self.terminators.contains(&c) {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = it.peek() {
+                    if !self.terminators.contains(&c) {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    it.next();
+                }
+                return Some((i, end, self.keep_delimiters));
+            }
+        }
+        None
+    }
+}
+
+impl SentenceFilter for SentenceSplitter {
+    fn filter(&self, sentence: &mut Sentence) {
+        let mut delimiters = vec![];
+        let mut stack = vec![];
+        let text = sentence.as_raw_text();
+        let mut offset = 0;
+        let mut char_offset = 0;
+        while let Some((start, end, keep)) = self.find_delimiter(&text[offset..], &mut stack) {
+            let start_char = char_offset + text[offset..offset + start].chars().count();
+            char_offset = start_char + text[offset + start..offset + end].chars().count();
+            delimiters.push((start_char, char_offset, keep));
+            offset += end;
+        }
+        for (start, end, keep) in delimiters {
+            if !keep && start != 0 {
+                sentence.boundaries_mut()[start - 1] = CharacterBoundary::WordBoundary;
+            }
+            if end <= sentence.boundaries().len() {
+                sentence.boundaries_mut()[end - 1] = CharacterBoundary::WordBoundary;
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`SentenceSplitter::split()`].
+pub struct SplitSentences<'a, 'b> {
+    splitter: &'b SentenceSplitter,
+    text: &'a str,
+    stack: Vec<char>,
+}
+
+impl<'a> Iterator for SplitSentences<'a, '_> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.text.is_empty() {
+            let (sentence, rest) = if let Some((start, end, keep)) =
+                self.splitter.find_delimiter(self.text, &mut self.stack)
+            {
+                let sentence_end = if keep { end } else { start };
+                (&self.text[..sentence_end], &self.text[end..])
+            } else {
+                (self.text, "")
+            };
+            self.text = rest;
+            if !sentence.is_empty() {
+                return Some(sentence);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    #[test]
+    fn test_split_default() {
+        let splitter = SentenceSplitter::default();
+        let sentences: Vec<_> = splitter.split("今日は晴れ。明日は雨！？本当に？").collect();
+        assert_eq!(vec!["今日は晴れ。", "明日は雨！？", "本当に？"], sentences);
+    }
+
+    #[test]
+    fn test_split_without_terminator() {
+        let splitter = SentenceSplitter::default();
+        let sentences: Vec<_> = splitter.split("終わらない文").collect();
+        assert_eq!(vec!["終わらない文"], sentences);
+
+        assert!(splitter.split("").next().is_none());
+    }
+
+    #[test]
+    fn test_split_nested_quotes() {
+        let splitter = SentenceSplitter::default();
+        let sentences: Vec<_> = splitter
+            .split("「彼は『行く。』と言った。」と聞いた。次。")
+            .collect();
+        assert_eq!(
+            vec!["「彼は『行く。』と言った。」と聞いた。", "次。"],
+            sentences
+        );
+    }
+
+    #[test]
+    fn test_split_symmetric_quotes() {
+        let mut builder = SentenceSplitterBuilder::new();
+        builder.set_terminators(['.', '?']);
+        builder.set_quote_pairs([('"', '"')]);
+        let splitter = builder.build();
+        let sentences: Vec<_> = splitter.split("He said \"Go.\" Why? OK.").collect();
+        assert_eq!(vec!["He said \"Go.\" Why?", " OK."], sentences);
+    }
+
+    #[test]
+    fn test_split_drop_delimiters() {
+        let mut builder = SentenceSplitterBuilder::new();
+        builder.set_keep_delimiters(false);
+        let splitter = builder.build();
+        let sentences: Vec<_> = splitter.split("。はい。！いいえ？").collect();
+        assert_eq!(vec!["はい", "いいえ"], sentences);
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let mut builder = SentenceSplitterBuilder::new();
+        builder.set_split_lines(true);
+        let splitter = builder.build();
+        let sentences: Vec<_> = splitter
+            .split("見出し\r\n\r\n本文です。「閉じない\n次の段落")
+            .collect();
+        assert_eq!(
+            vec!["見出し", "本文です。", "「閉じない", "次の段落"],
+            sentences
+        );
+
+        let sentences: Vec<_> = SentenceSplitter::default().split("見出し\n本文").collect();
+        assert_eq!(vec!["見出し\n本文"], sentences);
+    }
+
+    #[test]
+    fn test_filter_split_lines() {
+        let mut s = Sentence::from_tokenized("見出し\n\n本文です").unwrap();
+        let mut builder = SentenceSplitterBuilder::new();
+        builder.set_split_lines(true);
+        builder.build().filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("見出し \n\n 本文です", buf);
+    }
+
+    #[test]
+    fn test_filter_keep_delimiters() {
+        let mut s = Sentence::from_tokenized("今日は晴れ。明日").unwrap();
+        let splitter = SentenceSplitter::default();
+        splitter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("今日は晴れ。 明日", buf);
+    }
+
+    #[test]
+    fn test_filter_drop_delimiters() {
+        let mut s = Sentence::from_tokenized("「はい。」晴れ！？明日").unwrap();
+        let mut builder = SentenceSplitterBuilder::new();
+        builder.set_keep_delimiters(false);
+        let splitter = builder.build();
+        splitter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("「はい。」晴れ ！？ 明日", buf);
+    }
+}
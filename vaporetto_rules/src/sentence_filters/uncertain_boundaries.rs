@@ -0,0 +1,182 @@
+use alloc::vec::Vec;
+
+use vaporetto::{CharacterBoundary, Sentence};
+
+use crate::SentenceFilter;
+
+/// Filter that applies another filter only to uncertain boundaries.
+///
+/// A boundary is uncertain if the absolute value of its score is smaller than the threshold.
+/// Boundaries with confident scores keep the values before applying the inner filter. Tokens that
+/// are split or concatenated again by restoring the boundaries get their tags before applying the
+/// inner filter if they were tokens at that time, and no tags otherwise.
+/// If the sentence has no boundary scores, i.e., it has not been predicted, the inner filter is
+/// applied to all boundaries.
+#[derive(Clone)]
+pub struct UncertainBoundariesFilter<F> {
+    filter: F,
+    threshold: i32,
+}
+
+impl<F> UncertainBoundariesFilter<F> {
+    /// Creates a new UncertainBoundariesFilter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - A filter applied to uncertain boundaries.
+    /// * `threshold` - Boundaries with absolute scores smaller than this value are uncertain.
+    ///
+    /// # Returns
+    ///
+    /// A new UncertainBoundariesFilter.
+    pub const fn new(filter: F, threshold: i32) -> Self {
+        Self { filter, threshold }
+    }
+}
+
+impl<F> SentenceFilter for UncertainBoundariesFilter<F>
+where
+    F: SentenceFilter,
+{
+    fn filter(&self, sentence: &mut Sentence) {
+        if sentence.boundary_scores().is_empty() {
+            self.filter.filter(sentence);
+            return;
+        }
+        let threshold = self.threshold.unsigned_abs();
+        let confident_boundaries: Vec<_> = sentence
+            .boundaries()
+            .iter()
+            .zip(sentence.boundary_scores())
+            .map(|(&b, score)| (score.unsigned_abs() >= threshold).then_some(b))
+            .collect();
+        let orig_boundaries = sentence.boundaries().to_vec();
+        let orig_tags = sentence.tags().to_vec();
+        let orig_n_tags = sentence.n_tags();
+        self.filter.filter(sentence);
+        let filtered_boundaries = sentence.boundaries().to_vec();
+        for (b, orig_b) in sentence
+            .boundaries_mut()
+            .iter_mut()
+            .zip(confident_boundaries)
+        {
+            if let Some(orig_b) = orig_b {
+                *b = orig_b;
+            }
+        }
+        let n_tags = sentence.n_tags();
+        if n_tags == 0 {
+            return;
+        }
+        let spans: Vec<_> = sentence
+            .iter_tokens()
+            .map(|t| (t.start(), t.end()))
+            .collect();
+        for (start, end) in spans {
+            if is_token(&filtered_boundaries, start, end) {
+                continue;
+            }
+            let tags = &mut sentence.tags_mut()[(end - 1) * n_tags..end * n_tags];
+            if n_tags == orig_n_tags && is_token(&orig_boundaries, start, end) {
+                tags.clone_from_slice(&orig_tags[(end - 1) * n_tags..end * n_tags]);
+            } else {
+                tags.fill(None);
+            }
+        }
+    }
+}
+
+/// Returns `true` if the characters from `start` to `end` form a token under the given
+/// boundaries.
+fn is_token(boundaries: &[CharacterBoundary], start: usize, end: usize) -> bool {
+    (start == 0 || boundaries[start - 1] == CharacterBoundary::WordBoundary)
+        && (end == boundaries.len() + 1 || boundaries[end - 1] == CharacterBoundary::WordBoundary)
+        && boundaries[start..end - 1]
+            .iter()
+            .all(|&b| b != CharacterBoundary::WordBoundary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    use vaporetto::{CharacterType, Model, Predictor};
+
+    use crate::sentence_filters::KyteaWsConstFilter;
+
+    fn create_predictor() -> Predictor {
+        let (model, _) = Model::read_slice(include_bytes!("../../../resources/model.bin")).unwrap();
+        Predictor::new(model, false).unwrap()
+    }
+
+    #[test]
+    fn test_uncertain_boundaries_apply() {
+        let predictor = create_predictor();
+        let mut s = Sentence::from_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut s);
+        let filter =
+            UncertainBoundariesFilter::new(KyteaWsConstFilter::new(CharacterType::Kanji), 40000);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長 は 火星猫 だ", buf);
+    }
+
+    #[test]
+    fn test_uncertain_boundaries_keep_confident() {
+        let predictor = create_predictor();
+        let mut s = Sentence::from_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut s);
+        let filter =
+            UncertainBoundariesFilter::new(KyteaWsConstFilter::new(CharacterType::Kanji), 30000);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ 社長 は 火星 猫 だ", buf);
+    }
+
+    struct TaggedConcatenator;
+
+    impl SentenceFilter for TaggedConcatenator {
+        fn filter(&self, sentence: &mut Sentence) {
+            KyteaWsConstFilter::new(CharacterType::Kanji).filter(sentence);
+            let ends: Vec<_> = sentence.iter_tokens().map(|t| t.end()).collect();
+            for end in ends {
+                sentence.tags_mut()[end - 1] = Some("連結".into());
+            }
+        }
+    }
+
+    #[test]
+    fn test_uncertain_boundaries_restore_tags() {
+        let predictor = create_predictor();
+        let mut s = Sentence::from_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut s);
+        s.reset_tags(1);
+        let tokens: Vec<_> = s
+            .iter_tokens()
+            .map(|t| (t.end(), String::from(t.surface())))
+            .collect();
+        for (end, surface) in tokens {
+            s.tags_mut()[end - 1] = Some(surface.into());
+        }
+        let filter = UncertainBoundariesFilter::new(TaggedConcatenator, 30000);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("まぁ/連結 社長/連結 は/連結 火星/火星 猫/猫 だ/連結", buf);
+    }
+
+    #[test]
+    fn test_uncertain_boundaries_no_scores() {
+        let mut s = Sentence::from_tokenized("火星 猫 だ").unwrap();
+        let filter =
+            UncertainBoundariesFilter::new(KyteaWsConstFilter::new(CharacterType::Kanji), 30000);
+        filter.filter(&mut s);
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("火星猫 だ", buf);
+    }
+}
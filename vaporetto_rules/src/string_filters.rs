@@ -1,5 +1,16 @@
 //! Filters for [`String`](alloc::string::String).
 
+mod chain;
+mod char_map;
 mod kytea_fullwidth;
+#[cfg(feature = "unicode-normalization")]
+mod unicode_normalization;
 
+/// Name of the normalization recorded in models trained without any string filter.
+pub const NO_NORMALIZATION: &str = "none";
+
+pub use chain::ChainFilter;
+pub use char_map::CharMapFilter;
 pub use kytea_fullwidth::KyteaFullwidthFilter;
+#[cfg(feature = "unicode-normalization")]
+pub use unicode_normalization::{NfcFilter, NfdFilter, NfkcFilter, NfkdFilter};
@@ -0,0 +1,65 @@
+use alloc::string::String;
+
+use vaporetto::OffsetMap;
+
+use crate::StringFilter;
+
+/// Filter that applies two filters in order.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto_rules::{
+///     string_filters::{ChainFilter, KyteaFullwidthFilter},
+///     StringFilter,
+/// };
+///
+/// struct LowercaseFilter;
+///
+/// impl<S: AsRef<str>> StringFilter<S> for LowercaseFilter {
+///     fn filter(&self, string: S) -> String {
+///         string.as_ref().to_lowercase()
+///     }
+/// }
+///
+/// let filter = ChainFilter::new(LowercaseFilter, KyteaFullwidthFilter);
+/// assert_eq!("ｖａｐｏｒｅｔｔｏ", filter.filter("Vaporetto"));
+/// ```
+#[derive(Clone, Default)]
+pub struct ChainFilter<F1, F2> {
+    first: F1,
+    second: F2,
+}
+
+impl<F1, F2> ChainFilter<F1, F2> {
+    /// Creates a new ChainFilter.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - A filter applied first.
+    /// * `second` - A filter applied to the result of `first`.
+    ///
+    /// # Returns
+    ///
+    /// A new ChainFilter.
+    pub const fn new(first: F1, second: F2) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<S, F1, F2> StringFilter<S> for ChainFilter<F1, F2>
+where
+    S: AsRef<str>,
+    F1: StringFilter<S>,
+    F2: StringFilter<String>,
+{
+    fn filter(&self, string: S) -> String {
+        self.second.filter(self.first.filter(string))
+    }
+
+    fn filter_with_offsets(&self, string: S) -> (String, OffsetMap) {
+        let (first_result, first_offsets) = self.first.filter_with_offsets(string);
+        let (result, second_offsets) = self.second.filter_with_offsets(first_result);
+        (result, first_offsets.compose(&second_offsets))
+    }
+}
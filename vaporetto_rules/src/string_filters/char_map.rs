@@ -0,0 +1,121 @@
+use alloc::string::String;
+
+use hashbrown::HashMap;
+
+use super::KyteaFullwidthFilter;
+use crate::StringFilter;
+
+/// Filter that replaces characters according to a mapping table.
+///
+/// Characters not in the table are kept as is. Since each character is replaced with exactly one
+/// character, positions in the filtered string correspond to those in the original string.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto_rules::{string_filters::CharMapFilter, StringFilter};
+///
+/// // Unifies wave dashes into fullwidth tildes and hyphens into minus signs, and keeps periods.
+/// let filter = CharMapFilter::kytea_fullwidth()
+///     .insert('～', '～')
+///     .insert('〜', '～')
+///     .insert('‐', '−')
+///     .remove('.');
+/// assert_eq!("１～３～５−７.", filter.filter("1〜3～5‐7."));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CharMapFilter {
+    map: HashMap<char, char>,
+}
+
+impl CharMapFilter {
+    /// Creates a new CharMapFilter with an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new CharMapFilter with the table of [`KyteaFullwidthFilter`].
+    pub fn kytea_fullwidth() -> Self {
+        KyteaFullwidthFilter::TABLE.iter().copied().collect()
+    }
+
+    /// Adds a mapping. If `from` is already in the table, the mapping is overridden.
+    pub fn insert(mut self, from: char, to: char) -> Self {
+        self.map.insert(from, to);
+        self
+    }
+
+    /// Removes the mapping of `from`, so that `from` is kept as is.
+    pub fn remove(mut self, from: char) -> Self {
+        self.map.remove(&from);
+        self
+    }
+
+    /// Adds mappings. Existing mappings of the same characters are overridden.
+    pub fn extend<I>(mut self, mappings: I) -> Self
+    where
+        I: IntoIterator<Item = (char, char)>,
+    {
+        self.map.extend(mappings);
+        self
+    }
+
+    /// Returns the character that `c` is replaced with.
+    pub fn map_char(&self, c: char) -> char {
+        self.map.get(&c).copied().unwrap_or(c)
+    }
+}
+
+impl FromIterator<(char, char)> for CharMapFilter {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (char, char)>,
+    {
+        Self {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<S> StringFilter<S> for CharMapFilter
+where
+    S: AsRef<str>,
+{
+    fn filter(&self, string: S) -> String {
+        string.as_ref().chars().map(|c| self.map_char(c)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kytea_fullwidth_compatible() {
+        let text = "Vaporetto は､\"高速\"(100%)! ｢ﾃｽﾄ｣ a-b_c@d.e";
+        assert_eq!(
+            KyteaFullwidthFilter.filter(text),
+            CharMapFilter::kytea_fullwidth().filter(text),
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!("abc", CharMapFilter::new().filter("abc"));
+    }
+
+    #[test]
+    fn test_override() {
+        let filter = CharMapFilter::kytea_fullwidth()
+            .insert('-', 'ー')
+            .extend([('a', 'α'), ('〜', '～')]);
+        assert_eq!("ｂーα～", filter.filter("b-a〜"));
+    }
+
+    #[test]
+    fn test_offsets() {
+        let (filtered, offsets) = CharMapFilter::kytea_fullwidth().filter_with_offsets("ab-c");
+        assert_eq!("ａｂ−ｃ", filtered);
+        assert!(offsets.is_identity());
+    }
+}
@@ -3,116 +3,149 @@ use alloc::string::String;
 use crate::StringFilter;
 
 /// Half-width to full-width filter. This filter works like KyTea's preprocessor.
+///
+/// Use [`CharMapFilter`](super::CharMapFilter) to add or override mappings of this filter.
 #[derive(Clone, Default)]
 pub struct KyteaFullwidthFilter;
 
+impl KyteaFullwidthFilter {
+    /// Name of the normalization recorded in models trained with this filter.
+    pub const NAME: &'static str = "kytea-fullwidth";
+
+    /// Mapping table of this filter sorted by the source characters.
+    pub const TABLE: &'static [(char, char)] = &[
+        ('!', '！'),
+        ('"', '”'),
+        ('%', '％'),
+        ('&', '＆'),
+        ('\'', '’'),
+        ('(', '（'),
+        (')', '）'),
+        ('*', '＊'),
+        ('+', '＋'),
+        (',', '，'),
+        ('-', '−'),
+        ('.', '。'),
+        ('/', '／'),
+        ('0', '０'),
+        ('1', '１'),
+        ('2', '２'),
+        ('3', '３'),
+        ('4', '４'),
+        ('5', '５'),
+        ('6', '６'),
+        ('7', '７'),
+        ('8', '８'),
+        ('9', '９'),
+        (':', '：'),
+        ('<', '＜'),
+        ('=', '＝'),
+        ('>', '＞'),
+        ('?', '？'),
+        ('@', '＠'),
+        ('A', 'Ａ'),
+        ('B', 'Ｂ'),
+        ('C', 'Ｃ'),
+        ('D', 'Ｄ'),
+        ('E', 'Ｅ'),
+        ('F', 'Ｆ'),
+        ('G', 'Ｇ'),
+        ('H', 'Ｈ'),
+        ('I', 'Ｉ'),
+        ('J', 'Ｊ'),
+        ('K', 'Ｋ'),
+        ('L', 'Ｌ'),
+        ('M', 'Ｍ'),
+        ('N', 'Ｎ'),
+        ('O', 'Ｏ'),
+        ('P', 'Ｐ'),
+        ('Q', 'Ｑ'),
+        ('R', 'Ｒ'),
+        ('S', 'Ｓ'),
+        ('T', 'Ｔ'),
+        ('U', 'Ｕ'),
+        ('V', 'Ｖ'),
+        ('W', 'Ｗ'),
+        ('X', 'Ｘ'),
+        ('Y', 'Ｙ'),
+        ('Z', 'Ｚ'),
+        ('[', '［'),
+        (']', '］'),
+        ('_', '＿'),
+        ('a', 'ａ'),
+        ('b', 'ｂ'),
+        ('c', 'ｃ'),
+        ('d', 'ｄ'),
+        ('e', 'ｅ'),
+        ('f', 'ｆ'),
+        ('g', 'ｇ'),
+        ('h', 'ｈ'),
+        ('i', 'ｉ'),
+        ('j', 'ｊ'),
+        ('k', 'ｋ'),
+        ('l', 'ｌ'),
+        ('m', 'ｍ'),
+        ('n', 'ｎ'),
+        ('o', 'ｏ'),
+        ('p', 'ｐ'),
+        ('q', 'ｑ'),
+        ('r', 'ｒ'),
+        ('s', 'ｓ'),
+        ('t', 'ｔ'),
+        ('u', 'ｕ'),
+        ('v', 'ｖ'),
+        ('w', 'ｗ'),
+        ('x', 'ｘ'),
+        ('y', 'ｙ'),
+        ('z', 'ｚ'),
+        ('{', '｛'),
+        ('}', '｝'),
+        ('–', 'ー'),
+        ('―', 'ー'),
+        ('─', 'ー'),
+        ('－', 'ー'),
+        ('～', '〜'),
+        ('｡', '。'),
+        ('｢', '「'),
+        ('｣', '」'),
+        ('､', '、'),
+        ('･', '・'),
+    ];
+
+    /// Returns the character mapped from the given character.
+    #[inline]
+    pub fn map_char(c: char) -> char {
+        Self::TABLE
+            .binary_search_by_key(&c, |&(from, _)| from)
+            .map_or(c, |i| Self::TABLE[i].1)
+    }
+}
+
 impl<S> StringFilter<S> for KyteaFullwidthFilter
 where
     S: AsRef<str>,
 {
     fn filter(&self, string: S) -> String {
-        let mut result = String::new();
-        for c in string.as_ref().chars() {
-            result.push(match c {
-                'a' => 'ａ',
-                'b' => 'ｂ',
-                'c' => 'ｃ',
-                'd' => 'ｄ',
-                'e' => 'ｅ',
-                'f' => 'ｆ',
-                'g' => 'ｇ',
-                'h' => 'ｈ',
-                'i' => 'ｉ',
-                'j' => 'ｊ',
-                'k' => 'ｋ',
-                'l' => 'ｌ',
-                'm' => 'ｍ',
-                'n' => 'ｎ',
-                'o' => 'ｏ',
-                'p' => 'ｐ',
-                'q' => 'ｑ',
-                'r' => 'ｒ',
-                's' => 'ｓ',
-                't' => 'ｔ',
-                'u' => 'ｕ',
-                'v' => 'ｖ',
-                'w' => 'ｗ',
-                'x' => 'ｘ',
-                'y' => 'ｙ',
-                'z' => 'ｚ',
-                'A' => 'Ａ',
-                'B' => 'Ｂ',
-                'C' => 'Ｃ',
-                'D' => 'Ｄ',
-                'E' => 'Ｅ',
-                'F' => 'Ｆ',
-                'G' => 'Ｇ',
-                'H' => 'Ｈ',
-                'I' => 'Ｉ',
-                'J' => 'Ｊ',
-                'K' => 'Ｋ',
-                'L' => 'Ｌ',
-                'M' => 'Ｍ',
-                'N' => 'Ｎ',
-                'O' => 'Ｏ',
-                'P' => 'Ｐ',
-                'Q' => 'Ｑ',
-                'R' => 'Ｒ',
-                'S' => 'Ｓ',
-                'T' => 'Ｔ',
-                'U' => 'Ｕ',
-                'V' => 'Ｖ',
-                'W' => 'Ｗ',
-                'X' => 'Ｘ',
-                'Y' => 'Ｙ',
-                'Z' => 'Ｚ',
-                '0' => '０',
-                '1' => '１',
-                '2' => '２',
-                '3' => '３',
-                '4' => '４',
-                '5' => '５',
-                '6' => '６',
-                '7' => '７',
-                '8' => '８',
-                '9' => '９',
-                '(' => '（',
-                ')' => '）',
-                '{' => '｛',
-                '}' => '｝',
-                '<' => '＜',
-                '>' => '＞',
-                '｢' => '「',
-                '｣' => '」',
-                '[' => '［',
-                ']' => '］',
-                '-' => '−',
-                '～' => '〜',
-                '.' => '。',
-                '－' => 'ー',
-                '/' => '／',
-                '_' => '＿',
-                ',' => '，',
-                '%' => '％',
-                '?' => '？',
-                '､' => '、',
-                '―' => 'ー',
-                '"' => '”',
-                '\'' => '’',
-                '･' => '・',
-                '─' => 'ー',
-                '+' => '＋',
-                ':' => '：',
-                '–' => 'ー',
-                '!' => '！',
-                '｡' => '。',
-                '&' => '＆',
-                '*' => '＊',
-                '@' => '＠',
-                '=' => '＝',
-                c => c,
-            });
-        }
-        result
+        string.as_ref().chars().map(Self::map_char).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_sorted() {
+        assert!(KyteaFullwidthFilter::TABLE
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_kytea_fullwidth() {
+        assert_eq!(
+            "Ｖａｐｏｒｅｔｔｏ は、”高速”（１００％）！",
+            KyteaFullwidthFilter.filter("Vaporetto は､\"高速\"(100%)!"),
+        );
     }
 }
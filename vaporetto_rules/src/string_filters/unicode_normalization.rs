@@ -0,0 +1,173 @@
+use core::ops::Range;
+
+use alloc::string::String;
+
+use unicode_normalization::UnicodeNormalization;
+use vaporetto::OffsetMap;
+
+use crate::StringFilter;
+
+/// Normalizes the string by each run of characters that are normalized independently of the
+/// surroundings, and records the origin of each output character.
+fn normalize_with_offsets(string: &str, normalize: impl Fn(&str) -> String) -> (String, OffsetMap) {
+    let mut result = String::with_capacity(string.len());
+    let mut ranges = vec![];
+    let mut push_segment = |normalized: &str, range: Range<usize>| {
+        result.push_str(normalized);
+        ranges.extend(normalized.chars().map(|_| range.clone()));
+    };
+    let mut seg_byte_start = 0;
+    let mut seg_start = 0;
+    let mut seg_normalized = String::new();
+    let mut original_len = 0;
+    for (i, (pos, c)) in string.char_indices().enumerate() {
+        let joined = normalize(&string[seg_byte_start..pos + c.len_utf8()]);
+        let separated = normalize(&string[pos..pos + c.len_utf8()]);
+        if i != 0 && joined.strip_prefix(&seg_normalized) == Some(&separated) {
+            push_segment(&seg_normalized, seg_start..i);
+            seg_byte_start = pos;
+            seg_start = i;
+            seg_normalized = separated;
+        } else {
+            seg_normalized = joined;
+        }
+        original_len = i + 1;
+    }
+    push_segment(&seg_normalized, seg_start..original_len);
+    let expected = normalize(string);
+    if result != expected {
+        // Falls back to the coarsest mapping if characters affect distant ones.
+        ranges = vec![0..original_len; expected.chars().count()];
+        result = expected;
+    }
+    let offsets = OffsetMap::from_ranges(ranges, original_len).unwrap();
+    (result, offsets)
+}
+
+macro_rules! define_normalization_filter {
+    ( $name:ident, $method:ident, $form:literal, $norm_name:literal ) => {
+        #[doc = concat!("Unicode normalization filter converting strings into ", $form, ".")]
+        ///
+        /// The number of characters may be changed by this filter.
+        #[derive(Clone, Default)]
+        pub struct $name;
+
+        impl $name {
+            /// Name of the normalization recorded in models trained with this filter.
+            pub const NAME: &'static str = $norm_name;
+        }
+
+        impl<S> StringFilter<S> for $name
+        where
+            S: AsRef<str>,
+        {
+            fn filter(&self, string: S) -> String {
+                string.as_ref().$method().collect()
+            }
+
+            /// Normalizes the string by each run of characters that are normalized independently
+            /// of the surroundings, so that the mapping keeps the positions of unchanged
+            /// characters.
+            fn filter_with_offsets(&self, string: S) -> (String, OffsetMap) {
+                normalize_with_offsets(string.as_ref(), |s| s.$method().collect())
+            }
+        }
+    };
+}
+
+define_normalization_filter!(NfcFilter, nfc, "NFC", "nfc");
+define_normalization_filter!(NfdFilter, nfd, "NFD", "nfd");
+define_normalization_filter!(NfkcFilter, nfkc, "NFKC", "nfkc");
+define_normalization_filter!(NfkdFilter, nfkd, "NFKD", "nfkd");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    use vaporetto::{CharacterBoundary, Sentence};
+
+    use crate::string_filters::{ChainFilter, KyteaFullwidthFilter};
+
+    #[test]
+    fn test_nfc() {
+        assert_eq!("ガ", NfcFilter.filter("カ\u{3099}"));
+    }
+
+    #[test]
+    fn test_nfd() {
+        assert_eq!("カ\u{3099}", NfdFilter.filter("ガ"));
+    }
+
+    #[test]
+    fn test_nfkc() {
+        assert_eq!("ガギ1A", NfkcFilter.filter("ｶﾞギ１Ａ"));
+    }
+
+    #[test]
+    fn test_nfkc_kytea_fullwidth() {
+        let filter = ChainFilter::new(NfkcFilter, KyteaFullwidthFilter);
+        assert_eq!("ガ１Ａ", filter.filter("ｶﾞ１A"));
+    }
+
+    #[test]
+    fn test_nfkc_with_offsets() {
+        let (normalized, offsets) =
+            NfkcFilter.filter_with_offsets("A\u{ff76}\u{ff9e}1\u{3099}\u{337f}");
+        assert_eq!("Aガ1\u{3099}株式会社", normalized);
+        assert_eq!(
+            vec![0..1, 1..3, 3..4, 4..5, 5..6, 5..6, 5..6, 5..6],
+            (0..8)
+                .map(|i| offsets.to_original(i..i + 1))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(1..5, offsets.to_original(1..4));
+        assert_eq!(3..3, offsets.to_original(2..2));
+        assert_eq!(6..6, offsets.to_original(8..8));
+    }
+
+    #[test]
+    fn test_nfkc_kytea_fullwidth_with_offsets() {
+        let filter = ChainFilter::new(NfkcFilter, KyteaFullwidthFilter);
+        let (normalized, offsets) = filter.filter_with_offsets("\u{337f}ｶﾞ1");
+        assert_eq!("株式会社ガ１", normalized);
+        assert_eq!(0..1, offsets.to_original(1..3));
+        assert_eq!(1..4, offsets.to_original(4..6));
+    }
+
+    #[test]
+    fn test_nfkc_project_boundaries() {
+        let (normalized, offsets) = NfkcFilter.filter_with_offsets("ｶﾞｲﾄﾞは\u{337f}");
+        let mut s = Sentence::from_raw(normalized).unwrap();
+        s.boundaries_mut().fill(CharacterBoundary::WordBoundary);
+        let s_orig = s.project_boundaries("ｶﾞｲﾄﾞは\u{337f}", &offsets).unwrap();
+        let mut buf = String::new();
+        s_orig.write_tokenized_text(&mut buf);
+        assert_eq!("ｶﾞ ｲ ﾄﾞ は \u{337f}", buf);
+    }
+
+    #[test]
+    fn test_nfd_with_offsets() {
+        let (normalized, offsets) = NfdFilter.filter_with_offsets("が\u{0323}");
+        assert_eq!("か\u{3099}\u{0323}", normalized);
+        assert_eq!(0..1, offsets.to_original(0..2));
+        assert_eq!(1..2, offsets.to_original(2..3));
+
+        // The dot below is reordered before the acute accent.
+        let (normalized, offsets) = NfdFilter.filter_with_offsets("\u{e1}\u{0323}b");
+        assert_eq!("a\u{0323}\u{0301}b", normalized);
+        assert_eq!(0..2, offsets.to_original(0..1));
+        assert_eq!(0..2, offsets.to_original(2..3));
+        assert_eq!(2..3, offsets.to_original(3..4));
+
+        let (normalized, offsets) = NfdFilter.filter_with_offsets("");
+        assert_eq!("", normalized);
+        assert_eq!(0..0, offsets.to_original(0..0));
+    }
+
+    #[test]
+    fn test_nfkd() {
+        assert_eq!("カ\u{3099}1", NfkdFilter.filter("ｶﾞ１"));
+    }
+}
@@ -0,0 +1,160 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/**
+ * A token returned by `Vaporetto.tokenizeWithTags()`.
+ */
+export interface Token {
+    surface: string;
+    tag: string | null;
+    tags: (string | null)[];
+    start: number;
+    end: number;
+}
+
+
+
+/**
+ * Loader that receives a model in chunks, e.g., from `Response.body`, so that the whole response
+ * does not have to be buffered in JavaScript.
+ */
+export class ModelLoader {
+    free(): void;
+    [Symbol.dispose](): void;
+    /**
+     * Creates a new tokenizer from the received data and frees the loader.
+     *
+     * The arguments are the same as the constructor of [`Vaporetto`].
+     *
+     * # Errors
+     *
+     * Error is returned when
+     *   - the model is invalid,
+     *   - the normalization recorded in the model is unsupported, or
+     *   - `wsconst` contains an invalid character type.
+     */
+    build(wsconst: string, predict_tags: boolean): Vaporetto;
+    /**
+     * Creates a new empty loader.
+     */
+    constructor();
+    /**
+     * Appends a chunk of the model data.
+     */
+    push(chunk: Uint8Array): void;
+}
+
+/**
+ * Tokenizer that runs the predictor and the rules pipeline.
+ */
+export class Vaporetto {
+    free(): void;
+    [Symbol.dispose](): void;
+    /**
+     * Creates a new tokenizer from an `ArrayBuffer`, e.g., the result of
+     * `Response.arrayBuffer()`.
+     *
+     * The arguments are the same as the constructor.
+     *
+     * # Errors
+     *
+     * Error is returned when
+     *   - the model is invalid,
+     *   - the normalization recorded in the model is unsupported, or
+     *   - `wsconst` contains an invalid character type.
+     */
+    static fromArrayBuffer(buffer: ArrayBuffer, wsconst: string, predict_tags: boolean): Vaporetto;
+    /**
+     * Creates a new tokenizer.
+     *
+     * # Arguments
+     *
+     * * `model_bytes` - A model data of Vaporetto. Data compressed by zstd is also accepted.
+     * * `wsconst` - Character types that the tokenizer does not segment.
+     *   D: Digit, R: Roman, H: Hiragana, T: Katakana, K: Kanji, O: Other,
+     *   G: Grapheme cluster.
+     * * `predict_tags` - If `true`, tags are predicted.
+     *
+     * # Errors
+     *
+     * Error is returned when
+     *   - the model is invalid,
+     *   - the normalization recorded in the model is unsupported, or
+     *   - `wsconst` contains an invalid character type.
+     */
+    constructor(model_bytes: Uint8Array, wsconst: string, predict_tags: boolean);
+    /**
+     * Tokenizes the given text and returns the tokens with their tags and positions.
+     *
+     * Returns an array of objects `{surface, tag, tags, start, end}`, where `tag` is the first
+     * tag or `null`, `tags` is an array of all tags (missing ones are `null`), and `start` and
+     * `end` are offsets in UTF-16 code units, i.e., the indices of JavaScript strings.
+     * Tags are always `null` if the tokenizer is created without `predict_tags`.
+     *
+     * # Errors
+     *
+     * Error is returned when the text contains NULL.
+     */
+    tokenizeWithTags(text: string): Token[];
+    /**
+     * Tokenizes the given text and returns the surfaces of the tokens.
+     *
+     * Use `tokenizeWithTags()` to obtain tags and positions of the tokens.
+     *
+     * # Errors
+     *
+     * Error is returned when the text contains NULL.
+     */
+    tokenize(text: string): string[];
+}
+
+/**
+ * Returns the version of the package.
+ */
+export function version(): string;
+
+export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+
+export interface InitOutput {
+    readonly memory: WebAssembly.Memory;
+    readonly __wbg_modelloader_free: (a: number, b: number) => void;
+    readonly __wbg_vaporetto_free: (a: number, b: number) => void;
+    readonly modelloader_build: (a: number, b: number, c: number, d: number) => [number, number, number];
+    readonly modelloader_new: () => number;
+    readonly modelloader_push: (a: number, b: number, c: number) => void;
+    readonly vaporetto_fromArrayBuffer: (a: any, b: number, c: number, d: number) => [number, number, number];
+    readonly vaporetto_new: (a: number, b: number, c: number, d: number, e: number) => [number, number, number];
+    readonly vaporetto_tokenize: (a: number, b: number, c: number) => [number, number, number];
+    readonly vaporetto_tokenizeWithTags: (a: number, b: number, c: number) => [number, number, number];
+    readonly version: () => [number, number];
+    readonly __wbindgen_malloc: (a: number, b: number) => number;
+    readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
+    readonly __wbindgen_exn_store: (a: number) => void;
+    readonly __externref_table_alloc: () => number;
+    readonly __wbindgen_externrefs: WebAssembly.Table;
+    readonly __externref_table_dealloc: (a: number) => void;
+    readonly __wbindgen_free: (a: number, b: number, c: number) => void;
+    readonly __wbindgen_start: () => void;
+}
+
+export type SyncInitInput = BufferSource | WebAssembly.Module;
+
+/**
+ * Instantiates the given `module`, which can either be bytes or
+ * a precompiled `WebAssembly.Module`.
+ *
+ * @param {{ module: SyncInitInput }} module - Passing `SyncInitInput` directly is deprecated.
+ *
+ * @returns {InitOutput}
+ */
+export function initSync(module: { module: SyncInitInput } | SyncInitInput): InitOutput;
+
+/**
+ * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
+ * for everything else, calls `WebAssembly.instantiate` directly.
+ *
+ * @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
+ *
+ * @returns {Promise<InitOutput>}
+ */
+export default function __wbg_init (module_or_path?: { module_or_path: InitInput | Promise<InitInput> } | InitInput | Promise<InitInput>): Promise<InitOutput>;
@@ -0,0 +1,284 @@
+//! # vaporetto_wasm
+//!
+//! WebAssembly bindings of Vaporetto with a JavaScript-friendly API.
+//!
+//! The JavaScript API follows semantic versioning: the API of a release is kept until the next
+//! major version, and the declarations in `api.d.ts` are its snapshot.
+//!
+//! ## Examples
+//!
+//! ```js
+//! import init, { Vaporetto } from "./vaporetto_wasm.js";
+//!
+//! await init();
+//! const model = await (await fetch("model.zst")).arrayBuffer();
+//! const tokenizer = Vaporetto.fromArrayBuffer(model, "DG", true);
+//! console.log(tokenizer.tokenize("東京特許許可局"));
+//! for (const token of tokenizer.tokenizeWithTags("東京特許許可局")) {
+//!     console.log(token.surface, token.tag, token.start, token.end);
+//! }
+//! tokenizer.free();
+//! ```
+
+use std::io::Read;
+
+use js_sys::{Array, ArrayBuffer, Object, Reflect, Uint8Array};
+use vaporetto::{CharacterType, Model, Predictor, Sentence};
+use vaporetto_rules::{
+    sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter, SplitLinebreaksFilter},
+    string_filters::{KyteaFullwidthFilter, NO_NORMALIZATION},
+    SentenceFilter, StringFilter,
+};
+use wasm_bindgen::prelude::*;
+
+/// Magic number of zstd frames.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+fn build_post_filters(wsconst: &str) -> Result<Vec<Box<dyn SentenceFilter>>, JsError> {
+    let mut postfilters: Vec<Box<dyn SentenceFilter>> = vec![Box::new(SplitLinebreaksFilter)];
+    for c in wsconst.chars() {
+        postfilters.push(match c {
+            'D' => Box::new(KyteaWsConstFilter::new(CharacterType::Digit)),
+            'R' => Box::new(KyteaWsConstFilter::new(CharacterType::Roman)),
+            'H' => Box::new(KyteaWsConstFilter::new(CharacterType::Hiragana)),
+            'T' => Box::new(KyteaWsConstFilter::new(CharacterType::Katakana)),
+            'K' => Box::new(KyteaWsConstFilter::new(CharacterType::Kanji)),
+            'O' => Box::new(KyteaWsConstFilter::new(CharacterType::Other)),
+            'G' => Box::new(ConcatGraphemeClustersFilter),
+            _ => return Err(JsError::new("Could not parse a wsconst value")),
+        });
+    }
+    Ok(postfilters)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TOKEN: &str = r#"
+/**
+ * A token returned by `Vaporetto.tokenizeWithTags()`.
+ */
+export interface Token {
+    surface: string;
+    tag: string | null;
+    tags: (string | null)[];
+    start: number;
+    end: number;
+}
+"#;
+
+/// Returns the version of the package.
+#[wasm_bindgen]
+#[must_use]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+fn set(obj: &Object, key: &str, value: &JsValue) {
+    // Setting a property of a plain object never fails.
+    Reflect::set(obj, &JsValue::from_str(key), value).unwrap();
+}
+
+/// Loader that receives a model in chunks, e.g., from `Response.body`, so that the whole response
+/// does not have to be buffered in JavaScript.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct ModelLoader {
+    buf: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ModelLoader {
+    /// Creates a new empty loader.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk of the model data.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Creates a new tokenizer from the received data and frees the loader.
+    ///
+    /// The arguments are the same as the constructor of [`Vaporetto`].
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when
+    ///   - the model is invalid,
+    ///   - the normalization recorded in the model is unsupported, or
+    ///   - `wsconst` contains an invalid character type.
+    pub fn build(self, wsconst: &str, predict_tags: bool) -> Result<Vaporetto, JsError> {
+        Vaporetto::from_bytes(&self.buf, wsconst, predict_tags)
+    }
+}
+
+/// Tokenizer that runs the predictor and the rules pipeline.
+#[wasm_bindgen]
+pub struct Vaporetto {
+    predictor: Predictor,
+    prefilter: Option<KyteaFullwidthFilter>,
+    postfilters: Vec<Box<dyn SentenceFilter>>,
+    predict_tags: bool,
+}
+
+#[wasm_bindgen]
+impl Vaporetto {
+    /// Creates a new tokenizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_bytes` - A model data of Vaporetto. Data compressed by zstd is also accepted.
+    /// * `wsconst` - Character types that the tokenizer does not segment.
+    ///   D: Digit, R: Roman, H: Hiragana, T: Katakana, K: Kanji, O: Other,
+    ///   G: Grapheme cluster.
+    /// * `predict_tags` - If `true`, tags are predicted.
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when
+    ///   - the model is invalid,
+    ///   - the normalization recorded in the model is unsupported, or
+    ///   - `wsconst` contains an invalid character type.
+    #[wasm_bindgen(constructor)]
+    pub fn new(model_bytes: &[u8], wsconst: &str, predict_tags: bool) -> Result<Self, JsError> {
+        Self::from_bytes(model_bytes, wsconst, predict_tags)
+    }
+
+    /// Creates a new tokenizer from an `ArrayBuffer`, e.g., the result of
+    /// `Response.arrayBuffer()`.
+    ///
+    /// The arguments are the same as the constructor.
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when
+    ///   - the model is invalid,
+    ///   - the normalization recorded in the model is unsupported, or
+    ///   - `wsconst` contains an invalid character type.
+    #[wasm_bindgen(js_name = fromArrayBuffer)]
+    pub fn from_array_buffer(
+        buffer: &ArrayBuffer,
+        wsconst: &str,
+        predict_tags: bool,
+    ) -> Result<Self, JsError> {
+        Self::from_bytes(&Uint8Array::new(buffer).to_vec(), wsconst, predict_tags)
+    }
+
+    /// Tokenizes the given text and returns the surfaces of the tokens.
+    ///
+    /// Use `tokenizeWithTags()` to obtain tags and positions of the tokens.
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when the text contains NULL.
+    #[wasm_bindgen(unchecked_return_type = "string[]")]
+    pub fn tokenize(&self, text: &str) -> Result<Array, JsError> {
+        let result = Array::new();
+        if text.is_empty() {
+            return Ok(result);
+        }
+        let s = self.predict_text(text)?;
+        for token in s.iter_tokens() {
+            result.push(&JsValue::from_str(token.surface()));
+        }
+        Ok(result)
+    }
+
+    /// Tokenizes the given text and returns the tokens with their tags and positions.
+    ///
+    /// Returns an array of objects `{surface, tag, tags, start, end}`, where `tag` is the first
+    /// tag or `null`, `tags` is an array of all tags (missing ones are `null`), and `start` and
+    /// `end` are offsets in UTF-16 code units, i.e., the indices of JavaScript strings.
+    /// Tags are always `null` if the tokenizer is created without `predict_tags`.
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when the text contains NULL.
+    #[wasm_bindgen(js_name = tokenizeWithTags, unchecked_return_type = "Token[]")]
+    pub fn tokenize_with_tags(&self, text: &str) -> Result<Array, JsError> {
+        let result = Array::new();
+        if text.is_empty() {
+            return Ok(result);
+        }
+        let s = self.predict_text(text)?;
+
+        let mut start = 0;
+        for token in s.iter_tokens() {
+            let end = start + token.surface().encode_utf16().count();
+            let tags: Array = token
+                .tags()
+                .iter()
+                .map(|tag| tag.as_deref().map_or(JsValue::NULL, JsValue::from_str))
+                .collect();
+            let obj = Object::new();
+            set(&obj, "surface", &JsValue::from_str(token.surface()));
+            let tag = if tags.length() == 0 {
+                JsValue::NULL
+            } else {
+                tags.get(0)
+            };
+            set(&obj, "tag", &tag);
+            set(&obj, "tags", &tags);
+            set(&obj, "start", &JsValue::from(start));
+            set(&obj, "end", &JsValue::from(end));
+            result.push(&obj);
+            start = end;
+        }
+        Ok(result)
+    }
+}
+
+impl Vaporetto {
+    fn from_bytes(model_bytes: &[u8], wsconst: &str, predict_tags: bool) -> Result<Self, JsError> {
+        let mut buf = vec![];
+        let model_bytes = if model_bytes.starts_with(ZSTD_MAGIC) {
+            let mut decoder = ruzstd::StreamingDecoder::new(model_bytes)?;
+            decoder.read_to_end(&mut buf)?;
+            &buf
+        } else {
+            model_bytes
+        };
+        let (model, _) = Model::read_slice(model_bytes)?;
+        let prefilter = match model.normalization() {
+            None | Some(KyteaFullwidthFilter::NAME) => Some(KyteaFullwidthFilter),
+            Some(NO_NORMALIZATION) => None,
+            Some(name) => {
+                return Err(JsError::new(&format!(
+                    "Unsupported normalization of the model: {name}"
+                )))
+            }
+        };
+        Ok(Self {
+            predictor: Predictor::new(model, predict_tags)?,
+            prefilter,
+            postfilters: build_post_filters(wsconst)?,
+            predict_tags,
+        })
+    }
+
+    fn predict_text<'t>(&self, text: &'t str) -> Result<Sentence<'t, '_>, JsError> {
+        let mut s = Sentence::from_raw(text)?;
+        if let Some(prefilter) = self.prefilter.as_ref() {
+            let mut s_filtered = Sentence::from_raw(prefilter.filter(text))?;
+            self.predict(&mut s_filtered);
+            s.boundaries_mut().copy_from_slice(s_filtered.boundaries());
+            s.reset_tags(s_filtered.n_tags());
+            s.tags_mut().clone_from_slice(s_filtered.tags());
+        } else {
+            self.predict(&mut s);
+        }
+        Ok(s)
+    }
+
+    fn predict<'a>(&'a self, s: &mut Sentence<'_, 'a>) {
+        self.predictor.predict(s);
+        for filter in &self.postfilters {
+            filter.filter(s);
+        }
+        if self.predict_tags {
+            s.fill_tags();
+        }
+    }
+}